@@ -0,0 +1,137 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, Index, Member};
+
+// Derives Ord, PartialOrd, PartialEq, Eq and rusty_skiplist::key::SkipKey for
+// a struct, comparing fields in declaration order. Fields can be marked
+// #[skip_key(desc)] and, for string fields, #[skip_key(ignore_case)]; the
+// byte encoding follows the same rules, so it sorts exactly like Ord.
+#[proc_macro_derive(SkipKey, attributes(skip_key))]
+pub fn derive_skip_key(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+struct Field {
+    member: Member,
+    desc: bool,
+    ignore_case: bool,
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => parse_fields(&data.fields)?,
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "SkipKey can only be derived for structs",
+            ))
+        }
+    };
+
+    let cmps = fields.iter().map(|f| {
+        let m = &f.member;
+        let cmp = if f.ignore_case {
+            quote! {
+                ::rusty_skiplist::key::cmp_ignore_case(
+                    ::core::convert::AsRef::<str>::as_ref(&self.#m),
+                    ::core::convert::AsRef::<str>::as_ref(&other.#m),
+                )
+            }
+        } else {
+            quote! { ::core::cmp::Ord::cmp(&self.#m, &other.#m) }
+        };
+        if f.desc {
+            quote! { #cmp.reverse() }
+        } else {
+            cmp
+        }
+    });
+
+    let encodes = fields.iter().map(|f| {
+        let m = &f.member;
+        let encode = if f.ignore_case {
+            quote! {
+                ::rusty_skiplist::key::encode_ignore_case(
+                    ::core::convert::AsRef::<str>::as_ref(&self.#m),
+                    out,
+                )
+            }
+        } else {
+            quote! { ::rusty_skiplist::key::SkipKey::encode_key(&self.#m, out) }
+        };
+        if f.desc {
+            quote! {
+                let start = out.len();
+                #encode;
+                ::rusty_skiplist::key::invert(&mut out[start..]);
+            }
+        } else {
+            quote! { #encode; }
+        }
+    });
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::core::cmp::Ord for #name #ty_generics #where_clause {
+            fn cmp(&self, other: &Self) -> ::core::cmp::Ordering {
+                ::core::cmp::Ordering::Equal
+                    #(.then_with(|| #cmps))*
+            }
+        }
+
+        impl #impl_generics ::core::cmp::PartialOrd for #name #ty_generics #where_clause {
+            fn partial_cmp(&self, other: &Self) -> ::core::option::Option<::core::cmp::Ordering> {
+                ::core::option::Option::Some(::core::cmp::Ord::cmp(self, other))
+            }
+        }
+
+        impl #impl_generics ::core::cmp::PartialEq for #name #ty_generics #where_clause {
+            fn eq(&self, other: &Self) -> bool {
+                ::core::cmp::Ord::cmp(self, other) == ::core::cmp::Ordering::Equal
+            }
+        }
+
+        impl #impl_generics ::core::cmp::Eq for #name #ty_generics #where_clause {}
+
+        impl #impl_generics ::rusty_skiplist::key::SkipKey for #name #ty_generics #where_clause {
+            fn encode_key(&self, out: &mut ::rusty_skiplist::__private::Vec<u8>) {
+                #(#encodes)*
+            }
+        }
+    })
+}
+
+fn parse_fields(fields: &Fields) -> syn::Result<Vec<Field>> {
+    let mut out = Vec::new();
+    for (i, field) in fields.iter().enumerate() {
+        let member = match &field.ident {
+            Some(ident) => Member::Named(ident.clone()),
+            None => Member::Unnamed(Index::from(i)),
+        };
+        let mut f = Field {
+            member,
+            desc: false,
+            ignore_case: false,
+        };
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("skip_key")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("desc") {
+                    f.desc = true;
+                    Ok(())
+                } else if meta.path.is_ident("ignore_case") {
+                    f.ignore_case = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `desc` or `ignore_case`"))
+                }
+            })?;
+        }
+        out.push(f);
+    }
+    Ok(out)
+}
@@ -0,0 +1,73 @@
+use crate::builder::AllocNodes;
+use crate::storage::Nodes;
+use crate::{Levels, NodeLayout, SkipList};
+use alloc::alloc::GlobalAlloc;
+use alloc::sync::Arc;
+
+impl<K: Ord, V> SkipList<K, V> {
+    // Every node, the head included, is allocated and freed through alloc
+    // instead of the global allocator, e.g. one backed by huge pages or local
+    // to a NUMA node. Lists split off this one keep using it.
+    pub fn new_in<A: GlobalAlloc + Send + Sync + 'static>(alloc: A) -> Self {
+        Self::empty_in(
+            NodeLayout::Compact,
+            Levels::default(),
+            Nodes::Custom(Arc::new(AllocNodes(alloc))),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::SkipList;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::sync::atomic::{AtomicIsize, AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[derive(Default)]
+    struct Counts {
+        allocs: AtomicUsize,
+        live: AtomicIsize,
+    }
+
+    struct Counting(Arc<Counts>);
+
+    unsafe impl GlobalAlloc for Counting {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            self.0.allocs.fetch_add(1, Ordering::Relaxed);
+            self.0.live.fetch_add(1, Ordering::Relaxed);
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            self.0.live.fetch_sub(1, Ordering::Relaxed);
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[test]
+    fn nodes_come_from_the_allocator() {
+        let counts = Arc::new(Counts::default());
+        {
+            let mut sk = SkipList::new_in(Counting(Arc::clone(&counts)));
+            assert_eq!(counts.live.load(Ordering::Relaxed), 1);
+            for i in 0..100 {
+                sk.insert(i, i.to_string());
+            }
+            assert_eq!(counts.allocs.load(Ordering::Relaxed), 101);
+            for i in 0..10 {
+                sk.remove(&i);
+            }
+            assert_eq!(counts.live.load(Ordering::Relaxed), 91);
+
+            let mut tail = sk.split_off(&50);
+            let copy = tail.clone();
+            tail.insert(500, String::new());
+            sk.append(&mut tail);
+            assert_eq!(sk.len(), 91);
+            assert_eq!(copy.len(), 50);
+            assert_eq!(counts.live.load(Ordering::Relaxed), 91 + 2 + 51);
+        }
+        assert_eq!(counts.live.load(Ordering::Relaxed), 0);
+    }
+}
@@ -0,0 +1,81 @@
+use crate::SkipList;
+use alloc::boxed::Box;
+use core::any::Any;
+
+pub struct AnySkipList<K> {
+    inner: SkipList<K, Box<dyn Any + Send>>,
+}
+
+impl<K: Ord> AnySkipList<K> {
+    pub fn new() -> Self {
+        Self {
+            inner: SkipList::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    pub fn insert_any<T: Any + Send>(&mut self, key: K, val: T) -> Option<Box<dyn Any + Send>> {
+        self.inner.insert_full(key, Box::new(val)).1
+    }
+
+    pub fn get(&self, key: &K) -> Option<&(dyn Any + Send)> {
+        self.inner.get(key).map(|v| &**v)
+    }
+
+    // None both when the key is absent and when it holds some other type.
+    pub fn get_as<T: Any>(&self, key: &K) -> Option<&T> {
+        self.inner.get(key)?.downcast_ref()
+    }
+
+    pub fn get_mut_as<T: Any>(&mut self, key: &K) -> Option<&mut T> {
+        self.inner.get_mut(key)?.downcast_mut()
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<Box<dyn Any + Send>> {
+        self.inner.remove(key)
+    }
+}
+
+impl<K: Ord> Default for AnySkipList<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AnySkipList;
+
+    #[test]
+    fn typed_access() {
+        let mut sk = AnySkipList::new();
+        assert!(sk.insert_any("port", 8080u16).is_none());
+        sk.insert_any("name", String::from("node-1"));
+        sk.insert_any("ratio", 0.5f64);
+        assert_eq!(sk.len(), 3);
+
+        assert_eq!(sk.get_as::<u16>(&"port"), Some(&8080));
+        assert_eq!(sk.get_as::<u32>(&"port"), None);
+        assert_eq!(sk.get_as::<String>(&"name").unwrap(), "node-1");
+        assert!(sk.get(&"ratio").unwrap().is::<f64>());
+        assert!(sk.get(&"missing").is_none());
+
+        *sk.get_mut_as::<f64>(&"ratio").unwrap() *= 3.0;
+        assert_eq!(sk.get_as::<f64>(&"ratio"), Some(&1.5));
+
+        let old = sk.insert_any("port", "disabled").unwrap();
+        assert_eq!(old.downcast_ref::<u16>(), Some(&8080));
+        assert_eq!(sk.get_as::<&str>(&"port"), Some(&"disabled"));
+
+        let name = sk.remove(&"name").unwrap().downcast::<String>().unwrap();
+        assert_eq!(*name, "node-1");
+        assert_eq!(sk.len(), 2);
+    }
+}
@@ -0,0 +1,232 @@
+use crate::storage::Nodes;
+use crate::sync::Lock;
+use crate::{AllocError, Node, SkipList};
+use alloc::alloc::{alloc, dealloc, Layout};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::ptr::NonNull;
+
+// Bump allocator for nodes. Chunks are only handed back to the allocator when
+// the last list sharing the arena is dropped; nodes given up before that go on
+// the owning list's free list and are reused from there.
+pub(crate) struct Arena {
+    chunks: Vec<(NonNull<u8>, Layout)>,
+    chunk_size: usize,
+    next: usize,
+    end: usize,
+}
+
+impl Arena {
+    fn new(chunk_size: usize) -> Arena {
+        Arena {
+            chunks: Vec::new(),
+            chunk_size: chunk_size.max(1),
+            next: 0,
+            end: 0,
+        }
+    }
+
+    pub(crate) fn alloc(&mut self, layout: Layout) -> Option<NonNull<u8>> {
+        let start = (self.next + layout.align() - 1) & !(layout.align() - 1);
+        if self.next != 0 && start + layout.size() <= self.end {
+            self.next = start + layout.size();
+            return NonNull::new(start as *mut u8);
+        }
+        // A new chunk is aligned for the node that opened it, so that node
+        // always fits at its start without padding.
+        let chunk =
+            Layout::from_size_align(self.chunk_size.max(layout.size()), layout.align()).ok()?;
+        let base = NonNull::new(unsafe { alloc(chunk) })?;
+        self.chunks.push((base, chunk));
+        self.next = base.as_ptr() as usize + layout.size();
+        self.end = base.as_ptr() as usize + chunk.size();
+        Some(base)
+    }
+
+    // Makes sure the current chunk has size bytes left, opening a new one if
+    // it hasn't. What was left of the old chunk goes unused.
+    pub(crate) fn reserve(&mut self, size: usize, align: usize) -> Option<()> {
+        let start = (self.next + align - 1) & !(align - 1);
+        if self.next != 0 && start.checked_add(size)? <= self.end {
+            return Some(());
+        }
+        let chunk = Layout::from_size_align(self.chunk_size.max(size), align).ok()?;
+        let base = NonNull::new(unsafe { alloc(chunk) })?;
+        self.chunks.push((base, chunk));
+        self.next = base.as_ptr() as usize;
+        self.end = base.as_ptr() as usize + chunk.size();
+        Some(())
+    }
+}
+
+// The chunks are plain memory owned by the arena.
+unsafe impl Send for Arena {}
+
+impl Drop for Arena {
+    fn drop(&mut self) {
+        for (base, layout) in self.chunks.drain(..) {
+            unsafe { dealloc(base.as_ptr(), layout) };
+        }
+    }
+}
+
+impl<K, V> SkipList<K, V> {
+    pub(crate) fn use_arena(&mut self, capacity: usize) {
+        let per_node = Node::<K, V>::footprint(2, self.shape());
+        let arena = Arena::new(capacity.saturating_mul(per_node));
+        self.set_nodes(Nodes::Arena(Arc::new(Lock::new(arena))));
+    }
+}
+
+impl<K: Ord, V> SkipList<K, V> {
+    // Nodes are carved out of chunks sized for about `capacity` entries, so
+    // filling the list costs a handful of allocations instead of one per
+    // insert. Memory is not returned until the list (and any list split off
+    // it) is dropped; removed nodes are reused by later inserts.
+    pub fn with_arena(capacity: usize) -> Self {
+        let mut sk = Self::new();
+        sk.use_arena(capacity);
+        sk
+    }
+
+    // An arena list whose first chunk is allocated here, big enough for
+    // capacity entries, so loading that many takes no further allocation.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut sk = Self::with_arena(capacity);
+        sk.reserve(capacity);
+        sk
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        if let Err(why) = self.try_reserve(additional) {
+            panic!("{}", why);
+        }
+    }
+
+    // Sets aside arena memory for additional more entries, so running out
+    // shows up here instead of in a later insert. The room is worked out from
+    // the expected tower height, with an eighth more and one tallest tower on
+    // top to cover the spread. Lists on the global allocator allocate each
+    // node as it is inserted and have nothing to reserve; other storage is
+    // asked to set the room aside, which only an arena does by default.
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), AllocError> {
+        if let Nodes::Global = self.nodes {
+            return Ok(());
+        }
+        let tallest = Node::<K, V>::footprint(self.levels.max, self.shape());
+        let size = (additional as f64 * self.expected_node_size() * 1.125) as usize;
+        let size = size.checked_add(tallest).ok_or(AllocError)?;
+        self.nodes.storage().reserve(size, self.layout.align())
+    }
+
+    #[cfg(test)]
+    fn arena_chunks(&self) -> usize {
+        match &self.nodes {
+            Nodes::Arena(arena) => arena.with(|arena| arena.chunks.len()),
+            _ => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{AllocError, SkipList};
+    use std::rc::Rc;
+
+    #[test]
+    fn arena_allocates_in_chunks() {
+        let mut sk = SkipList::with_arena(1000);
+        for i in 0..1000 {
+            sk.insert(i, i.to_string());
+        }
+        assert_eq!(sk.len(), 1000);
+        assert!(sk.arena_chunks() < 10);
+        for i in 0..1000 {
+            assert_eq!(sk.get(&i).map(String::as_str), Some(i.to_string().as_str()));
+        }
+    }
+
+    #[test]
+    fn arena_reserves_ahead() {
+        let mut sk = SkipList::with_arena(16);
+        assert_eq!(sk.try_reserve(1000), Ok(()));
+        assert_eq!(sk.arena_chunks(), 1);
+        for i in 0..500 {
+            sk.insert(i, i);
+        }
+        assert_eq!(sk.arena_chunks(), 1);
+        assert_eq!(sk.try_reserve(usize::MAX), Err(AllocError));
+        assert_eq!(sk.try_reserve(isize::MAX as usize / 64), Err(AllocError));
+        assert_eq!(sk.len(), 500);
+        assert_eq!(SkipList::<u32, u32>::new().try_reserve(1000), Ok(()));
+    }
+
+    #[test]
+    fn capacity_is_allocated_up_front() {
+        for _ in 0..20 {
+            let mut sk = SkipList::with_capacity(1000);
+            assert_eq!(sk.arena_chunks(), 1);
+            for i in 0..1000 {
+                sk.insert(i, i.to_string());
+            }
+            assert_eq!(sk.arena_chunks(), 1);
+            sk.reserve(2000);
+            assert_eq!(sk.arena_chunks(), 2);
+        }
+    }
+
+    #[test]
+    fn arena_reuses_removed_nodes() {
+        let mut sk = SkipList::with_arena(64);
+        for round in 0..50 {
+            for i in 0..64 {
+                sk.insert(i, vec![round; 3]);
+            }
+            for i in 0..64 {
+                assert_eq!(sk.remove(&i), Some(vec![round; 3]));
+            }
+        }
+        assert!(sk.is_empty());
+        assert!(sk.arena_chunks() < 10);
+    }
+
+    #[test]
+    fn arena_drops_entries() {
+        let tracker = Rc::new(());
+        {
+            let mut sk = SkipList::with_arena(8);
+            for i in 0..100 {
+                sk.insert(i, Rc::clone(&tracker));
+            }
+            sk.truncate(30);
+            let _ = sk.pop_first_k(5);
+            let mut tail = sk.split_at_index(15);
+            tail.insert(1000, Rc::clone(&tracker));
+            drop(sk);
+            assert_eq!(tail.len(), 11);
+            assert_eq!(Rc::strong_count(&tracker), 12);
+        }
+        assert_eq!(Rc::strong_count(&tracker), 1);
+    }
+
+    #[test]
+    fn arena_list_moves_between_threads() {
+        let mut sk = SkipList::with_arena(64);
+        for i in 0..100 {
+            sk.insert(i, i);
+        }
+        let mut tail = sk.split_at_index(50);
+        let handle = std::thread::spawn(move || {
+            for i in 100..200 {
+                tail.insert(i, i);
+            }
+            tail
+        });
+        for i in 200..300 {
+            sk.insert(i, i);
+        }
+        let tail = handle.join().unwrap();
+        assert_eq!(sk.len() + tail.len(), 300);
+        assert_eq!(tail.get(&150), Some(&150));
+    }
+}
@@ -0,0 +1,139 @@
+use crate::{Range, SkipList};
+use arrow_array::{RecordBatch, RecordBatchReader};
+use arrow_schema::{ArrowError, SchemaRef};
+use std::ops::RangeBounds;
+
+const DEFAULT_BATCH_SIZE: usize = 8192;
+
+pub trait ArrowSchemaMapping<K, V> {
+    fn schema(&self) -> SchemaRef;
+
+    fn to_batch(&self, entries: &[(&K, &V)]) -> Result<RecordBatch, ArrowError>;
+
+    fn batch_size(&self) -> usize {
+        DEFAULT_BATCH_SIZE
+    }
+}
+
+impl<K, V, M: ArrowSchemaMapping<K, V> + ?Sized> ArrowSchemaMapping<K, V> for &M {
+    fn schema(&self) -> SchemaRef {
+        (**self).schema()
+    }
+
+    fn to_batch(&self, entries: &[(&K, &V)]) -> Result<RecordBatch, ArrowError> {
+        (**self).to_batch(entries)
+    }
+
+    fn batch_size(&self) -> usize {
+        (**self).batch_size()
+    }
+}
+
+pub struct RecordBatches<'a, K, V, M> {
+    entries: Range<'a, K, V>,
+    mapping: M,
+    buf: Vec<(&'a K, &'a V)>,
+}
+
+impl<'a, K, V, M: ArrowSchemaMapping<K, V>> Iterator for RecordBatches<'a, K, V, M> {
+    type Item = Result<RecordBatch, ArrowError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.buf.clear();
+        let batch_size = self.mapping.batch_size().max(1);
+        self.buf.extend(self.entries.by_ref().take(batch_size));
+        if self.buf.is_empty() {
+            return None;
+        }
+        Some(self.mapping.to_batch(&self.buf))
+    }
+}
+
+impl<K, V, M: ArrowSchemaMapping<K, V>> RecordBatchReader for RecordBatches<'_, K, V, M> {
+    fn schema(&self) -> SchemaRef {
+        self.mapping.schema()
+    }
+}
+
+impl<K: Ord, V> SkipList<K, V> {
+    pub fn to_record_batches<R, M>(
+        &self,
+        bounds: R,
+        schema_mapping: M,
+    ) -> RecordBatches<'_, K, V, M>
+    where
+        R: RangeBounds<K>,
+        M: ArrowSchemaMapping<K, V>,
+    {
+        RecordBatches {
+            entries: self.range(bounds),
+            buf: Vec::with_capacity(schema_mapping.batch_size().max(1)),
+            mapping: schema_mapping,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ArrowSchemaMapping;
+    use crate::SkipList;
+    use arrow_array::{Array, RecordBatch, RecordBatchReader, StringArray, UInt64Array};
+    use arrow_schema::{ArrowError, DataType, Field, Schema, SchemaRef};
+    use std::sync::Arc;
+
+    struct Mapping;
+
+    impl ArrowSchemaMapping<u64, String> for Mapping {
+        fn schema(&self) -> SchemaRef {
+            Arc::new(Schema::new(vec![
+                Field::new("key", DataType::UInt64, false),
+                Field::new("val", DataType::Utf8, false),
+            ]))
+        }
+
+        fn to_batch(&self, entries: &[(&u64, &String)]) -> Result<RecordBatch, ArrowError> {
+            let keys = UInt64Array::from_iter_values(entries.iter().map(|(k, _)| **k));
+            let vals = StringArray::from_iter_values(entries.iter().map(|(_, v)| v.as_str()));
+            RecordBatch::try_new(self.schema(), vec![Arc::new(keys), Arc::new(vals)])
+        }
+
+        fn batch_size(&self) -> usize {
+            4
+        }
+    }
+
+    #[test]
+    fn record_batches() {
+        let mut sk = SkipList::new();
+        for i in 0..20u64 {
+            sk.insert(i, i.to_string());
+        }
+
+        let reader = sk.to_record_batches(5..15, Mapping);
+        assert_eq!(reader.schema(), Mapping.schema());
+        let batches = reader.collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(
+            batches.iter().map(|b| b.num_rows()).collect::<Vec<_>>(),
+            vec![4, 4, 2]
+        );
+
+        let keys = batches
+            .iter()
+            .flat_map(|b| {
+                let col = b.column(0).as_any().downcast_ref::<UInt64Array>().unwrap();
+                col.values().to_vec()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(keys, (5..15).collect::<Vec<_>>());
+
+        let last = batches[2]
+            .column(1)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(last.value(1), "14");
+        assert_eq!(last.len(), 2);
+
+        assert_eq!(sk.to_record_batches(100.., &Mapping).count(), 0);
+    }
+}
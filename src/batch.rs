@@ -0,0 +1,191 @@
+use crate::{Node, SkipList, MAX_LEVEL};
+use alloc::vec::Vec;
+use core::ptr::NonNull;
+
+// Puts and deletes waiting to be applied together. The last one queued for a
+// key is the one that counts.
+pub struct WriteBatch<K, V> {
+    ops: Vec<(K, Option<V>)>,
+}
+
+impl<K, V> WriteBatch<K, V> {
+    pub fn new() -> Self {
+        Self { ops: Vec::new() }
+    }
+
+    pub fn put(&mut self, key: K, val: V) {
+        self.ops.push((key, Some(val)));
+    }
+
+    pub fn delete(&mut self, key: K) {
+        self.ops.push((key, None));
+    }
+
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.ops.clear();
+    }
+}
+
+impl<K, V> Default for WriteBatch<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord, V> SkipList<K, V> {
+    // Sorts the batch and applies it front to back, each search picking up
+    // from where the previous key's left off, so nearby keys cost a step or
+    // two rather than a search from the head.
+    pub fn apply_batch(&mut self, batch: WriteBatch<K, V>) {
+        let mut ops = batch.ops;
+        ops.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut update: [Option<NonNull<Node<K, V>>>; MAX_LEVEL] = [Some(self.head); MAX_LEVEL];
+        let mut ops = ops.into_iter().peekable();
+        while let Some((key, op)) = ops.next() {
+            if ops.peek().is_some_and(|(next, _)| *next == key) {
+                continue;
+            }
+            unsafe {
+                let found = self
+                    .advance_to(&key, &mut update)
+                    .filter(|node_ptr| node_ptr.as_ref().key == key);
+                match (found, op) {
+                    (Some(mut node_ptr), Some(val)) => {
+                        #[cfg(feature = "zeroize")]
+                        self.wipe_val(node_ptr.as_mut().val_mut());
+                        *node_ptr.as_mut().val_mut() = val;
+                    }
+                    (None, Some(val)) => {
+                        let node_ptr = self.link(key, val, &mut update);
+                        update[..node_ptr.as_ref().height()].fill(Some(node_ptr));
+                    }
+                    (Some(node_ptr), None) => {
+                        self.unlink(node_ptr, &update);
+                    }
+                    (None, None) => {}
+                }
+            }
+        }
+    }
+
+    // Moves update on to key's predecessors, given that it holds those of a
+    // smaller key. Climbs only as high as the first level whose link already
+    // reaches key, then searches down from there.
+    unsafe fn advance_to(
+        &self,
+        key: &K,
+        update: &mut [Option<NonNull<Node<K, V>>>; MAX_LEVEL],
+    ) -> Option<NonNull<Node<K, V>>> {
+        let behind = |update: &[Option<NonNull<Node<K, V>>>; MAX_LEVEL], l: usize| {
+            update[l].unwrap().as_ref().tower[l].is_some_and(|next| next.as_ref().key < *key)
+        };
+        let mut top = 0;
+        while top < self.level && behind(update, top) {
+            top += 1;
+        }
+        if top > 0 {
+            let mut x = update[top - 1].unwrap();
+            for l in (0..top).rev() {
+                while let Some(next) = x.as_ref().tower[l].filter(|next| next.as_ref().key < *key) {
+                    x = next;
+                }
+                update[l] = Some(x);
+            }
+        }
+        update[0].unwrap().as_ref().tower[0]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WriteBatch;
+    use crate::SkipList;
+    use std::cell::Cell;
+    use std::cmp::Ordering;
+    use std::collections::BTreeMap;
+
+    thread_local! {
+        static COMPARED: Cell<usize> = const { Cell::new(0) };
+    }
+
+    #[derive(PartialEq, Eq, Debug)]
+    struct Counted(u32);
+
+    impl PartialOrd for Counted {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Counted {
+        fn cmp(&self, other: &Self) -> Ordering {
+            COMPARED.with(|c| c.set(c.get() + 1));
+            self.0.cmp(&other.0)
+        }
+    }
+
+    #[test]
+    fn batch_matches_one_at_a_time() {
+        let mut sk = SkipList::new();
+        let mut model = BTreeMap::new();
+        for i in (0..2000).step_by(3) {
+            sk.insert(i, i);
+            model.insert(i, i);
+        }
+        let mut batch = WriteBatch::new();
+        for i in (0..2100).rev() {
+            match i % 5 {
+                0 | 1 => {
+                    batch.put(i, i * 10);
+                    model.insert(i, i * 10);
+                }
+                2 => {
+                    batch.delete(i);
+                    model.remove(&i);
+                }
+                _ => {}
+            }
+        }
+        // Later ops on a key win.
+        batch.delete(5);
+        batch.put(5, 55);
+        model.insert(5, 55);
+        batch.put(6, 66);
+        batch.delete(6);
+        model.remove(&6);
+        sk.apply_batch(batch);
+        assert!(sk.iter().eq(model.iter()));
+        for (i, k) in model.keys().enumerate() {
+            assert_eq!(sk.rank(k), i);
+        }
+    }
+
+    #[test]
+    fn sorted_batch_shares_the_search() {
+        let mut sk = SkipList::new();
+        for i in 0..10_000 {
+            sk.insert(Counted(i * 2), ());
+        }
+        let mut batch = WriteBatch::new();
+        for i in 0..10_000 {
+            batch.put(Counted(i * 2 + 1), ());
+        }
+        COMPARED.with(|c| c.set(0));
+        sk.apply_batch(batch);
+        let compared = COMPARED.with(Cell::get);
+        assert_eq!(sk.len(), 20_000);
+        // The batch is already sorted, so sorting it takes about one
+        // comparison per key. Searching from the head would add some 2 log n
+        // more for each. ord-checks compares each new key with its neighbours.
+        let per_key = if cfg!(feature = "ord-checks") { 16 } else { 10 };
+        assert!(compared < 10_000 * per_key, "{}", compared);
+    }
+}
@@ -0,0 +1,436 @@
+use crate::storage::Nodes;
+use crate::sync::Lock;
+use crate::{
+    hash_lvl, Comparator, ComparatorSkipList, Levels, NodeLayout, SkipList, Storage, MAX_LEVEL,
+};
+use alloc::alloc::GlobalAlloc;
+use alloc::sync::Arc;
+use core::hash::Hash;
+use core::mem;
+use rand::RngCore;
+
+// Tunes the tower shape. A lower probability gives shorter towers and less
+// memory per node at the cost of longer runs on each level; max_level bounds
+// the head tower, and about (1/p)^max_level entries fill it.
+//
+// Where tower heights come from (L) and where nodes live (M) are each one
+// choice, so they are type parameters: once one is picked the builder no
+// longer offers the others, and build only asks of K what the choices need,
+// e.g. Hash for hashed levels. A comparator is handed to build_by, which
+// builds a ComparatorSkipList and asks for no Ord at all.
+//
+// Spans and back-links aren't among the choices: every node carries them,
+// since rank, get_by_index and next_back are on every list. Making them
+// optional is separate work, listed in the README.
+#[derive(Clone, Debug)]
+pub struct SkipListBuilder<L = RandomLevels, M = GlobalNodes> {
+    levels: Levels,
+    layout: NodeLayout,
+    box_over: Option<usize>,
+    level_source: L,
+    node_source: M,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RandomLevels;
+
+#[derive(Clone, Debug)]
+pub struct RngLevels<R>(R);
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HashedLevels;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GlobalNodes;
+
+#[derive(Clone, Copy, Debug)]
+pub struct ArenaNodes(usize);
+
+#[derive(Clone, Debug)]
+pub struct AllocNodes<A>(pub(crate) A);
+
+#[derive(Clone, Debug)]
+pub struct StoredNodes<S>(S);
+
+// The traits that do the work live in a private module, so the choices
+// above are the only ones there are.
+mod sealed {
+    use crate::SkipList;
+
+    pub trait ApplyLevels<K> {
+        fn apply<V>(self, sk: &mut SkipList<K, V>);
+    }
+
+    // Where the built list gets its nodes: an allocator other than the
+    // global one, an arena of some capacity, other storage, or none of them.
+    pub trait NodesFrom {
+        fn apply<K, V>(self, sk: &mut SkipList<K, V>);
+    }
+}
+
+pub trait LevelSource<K>: sealed::ApplyLevels<K> {}
+
+pub trait NodeSource: sealed::NodesFrom {}
+
+impl<K, T: sealed::ApplyLevels<K>> LevelSource<K> for T {}
+
+impl<T: sealed::NodesFrom> NodeSource for T {}
+
+impl<K> sealed::ApplyLevels<K> for RandomLevels {
+    fn apply<V>(self, _: &mut SkipList<K, V>) {}
+}
+
+impl<K, R: RngCore + Send + 'static> sealed::ApplyLevels<K> for RngLevels<R> {
+    fn apply<V>(self, sk: &mut SkipList<K, V>) {
+        sk.rng = Some(Arc::new(Lock::new(self.0)));
+    }
+}
+
+impl<K: Hash> sealed::ApplyLevels<K> for HashedLevels {
+    fn apply<V>(self, sk: &mut SkipList<K, V>) {
+        sk.levels.adaptive = false;
+        sk.level_of = Some(hash_lvl::<K>);
+    }
+}
+
+impl sealed::NodesFrom for GlobalNodes {
+    fn apply<K, V>(self, _: &mut SkipList<K, V>) {}
+}
+
+impl sealed::NodesFrom for ArenaNodes {
+    fn apply<K, V>(self, sk: &mut SkipList<K, V>) {
+        sk.use_arena(self.0);
+    }
+}
+
+impl<A: GlobalAlloc + Send + Sync + 'static> sealed::NodesFrom for AllocNodes<A> {
+    fn apply<K, V>(self, sk: &mut SkipList<K, V>) {
+        sk.set_nodes(Nodes::Custom(Arc::new(self)));
+    }
+}
+
+impl<S: Storage + 'static> sealed::NodesFrom for StoredNodes<S> {
+    fn apply<K, V>(self, sk: &mut SkipList<K, V>) {
+        sk.set_nodes(Nodes::Custom(Arc::new(self.0)));
+    }
+}
+
+impl SkipListBuilder {
+    pub fn new() -> Self {
+        Self {
+            levels: Levels::default(),
+            layout: NodeLayout::default(),
+            box_over: None,
+            level_source: RandomLevels,
+            node_source: GlobalNodes,
+        }
+    }
+}
+
+impl<L, M> SkipListBuilder<L, M> {
+    pub fn max_level(mut self, n: usize) -> Self {
+        assert!(
+            (1..=MAX_LEVEL).contains(&n),
+            "max_level must be between 1 and {}, got {}",
+            MAX_LEVEL,
+            n
+        );
+        self.levels.max = n;
+        self
+    }
+
+    pub fn probability(mut self, p: f64) -> Self {
+        assert!(
+            p > 0.0 && p < 1.0,
+            "probability must be in (0, 1), got {}",
+            p
+        );
+        self.levels.climb = ((p * (1u64 << 32) as f64) as u64).clamp(1, u32::MAX as u64) as u32;
+        self
+    }
+
+    pub fn node_layout(mut self, layout: NodeLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    // Values bigger than bytes are kept in a box of their own, with only a
+    // pointer in the node, so a bulky V doesn't spread the nodes a search
+    // walks over more cache lines. Each value then costs an allocation from
+    // the global allocator, whatever the nodes come from.
+    pub fn box_values_over(mut self, bytes: usize) -> Self {
+        self.box_over = Some(bytes);
+        self
+    }
+
+    pub fn build<K: Ord, V>(self) -> SkipList<K, V>
+    where
+        L: LevelSource<K>,
+        M: NodeSource,
+    {
+        self.build_list()
+    }
+
+    // Keys ordered by cmp instead of their own Ord, which K then needn't
+    // have, as with SkipList::with_comparator.
+    pub fn build_by<K, V, C: Comparator<K>>(self, cmp: C) -> ComparatorSkipList<K, V, C>
+    where
+        L: LevelSource<K>,
+        M: NodeSource,
+    {
+        ComparatorSkipList::from_list(self.build_list(), cmp)
+    }
+
+    fn build_list<K, V>(self) -> SkipList<K, V>
+    where
+        L: LevelSource<K>,
+        M: NodeSource,
+    {
+        let mut sk = SkipList::empty_in(self.layout, self.levels, Nodes::Global);
+        sk.boxed = self
+            .box_over
+            .is_some_and(|bytes| mem::size_of::<V>() > bytes);
+        sealed::NodesFrom::apply(self.node_source, &mut sk);
+        sealed::ApplyLevels::apply(self.level_source, &mut sk);
+        sk
+    }
+
+    fn with_levels<N>(self, level_source: N) -> SkipListBuilder<N, M> {
+        SkipListBuilder {
+            levels: self.levels,
+            layout: self.layout,
+            box_over: self.box_over,
+            level_source,
+            node_source: self.node_source,
+        }
+    }
+
+    fn with_nodes<N>(self, node_source: N) -> SkipListBuilder<L, N> {
+        SkipListBuilder {
+            levels: self.levels,
+            layout: self.layout,
+            box_over: self.box_over,
+            level_source: self.level_source,
+            node_source,
+        }
+    }
+}
+
+impl<M> SkipListBuilder<RandomLevels, M> {
+    // On by default; off gives every tower the classic chance of reaching
+    // max_level however small the list is.
+    pub fn adaptive_levels(mut self, on: bool) -> Self {
+        self.levels.adaptive = on;
+        self
+    }
+
+    // Heights drawn from rng, and shared with lists split off the built one.
+    pub fn rng<R: RngCore + Send + 'static>(self, rng: R) -> SkipListBuilder<RngLevels<R>, M> {
+        self.with_levels(RngLevels(rng))
+    }
+
+    // Heights derived from each key's hash, so equal keys get equal towers
+    // in every list; there's no size-dependent cap to choose then.
+    pub fn hashed_levels(self) -> SkipListBuilder<HashedLevels, M> {
+        self.with_levels(HashedLevels)
+    }
+}
+
+impl<R, M> SkipListBuilder<RngLevels<R>, M> {
+    pub fn adaptive_levels(mut self, on: bool) -> Self {
+        self.levels.adaptive = on;
+        self
+    }
+}
+
+impl<L> SkipListBuilder<L, GlobalNodes> {
+    // Nodes from chunks sized for about capacity entries, as with_arena.
+    pub fn arena(self, capacity: usize) -> SkipListBuilder<L, ArenaNodes> {
+        self.with_nodes(ArenaNodes(capacity))
+    }
+
+    pub fn allocator<A: GlobalAlloc + Send + Sync + 'static>(
+        self,
+        alloc: A,
+    ) -> SkipListBuilder<L, AllocNodes<A>> {
+        self.with_nodes(AllocNodes(alloc))
+    }
+
+    // Nodes from storage of the caller's own, e.g. a pool or a region set
+    // aside for them; see Storage.
+    pub fn storage<S: Storage + 'static>(self, storage: S) -> SkipListBuilder<L, StoredNodes<S>> {
+        self.with_nodes(StoredNodes(storage))
+    }
+}
+
+impl Default for SkipListBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SkipListBuilder;
+    use crate::storage::Nodes;
+    use crate::{Node, SkipList};
+    use rand::prelude::*;
+    use std::alloc::System;
+    use std::rc::Rc;
+
+    #[test]
+    fn max_level_caps_towers() {
+        let mut sk: SkipList<u32, u32> = SkipListBuilder::new().max_level(3).build();
+        for i in 0..2000 {
+            sk.insert(i, i);
+        }
+        assert!(sk.level <= 3);
+        assert_eq!(unsafe { sk.head.as_ref().height() }, 3);
+        assert_eq!(sk.get_by_index(1234), Some((&1234, &1234)));
+        let tail = sk.split_at_index(1000);
+        assert_eq!(unsafe { tail.head.as_ref().height() }, 3);
+    }
+
+    #[test]
+    fn tall_towers() {
+        let mut sk: SkipList<u32, ()> = SkipListBuilder::new()
+            .max_level(32)
+            .probability(0.9)
+            .adaptive_levels(false)
+            .build();
+        for i in 0..500 {
+            sk.insert(i, ());
+        }
+        assert!(sk.level > 20);
+        assert!(sk.level <= 32);
+        assert!((0..500).all(|i| sk.contains_key(&i)));
+    }
+
+    #[test]
+    fn adaptive_levels_follow_the_size() {
+        let mut sk: SkipList<u32, ()> = SkipListBuilder::new().probability(0.9).build();
+        for i in 0..7 {
+            sk.insert(i, ());
+            assert!(sk.level <= 3, "{} levels for {} entries", sk.level, i + 1);
+        }
+        for i in 7..1000 {
+            sk.insert(i, ());
+        }
+        assert!(sk.level > 3 && sk.level <= 10);
+        assert!(sk.iter().map(|(k, _)| *k).eq(0..1000));
+    }
+
+    #[test]
+    fn lower_probability_flattens() {
+        let mut sk: SkipList<u32, ()> = SkipListBuilder::new().probability(0.0625).build();
+        for i in 0..4096 {
+            sk.insert(i, ());
+        }
+        assert!(sk.level <= 8);
+        assert_eq!(sk.rank(&4000), 4000);
+    }
+
+    #[test]
+    fn level_and_node_sources_combine() {
+        let build = || {
+            let mut sk: SkipList<u32, u32> = SkipListBuilder::new()
+                .max_level(12)
+                .rng(StdRng::seed_from_u64(3))
+                .arena(256)
+                .build();
+            for i in 0..256 {
+                sk.insert(i, i);
+            }
+            sk
+        };
+        let (a, b) = (build(), build());
+        assert!(matches!(a.nodes, Nodes::Arena(_)) && a.rng.is_some());
+        assert_eq!(a.level, b.level);
+        assert_eq!(a.stats(), b.stats());
+
+        let fill = |keys: &mut dyn Iterator<Item = u32>| {
+            let mut sk: SkipList<u32, ()> = SkipListBuilder::new()
+                .hashed_levels()
+                .allocator(System)
+                .build();
+            keys.for_each(|k| sk.insert(k, ()));
+            sk
+        };
+        let up = fill(&mut (0..500));
+        let down = fill(&mut (0..500).rev());
+        assert!(matches!(up.nodes, Nodes::Custom(_)) && !up.levels.adaptive);
+        assert_eq!(up.stats(), down.stats());
+        up.check_invariants();
+    }
+
+    #[test]
+    fn comparator_takes_the_other_choices() {
+        struct Score(f64);
+        let by_score = |a: &Score, b: &Score| a.0.total_cmp(&b.0);
+        let mut sk = SkipListBuilder::new()
+            .max_level(8)
+            .rng(StdRng::seed_from_u64(5))
+            .arena(64)
+            .build_by::<_, u32, _>(by_score);
+        for i in [3, 1, 2] {
+            sk.insert(Score(i as f64 / 2.0), i);
+        }
+        assert!(sk.iter().map(|(_, v)| *v).eq([1, 2, 3]));
+        assert!(matches!(sk.list.nodes, Nodes::Arena(_)) && sk.list.rng.is_some());
+        assert_eq!(sk.list.levels.max, 8);
+        sk.clear();
+        assert!(sk.is_empty() && matches!(sk.list.nodes, Nodes::Arena(_)));
+    }
+
+    #[test]
+    fn boxed_values_keep_nodes_small() {
+        type Bulky = (Rc<()>, [u8; 256]);
+        let small: SkipList<u32, u64> = SkipListBuilder::new().box_values_over(64).build();
+        assert!(!small.boxed);
+
+        let rc = Rc::new(());
+        let bulky = |i: u32| -> Bulky { (rc.clone(), [i as u8; 256]) };
+        for arena in [false, true] {
+            let builder = SkipListBuilder::new().box_values_over(64);
+            let mut sk: SkipList<u32, Bulky> = if arena {
+                builder.arena(64).build()
+            } else {
+                builder.build()
+            };
+            assert!(sk.boxed);
+            let ptr = std::mem::size_of::<usize>();
+            let layout = Node::<u32, Bulky>::layout(1, sk.shape());
+            assert_eq!(layout.size(), 6 * ptr);
+            for i in 0..200 {
+                sk.insert(i, bulky(i));
+            }
+            assert_eq!(sk.get(&7).map(|v| v.1[0]), Some(7));
+            sk.get_mut(&7).unwrap().1 = [70; 256];
+            assert_eq!(sk.get(&7).unwrap().1[255], 70);
+            sk.insert(8, bulky(80));
+            assert_eq!(sk.remove(&8).map(|v| v.1[0]), Some(80));
+            assert!(sk.swap_values(&1, &2));
+            assert_eq!((sk.get(&1).unwrap().1[0], sk.get(&2).unwrap().1[0]), (2, 1));
+            let copy = sk.clone();
+            assert!(copy.iter().eq(sk.iter()));
+            assert_eq!(copy.clone().drain().count(), 199);
+            assert!(sk.memory_usage() > 199 * 256);
+
+            sk.retain(|k, _| *k >= 100);
+            sk.insert(500, bulky(5));
+            for (k, v) in sk.iter_mut() {
+                v.1[1] = *k as u8;
+            }
+            assert_eq!(sk.get(&500).unwrap().1[..2], [5, 244]);
+            sk.check_invariants();
+            drop((sk, copy));
+            assert_eq!(Rc::strong_count(&rc), 1);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "max_level must be between 1 and 32")]
+    fn max_level_out_of_bounds() {
+        let _ = SkipListBuilder::new().max_level(33);
+    }
+}
@@ -0,0 +1,827 @@
+use crate::{Node, SkipList, MAX_LEVEL};
+use alloc::vec::Vec;
+use core::error::Error;
+use core::fmt;
+use core::ops::RangeBounds;
+use core::ptr::NonNull;
+
+// Decides what a bulk load keeps when a key shows up more than once. `old` is
+// the value already in the list (or loaded earlier from the same batch) and
+// `new` the one arriving after it.
+pub trait DuplicatePolicy<K, V> {
+    fn merge(&mut self, key: &K, old: &mut V, new: V);
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LastWins;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FirstWins;
+
+impl<K, V> DuplicatePolicy<K, V> for LastWins {
+    fn merge(&mut self, _key: &K, old: &mut V, new: V) {
+        *old = new;
+    }
+}
+
+impl<K, V> DuplicatePolicy<K, V> for FirstWins {
+    fn merge(&mut self, _key: &K, _old: &mut V, _new: V) {}
+}
+
+impl<K, V, F: FnMut(&K, &mut V, V)> DuplicatePolicy<K, V> for F {
+    fn merge(&mut self, key: &K, old: &mut V, new: V) {
+        self(key, old, new)
+    }
+}
+
+// Decides, as a rebalance or snapshot goes over the entries, which ones to
+// carry on with, e.g. to leave expired or superseded entries behind without
+// a pass of their own.
+pub trait CompactionFilter<K, V> {
+    fn filter(&mut self, key: &K, val: &V) -> Decision<V>;
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Decision<V> {
+    Keep,
+    Drop,
+    Replace(V),
+}
+
+impl<K, V, F: FnMut(&K, &V) -> Decision<V>> CompactionFilter<K, V> for F {
+    fn filter(&mut self, key: &K, val: &V) -> Decision<V> {
+        self(key, val)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DuplicateKey<K>(pub K);
+
+impl<K: fmt::Debug> fmt::Display for DuplicateKey<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "duplicate key {:?}", self.0)
+    }
+}
+
+impl<K: fmt::Debug> Error for DuplicateKey<K> {}
+
+impl<K: Ord, V> SkipList<K, V> {
+    pub fn from_unsorted_vec(entries: Vec<(K, V)>) -> Self {
+        Self::from_unsorted_vec_with(entries, LastWins)
+    }
+
+    pub fn from_unsorted_vec_with<P: DuplicatePolicy<K, V>>(
+        entries: Vec<(K, V)>,
+        policy: P,
+    ) -> Self {
+        let mut sk = Self::new();
+        sk.bulk_insert_unsorted_with(entries, policy);
+        sk
+    }
+
+    pub fn try_from_unsorted_vec(entries: Vec<(K, V)>) -> Result<Self, DuplicateKey<K>> {
+        let mut sk = Self::new();
+        sk.try_bulk_insert_unsorted(entries)?;
+        Ok(sk)
+    }
+
+    pub fn bulk_insert_unsorted(&mut self, entries: Vec<(K, V)>) {
+        self.bulk_insert_unsorted_with(entries, LastWins);
+    }
+
+    pub fn bulk_insert_unsorted_with<P: DuplicatePolicy<K, V>>(
+        &mut self,
+        mut entries: Vec<(K, V)>,
+        mut policy: P,
+    ) {
+        // Stable, so duplicates reach the policy in the order they were given.
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        self.merge_sorted(entries, &mut policy);
+    }
+
+    // Fails without changing the list if a key repeats within the batch or is
+    // already present.
+    pub fn try_bulk_insert_unsorted(
+        &mut self,
+        mut entries: Vec<(K, V)>,
+    ) -> Result<(), DuplicateKey<K>> {
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut dup = entries.windows(2).position(|w| w[0].0 == w[1].0);
+        if dup.is_none() && !self.is_empty() {
+            let mut existing = self.iter().peekable();
+            dup = entries.iter().position(|(key, _)| {
+                while existing.next_if(|(k, _)| *k < key).is_some() {}
+                matches!(existing.peek(), Some((k, _)) if *k == key)
+            });
+        }
+        if let Some(i) = dup {
+            return Err(DuplicateKey(entries.swap_remove(i).0));
+        }
+        self.merge_sorted(entries, &mut LastWins);
+        Ok(())
+    }
+
+    // collect and extend, but with a say in what happens to repeated keys.
+    // Both buffer the iterator and go through the sorted batch path above.
+    pub fn from_iter_with<I, P>(entries: I, policy: P) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        P: DuplicatePolicy<K, V>,
+    {
+        Self::from_unsorted_vec_with(entries.into_iter().collect(), policy)
+    }
+
+    pub fn try_from_iter<I: IntoIterator<Item = (K, V)>>(
+        entries: I,
+    ) -> Result<Self, DuplicateKey<K>> {
+        Self::try_from_unsorted_vec(entries.into_iter().collect())
+    }
+
+    pub fn extend_with<I, P>(&mut self, entries: I, policy: P)
+    where
+        I: IntoIterator<Item = (K, V)>,
+        P: DuplicatePolicy<K, V>,
+    {
+        self.bulk_insert_unsorted_with(entries.into_iter().collect(), policy);
+    }
+
+    pub fn try_extend<I: IntoIterator<Item = (K, V)>>(
+        &mut self,
+        entries: I,
+    ) -> Result<(), DuplicateKey<K>> {
+        self.try_bulk_insert_unsorted(entries.into_iter().collect())
+    }
+
+    // Builds the list in one pass over entries already sorted by key, linking
+    // each node after the last one on its levels. Heights are fixed instead of
+    // drawn: the i-th entry (from 1) gets one level plus the trailing zeros of
+    // i, the shape random heights average out to. A repeated key keeps its last
+    // value; a key that sorts before the one ahead of it panics.
+    pub fn from_sorted_iter<I: IntoIterator<Item = (K, V)>>(entries: I) -> Self {
+        let mut sk = Self::new();
+        let mut tails = [(sk.head, 0); MAX_LEVEL];
+        for (key, val) in entries {
+            unsafe {
+                if tails[0].0 != sk.head {
+                    let prev = &mut *tails[0].0.as_ptr();
+                    if key == prev.key {
+                        *prev.val_mut() = val;
+                        continue;
+                    }
+                    assert!(prev.key < key, "from_sorted_iter input is not sorted");
+                }
+                let x = sk.alloc_node(key, val, sk.sorted_height());
+                sk.push_back_node(x, &mut tails);
+            }
+        }
+        sk
+    }
+
+    // Joins lists whose key ranges follow one another, in order, by linking
+    // each onto the tail of the one before on every level, so the cost is the
+    // height of each list rather than its entries. This is the second half of
+    // a parallel load: build each sorted shard on its own thread with
+    // from_sorted_iter, then concatenate. Panics if a list doesn't start
+    // above where the one before it ends.
+    pub fn concat_sorted<I: IntoIterator<Item = SkipList<K, V>>>(lists: I) -> Self {
+        let mut lists = lists.into_iter();
+        let Some(mut sk) = lists.next() else {
+            return Self::new();
+        };
+        for mut next in lists {
+            if let (Some((last, _)), Some((first, _))) =
+                (sk.last_key_value(), next.first_key_value())
+            {
+                assert!(last < first, "concat_sorted lists overlap");
+            }
+            sk.append(&mut next);
+        }
+        sk
+    }
+
+    // Builds each shard on a thread of its own and concatenates the results.
+    // Each shard must be sorted, and every key in one below every key in the
+    // next; a repeated key within a shard keeps its last value.
+    #[cfg(feature = "std")]
+    pub fn from_sorted_shards(shards: Vec<Vec<(K, V)>>) -> Self
+    where
+        K: Send,
+        V: Send,
+    {
+        let lists: Vec<Self> = std::thread::scope(|scope| {
+            let built: Vec<_> = shards
+                .into_iter()
+                .map(|shard| scope.spawn(|| Self::from_sorted_iter(shard)))
+                .collect();
+            built
+                .into_iter()
+                .map(|handle| {
+                    handle
+                        .join()
+                        .unwrap_or_else(|e| std::panic::resume_unwind(e))
+                })
+                .collect()
+        });
+        Self::concat_sorted(lists)
+    }
+
+    // Relinks the list front to back with heights like from_sorted_iter's,
+    // for when removals have left the towers lopsided. Each level then holds
+    // every (1/p)-th node of the one below, every second by default, which is
+    // as short as searches get for the size. Nodes whose height doesn't
+    // change are kept; the rest are moved into nodes of the new height.
+    //
+    // Lists whose heights come from their keys, like hashed ones, get those
+    // heights back instead, so they keep the shape any list of the same keys
+    // has. That only changes anything when p or max_level has.
+    pub fn rebalance(&mut self) {
+        let mut tails = [(self.head, 0); MAX_LEVEL];
+        unsafe {
+            let mut x = self.head.as_ref().tower[0];
+            for l in 0..self.level {
+                self.head.as_mut().tower[l] = None;
+            }
+            self.size = 0;
+            self.level = 1;
+            while let Some(mut node_ptr) = x {
+                x = node_ptr.as_ref().tower[0];
+                let height = match (&self.rng, self.level_of) {
+                    (None, Some(level_of)) => level_of(&node_ptr.as_ref().key, self.levels),
+                    _ => self.sorted_height(),
+                };
+                if node_ptr.as_ref().height() != height {
+                    let (key, val) = self.take_entry(node_ptr);
+                    node_ptr = self.alloc_node(key, val, height);
+                }
+                // Reused nodes, from here or the free list, keep their links.
+                for l in 0..height {
+                    node_ptr.as_mut().tower[l] = None;
+                }
+                self.push_back_node(node_ptr, &mut tails);
+            }
+        }
+    }
+
+    // rebalance, with p picked for how searches use the list rather than
+    // kept as configured, and then used for new towers too. Returns the p it
+    // settled on.
+    //
+    // Each b = 1/p from 2 to 8 has a predicted search cost, (b + 1) / 2
+    // comparisons on each of log_b(reach) levels; the cheapest wins, and
+    // within 5% of it the largest, which has the fewest links per node. With
+    // search-stats the reach comes from the searches recorded since the last
+    // reset, which it then starts over, and a list nobody has searched keeps
+    // its p. Without it, searches are taken to range over the whole list.
+    pub fn rebalance_tuned(&mut self) -> f64 {
+        #[cfg(feature = "search-stats")]
+        let reach = self.searched_reach();
+        #[cfg(not(feature = "search-stats"))]
+        let reach = Some(self.size);
+        if let Some(reach) = reach.filter(|_| self.size > 1) {
+            let reach = reach.clamp(2, self.size);
+            // levels(b, n) is the least k with b^k >= n, and cost is twice
+            // the predicted comparisons, to keep it whole.
+            let levels = |b: usize, n: usize| {
+                let (mut k, mut span) = (0, 1usize);
+                while span < n {
+                    span = span.saturating_mul(b);
+                    k += 1;
+                }
+                k
+            };
+            let cost = |b: usize| (b + 1) * levels(b, reach);
+            let fits = |b: &usize| levels(*b, self.size) <= self.levels.max;
+            let best = (2..=8).filter(fits).map(cost).min().unwrap_or(usize::MAX);
+            if let Some(b) = (2..=8)
+                .filter(fits)
+                .rev()
+                .find(|&b| cost(b) * 100 <= best * 105)
+            {
+                self.levels.climb = ((1u64 << 32) / b as u64) as u32;
+            }
+        }
+        self.rebalance();
+        #[cfg(feature = "search-stats")]
+        self.reset_search_stats();
+        self.levels.climb as f64 / (1u64 << 32) as f64
+    }
+
+    // rebalance, minus the entries filter drops and with the replacements it
+    // hands back. Filtering goes through retain, so a filter that panics
+    // leaves the list whole, just not rebalanced.
+    pub fn rebalance_filtered<F: CompactionFilter<K, V>>(&mut self, mut filter: F) {
+        self.retain(|key, val| match filter.filter(key, val) {
+            Decision::Keep => true,
+            Decision::Drop => false,
+            Decision::Replace(new) => {
+                *val = new;
+                true
+            }
+        });
+        self.rebalance();
+    }
+
+    // One level, plus one for each time the fanout divides the next node's
+    // rank. With the default p of 1/2 that's the trailing zeros of the rank.
+    fn sorted_height(&self) -> usize {
+        let fanout = self.levels.fanout();
+        let (mut rank, mut height) = (self.size + 1, 1);
+        while height < self.levels.max && rank % fanout == 0 {
+            rank /= fanout;
+            height += 1;
+        }
+        height
+    }
+
+    pub fn clone_range<R: RangeBounds<K>>(&self, range: R) -> Self
+    where
+        K: Clone,
+        V: Clone,
+    {
+        let entries = self
+            .range(range)
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        let mut sk = self.empty_like();
+        sk.merge_sorted(entries, &mut LastWins);
+        sk
+    }
+
+    // Moves every entry of other into self, other's values winning on equal
+    // keys, and leaves other empty. When one list's keys all sort before the
+    // other's, the towers are spliced end to end in O(log n); otherwise the
+    // entries are merged in one forward pass.
+    pub fn append(&mut self, other: &mut SkipList<K, V>) {
+        if other.size == 0 {
+            return;
+        }
+        // Nodes can only change lists if both free them the same way and
+        // self's head is tall enough for them.
+        let same_nodes = self.shape() == other.shape()
+            && other.levels.max <= self.levels.max
+            && self.nodes.same(&other.nodes);
+        if same_nodes {
+            let (Some((self_first, _)), Some((self_last, _))) =
+                (self.first_key_value(), self.last_key_value())
+            else {
+                unsafe { self.splice_list(other, false) };
+                return;
+            };
+            let (other_first, _) = other.first_key_value().unwrap();
+            let (other_last, _) = other.last_key_value().unwrap();
+            if self_last < other_first {
+                unsafe { self.splice_list(other, false) };
+                return;
+            }
+            if other_last < self_first {
+                unsafe { self.splice_list(other, true) };
+                return;
+            }
+        }
+        let entries = other.pop_first_k(other.size);
+        self.merge_sorted(entries, &mut LastWins);
+    }
+
+    // Links the whole of back after the whole of front, where front and back
+    // are self and other in that order, or the reverse when other_first. The
+    // result ends up in self and other is left empty.
+    unsafe fn splice_list(&mut self, other: &mut SkipList<K, V>, other_first: bool) {
+        self.generation += 1;
+        other.generation += 1;
+        let (front, back) = if other_first {
+            (&mut *other, &mut *self)
+        } else {
+            (&mut *self, &mut *other)
+        };
+        let mut update: [Option<NonNull<Node<K, V>>>; MAX_LEVEL] = [None; MAX_LEVEL];
+        let ranks = front.find_index_predecessors(front.size, &mut update);
+        let level = front.level.max(back.level);
+        update[front.level..level].fill(Some(front.head));
+        // tower[l] and span(l) for the head of the joined list.
+        let mut joined = [(None, 0); MAX_LEVEL];
+        for (l, slot) in joined.iter_mut().enumerate().take(level) {
+            let mut prev = update[l].unwrap();
+            let (next, back_span) = if l < back.level {
+                (back.head.as_ref().tower[l], back.head.as_ref().span(l))
+            } else {
+                (None, 0)
+            };
+            let span = back_span + front.size - ranks[l];
+            if prev == front.head {
+                *slot = (next, span);
+            } else {
+                *slot = (front.head.as_ref().tower[l], front.head.as_ref().span(l));
+                prev.as_mut().tower[l] = next;
+                if next.is_some() {
+                    prev.as_mut().set_span(l, span);
+                }
+            }
+        }
+        if let Some(mut first) = back.head.as_ref().tower[0] {
+            first.as_mut().prev = update[0].filter(|prev| *prev != front.head);
+        }
+        let size = front.size + back.size;
+        for (l, (next, span)) in joined.into_iter().enumerate().take(level) {
+            self.head.as_mut().tower[l] = next;
+            self.head.as_mut().set_span(l, span);
+        }
+        for l in 0..other.level {
+            other.head.as_mut().tower[l] = None;
+        }
+        self.size = size;
+        self.level = level;
+        other.size = 0;
+        other.level = 1;
+    }
+
+    // Inserts entries sorted by key in one forward pass. update[l] is kept as a
+    // finger on the predecessor of the previous key, so each search resumes
+    // where the last one stopped instead of at the head.
+    fn merge_sorted<P: DuplicatePolicy<K, V>>(&mut self, entries: Vec<(K, V)>, policy: &mut P) {
+        let mut update: [Option<NonNull<Node<K, V>>>; MAX_LEVEL] = [Some(self.head); MAX_LEVEL];
+        for (key, val) in entries {
+            unsafe {
+                let mut x = self.head;
+                for l in (0..self.level).rev() {
+                    let finger = update[l].unwrap();
+                    if finger != self.head
+                        && (x == self.head || finger.as_ref().key > x.as_ref().key)
+                    {
+                        x = finger;
+                    }
+                    while let Some(node_ptr) = x.as_ref().tower[l] {
+                        if node_ptr.as_ref().key < key {
+                            x = node_ptr;
+                        } else {
+                            break;
+                        }
+                    }
+                    update[l] = Some(x);
+                }
+                if let Some(mut node_ptr) = x.as_ref().tower[0] {
+                    if node_ptr.as_ref().key == key {
+                        policy.merge(&key, node_ptr.as_mut().val_mut(), val);
+                        continue;
+                    }
+                }
+            }
+            self.link(key, val, &mut update);
+        }
+    }
+}
+
+impl<K: Ord, V> FromIterator<(K, V)> for SkipList<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(entries: I) -> Self {
+        Self::from_unsorted_vec(entries.into_iter().collect())
+    }
+}
+
+impl<K: Ord, V> Extend<(K, V)> for SkipList<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, entries: I) {
+        for (key, val) in entries {
+            self.insert(key, val);
+        }
+    }
+}
+
+impl<'a, K: Ord + Copy, V: Copy> Extend<(&'a K, &'a V)> for SkipList<K, V> {
+    fn extend<I: IntoIterator<Item = (&'a K, &'a V)>>(&mut self, entries: I) {
+        self.extend(entries.into_iter().map(|(k, v)| (*k, *v)));
+    }
+}
+
+impl<K: Ord, V, const N: usize> From<[(K, V); N]> for SkipList<K, V> {
+    fn from(entries: [(K, V); N]) -> Self {
+        Self::from_unsorted_vec(entries.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Decision, DuplicateKey, FirstWins};
+    use crate::SkipList;
+    use rand::prelude::*;
+
+    fn entries<K: Ord + Clone, V: Clone>(sk: &SkipList<K, V>) -> Vec<(K, V)> {
+        sk.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
+    }
+
+    #[test]
+    fn shards_are_stitched_in_order() {
+        let lists =
+            (0..4).map(|s| SkipList::from_sorted_iter((s * 250..(s + 1) * 250).map(|i| (i, i))));
+        let sk = SkipList::concat_sorted(lists);
+        sk.check_invariants();
+        assert!(sk.iter().map(|(k, _)| *k).eq(0..1000));
+        assert_eq!(sk.get_by_index(777), Some((&777, &777)));
+        assert!(SkipList::<u32, ()>::concat_sorted([]).is_empty());
+
+        #[cfg(feature = "std")]
+        {
+            let shards: Vec<Vec<_>> = (0..8)
+                .map(|s| (s * 1000..(s + 1) * 1000).map(|i| (i, ())).collect())
+                .collect();
+            let mut sk = SkipList::from_sorted_shards(shards);
+            sk.check_invariants();
+            assert_eq!(sk.len(), 8000);
+            assert_eq!(sk.rank(&4321), 4321);
+            sk.insert(8000, ());
+            sk.remove(&999);
+            sk.check_invariants();
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "concat_sorted lists overlap")]
+    fn overlapping_shards_panic() {
+        let a = SkipList::from_sorted_iter([(1, ()), (5, ())]);
+        let b = SkipList::from_sorted_iter([(5, ()), (9, ())]);
+        SkipList::concat_sorted([a, b]);
+    }
+
+    #[test]
+    fn from_unsorted_vec() {
+        let mut keys: Vec<u32> = (0..10_000).collect();
+        keys.shuffle(&mut thread_rng());
+        let sk = SkipList::from_unsorted_vec(keys.iter().map(|k| (*k, k * 2)).collect());
+        assert_eq!(sk.len(), 10_000);
+        assert_eq!(
+            entries(&sk),
+            (0..10_000).map(|k| (k, k * 2)).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn duplicate_policies() {
+        let batch = vec![(3, "a"), (1, "b"), (3, "c"), (2, "d"), (3, "e")];
+
+        let last = SkipList::from_unsorted_vec(batch.clone());
+        assert_eq!(entries(&last), vec![(1, "b"), (2, "d"), (3, "e")]);
+
+        let first = SkipList::from_unsorted_vec_with(batch.clone(), FirstWins);
+        assert_eq!(entries(&first), vec![(1, "b"), (2, "d"), (3, "a")]);
+
+        let counted = SkipList::from_unsorted_vec_with(
+            batch.iter().map(|(k, _)| (*k, 1)).collect(),
+            |_: &i32, old: &mut i32, new: i32| *old += new,
+        );
+        assert_eq!(entries(&counted), vec![(1, 1), (2, 1), (3, 3)]);
+
+        assert_eq!(
+            SkipList::try_from_unsorted_vec(batch).err(),
+            Some(DuplicateKey(3))
+        );
+    }
+
+    #[test]
+    fn bulk_insert_into_existing() {
+        let mut sk = SkipList::new();
+        for i in (0..100).step_by(3) {
+            sk.insert(i, 0);
+        }
+        let mut batch: Vec<_> = (0..100).step_by(2).map(|i| (i, 1)).collect();
+        batch.shuffle(&mut thread_rng());
+        sk.bulk_insert_unsorted_with(batch, |_: &i32, old: &mut i32, new: i32| *old += new + 10);
+
+        let expected: Vec<_> = (0..100)
+            .filter(|i| i % 2 == 0 || i % 3 == 0)
+            .map(|i| match (i % 2 == 0, i % 3 == 0) {
+                (true, true) => (i, 11),
+                (true, false) => (i, 1),
+                _ => (i, 0),
+            })
+            .collect();
+        assert_eq!(entries(&sk), expected);
+        assert_eq!(sk.len(), expected.len());
+        for (k, v) in &expected {
+            assert_eq!(sk.get(k), Some(v));
+        }
+
+        let before = entries(&sk);
+        assert_eq!(
+            sk.try_bulk_insert_unsorted(vec![(1000, 0), (99, 0), (-1, 0)]),
+            Err(DuplicateKey(99))
+        );
+        assert_eq!(entries(&sk), before);
+        assert!(sk
+            .try_bulk_insert_unsorted(vec![(1000, 0), (-1, 0)])
+            .is_ok());
+        assert_eq!(sk.len(), before.len() + 2);
+    }
+
+    #[test]
+    fn clone_range() {
+        let mut sk = SkipList::with_hashed_levels();
+        for i in 0..100 {
+            sk.insert(i, i.to_string());
+        }
+        let part = sk.clone_range(20..40);
+        assert_eq!(part.len(), 20);
+        assert_eq!(
+            entries(&part),
+            (20..40).map(|i| (i, i.to_string())).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            part.level_of.map(|f| f as usize),
+            sk.level_of.map(|f| f as usize)
+        );
+        assert_eq!(sk.len(), 100);
+        assert!(sk.clone_range(200..).is_empty());
+        assert_eq!(entries(&sk.clone_range(..)), entries(&sk));
+    }
+
+    #[test]
+    fn append() {
+        let build = |keys: std::ops::Range<u32>| {
+            let mut sk = SkipList::new();
+            for i in keys {
+                sk.insert(i, i);
+            }
+            sk
+        };
+        let check = |sk: &SkipList<u32, u32>, keys: Vec<u32>| {
+            assert_eq!(sk.len(), keys.len());
+            for (i, key) in keys.iter().enumerate() {
+                assert_eq!(sk.get_by_index(i).map(|(k, _)| *k), Some(*key));
+                assert_eq!(sk.rank(key), i);
+            }
+            let back: Vec<_> = sk.iter().rev().map(|(k, _)| *k).collect();
+            assert_eq!(back, keys.iter().rev().copied().collect::<Vec<_>>());
+        };
+
+        let mut a = build(0..300);
+        let mut b = build(300..350);
+        a.append(&mut b);
+        check(&a, (0..350).collect());
+        assert!(b.is_empty());
+        b.insert(7, 7);
+        assert_eq!(b.len(), 1);
+
+        let mut low = build(0..40);
+        let mut high = build(100..400);
+        high.append(&mut low);
+        check(&high, (0..40).chain(100..400).collect());
+        assert!(low.is_empty());
+
+        let mut a = build(0..100);
+        let mut b = SkipList::new();
+        for i in (50..150).step_by(2) {
+            b.insert(i, i + 1000);
+        }
+        a.append(&mut b);
+        assert_eq!(a.len(), 125);
+        assert_eq!(a.get(&52), Some(&1052));
+        assert_eq!(a.get(&53), Some(&53));
+        assert!(b.is_empty());
+
+        let mut empty = SkipList::new();
+        let mut c = build(0..10);
+        empty.append(&mut c);
+        check(&empty, (0..10).collect());
+
+        let mut short: SkipList<u32, u32> = crate::SkipListBuilder::new().max_level(2).build();
+        let mut tall = build(0..100);
+        short.append(&mut tall);
+        check(&short, (0..100).collect());
+        assert!(short.level <= 2);
+    }
+
+    #[test]
+    fn from_sorted_iter() {
+        let sk = SkipList::from_sorted_iter((0..5000).map(|k| (k / 2, k)));
+        assert_eq!(sk.len(), 2500);
+        assert_eq!(sk.level, 12);
+        assert_eq!(sk.get(&1234), Some(&2469));
+        assert_eq!(sk.get_by_index(777), Some((&777, &1555)));
+        assert_eq!(sk.rank(&2000), 2000);
+        assert_eq!(sk.iter().rev().nth(1), Some((&2498, &4997)));
+
+        let mut sk = sk;
+        for k in (0..2500).step_by(3) {
+            assert_eq!(sk.remove(&k), Some(k * 2 + 1));
+        }
+        sk.insert(3000, 0);
+        assert_eq!(sk.len(), 1667);
+        assert_eq!(sk.get_by_index(1666), Some((&3000, &0)));
+        assert!(SkipList::<u8, ()>::from_sorted_iter(None).is_empty());
+    }
+
+    #[test]
+    fn rebalance_evens_out_towers() {
+        for mut sk in [SkipList::new(), SkipList::with_arena(64)] {
+            for i in 0..5000 {
+                sk.insert(i, i.to_string());
+            }
+            // Removing from the front leaves the tallest towers at the back.
+            for i in 0..4900 {
+                sk.remove(&i);
+            }
+            sk.rebalance();
+            let sorted = SkipList::from_sorted_iter((4900..5000).map(|i| (i, i.to_string())));
+            assert!(sk.iter().eq(sorted.iter()));
+            assert_eq!(crate::tests::levels(&sk), crate::tests::levels(&sorted));
+            assert_eq!(sk.level, 7);
+            assert_eq!(sk.get_by_index(50), Some((&4950, &"4950".to_string())));
+            assert_eq!(sk.iter().next_back(), Some((&4999, &"4999".to_string())));
+            sk.insert(0, String::new());
+            assert_eq!(sk.rank(&4950), 51);
+        }
+        // Hashed towers don't depend on the order keys came in, and rebalance
+        // keeps them that way.
+        let mut sk = SkipList::with_hashed_levels();
+        for i in (0..5000).rev() {
+            sk.insert(i, ());
+        }
+        for i in 0..4900 {
+            sk.remove(&i);
+        }
+        sk.rebalance();
+        let mut fresh = SkipList::with_hashed_levels();
+        for i in 4900..5000 {
+            fresh.insert(i, ());
+        }
+        sk.check_invariants();
+        assert_eq!(crate::tests::levels(&sk), crate::tests::levels(&fresh));
+        assert_eq!(sk.stats(), fresh.stats());
+
+        let mut empty = SkipList::<u8, ()>::new();
+        empty.rebalance();
+        assert!(empty.is_empty());
+
+        let mut sk: SkipList<_, _> = (0..1000).map(|i| (i, i)).collect();
+        sk.rebalance_filtered(|k: &i32, v: &i32| match k % 3 {
+            0 => Decision::Drop,
+            1 => Decision::Replace(v * 10),
+            _ => Decision::Keep,
+        });
+        sk.check_invariants();
+        assert_eq!(sk.len(), 666);
+        assert_eq!(sk.get(&3), None);
+        assert_eq!(sk.get(&4), Some(&40));
+        assert_eq!(sk.get(&5), Some(&5));
+        let sorted = SkipList::from_sorted_iter(sk.iter().map(|(k, v)| (*k, *v)));
+        assert_eq!(crate::tests::levels(&sk), crate::tests::levels(&sorted));
+
+        // A hashed list comes out of the filter shaped like one made from
+        // what's left.
+        let mut sk = SkipList::with_hashed_levels();
+        let mut fresh = SkipList::with_hashed_levels();
+        for i in 0..1000 {
+            sk.insert(i, i);
+            if i % 3 != 0 {
+                fresh.insert(i, i);
+            }
+        }
+        sk.rebalance_filtered(|k: &i32, _: &i32| match k % 3 {
+            0 => Decision::Drop,
+            _ => Decision::Keep,
+        });
+        assert_eq!(sk.stats(), fresh.stats());
+    }
+
+    #[test]
+    fn rebalance_tuned_without_hot_keys() {
+        // Searches across the whole list, or no search stats at all, do best
+        // with four nodes per link.
+        let mut sk = SkipList::from_sorted_iter((0..4_096u32).map(|i| (i, i)));
+        for i in (0..4_096).step_by(3) {
+            sk.get(&i);
+        }
+        assert_eq!(sk.rebalance_tuned(), 0.25);
+        sk.check_invariants();
+        assert_eq!(sk.stats().heights[0], 4_096 - 1_024);
+        sk.insert(5_000, 0);
+        assert_eq!(sk.len(), 4_097);
+    }
+
+    #[test]
+    #[should_panic(expected = "from_sorted_iter input is not sorted")]
+    fn from_sorted_iter_rejects_unsorted() {
+        let _ = SkipList::from_sorted_iter([(1, ()), (3, ()), (2, ())]);
+    }
+
+    #[test]
+    fn collect_and_extend() {
+        let mut sk: SkipList<_, _> = [(3, "c"), (1, "a"), (3, "C")].into_iter().collect();
+        sk.extend([(2, "b"), (1, "A")]);
+        assert_eq!(entries(&sk), vec![(1, "A"), (2, "b"), (3, "C")]);
+
+        let mut sk = SkipList::from_iter_with([(3, "c"), (1, "a"), (3, "C")], FirstWins);
+        assert_eq!(entries(&sk), vec![(1, "a"), (3, "c")]);
+        sk.extend_with([(2, "b"), (1, "A"), (2, "B")], FirstWins);
+        assert_eq!(entries(&sk), vec![(1, "a"), (2, "b"), (3, "c")]);
+        sk.check_invariants();
+
+        assert_eq!(sk.try_extend([(4, "d"), (2, "x")]), Err(DuplicateKey(2)));
+        assert_eq!(sk.len(), 3);
+        assert_eq!(sk.try_extend([(4, "d")]), Ok(()));
+        assert_eq!(
+            SkipList::try_from_iter([(1, ()), (2, ()), (1, ())]).err(),
+            Some(DuplicateKey(1))
+        );
+        assert_eq!(
+            SkipList::try_from_iter([(1, ()), (2, ())]).unwrap().len(),
+            2
+        );
+    }
+}
@@ -0,0 +1,167 @@
+use crate::SkipList;
+use alloc::vec::Vec;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change<'a, K, V> {
+    Upsert(&'a K, &'a V),
+    Delete(&'a K),
+}
+
+// Every mutation takes the next sequence number. Live entries and tombstones
+// remember the sequence of their last change, and `by_seq` indexes keys by
+// that sequence, so changed_since only visits what actually changed.
+pub struct TrackedSkipList<K, V> {
+    entries: SkipList<K, (u64, V)>,
+    tombstones: SkipList<K, u64>,
+    by_seq: SkipList<u64, K>,
+    seq: u64,
+}
+
+impl<K: Ord + Clone, V> TrackedSkipList<K, V> {
+    pub fn new() -> Self {
+        Self {
+            entries: SkipList::new(),
+            tombstones: SkipList::new(),
+            by_seq: SkipList::new(),
+            seq: 0,
+        }
+    }
+
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.entries.get(key).map(|(_, v)| v)
+    }
+
+    // Handing out &mut V counts as a modification whether or not the caller
+    // writes through it.
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let (last, val) = self.entries.get_mut(key)?;
+        self.seq += 1;
+        self.by_seq.remove(last);
+        *last = self.seq;
+        self.by_seq.insert(self.seq, key.clone());
+        Some(val)
+    }
+
+    pub fn insert(&mut self, key: K, val: V) {
+        self.seq += 1;
+        let last = match self.entries.get(&key) {
+            Some((last, _)) => Some(*last),
+            None => self.tombstones.remove(&key),
+        };
+        if let Some(last) = last {
+            self.by_seq.remove(&last);
+        }
+        self.by_seq.insert(self.seq, key.clone());
+        self.entries.insert(key, (self.seq, val));
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let (last, val) = self.entries.remove(key)?;
+        self.seq += 1;
+        self.by_seq.remove(&last);
+        self.by_seq.insert(self.seq, key.clone());
+        self.tombstones.insert(key.clone(), self.seq);
+        Some(val)
+    }
+
+    // Yields the latest change of every key modified after `seq`, in sequence
+    // order. Keys changed several times are reported once.
+    pub fn changed_since(&self, seq: u64) -> impl Iterator<Item = (u64, Change<'_, K, V>)> + '_ {
+        self.by_seq
+            .range(seq.saturating_add(1)..)
+            .map(move |(seq, key)| match self.entries.get(key) {
+                Some((_, val)) => (*seq, Change::Upsert(key, val)),
+                None => (*seq, Change::Delete(key)),
+            })
+    }
+
+    // Forgets deletions at or before `seq`, once every consumer has caught up.
+    pub fn purge_tombstones(&mut self, seq: u64) {
+        let purged: Vec<(u64, K)> = self
+            .by_seq
+            .range(..=seq)
+            .filter(|(_, key)| self.entries.get(key).is_none())
+            .map(|(seq, key)| (*seq, key.clone()))
+            .collect();
+        for (seq, key) in purged {
+            self.by_seq.remove(&seq);
+            self.tombstones.remove(&key);
+        }
+    }
+}
+
+impl<K: Ord + Clone, V> Default for TrackedSkipList<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Change, TrackedSkipList};
+
+    #[test]
+    fn changed_since() {
+        let mut m = TrackedSkipList::new();
+        for i in 0..5 {
+            m.insert(i, i * 10);
+        }
+        let checkpoint = m.seq();
+        assert_eq!(checkpoint, 5);
+        assert_eq!(m.changed_since(0).count(), 5);
+        assert_eq!(m.changed_since(checkpoint).count(), 0);
+
+        m.insert(1, 11);
+        *m.get_mut(&3).unwrap() += 3;
+        m.remove(&4);
+        m.insert(1, 111);
+        assert_eq!(m.get_mut(&42), None);
+        assert_eq!(m.remove(&42), None);
+
+        let changes: Vec<_> = m.changed_since(checkpoint).collect();
+        assert_eq!(
+            changes,
+            vec![
+                (7, Change::Upsert(&3, &33)),
+                (8, Change::Delete(&4)),
+                (9, Change::Upsert(&1, &111)),
+            ]
+        );
+        assert_eq!(m.len(), 4);
+
+        m.insert(4, 40);
+        let changes: Vec<_> = m.changed_since(checkpoint).collect();
+        assert_eq!(changes.last(), Some(&(10, Change::Upsert(&4, &40))));
+        assert_eq!(changes.len(), 3);
+    }
+
+    #[test]
+    fn purge_tombstones() {
+        let mut m = TrackedSkipList::new();
+        m.insert("a", 1);
+        m.insert("b", 2);
+        m.remove(&"a");
+        m.remove(&"b");
+        m.insert("c", 3);
+        let upto = m.seq();
+        m.remove(&"c");
+
+        m.purge_tombstones(upto);
+        let changes: Vec<_> = m.changed_since(0).collect();
+        assert_eq!(changes, vec![(6, Change::Delete(&"c"))]);
+        assert!(m.tombstones.get(&"a").is_none());
+        assert!(m.is_empty());
+    }
+}
@@ -0,0 +1,149 @@
+use icu_collator::options::{CaseLevel, CollatorOptions, Strength};
+use icu_collator::{Collator, CollatorBorrowed, CollatorPreferences};
+use icu_provider::DataError;
+use std::cmp::Ordering;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CollationOptions {
+    pub ignore_case: bool,
+    pub ignore_diacritics: bool,
+}
+
+impl CollationOptions {
+    fn to_collator_options(self) -> CollatorOptions {
+        let mut options = CollatorOptions::default();
+        match (self.ignore_diacritics, self.ignore_case) {
+            (false, false) => options.strength = Some(Strength::Tertiary),
+            (false, true) => options.strength = Some(Strength::Secondary),
+            (true, true) => options.strength = Some(Strength::Primary),
+            (true, false) => {
+                options.strength = Some(Strength::Primary);
+                options.case_level = Some(CaseLevel::On);
+            }
+        }
+        options
+    }
+}
+
+pub struct Collation {
+    collator: CollatorBorrowed<'static>,
+}
+
+impl Collation {
+    pub fn try_new(
+        prefs: impl Into<CollatorPreferences>,
+        options: CollationOptions,
+    ) -> Result<Self, DataError> {
+        Ok(Self {
+            collator: Collator::try_new(prefs.into(), options.to_collator_options())?,
+        })
+    }
+
+    pub fn key(&self, text: impl Into<String>) -> CollatedKey {
+        let text = text.into();
+        let mut sort_key = Vec::new();
+        let Ok(()) = self.collator.write_sort_key_to(&text, &mut sort_key);
+        CollatedKey {
+            sort_key: sort_key.into_boxed_slice(),
+            text,
+        }
+    }
+}
+
+// The sort key is computed once when the key is built and lives inside the
+// node, so traversal only compares bytes. Strings that collate equal (e.g.
+// "a" and "A" with ignore_case) are ordered by their text so they stay
+// distinct keys.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CollatedKey {
+    sort_key: Box<[u8]>,
+    text: String,
+}
+
+impl CollatedKey {
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn sort_key(&self) -> &[u8] {
+        &self.sort_key
+    }
+
+    pub fn into_string(self) -> String {
+        self.text
+    }
+}
+
+impl PartialOrd for CollatedKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for CollatedKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.sort_key
+            .cmp(&other.sort_key)
+            .then_with(|| self.text.cmp(&other.text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Collation, CollationOptions};
+    use crate::SkipList;
+    use icu_locale_core::locale;
+
+    fn sorted(collation: &Collation, words: &[&str]) -> Vec<String> {
+        let mut sk = SkipList::new();
+        for (i, w) in words.iter().enumerate() {
+            sk.insert(collation.key(*w), i);
+        }
+        sk.iter().map(|(k, _)| k.as_str().to_string()).collect()
+    }
+
+    #[test]
+    fn locale_order() {
+        let words = ["zebra", "Äpfel", "apple", "Zoo", "äther"];
+
+        let de = Collation::try_new(locale!("de"), CollationOptions::default()).unwrap();
+        assert_eq!(
+            sorted(&de, &words),
+            vec!["Äpfel", "apple", "äther", "zebra", "Zoo"]
+        );
+
+        let sv = Collation::try_new(locale!("sv"), CollationOptions::default()).unwrap();
+        assert_eq!(
+            sorted(&sv, &words),
+            vec!["apple", "zebra", "Zoo", "Äpfel", "äther"]
+        );
+    }
+
+    #[test]
+    fn case_and_diacritic_options() {
+        let strict = Collation::try_new(locale!("en"), CollationOptions::default()).unwrap();
+        assert_ne!(
+            strict.key("resume").sort_key(),
+            strict.key("Résumé").sort_key()
+        );
+
+        let loose = Collation::try_new(
+            locale!("en"),
+            CollationOptions {
+                ignore_case: true,
+                ignore_diacritics: true,
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            loose.key("resume").sort_key(),
+            loose.key("Résumé").sort_key()
+        );
+        assert_ne!(loose.key("resume"), loose.key("Résumé"));
+
+        let mut sk = SkipList::new();
+        sk.insert(loose.key("Résumé"), 1);
+        assert_eq!(sk.get(&loose.key("Résumé")), Some(&1));
+        assert_eq!(sk.get(&loose.key("resume")), None);
+    }
+}
@@ -0,0 +1,229 @@
+use crate::iter::{Iter, IterMut, Range};
+use crate::{Node, NodeLayout, SkipList, MAX_LEVEL};
+use core::cmp::Ordering;
+use core::ops::{Bound, RangeBounds};
+use core::ptr::NonNull;
+
+pub trait Comparator<K: ?Sized> {
+    fn compare(&self, a: &K, b: &K) -> Ordering;
+}
+
+impl<K: ?Sized, F: Fn(&K, &K) -> Ordering> Comparator<K> for F {
+    fn compare(&self, a: &K, b: &K) -> Ordering {
+        self(a, b)
+    }
+}
+
+// Keys are ordered by the comparator alone, so K needs no Ord of its own and
+// any Ord it has is ignored. The comparator must be a total order and must not
+// change its answers while keys are in the list.
+pub struct ComparatorSkipList<K, V, C> {
+    pub(crate) list: SkipList<K, V>,
+    cmp: C,
+}
+
+impl<K, V> SkipList<K, V> {
+    pub fn with_comparator<C: Comparator<K>>(cmp: C) -> ComparatorSkipList<K, V, C> {
+        ComparatorSkipList::from_list(SkipList::empty(NodeLayout::Compact), cmp)
+    }
+}
+
+impl<K, V, C> ComparatorSkipList<K, V, C> {
+    // list must be empty.
+    pub(crate) fn from_list(list: SkipList<K, V>, cmp: C) -> Self {
+        debug_assert_eq!(list.size, 0);
+        ComparatorSkipList { list, cmp }
+    }
+}
+
+impl<K, V, C: Comparator<K>> ComparatorSkipList<K, V, C> {
+    pub fn comparator(&self) -> &C {
+        &self.cmp
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.size == 0
+    }
+
+    // Returns the value the key had before, keeping the key already stored.
+    pub fn insert(&mut self, key: K, val: V) -> Option<V> {
+        let mut update: [Option<NonNull<Node<K, V>>>; MAX_LEVEL] = [None; MAX_LEVEL];
+        unsafe {
+            if let Some(mut node_ptr) = self.lower_bound(&key, &mut update) {
+                if self.cmp.compare(&node_ptr.as_ref().key, &key) == Ordering::Equal {
+                    return Some(core::mem::replace(node_ptr.as_mut().val_mut(), val));
+                }
+            }
+            self.list.splice(key, val, &mut update);
+        }
+        None
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.find(key)
+            .map(|node_ptr| unsafe { (*node_ptr.as_ptr()).val() })
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.find(key)
+            .map(|node_ptr| unsafe { (*node_ptr.as_ptr()).val_mut() })
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.find(key).is_some()
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<(K, V)> {
+        let mut update: [Option<NonNull<Node<K, V>>>; MAX_LEVEL] = [None; MAX_LEVEL];
+        unsafe {
+            let node_ptr = self.lower_bound(key, &mut update)?;
+            if self.cmp.compare(&node_ptr.as_ref().key, key) != Ordering::Equal {
+                return None;
+            }
+            Some(self.list.unlink(node_ptr, &update))
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        self.list.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        self.list.iter_mut()
+    }
+
+    pub fn last_key_value(&self) -> Option<(&K, &V)> {
+        self.list.last_key_value()
+    }
+
+    // Panics on a backwards or empty-excluded range, as SkipList::range does,
+    // with "backwards" decided by the comparator.
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> Range<'_, K, V> {
+        match (range.start_bound(), range.end_bound()) {
+            (Bound::Excluded(s), Bound::Excluded(e))
+                if self.cmp.compare(s, e) == Ordering::Equal =>
+            {
+                panic!("range start and end are equal and excluded in SkipList")
+            }
+            (Bound::Included(s) | Bound::Excluded(s), Bound::Included(e) | Bound::Excluded(e))
+                if self.cmp.compare(s, e) == Ordering::Greater =>
+            {
+                panic!("range start is greater than range end in SkipList")
+            }
+            _ => {}
+        }
+        let front = match range.start_bound() {
+            Bound::Included(key) => self.seek(key, false),
+            Bound::Excluded(key) => self.seek(key, true),
+            Bound::Unbounded => unsafe { self.list.head.as_ref().tower[0] },
+        };
+        let end = match range.end_bound() {
+            Bound::Included(key) => self.seek(key, true),
+            Bound::Excluded(key) => self.seek(key, false),
+            Bound::Unbounded => None,
+        };
+        self.list.range_between(front, end)
+    }
+
+    pub fn clear(&mut self) {
+        self.list = self.list.empty_like();
+    }
+
+    unsafe fn lower_bound(
+        &self,
+        key: &K,
+        update: &mut [Option<NonNull<Node<K, V>>>; MAX_LEVEL],
+    ) -> Option<NonNull<Node<K, V>>> {
+        self.list
+            .find_by(|k| self.cmp.compare(k, key) == Ordering::Less, update)
+    }
+
+    fn find(&self, key: &K) -> Option<NonNull<Node<K, V>>> {
+        let mut update: [Option<NonNull<Node<K, V>>>; MAX_LEVEL] = [None; MAX_LEVEL];
+        unsafe {
+            self.lower_bound(key, &mut update)
+                .filter(|node_ptr| self.cmp.compare(&node_ptr.as_ref().key, key) == Ordering::Equal)
+        }
+    }
+
+    fn seek(&self, key: &K, skip_equal: bool) -> Option<NonNull<Node<K, V>>> {
+        let mut update: [Option<NonNull<Node<K, V>>>; MAX_LEVEL] = [None; MAX_LEVEL];
+        unsafe {
+            match self.lower_bound(key, &mut update) {
+                Some(node_ptr)
+                    if skip_equal
+                        && self.cmp.compare(&node_ptr.as_ref().key, key) == Ordering::Equal =>
+                {
+                    node_ptr.as_ref().tower[0]
+                }
+                x => x,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::SkipList;
+    use std::cmp::Ordering;
+
+    #[test]
+    fn reverse_bytes() {
+        let mut sk = SkipList::with_comparator(|a: &Vec<u8>, b: &Vec<u8>| b.cmp(a));
+        for key in [b"b".to_vec(), b"a".to_vec(), b"c".to_vec()] {
+            let val = key[0];
+            assert_eq!(sk.insert(key, val), None);
+        }
+        assert_eq!(sk.insert(b"a".to_vec(), 0), Some(b'a'));
+        let keys: Vec<_> = sk.iter().map(|(k, _)| k.clone()).collect();
+        assert_eq!(keys, [b"c".to_vec(), b"b".to_vec(), b"a".to_vec()]);
+        assert_eq!(sk.get(&b"a".to_vec()), Some(&0));
+        let range: Vec<_> = sk
+            .range(b"c".to_vec()..b"a".to_vec())
+            .map(|(k, _)| k[0])
+            .collect();
+        assert_eq!(range, [b'c', b'b']);
+        assert_eq!(sk.remove(&b"b".to_vec()), Some((b"b".to_vec(), b'b')));
+        assert_eq!(sk.len(), 2);
+        assert_eq!(sk.last_key_value(), Some((&b"a".to_vec(), &0)));
+    }
+
+    #[test]
+    fn case_insensitive_keys_collide() {
+        let mut sk = SkipList::with_comparator(|a: &String, b: &String| {
+            a.to_lowercase().cmp(&b.to_lowercase())
+        });
+        sk.insert("Apple".to_string(), 1);
+        sk.insert("banana".to_string(), 2);
+        assert_eq!(sk.insert("APPLE".to_string(), 3), Some(1));
+        assert!(sk.contains_key(&"apple".to_string()));
+        let keys: Vec<_> = sk.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+        assert_eq!(keys, [("Apple", 3), ("banana", 2)]);
+    }
+
+    // Works for keys without Ord.
+    #[test]
+    fn by_field() {
+        struct Point(f64);
+        let mut sk = SkipList::with_comparator(|a: &Point, b: &Point| {
+            a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal)
+        });
+        sk.insert(Point(2.5), "b");
+        sk.insert(Point(-1.0), "a");
+        assert_eq!(sk.iter().map(|(_, v)| *v).collect::<Vec<_>>(), ["a", "b"]);
+        sk.clear();
+        assert!(sk.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "range start is greater than range end")]
+    fn backwards_range_by_comparator() {
+        let sk: crate::ComparatorSkipList<u32, (), _> =
+            SkipList::with_comparator(|a: &u32, b: &u32| b.cmp(a));
+        let _ = sk.range(1..5);
+    }
+}
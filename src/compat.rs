@@ -0,0 +1,290 @@
+use crate::concurrent::{self, pin, Guard, RawEntry};
+use std::ops::{Bound, RangeBounds};
+
+// crossbeam_skiplist::SkipMap's shape over concurrent::SkipMap, so code
+// written against crossbeam can switch by changing the import. Entries come
+// back without a guard to pass in: each one keeps the thread pinned while it
+// lives, which is what keeps its node around after a remove. Don't hold on
+// to them for long, nothing retired meanwhile can be freed.
+//
+// Lookups take &K rather than a borrowed form of it, as concurrent's do, and
+// ranges only run forwards.
+pub struct SkipMap<K, V> {
+    map: concurrent::SkipMap<K, V>,
+}
+
+// Looks an entry up under the guard it's handed.
+trait Find<K, V>:
+    for<'g> FnOnce(&'g concurrent::SkipMap<K, V>, &'g Guard) -> Option<concurrent::Entry<'g, K, V>>
+{
+}
+
+impl<K, V, F> Find<K, V> for F where
+    F: for<'g> FnOnce(
+        &'g concurrent::SkipMap<K, V>,
+        &'g Guard,
+    ) -> Option<concurrent::Entry<'g, K, V>>
+{
+}
+
+pub struct Entry<'a, K, V> {
+    map: &'a concurrent::SkipMap<K, V>,
+    raw: RawEntry<K, V>,
+    guard: Guard,
+}
+
+pub struct Iter<'a, K, V> {
+    map: &'a concurrent::SkipMap<K, V>,
+    last: Option<Entry<'a, K, V>>,
+    done: bool,
+}
+
+pub struct Range<'a, K, V, R> {
+    iter: Iter<'a, K, V>,
+    range: R,
+}
+
+impl<K: Ord + Send + 'static, V: Send + 'static> SkipMap<K, V> {
+    pub fn new() -> Self {
+        Self {
+            map: concurrent::SkipMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    pub fn get(&self, key: &K) -> Option<Entry<'_, K, V>> {
+        self.entry(|map, guard| map.get_entry(key, guard))
+    }
+
+    pub fn front(&self) -> Option<Entry<'_, K, V>> {
+        self.entry(|map, guard| map.front(guard))
+    }
+
+    pub fn back(&self) -> Option<Entry<'_, K, V>> {
+        self.entry(|map, guard| map.back(guard))
+    }
+
+    pub fn lower_bound(&self, bound: Bound<&K>) -> Option<Entry<'_, K, V>> {
+        self.entry(|map, guard| map.lower_bound(bound, guard))
+    }
+
+    // Replaces the value if the key is present, unlike concurrent's insert.
+    pub fn insert(&self, key: K, val: V) -> Entry<'_, K, V> {
+        self.entry(|map, guard| Some(map.insert_entry(key, val, guard)))
+            .unwrap()
+    }
+
+    pub fn get_or_insert(&self, key: K, val: V) -> Entry<'_, K, V> {
+        self.entry(|map, guard| Some(map.get_or_insert_entry(key, val, guard)))
+            .unwrap()
+    }
+
+    // The entry taken out, still readable through the returned handle.
+    pub fn remove(&self, key: &K) -> Option<Entry<'_, K, V>> {
+        self.entry(|map, guard| map.remove_entry(key, guard))
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            map: &self.map,
+            last: None,
+            done: false,
+        }
+    }
+
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> Range<'_, K, V, R> {
+        Range {
+            iter: self.iter(),
+            range,
+        }
+    }
+
+    pub fn inner(&self) -> &concurrent::SkipMap<K, V> {
+        &self.map
+    }
+
+    fn entry(&self, find: impl Find<K, V>) -> Option<Entry<'_, K, V>> {
+        Entry::pinned(&self.map, find)
+    }
+}
+
+impl<K: Ord + Send + 'static, V: Send + 'static> Default for SkipMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, K: Ord + Send + 'static, V: Send + 'static> Entry<'a, K, V> {
+    // Pins a guard for find and keeps it with whatever entry find comes back
+    // with.
+    fn pinned(map: &'a concurrent::SkipMap<K, V>, find: impl Find<K, V>) -> Option<Self> {
+        let guard = pin();
+        let raw = find(map, &guard)?.into_raw();
+        Some(Entry { map, raw, guard })
+    }
+
+    fn get(&self) -> concurrent::Entry<'_, K, V> {
+        unsafe { self.raw.get(&self.guard) }
+    }
+
+    pub fn key(&self) -> &K {
+        self.get().key()
+    }
+
+    pub fn value(&self) -> &V {
+        self.get().value()
+    }
+
+    pub fn is_removed(&self) -> bool {
+        self.get().is_removed()
+    }
+
+    // False if it was removed already, by this or anything else.
+    pub fn remove(&self) -> bool {
+        self.map.remove_exact(&self.get())
+    }
+
+    pub fn next(&self) -> Option<Entry<'a, K, V>> {
+        // Our guard keeps the node readable while the new one gets pinned.
+        Entry::pinned(self.map, |_, guard| unsafe { self.raw.get(guard) }.next())
+    }
+}
+
+impl<K, V> Clone for Entry<'_, K, V> {
+    fn clone(&self) -> Self {
+        Entry {
+            map: self.map,
+            raw: self.raw,
+            guard: pin(),
+        }
+    }
+}
+
+impl<'a, K: Ord + Send + 'static, V: Send + 'static> Iter<'a, K, V> {
+    // Moves on from the last entry handed out, or to first if there is none
+    // yet, and stops for good at the first entry keep turns down.
+    fn advance(
+        &mut self,
+        first: impl Find<K, V>,
+        keep: impl FnOnce(&K) -> bool,
+    ) -> Option<Entry<'a, K, V>> {
+        if self.done {
+            return None;
+        }
+        let next = match &self.last {
+            Some(last) => last.next(),
+            None => Entry::pinned(self.map, first),
+        };
+        let next = next.filter(|entry| keep(entry.key()));
+        self.done = next.is_none();
+        self.last = next.clone();
+        next
+    }
+}
+
+impl<'a, K: Ord + Send + 'static, V: Send + 'static> Iterator for Iter<'a, K, V> {
+    type Item = Entry<'a, K, V>;
+
+    fn next(&mut self) -> Option<Entry<'a, K, V>> {
+        self.advance(|map, guard| map.front(guard), |_| true)
+    }
+}
+
+impl<'a, K: Ord + Send + 'static, V: Send + 'static, R: RangeBounds<K>> Iterator
+    for Range<'a, K, V, R>
+{
+    type Item = Entry<'a, K, V>;
+
+    // Everything from lower_bound on is past the start, so only the end is
+    // left to check.
+    fn next(&mut self) -> Option<Entry<'a, K, V>> {
+        let range = &self.range;
+        self.iter.advance(
+            |map, guard| map.lower_bound(range.start_bound(), guard),
+            |key| range.contains(key),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SkipMap;
+    use std::ops::Bound;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn crossbeam_shaped_calls() {
+        let map = SkipMap::new();
+        assert!(map.is_empty());
+        for i in (0..20).rev() {
+            let entry = map.insert(i, i * 10);
+            assert_eq!((entry.key(), entry.value()), (&i, &(i * 10)));
+        }
+        // insert replaces, get_or_insert doesn't.
+        assert_eq!(*map.insert(3, 0).value(), 0);
+        assert_eq!(*map.get_or_insert(3, 7).value(), 0);
+        assert_eq!(*map.get_or_insert(30, 7).value(), 7);
+        assert_eq!(map.len(), 21);
+
+        assert_eq!(map.get(&4).map(|e| *e.value()), Some(40));
+        assert!(map.get(&40).is_none());
+        assert_eq!(*map.front().unwrap().key(), 0);
+        assert_eq!(*map.back().unwrap().key(), 30);
+        assert_eq!(*map.lower_bound(Bound::Excluded(&4)).unwrap().key(), 5);
+
+        let removed = map.remove(&5).unwrap();
+        assert!(removed.is_removed());
+        assert_eq!(*removed.value(), 50);
+        assert!(map.remove(&5).is_none());
+        // A removed entry still leads on to the next.
+        assert_eq!(*removed.next().unwrap().key(), 6);
+        let six = map.get(&6).unwrap();
+        assert!(six.remove());
+        assert!(!six.clone().remove());
+
+        assert!(map
+            .iter()
+            .map(|e| *e.key())
+            .eq((0..20).filter(|&k| k != 5 && k != 6).chain([30])));
+        assert!(map.range(3..8).map(|e| *e.key()).eq([3, 4, 7]));
+        assert!(map.range(..=2).map(|e| *e.key()).eq([0, 1, 2]));
+        assert_eq!(map.range(21..30).count(), 0);
+        assert!(map.range(19..).map(|e| *e.key()).eq([19, 30]));
+        assert!(map.inner().contains_key(&30));
+    }
+
+    #[test]
+    fn entries_outlive_concurrent_removes() {
+        let map = Arc::new(SkipMap::new());
+        for i in 0..1_000 {
+            map.insert(i, i.to_string());
+        }
+        let held: Vec<_> = map.range(100..200).collect();
+        let remover = {
+            let map = map.clone();
+            thread::spawn(move || {
+                for i in 0..1_000 {
+                    map.remove(&i);
+                }
+            })
+        };
+        remover.join().unwrap();
+        assert!(map.is_empty());
+        for (i, entry) in (100..200).zip(&held) {
+            assert_eq!(entry.value(), &i.to_string());
+            assert!(entry.is_removed());
+        }
+    }
+}
@@ -0,0 +1,1640 @@
+use crate::sync::Lock;
+use crate::MergeOperator;
+use crossbeam_epoch::{self as epoch, Atomic, Owned, Shared};
+use rand::random;
+use std::mem;
+use std::ops::{Bound, RangeBounds};
+use std::sync::atomic::{AtomicU64, AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+
+pub use crossbeam_epoch::{pin, Guard};
+
+const MAX_LEVEL: usize = 20;
+
+// A node is removed by tagging its next pointers, top level first; whoever
+// tags level 0 owns the removal. Tagged nodes are snipped out by any search
+// that runs into them.
+//
+// An insert may still be linking the upper levels of a node while it is
+// being removed, so neither side can retire it alone. Each sets its bit in
+// state when done, and the one that finds the other bit already set searches
+// once more to snip any link made in between and then retires the node.
+const INSERT_DONE: u8 = 1;
+const REMOVED: u8 = 2;
+
+// Snapshots go by a clock that inserts and removes tick. A node records when
+// it went in and when it was removed, and a snapshot taken at version s sees
+// it if inserted < s <= removed. A stamp is PENDING between the change taking
+// effect and the tick being read; anyone who runs into it then ticks the
+// clock on the writer's behalf, so every reader agrees on a single value.
+//
+// Removing a key only stamps removed while a snapshot that could still see
+// it is open; the node stays linked, passed over by searches, and is parked
+// until the last such snapshot closes and unlinks it. Otherwise it is
+// unlinked right away as before.
+const LIVE: u64 = u64::MAX;
+const PENDING: u64 = u64::MAX - 1;
+
+// The value sits behind its own pointer so that it can be swapped for a new
+// one without touching the links. A replaced value is retired like a node,
+// so readers that got to it first can finish with it. Values aren't
+// versioned: a snapshot sees whatever an entry holds when it gets there.
+struct Node<K, V> {
+    key: K,
+    val: Atomic<V>,
+    state: AtomicU8,
+    inserted: AtomicU64,
+    removed: AtomicU64,
+    next: Box<[Atomic<Node<K, V>>]>,
+}
+
+pub struct SkipMap<K, V> {
+    head: [Atomic<Node<K, V>>; MAX_LEVEL],
+    len: AtomicUsize,
+    clock: AtomicU64,
+    // The lowest floor among open snapshots, or LIVE with none open. A node
+    // removed before it can be unlinked.
+    oldest: AtomicU64,
+    holds: Lock<Holds<K, V>>,
+    // Removed nodes are left linked as tombstones for compact to take out.
+    lazy: bool,
+    counters: Counters,
+    fences: Fences<K>,
+}
+
+// Key ranges locked against writers. Every write is counted in writing
+// while it runs, which lock_range waits on to drain. Writers only look at
+// the list when held says some range is locked; until then counting is all
+// a write costs. With a range locked, a writer checks its key under the
+// mutex, waits there only if a locked range has it, and counts itself and
+// lets go of the mutex before it writes, so writes outside every locked
+// range still run side by side.
+struct Fences<K> {
+    held: AtomicUsize,
+    writing: AtomicUsize,
+    ranges: Mutex<Fenced<K>>,
+    freed: Condvar,
+}
+
+struct Fenced<K> {
+    next: u64,
+    ranges: Vec<(u64, Bound<K>, Bound<K>)>,
+}
+
+// Lets one write through, counted in writing until dropped.
+struct Pass<'a>(&'a AtomicUsize);
+
+// Holds writers other than itself out of a key range until dropped, so a
+// few writes in it, e.g. moving an entry from one key to another, happen as
+// one as far as any other writer can tell. Readers aren't held out and may
+// see the writes in between. Writing to a locked range through the map
+// rather than the lock waits for the lock, so it deadlocks from the thread
+// that holds it.
+pub struct RangeLock<'a, K: Ord + Send + 'static, V: Send + 'static> {
+    map: &'a SkipMap<K, V>,
+    id: u64,
+    start: Bound<K>,
+    end: Bound<K>,
+}
+
+// How often threads got in each other's way since the map was made. Only the
+// contended paths bump these, so a map nobody fights over pays nothing; rising
+// numbers under a steady load point at a few hot keys.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ContentionMetrics {
+    // Compare-and-swaps that lost to another thread.
+    pub cas_failures: u64,
+    // Times an operation went back and searched or loaded again after losing.
+    pub retries: u64,
+    // Links past removed nodes that a search snipped out on the way, which
+    // finishes the remover's work for it.
+    pub helped: u64,
+}
+
+#[derive(Default)]
+struct Counters {
+    cas_failures: AtomicU64,
+    retries: AtomicU64,
+    helped: AtomicU64,
+}
+
+impl Counters {
+    fn lost(&self) {
+        self.cas_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn retried(&self) {
+        self.lost();
+        self.retries.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+struct Holds<K, V> {
+    floors: Vec<u64>,
+    parked: Vec<*const Node<K, V>>,
+}
+
+// Iterating skips whatever the snapshot can't see; nodes it can are kept
+// linked, and the pinned guard keeps the rest from being freed under it.
+pub struct Snapshot<'a, K: Ord + Send + 'static, V: Send + 'static> {
+    map: &'a SkipMap<K, V>,
+    guard: Guard,
+    floor: u64,
+    version: u64,
+}
+
+pub struct SnapshotIter<'a, K, V> {
+    next: Shared<'a, Node<K, V>>,
+    version: u64,
+    map: &'a SkipMap<K, V>,
+    guard: &'a Guard,
+}
+
+unsafe impl<K: Send + Sync, V: Send + Sync> Send for SkipMap<K, V> {}
+unsafe impl<K: Send + Sync, V: Send + Sync> Sync for SkipMap<K, V> {}
+
+// A key and its value borrowed for as long as the guard stays pinned, which
+// keeps the node and the value it had from being freed even if a writer
+// removes or replaces them meanwhile. Hold guards briefly: nothing retired
+// while one is pinned can be freed until it is dropped.
+pub struct Entry<'g, K, V> {
+    node: &'g Node<K, V>,
+    guard: &'g Guard,
+}
+
+pub struct Iter<'g, K, V> {
+    next: Shared<'g, Node<K, V>>,
+    guard: &'g Guard,
+}
+
+struct Position<'g, K, V> {
+    preds: [&'g [Atomic<Node<K, V>>]; MAX_LEVEL],
+    succs: [Shared<'g, Node<K, V>>; MAX_LEVEL],
+}
+
+impl<K: Ord + Send + 'static, V: Send + 'static> SkipMap<K, V> {
+    pub fn new() -> Self {
+        Self {
+            head: Default::default(),
+            len: AtomicUsize::new(0),
+            clock: AtomicU64::new(0),
+            oldest: AtomicU64::new(LIVE),
+            holds: Lock::new(Holds {
+                floors: Vec::new(),
+                parked: Vec::new(),
+            }),
+            lazy: false,
+            counters: Counters::default(),
+            fences: Fences {
+                held: AtomicUsize::new(0),
+                writing: AtomicUsize::new(0),
+                ranges: Mutex::new(Fenced {
+                    next: 0,
+                    ranges: Vec::new(),
+                }),
+                freed: Condvar::new(),
+            },
+        }
+    }
+
+    // remove only marks the entry, which costs a single CAS, and leaves the
+    // node where it is until compact runs. Searches and iterators pass over
+    // the marked nodes the way they pass over ones kept for a snapshot.
+    pub fn with_lazy_delete() -> Self {
+        let mut map = Self::new();
+        map.lazy = true;
+        map
+    }
+
+    // A snapshot: concurrent inserts and removes may move it at any time.
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn metrics(&self) -> ContentionMetrics {
+        ContentionMetrics {
+            cas_failures: self.counters.cas_failures.load(Ordering::Relaxed),
+            retries: self.counters.retries.load(Ordering::Relaxed),
+            helped: self.counters.helped.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        let guard = &epoch::pin();
+        self.find_node(key, guard).is_some()
+    }
+
+    pub fn get(&self, key: &K) -> Option<V>
+    where
+        V: Clone,
+    {
+        let guard = &epoch::pin();
+        self.find_node(key, guard)
+            .map(|node| node.value(guard).clone())
+    }
+
+    pub fn get_entry<'g>(&'g self, key: &K, guard: &'g Guard) -> Option<Entry<'g, K, V>> {
+        let node = self.find_node(key, guard)?;
+        Some(Entry { node, guard })
+    }
+
+    pub fn front<'g>(&'g self, guard: &'g Guard) -> Option<Entry<'g, K, V>> {
+        self.iter(guard).next()
+    }
+
+    // Descends to the last live node, moving ahead on each level only onto
+    // nodes that are neither being removed nor kept as tombstones.
+    pub fn back<'g>(&'g self, guard: &'g Guard) -> Option<Entry<'g, K, V>> {
+        let mut last: Option<&'g Node<K, V>> = None;
+        let mut pred: &'g [Atomic<Node<K, V>>] = &self.head;
+        for l in (0..MAX_LEVEL).rev() {
+            let mut curr = pred[l].load(Ordering::Acquire, guard).with_tag(0);
+            while let Some(c) = unsafe { curr.as_ref() } {
+                let succ = c.next[l].load(Ordering::Acquire, guard);
+                if succ.tag() == 0 && c.removed.load(Ordering::Acquire) == LIVE {
+                    last = Some(c);
+                    pred = &c.next;
+                }
+                curr = succ.with_tag(0);
+            }
+        }
+        let node = last?;
+        self.stamp(&node.inserted);
+        Some(Entry { node, guard })
+    }
+
+    // The first entry within bound, as seen from below.
+    pub fn lower_bound<'g>(
+        &'g self,
+        bound: Bound<&K>,
+        guard: &'g Guard,
+    ) -> Option<Entry<'g, K, V>> {
+        let next = match bound {
+            Bound::Included(key) | Bound::Excluded(key) => self.position(key, guard).succs[0],
+            Bound::Unbounded => self.head[0].load(Ordering::Acquire, guard),
+        };
+        Iter { next, guard }.find(|entry| match bound {
+            Bound::Excluded(key) => entry.key() > key,
+            _ => true,
+        })
+    }
+
+    // In key order, passing over entries removed before the walk gets to
+    // them. Entries inserted meanwhile may or may not be seen.
+    pub fn iter<'g>(&'g self, guard: &'g Guard) -> Iter<'g, K, V> {
+        Iter {
+            next: self.head[0].load(Ordering::Acquire, guard),
+            guard,
+        }
+    }
+
+    // Adds the entry unless the key is already present, in which case the map
+    // is left alone and false is returned.
+    pub fn insert(&self, key: K, val: V) -> bool {
+        let _pass = self.fences.pass(&key);
+        self.insert_unfenced(key, val)
+    }
+
+    fn insert_unfenced(&self, key: K, val: V) -> bool {
+        let guard = &epoch::pin();
+        self.link(Node::new(key, val), guard).is_ok()
+    }
+
+    // Stores val if key is missing or pred holds for the value it has, and
+    // returns whether it did. pred is asked again about the newer value if
+    // another writer replaces it first, so it may run more than once.
+    pub fn compare_insert<F: FnMut(&V) -> bool>(&self, key: K, val: V, pred: F) -> bool {
+        let _pass = self.fences.pass(&key);
+        self.compare_link(key, val, pred, &epoch::pin()).is_some()
+    }
+
+    // compare_insert, handing back the node that holds val.
+    fn compare_link<'g, F: FnMut(&V) -> bool>(
+        &'g self,
+        key: K,
+        val: V,
+        mut pred: F,
+        guard: &'g Guard,
+    ) -> Option<&'g Node<K, V>> {
+        let mut node = Node::new(key, val);
+        loop {
+            if let Some(n) = self.find_node(&node.key, guard) {
+                let val = mem::replace(&mut node.val, Atomic::null());
+                let mut val = unsafe { val.into_owned() };
+                loop {
+                    let current = n.val.load(Ordering::Acquire, guard);
+                    if !pred(unsafe { current.deref() }) {
+                        return None;
+                    }
+                    match n.val.compare_exchange(
+                        current,
+                        val,
+                        Ordering::AcqRel,
+                        Ordering::Acquire,
+                        guard,
+                    ) {
+                        Ok(_) => {
+                            unsafe { guard.defer_destroy(current) };
+                            return Some(n);
+                        }
+                        Err(err) => {
+                            self.counters.retried();
+                            val = err.new;
+                        }
+                    }
+                }
+            }
+            match self.link(node, guard) {
+                Ok(n) => return Some(n),
+                Err(back) => {
+                    self.counters.retries.fetch_add(1, Ordering::Relaxed);
+                    node = back;
+                }
+            }
+        }
+    }
+
+    // Replaces the value under key with f of it and returns false if the key
+    // is missing. f runs again on the newer value whenever another writer
+    // gets in between, so concurrent updates are never lost.
+    pub fn update<F: FnMut(&V) -> V>(&self, key: &K, f: F) -> bool {
+        let _pass = self.fences.pass(key);
+        self.update_unfenced(key, f)
+    }
+
+    fn update_unfenced<F: FnMut(&V) -> V>(&self, key: &K, mut f: F) -> bool {
+        let guard = &epoch::pin();
+        let Some(n) = self.find_node(key, guard) else {
+            return false;
+        };
+        let mut current = n.val.load(Ordering::Acquire, guard);
+        loop {
+            let val = Owned::new(f(unsafe { current.deref() }));
+            match n
+                .val
+                .compare_exchange(current, val, Ordering::AcqRel, Ordering::Acquire, guard)
+            {
+                Ok(_) => {
+                    unsafe { guard.defer_destroy(current) };
+                    return true;
+                }
+                Err(err) => {
+                    self.counters.retried();
+                    current = err.current;
+                }
+            }
+        }
+    }
+
+    // Stores f of the present value, or of None if key is missing, without
+    // taking a lock. Like update, f runs again whenever another writer gets in
+    // first, including one that inserts or removes the key meanwhile.
+    pub fn merge_with<F: FnMut(&K, Option<&V>) -> V>(&self, key: K, mut f: F) {
+        let _pass = self.fences.pass(&key);
+        let guard = &epoch::pin();
+        let mut node = Node::vacant(key);
+        loop {
+            if let Some(n) = self.find_node(&node.key, guard) {
+                let mut current = n.val.load(Ordering::Acquire, guard);
+                loop {
+                    let val = Owned::new(f(&n.key, Some(unsafe { current.deref() })));
+                    match n.val.compare_exchange(
+                        current,
+                        val,
+                        Ordering::AcqRel,
+                        Ordering::Acquire,
+                        guard,
+                    ) {
+                        Ok(_) => {
+                            unsafe { guard.defer_destroy(current) };
+                            return;
+                        }
+                        Err(err) => {
+                            self.counters.retried();
+                            current = err.current;
+                        }
+                    }
+                }
+            }
+            node.val = Atomic::new(f(&node.key, None));
+            match self.link(node, guard) {
+                Ok(_) => return,
+                Err(back) => {
+                    self.counters.retries.fetch_add(1, Ordering::Relaxed);
+                    node = back;
+                    let val = mem::replace(&mut node.val, Atomic::null());
+                    drop(unsafe { val.into_owned() });
+                }
+            }
+        }
+    }
+
+    // Hands the node back if its key turns out to be present already.
+    fn link<'g>(
+        &'g self,
+        mut node: Owned<Node<K, V>>,
+        guard: &'g Guard,
+    ) -> Result<&'g Node<K, V>, Owned<Node<K, V>>> {
+        let height = node.next.len();
+        let (node, mut pos) = loop {
+            let Some(pos) = self.search(&node.key, guard) else {
+                return Err(node);
+            };
+            for (l, next) in node.next.iter().enumerate() {
+                next.store(pos.succs[l], Ordering::Relaxed);
+            }
+            match pos.preds[0][0].compare_exchange(
+                pos.succs[0],
+                node,
+                Ordering::Release,
+                Ordering::Relaxed,
+                guard,
+            ) {
+                Ok(node) => break (node, pos),
+                Err(err) => {
+                    self.counters.retried();
+                    node = err.new;
+                }
+            }
+        };
+        let n = unsafe { node.deref() };
+        self.stamp(&n.inserted);
+        self.len.fetch_add(1, Ordering::Relaxed);
+
+        'levels: for l in 1..height {
+            loop {
+                let next = n.next[l].load(Ordering::Acquire, guard);
+                if next.tag() == 1 {
+                    break 'levels;
+                }
+                if next != pos.succs[l]
+                    && n.next[l]
+                        .compare_exchange(
+                            next,
+                            pos.succs[l],
+                            Ordering::Release,
+                            Ordering::Relaxed,
+                            guard,
+                        )
+                        .is_err()
+                {
+                    self.counters.lost();
+                    break 'levels;
+                }
+                if pos.preds[l][l]
+                    .compare_exchange(
+                        pos.succs[l],
+                        node,
+                        Ordering::Release,
+                        Ordering::Relaxed,
+                        guard,
+                    )
+                    .is_ok()
+                {
+                    break;
+                }
+                // Lost a race with a neighbour; stop if the node itself has
+                // been removed in the meantime.
+                self.counters.retried();
+                pos = self.position(&n.key, guard);
+                if pos.succs[0] != node {
+                    break 'levels;
+                }
+            }
+        }
+        self.finish(node, INSERT_DONE, guard);
+        Ok(n)
+    }
+
+    pub fn remove(&self, key: &K) -> bool {
+        let _pass = self.fences.pass(key);
+        self.remove_unfenced(key)
+    }
+
+    fn remove_unfenced(&self, key: &K) -> bool {
+        let guard = &epoch::pin();
+        self.find_node(key, guard)
+            .is_some_and(|n| self.remove_node(n, guard))
+    }
+
+    // False if someone else got to n first.
+    fn remove_node(&self, n: &Node<K, V>, guard: &Guard) -> bool {
+        if n.removed
+            .compare_exchange(LIVE, PENDING, Ordering::SeqCst, Ordering::SeqCst)
+            .is_err()
+        {
+            return false;
+        }
+        let removed = self.stamp(&n.removed);
+        self.len.fetch_sub(1, Ordering::Relaxed);
+        if self.lazy {
+            return true;
+        }
+        // Checked again under the lock, so a closing snapshot can't miss it.
+        let parked = removed >= self.oldest.load(Ordering::SeqCst)
+            && self.holds.with(|holds| {
+                let seen = removed >= self.oldest.load(Ordering::SeqCst);
+                if seen {
+                    holds.parked.push(n);
+                }
+                seen
+            });
+        if !parked {
+            self.unlink(n, guard);
+        }
+        true
+    }
+
+    // The writes compat hands entries back from, fenced like the rest.
+    pub(crate) fn insert_entry<'g>(&'g self, key: K, val: V, guard: &'g Guard) -> Entry<'g, K, V> {
+        let _pass = self.fences.pass(&key);
+        let node = self.compare_link(key, val, |_| true, guard).unwrap();
+        Entry { node, guard }
+    }
+
+    pub(crate) fn get_or_insert_entry<'g>(
+        &'g self,
+        key: K,
+        val: V,
+        guard: &'g Guard,
+    ) -> Entry<'g, K, V> {
+        let _pass = self.fences.pass(&key);
+        let mut node = Node::new(key, val);
+        loop {
+            if let Some(node) = self.find_node(&node.key, guard) {
+                return Entry { node, guard };
+            }
+            match self.link(node, guard) {
+                Ok(node) => return Entry { node, guard },
+                Err(back) => {
+                    self.counters.retries.fetch_add(1, Ordering::Relaxed);
+                    node = back;
+                }
+            }
+        }
+    }
+
+    pub(crate) fn remove_entry<'g>(&'g self, key: &K, guard: &'g Guard) -> Option<Entry<'g, K, V>> {
+        let _pass = self.fences.pass(key);
+        let node = self.find_node(key, guard)?;
+        self.remove_node(node, guard)
+            .then_some(Entry { node, guard })
+    }
+
+    // Removes this very entry, not whatever the key holds by now.
+    pub(crate) fn remove_exact(&self, entry: &Entry<'_, K, V>) -> bool {
+        let _pass = self.fences.pass(entry.key());
+        self.remove_node(entry.node, entry.guard)
+    }
+
+    // Waits for any locked range overlapping this one to be released, and
+    // for the writes already under way to finish.
+    pub fn lock_range<R: RangeBounds<K>>(&self, range: R) -> RangeLock<'_, K, V>
+    where
+        K: Clone,
+    {
+        let (start, end) = (range.start_bound().cloned(), range.end_bound().cloned());
+        let fences = &self.fences;
+        let mut fenced = fences.lock();
+        while fenced
+            .ranges
+            .iter()
+            .any(|(_, s, e)| !ends_before(e, &start) && !ends_before(&end, s))
+        {
+            fenced = fences
+                .freed
+                .wait(fenced)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        let id = fenced.next;
+        fenced.next += 1;
+        fenced.ranges.push((id, start.clone(), end.clone()));
+        fences.held.fetch_add(1, Ordering::SeqCst);
+        // Writes that started before held went up didn't see it, and ones
+        // that checked their keys before the range went in may be in it;
+        // later ones wait on the lock we hold.
+        while fences.writing.load(Ordering::SeqCst) != 0 {
+            std::thread::yield_now();
+        }
+        RangeLock {
+            map: self,
+            id,
+            start,
+            end,
+        }
+    }
+
+    // Unlinks the tombstones no open snapshot can see and returns how many it
+    // took out. Runs under the snapshot lock, so two compacts never claim the
+    // same node and no snapshot can open at a version below what it frees.
+    // Does nothing unless the map deletes lazily.
+    pub fn compact(&self) -> usize {
+        if !self.lazy {
+            return 0;
+        }
+        let guard = &epoch::pin();
+        self.holds.with(|_| {
+            let oldest = self.oldest.load(Ordering::SeqCst);
+            let mut dead = Vec::new();
+            let mut x = self.head[0].load(Ordering::Acquire, guard);
+            while let Some(node) = unsafe { x.as_ref() } {
+                let next = node.next[0].load(Ordering::Acquire, guard);
+                if next.tag() == 0
+                    && node.removed.load(Ordering::SeqCst) != LIVE
+                    && self.stamp(&node.removed) < oldest
+                {
+                    dead.push(node);
+                }
+                x = next.with_tag(0);
+            }
+            for node in &dead {
+                self.unlink(node, guard);
+            }
+            dead.len()
+        })
+    }
+
+    // A consistent view as of now that later inserts and removes don't
+    // change. Writers aren't held up, but nodes removed while it is open
+    // can't be freed until it is dropped.
+    pub fn snapshot(&self) -> Snapshot<'_, K, V> {
+        let guard = epoch::pin();
+        let floor = self.holds.with(|holds| {
+            let floor = self.clock.load(Ordering::SeqCst);
+            holds.floors.push(floor);
+            self.oldest.fetch_min(floor, Ordering::SeqCst);
+            floor
+        });
+        // Read after publishing the floor: a remove stamped before it may
+        // have been unlinked already, so the snapshot must not see it.
+        let version = self.clock.load(Ordering::SeqCst);
+        Snapshot {
+            map: self,
+            guard,
+            floor,
+            version,
+        }
+    }
+
+    // Only the thread that stamped removed gets here, once it is safe to take
+    // the node out of the list.
+    fn unlink(&self, n: &Node<K, V>, guard: &Guard) {
+        for l in (1..n.next.len()).rev() {
+            let mut next = n.next[l].load(Ordering::Acquire, guard);
+            while next.tag() == 0 {
+                match n.next[l].compare_exchange(
+                    next,
+                    next.with_tag(1),
+                    Ordering::AcqRel,
+                    Ordering::Acquire,
+                    guard,
+                ) {
+                    Ok(_) => break,
+                    Err(err) => {
+                        self.counters.lost();
+                        next = err.current;
+                    }
+                }
+            }
+        }
+        let mut next = n.next[0].load(Ordering::Acquire, guard);
+        while let Err(err) = n.next[0].compare_exchange(
+            next,
+            next.with_tag(1),
+            Ordering::AcqRel,
+            Ordering::Acquire,
+            guard,
+        ) {
+            self.counters.lost();
+            next = err.current;
+        }
+        self.position(&n.key, guard);
+        self.finish(Shared::from(n as *const _), REMOVED, guard);
+    }
+
+    // Settles a PENDING stamp, ticking the clock if nobody has yet.
+    fn stamp(&self, at: &AtomicU64) -> u64 {
+        let current = at.load(Ordering::SeqCst);
+        if current != PENDING {
+            return current;
+        }
+        let tick = self.clock.fetch_add(1, Ordering::SeqCst);
+        match at.compare_exchange(PENDING, tick, Ordering::SeqCst, Ordering::SeqCst) {
+            Ok(_) => tick,
+            Err(current) => current,
+        }
+    }
+
+    // Visits the entries in key order. Entries inserted or removed while the
+    // walk runs may or may not be seen.
+    pub fn for_each<F: FnMut(&K, &V)>(&self, mut f: F) {
+        let guard = &epoch::pin();
+        let mut x = self.head[0].load(Ordering::Acquire, guard);
+        while let Some(node) = unsafe { x.as_ref() } {
+            let next = node.next[0].load(Ordering::Acquire, guard);
+            if next.tag() == 0 && node.removed.load(Ordering::Acquire) == LIVE {
+                f(&node.key, node.value(guard));
+            }
+            x = next;
+        }
+    }
+
+    // The same for the entries in range, starting with a search for its start.
+    pub fn for_each_range<R: RangeBounds<K>, F: FnMut(&K, &V)>(&self, range: R, mut f: F) {
+        let guard = &epoch::pin();
+        let mut x = match range.start_bound() {
+            Bound::Included(start) | Bound::Excluded(start) => self.position(start, guard).succs[0],
+            Bound::Unbounded => self.head[0].load(Ordering::Acquire, guard),
+        };
+        while let Some(node) = unsafe { x.as_ref() } {
+            let past_end = match range.end_bound() {
+                Bound::Included(end) => node.key > *end,
+                Bound::Excluded(end) => node.key >= *end,
+                Bound::Unbounded => false,
+            };
+            if past_end {
+                return;
+            }
+            let next = node.next[0].load(Ordering::Acquire, guard);
+            if next.tag() == 0
+                && node.removed.load(Ordering::Acquire) == LIVE
+                && range.contains(&node.key)
+            {
+                f(&node.key, node.value(guard));
+            }
+            x = next.with_tag(0);
+        }
+    }
+
+    fn finish(&self, node: Shared<'_, Node<K, V>>, bit: u8, guard: &Guard) {
+        let n = unsafe { node.deref() };
+        if n.state.fetch_or(bit, Ordering::AcqRel) | bit == INSERT_DONE | REMOVED {
+            self.position(&n.key, guard);
+            unsafe { guard.defer_destroy(node) };
+        }
+    }
+
+    // Settles the insert stamp of what it finds, so a snapshot taken after
+    // this returns sees the entry too.
+    fn find_node<'g>(&'g self, key: &K, guard: &'g Guard) -> Option<&'g Node<K, V>> {
+        let pos = self.position(key, guard);
+        let node = unsafe { pos.succs[0].as_ref() }.filter(|node| node.key == *key)?;
+        self.stamp(&node.inserted);
+        Some(node)
+    }
+
+    // None when the key is present, otherwise where to link it.
+    fn search<'g>(&'g self, key: &K, guard: &'g Guard) -> Option<Position<'g, K, V>> {
+        let pos = self.position(key, guard);
+        match unsafe { pos.succs[0].as_ref() } {
+            Some(node) if node.key == *key => {
+                self.stamp(&node.inserted);
+                None
+            }
+            _ => Some(pos),
+        }
+    }
+
+    // Finds the last node below key and the one after it on every level,
+    // snipping out tagged nodes along the way. Starts over whenever a
+    // predecessor turns out to be tagged itself. Removed nodes still linked
+    // for a snapshot count as below their own key, so a new entry for it
+    // goes after them; their stamp is settled first so it comes later.
+    fn position<'g>(&'g self, key: &K, guard: &'g Guard) -> Position<'g, K, V> {
+        'retry: loop {
+            let mut pos = Position {
+                preds: [&self.head[..]; MAX_LEVEL],
+                succs: [Shared::null(); MAX_LEVEL],
+            };
+            let mut pred: &'g [Atomic<Node<K, V>>] = &self.head;
+            for l in (0..MAX_LEVEL).rev() {
+                let mut curr = pred[l].load(Ordering::Acquire, guard);
+                if curr.tag() == 1 {
+                    self.counters.retries.fetch_add(1, Ordering::Relaxed);
+                    continue 'retry;
+                }
+                while let Some(c) = unsafe { curr.as_ref() } {
+                    let succ = c.next[l].load(Ordering::Acquire, guard);
+                    if succ.tag() == 1 {
+                        match pred[l].compare_exchange(
+                            curr,
+                            succ.with_tag(0),
+                            Ordering::AcqRel,
+                            Ordering::Acquire,
+                            guard,
+                        ) {
+                            Ok(_) => {
+                                self.counters.helped.fetch_add(1, Ordering::Relaxed);
+                                curr = succ.with_tag(0);
+                            }
+                            Err(_) => {
+                                self.counters.retried();
+                                continue 'retry;
+                            }
+                        }
+                        continue;
+                    }
+                    if c.key < *key || (c.key == *key && c.removed.load(Ordering::SeqCst) != LIVE) {
+                        self.stamp(&c.removed);
+                        pred = &c.next;
+                        curr = succ;
+                    } else {
+                        break;
+                    }
+                }
+                pos.preds[l] = pred;
+                pos.succs[l] = curr;
+            }
+            return pos;
+        }
+    }
+}
+
+impl<K, V> Node<K, V> {
+    fn new(key: K, val: V) -> Owned<Node<K, V>> {
+        let mut node = Node::vacant(key);
+        node.val = Atomic::new(val);
+        node
+    }
+
+    // A node with no value yet, for when the value depends on what the map
+    // turns out to hold.
+    fn vacant(key: K) -> Owned<Node<K, V>> {
+        let height = ((random::<u32>().trailing_zeros() as usize) + 1).min(MAX_LEVEL);
+        Owned::new(Node {
+            key,
+            val: Atomic::null(),
+            state: AtomicU8::new(0),
+            inserted: AtomicU64::new(PENDING),
+            removed: AtomicU64::new(LIVE),
+            next: (0..height).map(|_| Atomic::null()).collect(),
+        })
+    }
+
+    fn value<'g>(&self, guard: &'g Guard) -> &'g V {
+        unsafe { self.val.load(Ordering::Acquire, guard).deref() }
+    }
+}
+
+// By the time a node is destroyed nobody can reach it, nor the value it holds
+// last; values it held before were retired when they were replaced.
+impl<K, V> Drop for Node<K, V> {
+    fn drop(&mut self) {
+        unsafe {
+            let val = self.val.load(Ordering::Relaxed, epoch::unprotected());
+            if !val.is_null() {
+                drop(val.into_owned());
+            }
+        }
+    }
+}
+
+impl<K: Ord + Send + 'static, V: Send + 'static> Default for SkipMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord> Fences<K> {
+    fn lock(&self) -> MutexGuard<'_, Fenced<K>> {
+        self.ranges.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn pass(&self, key: &K) -> Pass<'_> {
+        self.writing.fetch_add(1, Ordering::SeqCst);
+        if self.held.load(Ordering::SeqCst) == 0 {
+            return Pass(&self.writing);
+        }
+        self.writing.fetch_sub(1, Ordering::SeqCst);
+        // Counting under the mutex keeps lock_range, which drains writing
+        // while it holds the mutex, from missing us.
+        let mut fenced = self.lock();
+        while fenced
+            .ranges
+            .iter()
+            .any(|(_, start, end)| (start.as_ref(), end.as_ref()).contains(key))
+        {
+            fenced = self
+                .freed
+                .wait(fenced)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        self.writing.fetch_add(1, Ordering::SeqCst);
+        drop(fenced);
+        Pass(&self.writing)
+    }
+}
+
+impl Drop for Pass<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// Whether every key up to end comes before every key from start on.
+fn ends_before<K: Ord>(end: &Bound<K>, start: &Bound<K>) -> bool {
+    match (end, start) {
+        (Bound::Unbounded, _) | (_, Bound::Unbounded) => false,
+        (Bound::Included(end), Bound::Included(start)) => end < start,
+        (
+            Bound::Included(end) | Bound::Excluded(end),
+            Bound::Included(start) | Bound::Excluded(start),
+        ) => end <= start,
+    }
+}
+
+impl<K: Ord + Send + 'static, V: Send + 'static> RangeLock<'_, K, V> {
+    pub fn get(&self, key: &K) -> Option<V>
+    where
+        V: Clone,
+    {
+        self.map.get(key)
+    }
+
+    pub fn insert(&self, key: K, val: V) -> bool {
+        self.check(&key);
+        self.map.insert_unfenced(key, val)
+    }
+
+    pub fn update<F: FnMut(&V) -> V>(&self, key: &K, f: F) -> bool {
+        self.check(key);
+        self.map.update_unfenced(key, f)
+    }
+
+    pub fn remove(&self, key: &K) -> bool {
+        self.check(key);
+        self.map.remove_unfenced(key)
+    }
+
+    fn check(&self, key: &K) {
+        assert!(
+            (self.start.as_ref(), self.end.as_ref()).contains(key),
+            "key outside the locked range"
+        );
+    }
+}
+
+impl<K: Ord + Send + 'static, V: Send + 'static> Drop for RangeLock<'_, K, V> {
+    fn drop(&mut self) {
+        let fences = &self.map.fences;
+        let mut fenced = fences.lock();
+        fenced.ranges.retain(|(id, _, _)| *id != self.id);
+        fences.held.fetch_sub(1, Ordering::SeqCst);
+        drop(fenced);
+        fences.freed.notify_all();
+    }
+}
+
+// A SkipMap with nothing stored beside the keys, sharing all of its
+// lock-free machinery.
+pub struct SkipSet<K> {
+    map: SkipMap<K, ()>,
+}
+
+impl<K: Ord + Send + Sync + 'static> SkipSet<K> {
+    pub fn new() -> Self {
+        Self {
+            map: SkipMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    // False if the key was there already.
+    pub fn insert(&self, key: K) -> bool {
+        self.map.insert(key, ())
+    }
+
+    pub fn remove(&self, key: &K) -> bool {
+        self.map.remove(key)
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    pub fn for_each<F: FnMut(&K)>(&self, mut f: F) {
+        self.map.for_each(|key, _| f(key));
+    }
+
+    pub fn for_each_range<R: RangeBounds<K>, F: FnMut(&K)>(&self, range: R, mut f: F) {
+        self.map.for_each_range(range, |key, _| f(key));
+    }
+}
+
+impl<K: Ord + Send + Sync + 'static> Default for SkipSet<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// A map with a merge operator registered. Operands are cloned for each
+// attempt, since the operator may have to run again after losing a race.
+pub struct MergeMap<K, V, M> {
+    map: SkipMap<K, V>,
+    op: M,
+}
+
+impl<K: Ord + Send + 'static, V: Send + 'static, M> MergeMap<K, V, M> {
+    pub fn new(op: M) -> Self {
+        Self {
+            map: SkipMap::new(),
+            op,
+        }
+    }
+
+    pub fn merge<O: Clone>(&self, key: K, operand: O)
+    where
+        M: MergeOperator<K, V, O>,
+    {
+        self.map
+            .merge_with(key, |key, old| self.op.merge(key, old, operand.clone()));
+    }
+
+    pub fn map(&self) -> &SkipMap<K, V> {
+        &self.map
+    }
+}
+
+impl<'g, K, V> Entry<'g, K, V> {
+    pub fn key(&self) -> &'g K {
+        &self.node.key
+    }
+
+    // The value the entry holds as of this call; a concurrent update may
+    // swap in a newer one for the next.
+    pub fn value(&self) -> &'g V {
+        self.node.value(self.guard)
+    }
+
+    pub fn is_removed(&self) -> bool {
+        self.node.removed.load(Ordering::Acquire) != LIVE
+    }
+
+    // The live entry after this one. A removed entry still points on along
+    // level 0, so this works from one as well.
+    pub fn next(&self) -> Option<Entry<'g, K, V>> {
+        let next = self.node.next[0].load(Ordering::Acquire, self.guard);
+        Iter {
+            next: next.with_tag(0),
+            guard: self.guard,
+        }
+        .next()
+    }
+
+    pub(crate) fn into_raw(self) -> RawEntry<K, V> {
+        RawEntry(self.node)
+    }
+}
+
+// An entry with its guard split off, for compat, whose entries keep their
+// own guard beside it.
+pub(crate) struct RawEntry<K, V>(*const Node<K, V>);
+
+impl<K, V> RawEntry<K, V> {
+    // guard, or another guard that outlives it, must have been pinned while
+    // the entry was found.
+    pub(crate) unsafe fn get<'g>(&self, guard: &'g Guard) -> Entry<'g, K, V> {
+        Entry {
+            node: &*self.0,
+            guard,
+        }
+    }
+}
+
+impl<K, V> Clone for RawEntry<K, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K, V> Copy for RawEntry<K, V> {}
+
+impl<'g, K, V> Iterator for Iter<'g, K, V> {
+    type Item = Entry<'g, K, V>;
+
+    fn next(&mut self) -> Option<Entry<'g, K, V>> {
+        while let Some(node) = unsafe { self.next.as_ref() } {
+            let next = node.next[0].load(Ordering::Acquire, self.guard);
+            self.next = next.with_tag(0);
+            if next.tag() == 0 && node.removed.load(Ordering::Acquire) == LIVE {
+                return Some(Entry {
+                    node,
+                    guard: self.guard,
+                });
+            }
+        }
+        None
+    }
+}
+
+impl<'a, K: Ord + Send + 'static, V: Send + 'static> Snapshot<'a, K, V> {
+    pub fn iter(&self) -> SnapshotIter<'_, K, V> {
+        SnapshotIter {
+            next: self.map.head[0].load(Ordering::Acquire, &self.guard),
+            version: self.version,
+            map: self.map,
+            guard: &self.guard,
+        }
+    }
+}
+
+// Unlinks the parked nodes no other open snapshot can see.
+impl<K: Ord + Send + 'static, V: Send + 'static> Drop for Snapshot<'_, K, V> {
+    fn drop(&mut self) {
+        let map = self.map;
+        let ready = map.holds.with(|holds| {
+            let at = holds.floors.iter().position(|f| *f == self.floor).unwrap();
+            holds.floors.swap_remove(at);
+            let oldest = holds.floors.iter().copied().min().unwrap_or(LIVE);
+            map.oldest.store(oldest, Ordering::SeqCst);
+            let (ready, parked): (Vec<_>, Vec<_>) = holds
+                .parked
+                .drain(..)
+                .partition(|n| unsafe { (**n).removed.load(Ordering::SeqCst) } < oldest);
+            holds.parked = parked;
+            ready
+        });
+        for n in ready {
+            map.unlink(unsafe { &*n }, &self.guard);
+        }
+    }
+}
+
+impl<'a, K: Ord + Send + 'static, V: Send + 'static> Iterator for SnapshotIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        while let Some(node) = unsafe { self.next.as_ref() } {
+            self.next = node.next[0].load(Ordering::Acquire, self.guard).with_tag(0);
+            if self.map.stamp(&node.inserted) < self.version
+                && self.map.stamp(&node.removed) >= self.version
+            {
+                return Some((&node.key, node.value(self.guard)));
+            }
+        }
+        None
+    }
+}
+
+// With &mut self no other thread is inside the map, and every retired node
+// was unlinked before it was retired, so level 0 holds exactly the nodes
+// still to free, tombstones included.
+impl<K, V> Drop for SkipMap<K, V> {
+    fn drop(&mut self) {
+        unsafe {
+            let guard = epoch::unprotected();
+            let mut x = self.head[0].load(Ordering::Relaxed, guard);
+            while !x.is_null() {
+                let next = x.deref().next[0].load(Ordering::Relaxed, guard);
+                drop(x.into_owned());
+                x = next.with_tag(0);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pin, ContentionMetrics, MergeMap, SkipMap, SkipSet};
+    use std::collections::BTreeSet;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{mpsc, Arc};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn locked_ranges_hold_writers_out() {
+        let map = Arc::new(SkipMap::new());
+        map.insert(1, 100);
+        map.insert(50, 0);
+        let lock = map.lock_range(0..10);
+        // Moves the value from key 1 to key 2. A writer into the range waits
+        // for the move, so its update never finds key 2 missing.
+        let writer = {
+            let map = map.clone();
+            thread::spawn(move || {
+                assert!(map.insert(60, 0));
+                assert!(map.update(&2, |v| v + 1));
+                assert!(!map.remove(&1));
+            })
+        };
+        thread::sleep(Duration::from_millis(20));
+        let val = lock.get(&1).unwrap();
+        assert!(lock.remove(&1));
+        thread::sleep(Duration::from_millis(20));
+        assert!(lock.insert(2, val));
+        assert!(!map.contains_key(&1) && map.get(&2) == Some(100));
+        drop(lock);
+        writer.join().unwrap();
+        assert_eq!(map.get(&2), Some(101));
+        assert!(map.contains_key(&60));
+
+        // Disjoint ranges don't wait on each other; the writes in between
+        // are the usual lock-free ones again once both are gone.
+        let a = map.lock_range(..=2);
+        let b = map.lock_range(3..);
+        assert!(b.update(&50, |v| v + 5));
+        drop((a, b));
+        assert!(map.insert(3, 3));
+        assert_eq!(map.get(&50), Some(5));
+
+        // Nor do writers outside a locked range hold each other up: this
+        // update waits, in the middle of its write, for an insert next to it.
+        let lock = map.lock_range(0..10);
+        let (inserted, wait) = mpsc::channel();
+        let mut wait = Some(wait);
+        thread::scope(|s| {
+            s.spawn(|| {
+                assert!(map.insert(70, 0));
+                inserted.send(()).unwrap();
+            });
+            assert!(map.update(&50, |v| {
+                if let Some(wait) = wait.take() {
+                    assert!(wait.recv_timeout(Duration::from_secs(10)).is_ok());
+                }
+                v + 1
+            }));
+        });
+        drop(lock);
+        assert_eq!(map.get(&50), Some(6));
+    }
+
+    #[test]
+    #[should_panic(expected = "key outside the locked range")]
+    fn range_lock_writes_stay_in_range() {
+        let map = SkipMap::new();
+        let lock = map.lock_range(0..10);
+        lock.insert(10, ());
+    }
+
+    #[test]
+    fn single_thread() {
+        let map = SkipMap::new();
+        assert!(map.insert(2, "b"));
+        assert!(map.insert(1, "a"));
+        assert!(!map.insert(1, "z"));
+        assert_eq!(map.get(&1), Some("a"));
+        assert_eq!(map.len(), 2);
+        assert!(map.remove(&1));
+        assert!(!map.remove(&1));
+        assert!(!map.contains_key(&1));
+        assert!(map.insert(1, "c"));
+        let mut seen = Vec::new();
+        map.for_each(|k, v| seen.push((*k, *v)));
+        assert_eq!(seen, vec![(1, "c"), (2, "b")]);
+    }
+
+    #[test]
+    fn conditional_updates() {
+        let map = SkipMap::new();
+        assert!(map.compare_insert(1, 10, |_| unreachable!()));
+        assert!(!map.compare_insert(1, 5, |old| *old < 5));
+        assert!(map.compare_insert(1, 20, |old| *old < 20));
+        assert_eq!(map.get(&1), Some(20));
+        assert!(map.update(&1, |old| old + 1));
+        assert!(!map.update(&2, |old| old + 1));
+        assert_eq!(map.get(&1), Some(21));
+        assert_eq!(map.len(), 1);
+
+        // Counters and last-writer-wins merges under contention.
+        let map = SkipMap::new();
+        map.insert("count", 0u64);
+        thread::scope(|s| {
+            for t in 0..8u64 {
+                let map = &map;
+                s.spawn(move || {
+                    for i in 0..1_000u64 {
+                        map.update(&"count", |n| n + 1);
+                        let ts = i * 8 + t;
+                        map.compare_insert("latest", ts, |old| *old < ts);
+                    }
+                });
+            }
+        });
+        assert_eq!(map.get(&"count"), Some(8_000));
+        assert_eq!(map.get(&"latest"), Some(7_999));
+    }
+
+    #[test]
+    fn guarded_references_outlive_removal() {
+        let map = SkipMap::new();
+        for i in 0..10 {
+            map.insert(i, i.to_string());
+        }
+        let guard = &pin();
+        let five = map.get_entry(&5, guard).unwrap();
+        assert_eq!((*five.key(), five.value().as_str()), (5, "5"));
+        assert!(map.remove(&5));
+        map.update(&6, |_| "six".to_string());
+        assert!(five.is_removed());
+        assert_eq!(five.value(), "5");
+        assert!(map.get_entry(&5, guard).is_none());
+        assert_eq!(map.get_entry(&6, guard).unwrap().value(), "six");
+        assert_eq!(*map.front(guard).unwrap().key(), 0);
+        assert_eq!(*map.back(guard).unwrap().key(), 9);
+        assert!(map.remove(&9));
+        assert_eq!(*map.back(guard).unwrap().key(), 8);
+        let keys: Vec<_> = map.iter(guard).map(|e| *e.key()).collect();
+        assert_eq!(keys, [0, 1, 2, 3, 4, 6, 7, 8]);
+
+        let empty = SkipMap::<u32, u32>::new();
+        assert!(empty.front(guard).is_none());
+        assert!(empty.back(guard).is_none());
+    }
+
+    #[test]
+    fn merges_race_without_losing_operands() {
+        let map = Arc::new(MergeMap::new(|_: &u32, old: Option<&u64>, n: u64| {
+            old.copied().unwrap_or(0) + n
+        }));
+        let threads: Vec<_> = (0..4)
+            .map(|t| {
+                let map = Arc::clone(&map);
+                thread::spawn(move || {
+                    for i in 0..1_000u32 {
+                        map.merge(i % 10, 1);
+                        if i % 100 == 0 {
+                            map.merge(100 + t, i as u64);
+                        }
+                    }
+                })
+            })
+            .collect();
+        for t in threads {
+            t.join().unwrap();
+        }
+        for k in 0..10 {
+            assert_eq!(map.map().get(&k), Some(400));
+        }
+        assert_eq!(map.map().get(&101), Some(4_500));
+        assert_eq!(map.map().len(), 14);
+    }
+
+    #[test]
+    fn set_of_watermarks() {
+        let set = SkipSet::new();
+        thread::scope(|s| {
+            for t in 0..4u64 {
+                let set = &set;
+                s.spawn(move || {
+                    for i in 0..500 {
+                        set.insert(i * 4 + t);
+                        assert!(!set.insert(i * 4 + t));
+                    }
+                    for i in (0..500).step_by(2) {
+                        assert!(set.remove(&(i * 4 + t)));
+                    }
+                });
+            }
+        });
+        assert_eq!(set.len(), 1_000);
+        assert!(set.contains(&4));
+        assert!(!set.contains(&8));
+        let mut seen = Vec::new();
+        set.for_each_range(5..=20, |k| seen.push(*k));
+        assert_eq!(seen, [5, 6, 7, 12, 13, 14, 15, 20]);
+        seen.clear();
+        set.for_each_range(
+            (std::ops::Bound::Excluded(1_995), std::ops::Bound::Unbounded),
+            |k| seen.push(*k),
+        );
+        assert_eq!(seen, [1_996, 1_997, 1_998, 1_999]);
+        let mut count = 0;
+        set.for_each(|_| count += 1);
+        assert_eq!(count, 1_000);
+    }
+
+    #[test]
+    fn contention_is_counted() {
+        let map = SkipMap::new();
+        for i in 0..10 {
+            map.insert(i, 0u64);
+        }
+        map.update(&3, |n| n + 1);
+        assert_eq!(map.metrics(), ContentionMetrics::default());
+        assert!(map.remove(&3));
+        map.get(&4);
+        let metrics = map.metrics();
+        assert!(metrics.helped >= 1);
+        assert_eq!((metrics.cas_failures, metrics.retries), (0, 0));
+
+        // Every lost update runs f once more and is counted once.
+        let map = SkipMap::new();
+        map.insert("hot", 0u64);
+        let calls = AtomicUsize::new(0);
+        thread::scope(|s| {
+            for _ in 0..8 {
+                let (map, calls) = (&map, &calls);
+                s.spawn(move || {
+                    for _ in 0..1_000 {
+                        map.update(&"hot", |n| {
+                            calls.fetch_add(1, Ordering::Relaxed);
+                            n + 1
+                        });
+                    }
+                });
+            }
+        });
+        let metrics = map.metrics();
+        assert_eq!(map.get(&"hot"), Some(8_000));
+        assert_eq!(metrics.retries, metrics.cas_failures);
+        assert_eq!(
+            calls.load(Ordering::Relaxed) as u64,
+            8_000 + metrics.retries
+        );
+    }
+
+    #[test]
+    fn lazy_delete_leaves_tombstones_for_compact() {
+        let map = SkipMap::with_lazy_delete();
+        for i in 0..100 {
+            map.insert(i, i);
+        }
+        for i in (0..100).step_by(2) {
+            assert!(map.remove(&i));
+        }
+        assert!(!map.remove(&0));
+        assert!(!map.contains_key(&0));
+        assert_eq!(map.get(&1), Some(1));
+        assert!(map.insert(0, 1000));
+        assert_eq!(map.get(&0), Some(1000));
+        assert_eq!(map.len(), 51);
+        let mut keys = Vec::new();
+        map.for_each(|k, _| keys.push(*k));
+        assert_eq!(keys.len(), 51);
+
+        // A snapshot keeps what it can see until it closes.
+        let snap = map.snapshot();
+        assert!(map.remove(&1));
+        assert_eq!(map.compact(), 50);
+        assert_eq!(snap.iter().count(), 51);
+        drop(snap);
+        assert_eq!(map.compact(), 1);
+        assert_eq!(map.compact(), 0);
+        assert_eq!(map.len(), 50);
+
+        let mut keys = Vec::new();
+        map.for_each(|k, _| keys.push(*k));
+        assert_eq!(
+            keys,
+            [0].into_iter()
+                .chain((3..100).step_by(2))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(SkipMap::<u32, u32>::new().compact(), 0);
+    }
+
+    #[test]
+    fn snapshot_is_point_in_time() {
+        let map = SkipMap::new();
+        for i in 0..100 {
+            map.insert(i, i);
+        }
+        let snap = map.snapshot();
+        for i in (0..100).step_by(2) {
+            assert!(map.remove(&i));
+        }
+        for i in 100..200 {
+            map.insert(i, i);
+        }
+        assert!(map.insert(4, 400));
+        assert!(map.remove(&4));
+        assert!(map.insert(4, 4000));
+        assert_eq!(map.get(&4), Some(4000));
+        assert_eq!(map.len(), 151);
+
+        let seen: Vec<_> = snap.iter().map(|(k, v)| (*k, *v)).collect();
+        assert_eq!(seen, (0..100).map(|i| (i, i)).collect::<Vec<_>>());
+        let later = map.snapshot();
+        drop(snap);
+        assert_eq!(later.iter().count(), 151);
+        assert_eq!(later.iter().find(|(k, _)| **k == 4), Some((&4, &4000)));
+        drop(later);
+
+        let mut keys = Vec::new();
+        map.for_each(|k, _| keys.push(*k));
+        assert_eq!(keys.len(), 151);
+        assert!(keys.windows(2).all(|w| w[0] < w[1]));
+        assert!(map
+            .holds
+            .with(|holds| holds.parked.is_empty() && holds.floors.is_empty()));
+    }
+
+    #[test]
+    fn snapshots_while_writing() {
+        let map = SkipMap::new();
+        for i in 0..1_000u64 {
+            map.insert(i, 0);
+        }
+        let done = std::sync::atomic::AtomicBool::new(false);
+        thread::scope(|s| {
+            for t in 0..4u64 {
+                let (map, done) = (&map, &done);
+                s.spawn(move || {
+                    let mut i = t;
+                    while !done.load(Ordering::Relaxed) {
+                        let key = (i * 7919) % 1_000;
+                        if map.remove(&key) {
+                            map.insert(key, i);
+                        }
+                        i += 4;
+                    }
+                });
+            }
+            for _ in 0..200 {
+                let snap = map.snapshot();
+                let first: Vec<_> = snap.iter().map(|(k, v)| (*k, *v)).collect();
+                // Every key is removed and put straight back, so a consistent
+                // view holds each one at most once and misses few.
+                assert!(first.windows(2).all(|w| w[0].0 < w[1].0));
+                assert!(first.len() >= 1_000 - 4 && first.len() <= 1_000);
+                assert_eq!(
+                    first,
+                    snap.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>()
+                );
+            }
+            done.store(true, Ordering::Relaxed);
+        });
+        assert_eq!(map.len(), 1_000);
+    }
+
+    #[test]
+    fn concurrent_inserts_and_removes() {
+        let map = SkipMap::new();
+        let threads = 8;
+        let per_thread = 2_000;
+        thread::scope(|s| {
+            for t in 0..threads {
+                let map = &map;
+                s.spawn(move || {
+                    for i in 0..per_thread {
+                        assert!(map.insert(i * threads + t, t));
+                    }
+                    for i in (0..per_thread).filter(|i| i % 2 == 0) {
+                        assert!(map.remove(&(i * threads + t)));
+                    }
+                });
+            }
+        });
+        let mut keys = Vec::new();
+        map.for_each(|k, _| keys.push(*k));
+        let expected: Vec<usize> = (0..threads * per_thread)
+            .filter(|k| (k / threads) % 2 == 1)
+            .collect();
+        assert_eq!(keys, expected);
+        assert_eq!(map.len(), expected.len());
+    }
+
+    #[test]
+    fn contended_keys() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        struct Counted(Arc<AtomicUsize>);
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let map = SkipMap::new();
+        let inserted = AtomicUsize::new(0);
+        thread::scope(|s| {
+            for t in 0..8u64 {
+                let (map, drops, inserted) = (&map, &drops, &inserted);
+                s.spawn(move || {
+                    for i in 0..5_000u64 {
+                        let key = (i * 31 + t * 7) % 64;
+                        if (i + t) % 3 == 0 {
+                            map.remove(&key);
+                        } else if map.insert(key, Counted(drops.clone())) {
+                            inserted.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                });
+            }
+        });
+        let mut keys = BTreeSet::new();
+        map.for_each(|k, _| assert!(keys.insert(*k)));
+        assert_eq!(keys.len(), map.len());
+        drop(map);
+        // Removed entries are freed once the epoch moves on; flush until it
+        // has. Rejected duplicates were dropped on the spot.
+        for _ in 0..1_000 {
+            crossbeam_epoch::pin().flush();
+            if drops.load(Ordering::Relaxed) >= inserted.load(Ordering::Relaxed) {
+                break;
+            }
+        }
+        assert!(drops.load(Ordering::Relaxed) >= inserted.load(Ordering::Relaxed));
+    }
+}
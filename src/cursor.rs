@@ -0,0 +1,379 @@
+use crate::{Node, SkipList, MAX_LEVEL};
+use core::borrow::Borrow;
+use core::error::Error;
+use core::fmt;
+use core::ptr::NonNull;
+
+// A cursor sits on an entry or on the "ghost" position past the last entry and
+// before the first, where it is None. Moving off either end lands on the
+// ghost and moving on from the ghost wraps around, as with LinkedList's
+// cursors. Stepping uses level 0 and prev, so it is O(1).
+pub struct Cursor<'a, K, V> {
+    list: &'a SkipList<K, V>,
+    current: Option<NonNull<Node<K, V>>>,
+}
+
+pub struct CursorMut<'a, K, V> {
+    list: &'a mut SkipList<K, V>,
+    current: Option<NonNull<Node<K, V>>>,
+}
+
+// A cursor position kept without borrowing the list, for holding on to a
+// place across writes. It only turns back into a Cursor on the list it came
+// from and while that list's generation is unchanged; any insert or remove
+// since, which could have freed its node, makes it stale instead.
+#[derive(Debug)]
+pub struct RawCursor<K, V> {
+    list: u64,
+    current: Option<NonNull<Node<K, V>>>,
+    generation: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StaleCursor;
+
+impl fmt::Display for StaleCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("the list has changed since the cursor was detached")
+    }
+}
+
+impl Error for StaleCursor {}
+
+// Returned by the cursor inserts when the key would not sit between the
+// neighbours of the insert position, with the entry handed back.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnorderedKey<K, V> {
+    pub key: K,
+    pub val: V,
+}
+
+impl<K: fmt::Debug, V> fmt::Display for UnorderedKey<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "key {:?} is out of order at the cursor", self.key)
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> Error for UnorderedKey<K, V> {}
+
+impl<K, V> SkipList<K, V> {
+    pub fn cursor_front(&self) -> Cursor<'_, K, V> {
+        Cursor {
+            current: unsafe { self.head.as_ref().tower[0] },
+            list: self,
+        }
+    }
+
+    pub fn cursor_back(&self) -> Cursor<'_, K, V> {
+        Cursor {
+            current: self.last_node(),
+            list: self,
+        }
+    }
+
+    pub fn cursor_front_mut(&mut self) -> CursorMut<'_, K, V> {
+        CursorMut {
+            current: unsafe { self.head.as_ref().tower[0] },
+            list: self,
+        }
+    }
+
+    pub fn cursor_back_mut(&mut self) -> CursorMut<'_, K, V> {
+        CursorMut {
+            current: self.last_node(),
+            list: self,
+        }
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn attach(&self, raw: &RawCursor<K, V>) -> Result<Cursor<'_, K, V>, StaleCursor> {
+        if raw.list != self.id || raw.generation != self.generation {
+            return Err(StaleCursor);
+        }
+        Ok(Cursor {
+            list: self,
+            current: raw.current,
+        })
+    }
+
+    fn step_next(&self, current: Option<NonNull<Node<K, V>>>) -> Option<NonNull<Node<K, V>>> {
+        match current {
+            Some(node_ptr) => unsafe { node_ptr.as_ref().tower[0] },
+            None => unsafe { self.head.as_ref().tower[0] },
+        }
+    }
+
+    fn step_prev(&self, current: Option<NonNull<Node<K, V>>>) -> Option<NonNull<Node<K, V>>> {
+        match current {
+            Some(node_ptr) => unsafe { node_ptr.as_ref().prev },
+            None => self.last_node(),
+        }
+    }
+}
+
+fn entry<'a, K, V>(node_ptr: Option<NonNull<Node<K, V>>>) -> Option<(&'a K, &'a V)> {
+    node_ptr.map(|node_ptr| unsafe {
+        let node = &*node_ptr.as_ptr();
+        (&node.key, node.val())
+    })
+}
+
+impl<'a, K, V> Cursor<'a, K, V> {
+    pub fn key_value(&self) -> Option<(&'a K, &'a V)> {
+        entry(self.current)
+    }
+
+    pub fn peek_next(&self) -> Option<(&'a K, &'a V)> {
+        entry(self.list.step_next(self.current))
+    }
+
+    pub fn peek_prev(&self) -> Option<(&'a K, &'a V)> {
+        entry(self.list.step_prev(self.current))
+    }
+
+    pub fn move_next(&mut self) {
+        self.current = self.list.step_next(self.current);
+    }
+
+    pub fn move_prev(&mut self) {
+        self.current = self.list.step_prev(self.current);
+    }
+
+    pub fn detach(&self) -> RawCursor<K, V> {
+        RawCursor {
+            list: self.list.id,
+            current: self.current,
+            generation: self.list.generation,
+        }
+    }
+}
+
+impl<K: Ord, V> Cursor<'_, K, V> {
+    // Moves to the first entry with a key >= key, or the ghost if none.
+    pub fn seek<Q>(&mut self, key: &Q)
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.current = self.list.seek(key, false);
+    }
+}
+
+impl<K, V> Clone for Cursor<'_, K, V> {
+    fn clone(&self) -> Self {
+        Cursor {
+            list: self.list,
+            current: self.current,
+        }
+    }
+}
+
+impl<K, V> Clone for RawCursor<K, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K, V> Copy for RawCursor<K, V> {}
+
+impl<K, V> CursorMut<'_, K, V> {
+    pub fn key_value(&self) -> Option<(&K, &V)> {
+        entry(self.current)
+    }
+
+    pub fn key_value_mut(&mut self) -> Option<(&K, &mut V)> {
+        self.current.map(|node_ptr| unsafe {
+            let node = &mut *node_ptr.as_ptr();
+            node.entry_mut()
+        })
+    }
+
+    pub fn peek_next(&self) -> Option<(&K, &V)> {
+        entry(self.list.step_next(self.current))
+    }
+
+    pub fn peek_prev(&self) -> Option<(&K, &V)> {
+        entry(self.list.step_prev(self.current))
+    }
+
+    pub fn move_next(&mut self) {
+        self.current = self.list.step_next(self.current);
+    }
+
+    pub fn move_prev(&mut self) {
+        self.current = self.list.step_prev(self.current);
+    }
+
+    pub fn as_cursor(&self) -> Cursor<'_, K, V> {
+        Cursor {
+            list: self.list,
+            current: self.current,
+        }
+    }
+}
+
+impl<K: Ord, V> CursorMut<'_, K, V> {
+    pub fn seek<Q>(&mut self, key: &Q)
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.current = self.list.seek(key, false);
+    }
+
+    // Removes the current entry and moves on to the one after it. Does nothing
+    // on the ghost.
+    pub fn remove_current(&mut self) -> Option<(K, V)> {
+        let node_ptr = self.current?;
+        let mut update: [Option<NonNull<Node<K, V>>>; MAX_LEVEL] = [None; MAX_LEVEL];
+        unsafe {
+            let key = &(*node_ptr.as_ptr()).key;
+            self.list.find_by(|k| k < key, &mut update);
+            self.current = node_ptr.as_ref().tower[0];
+            Some(self.list.unlink(node_ptr, &update))
+        }
+    }
+
+    // Both leave the cursor where it is. After the ghost means at the front,
+    // and before it at the back.
+    pub fn insert_after(&mut self, key: K, val: V) -> Result<(), UnorderedKey<K, V>> {
+        let next = self.list.step_next(self.current);
+        let fits = entry(self.current).is_none_or(|(k, _)| *k < key)
+            && entry(next).is_none_or(|(k, _)| key < *k);
+        self.insert_checked(fits, key, val)
+    }
+
+    pub fn insert_before(&mut self, key: K, val: V) -> Result<(), UnorderedKey<K, V>> {
+        let prev = self.list.step_prev(self.current);
+        let fits = entry(prev).is_none_or(|(k, _)| *k < key)
+            && entry(self.current).is_none_or(|(k, _)| key < *k);
+        self.insert_checked(fits, key, val)
+    }
+
+    fn insert_checked(&mut self, fits: bool, key: K, val: V) -> Result<(), UnorderedKey<K, V>> {
+        if !fits {
+            return Err(UnorderedKey { key, val });
+        }
+        let mut update: [Option<NonNull<Node<K, V>>>; MAX_LEVEL] = [None; MAX_LEVEL];
+        unsafe { self.list.find_by(|k| *k < key, &mut update) };
+        self.list.link(key, val, &mut update);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StaleCursor, UnorderedKey};
+    use crate::SkipList;
+
+    fn keys(sk: &SkipList<u32, u32>) -> Vec<u32> {
+        sk.iter().map(|(k, _)| *k).collect()
+    }
+
+    #[test]
+    fn walk_and_seek() {
+        let mut sk = SkipList::new();
+        for i in 0..10 {
+            sk.insert(i * 10, i);
+        }
+        let mut c = sk.cursor_front();
+        assert_eq!(c.key_value(), Some((&0, &0)));
+        assert_eq!(c.peek_prev(), None);
+        c.move_prev();
+        assert_eq!(c.key_value(), None);
+        c.move_prev();
+        assert_eq!(c.key_value(), Some((&90, &9)));
+        c.seek(&35);
+        assert_eq!(c.key_value(), Some((&40, &4)));
+        assert_eq!(c.peek_next(), Some((&50, &5)));
+        assert_eq!(c.peek_prev(), Some((&30, &3)));
+        c.seek(&95);
+        assert_eq!(c.key_value(), None);
+        c.move_next();
+        assert_eq!(c.key_value(), Some((&0, &0)));
+        assert_eq!(sk.cursor_back().key_value(), Some((&90, &9)));
+    }
+
+    #[test]
+    fn edit_at_cursor() {
+        let mut sk = SkipList::new();
+        for i in 0..10 {
+            sk.insert(i * 10, i);
+        }
+        let mut c = sk.cursor_front_mut();
+        c.seek(&30);
+        *c.key_value_mut().unwrap().1 += 100;
+        assert_eq!(c.remove_current(), Some((30, 103)));
+        assert_eq!(c.key_value(), Some((&40, &4)));
+        assert_eq!(c.insert_before(35, 0), Ok(()));
+        assert_eq!(c.insert_after(45, 0), Ok(()));
+        assert_eq!(c.insert_after(60, 1), Err(UnorderedKey { key: 60, val: 1 }));
+        assert_eq!(
+            c.insert_before(40, 1),
+            Err(UnorderedKey { key: 40, val: 1 })
+        );
+        assert_eq!(c.key_value(), Some((&40, &4)));
+        c.move_next();
+        assert_eq!(c.key_value(), Some((&45, &0)));
+
+        let mut c = sk.cursor_back_mut();
+        c.move_next();
+        assert!(c.insert_after(0, 0).is_err());
+        assert_eq!(c.insert_before(100, 0), Ok(()));
+        c.move_prev();
+        c.move_prev();
+        assert_eq!(c.remove_current(), Some((90, 9)));
+        assert_eq!(c.key_value(), Some((&100, &0)));
+        assert_eq!(c.remove_current(), Some((100, 0)));
+        assert_eq!(c.key_value(), None);
+        assert_eq!(keys(&sk), [0, 10, 20, 35, 40, 45, 50, 60, 70, 80]);
+        assert_eq!(sk.get_by_index(3), Some((&35, &0)));
+        assert_eq!(sk.rank(&80), 9);
+    }
+
+    #[test]
+    fn raw_cursors_go_stale_on_structural_change() {
+        let mut sk: SkipList<u32, u32> = (0..10).map(|i| (i * 10, i)).collect();
+        let mut c = sk.cursor_front();
+        c.seek(&30);
+        let raw = c.detach();
+        *sk.get_mut(&30).unwrap() += 100;
+        let mut c = sk.attach(&raw).unwrap();
+        assert_eq!(c.key_value(), Some((&30, &103)));
+        c.move_next();
+        let next = c.detach();
+
+        let before = sk.generation();
+        sk.insert(35, 0);
+        assert_ne!(sk.generation(), before);
+        assert_eq!(sk.attach(&raw).err(), Some(StaleCursor));
+        let raw = sk.cursor_back().detach();
+        sk.remove(&40);
+        assert!(sk.attach(&next).is_err());
+        assert!(sk.attach(&raw).is_err());
+
+        let mut ghost = sk.cursor_front();
+        ghost.move_prev();
+        let ghost = ghost.detach();
+        let other = sk.clone();
+        assert!(other.attach(&ghost).is_err());
+        let tail = sk.split_off(&50);
+        assert!(sk.attach(&ghost).is_err());
+        assert!(tail.attach(&tail.cursor_front().detach()).is_ok());
+        sk.clear();
+        let raw = sk.cursor_front().detach();
+        assert_eq!(sk.attach(&raw).unwrap().key_value(), None);
+
+        // A new list at the same generation, whose head most allocators put
+        // where the dropped one's was, still isn't the same list.
+        let sk: SkipList<u32, u32> = (0..3).map(|i| (i, i)).collect();
+        let (generation, raw) = (sk.generation(), sk.cursor_front().detach());
+        drop(sk);
+        let sk: SkipList<u32, u32> = (0..3).map(|i| (i, i)).collect();
+        assert_eq!(sk.generation(), generation);
+        assert_eq!(sk.attach(&raw).err(), Some(StaleCursor));
+    }
+}
@@ -0,0 +1,144 @@
+use crate::{Node, SkipList};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+use core::ptr::NonNull;
+
+impl<K: fmt::Debug, V> SkipList<K, V> {
+    // One row per level, top first, with every key in its own column: a key
+    // shows up on the levels its tower reaches and dashes run under the links
+    // that pass over it. The output only depends on the keys and tower
+    // heights, so it can be compared or pasted into a bug report.
+    //
+    //     1 head ------> 2 -> nil
+    //     0 head -> 1 -> 2 -> nil
+    pub fn debug_dump(&self) -> String {
+        let labels = self.dump_labels();
+        let levels = self.level.max(1);
+        let width = format!("{}", levels - 1).len();
+        let mut out = String::new();
+        for l in (0..levels).rev() {
+            let _ = write!(out, "{:>width$} head", l);
+            let mut passing = false;
+            for (label, height) in &labels {
+                let arrow = if passing { "--> " } else { " -> " };
+                if *height > l {
+                    out.push_str(arrow);
+                    out.push_str(label);
+                    passing = false;
+                } else {
+                    out.push(if passing { '-' } else { ' ' });
+                    out.extend(core::iter::repeat_n('-', label.chars().count() + 3));
+                    passing = true;
+                }
+            }
+            out.push_str(if passing { "--> nil\n" } else { " -> nil\n" });
+        }
+        out
+    }
+
+    // The same structure as a Graphviz digraph: each node a record with one
+    // field per level, and an edge for every forward link.
+    pub fn to_dot(&self) -> String {
+        let labels = self.dump_labels();
+        let levels = self.level.max(1);
+        let mut out =
+            String::from("digraph skiplist {\n    rankdir=LR;\n    node [shape=record];\n");
+        let fields = |name: &str, height: usize| {
+            let mut s = String::new();
+            for l in (0..height).rev() {
+                let _ = write!(s, "<l{}> |", l);
+            }
+            s.push_str(name);
+            s
+        };
+        let _ = writeln!(out, "    head [label=\"{}\"];", fields("head", levels));
+        for (i, (label, height)) in labels.iter().enumerate() {
+            let _ = writeln!(
+                out,
+                "    n{} [label=\"{}\"];",
+                i,
+                fields(&escape(label), *height)
+            );
+        }
+        out.push_str("    nil [shape=plaintext];\n");
+        // last[l] is the node whose level l link is still to be drawn.
+        let mut last: Vec<String> = (0..levels).map(|_| String::from("head")).collect();
+        for (i, (_, height)) in labels.iter().enumerate() {
+            for (l, from) in last.iter_mut().enumerate().take(*height) {
+                let to = format!("n{}", i);
+                let _ = writeln!(out, "    {}:l{} -> {}:l{};", from, l, to, l);
+                *from = to;
+            }
+        }
+        for (l, from) in last.iter().enumerate() {
+            let _ = writeln!(out, "    {}:l{} -> nil;", from, l);
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    fn dump_labels(&self) -> Vec<(String, usize)> {
+        let mut labels = Vec::with_capacity(self.size);
+        let mut x: Option<NonNull<Node<K, V>>> = unsafe { self.head.as_ref().tower[0] };
+        while let Some(node_ptr) = x {
+            let node = unsafe { node_ptr.as_ref() };
+            labels.push((format!("{:?}", node.key), node.height()));
+            x = node.tower[0];
+        }
+        labels
+    }
+}
+
+// Characters that record labels treat as syntax.
+fn escape(label: &str) -> String {
+    let mut out = String::with_capacity(label.len());
+    for c in label.chars() {
+        if matches!(c, '"' | '\\' | '{' | '}' | '|' | '<' | '>' | ' ') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Levels, SkipList};
+
+    fn by_trailing_zeros(key: &u32, levels: Levels) -> usize {
+        (key.trailing_zeros() as usize + 1).min(levels.max)
+    }
+
+    #[test]
+    fn dumps_are_deterministic() {
+        let mut sk = SkipList::new();
+        sk.level_of = Some(by_trailing_zeros);
+        for i in 1..=4 {
+            sk.insert(i, ());
+        }
+        assert_eq!(
+            sk.debug_dump(),
+            "2 head ----------------> 4 -> nil\n\
+             1 head ------> 2 ------> 4 -> nil\n\
+             0 head -> 1 -> 2 -> 3 -> 4 -> nil\n"
+        );
+        assert_eq!(SkipList::<u32, ()>::new().debug_dump(), "0 head -> nil\n");
+
+        let mut sk = SkipList::new();
+        sk.level_of = Some(|_: &&str, _| 1);
+        sk.insert("a|b", ());
+        let dot = sk.to_dot();
+        assert!(
+            dot.contains("n0 [label=\"<l0> |\\\"a\\|b\\\"\"];"),
+            "{}",
+            dot
+        );
+        assert!(
+            dot.contains("head:l0 -> n0:l0;\n    n0:l0 -> nil;\n}"),
+            "{}",
+            dot
+        );
+    }
+}
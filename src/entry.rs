@@ -0,0 +1,260 @@
+use crate::{Node, SkipList, MAX_LEVEL};
+use core::error::Error;
+use core::fmt;
+use core::mem;
+use core::ptr::NonNull;
+
+pub enum Entry<'a, K, V> {
+    Occupied(OccupiedEntry<'a, K, V>),
+    Vacant(VacantEntry<'a, K, V>),
+}
+
+// Both keep the predecessors from the one search that made the entry, so
+// inserting into a vacant entry or removing an occupied one needs no second
+// traversal.
+pub struct OccupiedEntry<'a, K, V> {
+    list: &'a mut SkipList<K, V>,
+    node: NonNull<Node<K, V>>,
+    update: [Option<NonNull<Node<K, V>>>; MAX_LEVEL],
+}
+
+pub struct VacantEntry<'a, K, V> {
+    list: &'a mut SkipList<K, V>,
+    key: K,
+    update: [Option<NonNull<Node<K, V>>>; MAX_LEVEL],
+}
+
+// Returned by try_insert when the key is taken: the entry that is there and
+// the value that was not inserted.
+pub struct OccupiedError<'a, K, V> {
+    pub entry: OccupiedEntry<'a, K, V>,
+    pub value: V,
+}
+
+impl<K: fmt::Debug + Ord, V: fmt::Debug> fmt::Debug for OccupiedError<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OccupiedError")
+            .field("key", self.entry.key())
+            .field("old_value", self.entry.get())
+            .field("new_value", &self.value)
+            .finish()
+    }
+}
+
+impl<K: fmt::Debug + Ord, V: fmt::Debug> fmt::Display for OccupiedError<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "failed to insert {:?}, key {:?} already exists with value {:?}",
+            self.value,
+            self.entry.key(),
+            self.entry.get()
+        )
+    }
+}
+
+impl<K: fmt::Debug + Ord, V: fmt::Debug> Error for OccupiedError<'_, K, V> {}
+
+impl<K: Ord, V> SkipList<K, V> {
+    pub fn get_or_insert_with<F: FnOnce() -> V>(&mut self, key: K, default: F) -> &mut V {
+        self.entry(key).or_insert_with(default)
+    }
+
+    // Inserts only if key is missing; otherwise val comes back in the error
+    // and the list is left alone. The error carries the entry's predecessors
+    // so it can still be used to remove or replace what's there.
+    #[allow(clippy::result_large_err)]
+    pub fn try_insert(&mut self, key: K, val: V) -> Result<&mut V, OccupiedError<'_, K, V>> {
+        match self.entry(key) {
+            Entry::Occupied(entry) => Err(OccupiedError { entry, value: val }),
+            Entry::Vacant(entry) => Ok(entry.insert(val)),
+        }
+    }
+
+    pub fn first_entry(&mut self) -> Option<OccupiedEntry<'_, K, V>> {
+        let node = unsafe { self.head.as_ref().tower[0]? };
+        Some(OccupiedEntry {
+            update: [Some(self.head); MAX_LEVEL],
+            list: self,
+            node,
+        })
+    }
+
+    // The predecessors are found by position, so no keys are compared.
+    pub fn last_entry(&mut self) -> Option<OccupiedEntry<'_, K, V>> {
+        let index = self.size.checked_sub(1)?;
+        let mut update: [Option<NonNull<Node<K, V>>>; MAX_LEVEL] = [None; MAX_LEVEL];
+        let node = unsafe {
+            self.find_index_predecessors(index, &mut update);
+            update[0].unwrap().as_ref().tower[0].unwrap()
+        };
+        Some(OccupiedEntry {
+            list: self,
+            node,
+            update,
+        })
+    }
+
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
+        let mut update: [Option<NonNull<Node<K, V>>>; MAX_LEVEL] = [None; MAX_LEVEL];
+        match unsafe { self.find_gt_or_eq_node(&key, &mut update) } {
+            Some(node) if unsafe { node.as_ref().key == key } => Entry::Occupied(OccupiedEntry {
+                list: self,
+                node,
+                update,
+            }),
+            _ => Entry::Vacant(VacantEntry {
+                list: self,
+                key,
+                update,
+            }),
+        }
+    }
+}
+
+impl<'a, K: Ord, V> Entry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(e) => e.key(),
+            Entry::Vacant(e) => e.key(),
+        }
+    }
+
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(e) => e.insert(default()),
+        }
+    }
+
+    pub fn or_insert_with_key<F: FnOnce(&K) -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(e) => {
+                let val = default(&e.key);
+                e.insert(val)
+            }
+        }
+    }
+
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    pub fn and_modify<F: FnOnce(&mut V)>(mut self, f: F) -> Self {
+        if let Entry::Occupied(e) = &mut self {
+            f(e.get_mut());
+        }
+        self
+    }
+}
+
+impl<'a, K: Ord, V> OccupiedEntry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        unsafe { &(*self.node.as_ptr()).key }
+    }
+
+    pub fn get(&self) -> &V {
+        unsafe { (*self.node.as_ptr()).val() }
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        unsafe { (*self.node.as_ptr()).val_mut() }
+    }
+
+    pub fn into_mut(self) -> &'a mut V {
+        unsafe { (*self.node.as_ptr()).val_mut() }
+    }
+
+    pub fn insert(&mut self, val: V) -> V {
+        mem::replace(self.get_mut(), val)
+    }
+
+    pub fn remove_entry(self) -> (K, V) {
+        unsafe { self.list.unlink(self.node, &self.update) }
+    }
+
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+}
+
+impl<'a, K: Ord, V> VacantEntry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    pub fn insert(mut self, val: V) -> &'a mut V {
+        let node = self.list.link(self.key, val, &mut self.update);
+        unsafe { (*node.as_ptr()).val_mut() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Entry;
+    use crate::SkipList;
+
+    #[test]
+    fn insert_only_if_missing() {
+        let mut sk = SkipList::new();
+        *sk.get_or_insert_with("a", || 1) += 10;
+        assert_eq!(*sk.get_or_insert_with("a", || unreachable!()), 11);
+        assert_eq!(sk.try_insert("b", 2).ok().copied(), Some(2));
+        let err = sk.try_insert("b", 3).unwrap_err();
+        assert_eq!(err.value, 3);
+        assert_eq!(*err.entry.get(), 2);
+        assert_eq!(
+            err.to_string(),
+            "failed to insert 3, key \"b\" already exists with value 2"
+        );
+        assert_eq!(err.entry.remove(), 2);
+        assert_eq!(sk.len(), 1);
+    }
+
+    #[test]
+    fn entry_api() {
+        let mut sk = SkipList::new();
+        for word in "the cat and the hat and the bat".split(' ') {
+            sk.entry(word).and_modify(|n| *n += 1).or_insert(1);
+        }
+        assert_eq!(sk.get(&"the"), Some(&3));
+        assert_eq!(sk.get(&"and"), Some(&2));
+        assert_eq!(sk.get(&"cat"), Some(&1));
+        assert_eq!(sk.len(), 5);
+
+        *sk.entry("cow").or_default() += 10;
+        assert_eq!(sk.get(&"cow"), Some(&10));
+        assert_eq!(*sk.entry("pig").or_insert_with_key(|k| k.len()), 3);
+
+        match sk.entry("hat") {
+            Entry::Occupied(mut e) => {
+                assert_eq!(e.key(), &"hat");
+                assert_eq!(e.insert(7), 1);
+                assert_eq!(e.remove(), 7);
+            }
+            Entry::Vacant(_) => unreachable!(),
+        }
+        assert_eq!(sk.get(&"hat"), None);
+        match sk.entry("hat") {
+            Entry::Vacant(e) => assert_eq!(e.into_key(), "hat"),
+            Entry::Occupied(_) => unreachable!(),
+        }
+        assert_eq!(sk.len(), 6);
+        assert_eq!(
+            sk.iter().map(|(k, _)| *k).collect::<Vec<_>>(),
+            vec!["and", "bat", "cat", "cow", "pig", "the"]
+        );
+    }
+}
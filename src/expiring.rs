@@ -0,0 +1,201 @@
+use crate::SkipList;
+use core::ops::RangeBounds;
+use std::time::{Duration, Instant};
+
+// Entries carry an optional deadline. Reads skip entries whose deadline has
+// passed; they are dropped when a write touches their key or by
+// expire_before, which pops them off by_deadline without a full scan.
+pub struct ExpiringSkipList<K, V> {
+    entries: SkipList<K, (Option<Instant>, V)>,
+    by_deadline: SkipList<(Instant, K), ()>,
+}
+
+impl<K: Ord + Clone, V> ExpiringSkipList<K, V> {
+    pub fn new() -> Self {
+        Self {
+            entries: SkipList::new(),
+            by_deadline: SkipList::new(),
+        }
+    }
+
+    // Expired entries that haven't been purged yet are counted.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Never expires.
+    pub fn insert(&mut self, key: K, val: V) -> Option<V> {
+        self.insert_with_deadline(key, val, None)
+    }
+
+    pub fn insert_with_ttl(&mut self, key: K, val: V, ttl: Duration) -> Option<V> {
+        let deadline = Instant::now().checked_add(ttl);
+        self.insert_with_deadline(key, val, deadline)
+    }
+
+    // Returns the previous value unless it had already expired.
+    pub fn insert_with_deadline(&mut self, key: K, val: V, deadline: Option<Instant>) -> Option<V> {
+        if let Some(deadline) = deadline {
+            self.by_deadline.insert((deadline, key.clone()), ());
+        }
+        let (old_deadline, old) = self.entries.insert_full(key.clone(), (deadline, val)).1?;
+        match old_deadline {
+            None => Some(old),
+            Some(old_deadline) => {
+                if Some(old_deadline) != deadline {
+                    self.by_deadline.remove(&(old_deadline, key));
+                }
+                (old_deadline > Instant::now()).then_some(old)
+            }
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let (deadline, val) = self.entries.get(key)?;
+        live(deadline, Instant::now()).then_some(val)
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        if !self.purge_if_expired(key) {
+            return None;
+        }
+        self.entries.get_mut(key).map(|(_, val)| val)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    // The deadline of a live entry, None for one that never expires.
+    pub fn deadline(&self, key: &K) -> Option<Option<Instant>> {
+        let (deadline, _) = self.entries.get(key)?;
+        live(deadline, Instant::now()).then_some(*deadline)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        if !self.purge_if_expired(key) {
+            return None;
+        }
+        let (deadline, val) = self.entries.remove(key)?;
+        if let Some(deadline) = deadline {
+            self.by_deadline.remove(&(deadline, key.clone()));
+        }
+        Some(val)
+    }
+
+    // Drops every entry whose deadline is before instant and returns how
+    // many went.
+    pub fn expire_before(&mut self, instant: Instant) -> usize {
+        let mut expired = 0;
+        while let Some(((deadline, _), _)) = self.by_deadline.first_key_value() {
+            if *deadline >= instant {
+                break;
+            }
+            let ((_, key), ()) = self.by_deadline.pop_first().unwrap();
+            self.entries.remove(&key);
+            expired += 1;
+        }
+        expired
+    }
+
+    pub fn purge_expired(&mut self) -> usize {
+        self.expire_before(Instant::now())
+    }
+
+    // Live entries in key order, as of when the iterator was made.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&K, &V)> + '_ {
+        self.range(..)
+    }
+
+    pub fn range<R: RangeBounds<K>>(
+        &self,
+        range: R,
+    ) -> impl DoubleEndedIterator<Item = (&K, &V)> + '_ {
+        let now = Instant::now();
+        self.entries
+            .range(range)
+            .filter(move |(_, (deadline, _))| live(deadline, now))
+            .map(|(key, (_, val))| (key, val))
+    }
+
+    // False once the entry under key is gone, whether it was expired and
+    // dropped here or was never there.
+    fn purge_if_expired(&mut self, key: &K) -> bool {
+        match self.entries.get(key) {
+            None => false,
+            Some((deadline, _)) if live(deadline, Instant::now()) => true,
+            Some((deadline, _)) => {
+                self.by_deadline.remove(&(deadline.unwrap(), key.clone()));
+                self.entries.remove(key);
+                false
+            }
+        }
+    }
+}
+
+impl<K: Ord + Clone, V> Default for ExpiringSkipList<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn live(deadline: &Option<Instant>, now: Instant) -> bool {
+    deadline.is_none_or(|deadline| deadline > now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ExpiringSkipList;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn expired_entries_are_hidden_and_swept() {
+        let now = Instant::now();
+        let past = now - Duration::from_millis(1);
+        let hour = Duration::from_secs(3600);
+        let mut cache = ExpiringSkipList::new();
+        for i in 0..10 {
+            let deadline = if i % 2 == 0 {
+                Some(past)
+            } else {
+                Some(now + hour)
+            };
+            cache.insert_with_deadline(i, i * 10, deadline);
+        }
+        cache.insert(100, 1000);
+        assert_eq!(cache.len(), 11);
+        assert_eq!(cache.get(&2), None);
+        assert_eq!(cache.get(&3), Some(&30));
+        assert_eq!(cache.deadline(&100), Some(None));
+        assert_eq!(
+            cache.iter().map(|(k, _)| *k).collect::<Vec<_>>(),
+            [1, 3, 5, 7, 9, 100]
+        );
+        assert_eq!(cache.range(4..8).rev().count(), 2);
+
+        // Touching an expired key drops it.
+        assert_eq!(cache.remove(&4), None);
+        assert_eq!(cache.get_mut(&6), None);
+        assert_eq!(cache.len(), 9);
+        assert_eq!(cache.insert_with_ttl(8, 80, hour), None);
+        assert_eq!(cache.get(&8), Some(&80));
+
+        assert_eq!(cache.expire_before(now), 2);
+        assert_eq!(cache.len(), 7);
+        assert_eq!(cache.expire_before(now + hour + hour), 6);
+        assert_eq!(cache.iter().collect::<Vec<_>>(), [(&100, &1000)]);
+        assert_eq!(cache.by_deadline.len(), 0);
+
+        // Replacing an entry that never expires hands its value back, with
+        // or without a deadline for the new one.
+        assert_eq!(cache.insert(100, 1001), Some(1000));
+        assert_eq!(cache.insert_with_ttl(100, 1002, hour), Some(1001));
+        assert_eq!(cache.insert(100, 1003), Some(1002));
+        assert_eq!(cache.by_deadline.len(), 0);
+        assert_eq!(cache.deadline(&100), Some(None));
+    }
+}
@@ -0,0 +1,109 @@
+use std::cell::Cell;
+
+// Failpoints are per thread, so tests running in parallel don't trip each
+// other's failures.
+thread_local! {
+    static ALLOC_FAIL_AFTER: Cell<Option<usize>> = const { Cell::new(None) };
+    static FORCED_LEVEL: Cell<Option<Level>> = const { Cell::new(None) };
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Level {
+    Min,
+    Max,
+}
+
+// Lets the next n node allocations succeed and fails every one after that.
+pub fn fail_alloc_after(n: usize) {
+    ALLOC_FAIL_AFTER.with(|c| c.set(Some(n)));
+}
+
+pub fn force_level(level: Option<Level>) {
+    FORCED_LEVEL.with(|c| c.set(level));
+}
+
+pub fn reset() {
+    ALLOC_FAIL_AFTER.with(|c| c.set(None));
+    FORCED_LEVEL.with(|c| c.set(None));
+}
+
+pub(crate) fn alloc_should_fail() -> bool {
+    ALLOC_FAIL_AFTER.with(|c| match c.get() {
+        Some(0) => true,
+        Some(n) => {
+            c.set(Some(n - 1));
+            false
+        }
+        None => false,
+    })
+}
+
+pub(crate) fn forced_level(max: usize) -> Option<usize> {
+    FORCED_LEVEL.with(|c| match c.get()? {
+        Level::Min => Some(1),
+        Level::Max => Some(max),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fail_alloc_after, force_level, reset, Level};
+    use crate::{AllocError, SkipList, SkipListBuilder, DEFAULT_MAX_LEVEL};
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    #[test]
+    fn alloc_failure() {
+        let mut sk = SkipList::new();
+        fail_alloc_after(3);
+        for i in 0..3 {
+            sk.insert(i, i);
+        }
+        let res = catch_unwind(AssertUnwindSafe(|| sk.insert(3, 3)));
+        assert!(res.is_err());
+        assert!(catch_unwind(SkipList::<u32, u32>::new).is_err());
+        reset();
+
+        assert_eq!(sk.len(), 3);
+        assert_eq!(sk.get(&3), None);
+        sk.insert(3, 3);
+        for i in 0..4 {
+            assert_eq!(sk.get(&i), Some(&i));
+        }
+    }
+
+    #[test]
+    fn fallible_insert_reports_failure() {
+        let mut sk = SkipList::new();
+        fail_alloc_after(2);
+        assert_eq!(sk.insert_fallible(1, 1), Ok(None));
+        assert_eq!(sk.insert_fallible(2, 2), Ok(None));
+        assert_eq!(sk.insert_fallible(3, 3), Err(AllocError));
+        // Replacing a value needs no node.
+        assert_eq!(sk.insert_fallible(1, 10), Ok(Some(1)));
+        reset();
+
+        assert_eq!(sk.len(), 2);
+        assert_eq!(sk.get(&3), None);
+        assert_eq!(sk.insert_fallible(3, 3), Ok(None));
+        assert!(sk.iter().eq([(&1, &10), (&2, &2), (&3, &3)]));
+    }
+
+    #[test]
+    fn forced_levels() {
+        let mut sk: SkipList<u32, u32> = SkipListBuilder::new().adaptive_levels(false).build();
+        force_level(Some(Level::Min));
+        for i in 0..100 {
+            sk.insert(i, i);
+        }
+        assert_eq!(sk.level, 1);
+
+        force_level(Some(Level::Max));
+        sk.insert(100, 100);
+        assert_eq!(sk.level, DEFAULT_MAX_LEVEL);
+        reset();
+
+        for i in 0..=100 {
+            assert_eq!(sk.get(&i), Some(&i));
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use alloc::vec::Vec;
+
+const LEVELS: usize = 16;
+const NIL: u32 = u32::MAX;
+const HEAD: u32 = 0;
+
+// Every node has a full-height tower of slot indices, so nodes are plain
+// fixed-size slots in one Vec and removed slots are pooled for reuse. Slot 0
+// is the head and its key is never compared.
+struct Slot<V> {
+    key: u64,
+    next: [u32; LEVELS],
+    val: Option<V>,
+}
+
+pub struct SkipListU64<V> {
+    slots: Vec<Slot<V>>,
+    free: Vec<u32>,
+    size: usize,
+    level: usize,
+}
+
+impl<V> SkipListU64<V> {
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut slots = Vec::with_capacity(capacity + 1);
+        slots.push(Slot {
+            key: 0,
+            next: [NIL; LEVELS],
+            val: None,
+        });
+        Self {
+            slots,
+            free: Vec::new(),
+            size: 0,
+            level: 1,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    pub fn insert(&mut self, key: u64, val: V) -> Option<V> {
+        let mut update = [HEAD; LEVELS];
+        let next = self.find(key, &mut update);
+        if next != NIL && self.slot(next).key == key {
+            return self.slots[next as usize].val.replace(val);
+        }
+
+        // One random word gives the height: each trailing zero is a coin flip.
+        let height = ((crate::random_u32().trailing_zeros() as usize) + 1).min(LEVELS);
+        if height > self.level {
+            self.level = height;
+        }
+        let mut slot = Slot {
+            key,
+            next: [NIL; LEVELS],
+            val: Some(val),
+        };
+        for (l, prev) in update.iter().enumerate().take(height) {
+            slot.next[l] = self.slot(*prev).next[l];
+        }
+        let idx = match self.free.pop() {
+            Some(idx) => {
+                self.slots[idx as usize] = slot;
+                idx
+            }
+            None => {
+                let idx = self.slots.len() as u32;
+                assert!(idx != NIL, "SkipListU64 is full");
+                self.slots.push(slot);
+                idx
+            }
+        };
+        for (l, prev) in update.iter().enumerate().take(height) {
+            self.slots[*prev as usize].next[l] = idx;
+        }
+        self.size += 1;
+        None
+    }
+
+    pub fn get(&self, key: u64) -> Option<&V> {
+        let idx = self.lookup(key)?;
+        self.slot(idx).val.as_ref()
+    }
+
+    pub fn get_mut(&mut self, key: u64) -> Option<&mut V> {
+        let idx = self.lookup(key)?;
+        self.slots[idx as usize].val.as_mut()
+    }
+
+    pub fn contains_key(&self, key: u64) -> bool {
+        self.lookup(key).is_some()
+    }
+
+    pub fn remove(&mut self, key: u64) -> Option<V> {
+        let mut update = [HEAD; LEVELS];
+        let idx = self.find(key, &mut update);
+        if idx == NIL || self.slot(idx).key != key {
+            return None;
+        }
+        let next = self.slot(idx).next;
+        for (l, prev) in update.iter().enumerate().take(self.level) {
+            let prev = &mut self.slots[*prev as usize].next[l];
+            if *prev != idx {
+                break;
+            }
+            *prev = next[l];
+        }
+        while self.level > 1 && self.slot(HEAD).next[self.level - 1] == NIL {
+            self.level -= 1;
+        }
+        self.size -= 1;
+        self.free.push(idx);
+        self.slots[idx as usize].val.take()
+    }
+
+    pub fn clear(&mut self) {
+        self.slots.truncate(1);
+        self.slots[0].next = [NIL; LEVELS];
+        self.free.clear();
+        self.size = 0;
+        self.level = 1;
+    }
+
+    fn slot(&self, idx: u32) -> &Slot<V> {
+        &self.slots[idx as usize]
+    }
+
+    // Search without recording predecessors, for the read paths.
+    fn lookup(&self, key: u64) -> Option<u32> {
+        let mut x = HEAD;
+        for l in (0..self.level).rev() {
+            loop {
+                let next = self.slot(x).next[l];
+                if next == NIL || self.slot(next).key >= key {
+                    break;
+                }
+                x = next;
+            }
+        }
+        let next = self.slot(x).next[0];
+        (next != NIL && self.slot(next).key == key).then_some(next)
+    }
+
+    fn find(&self, key: u64, update: &mut [u32; LEVELS]) -> u32 {
+        let mut x = HEAD;
+        for l in (0..self.level).rev() {
+            loop {
+                let next = self.slot(x).next[l];
+                if next == NIL || self.slot(next).key >= key {
+                    break;
+                }
+                x = next;
+            }
+            update[l] = x;
+        }
+        self.slot(x).next[0]
+    }
+}
+
+impl<V> Default for SkipListU64<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SkipListU64;
+    use rand::prelude::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn matches_btreemap() {
+        let mut rng = thread_rng();
+        let mut sk = SkipListU64::new();
+        let mut model = BTreeMap::new();
+        for _ in 0..20_000 {
+            let key = rng.gen_range(0..2_000u64);
+            match rng.gen_range(0..3) {
+                0 | 1 => assert_eq!(sk.insert(key, key * 3), model.insert(key, key * 3)),
+                _ => assert_eq!(sk.remove(key), model.remove(&key)),
+            }
+        }
+        assert_eq!(sk.len(), model.len());
+        for key in 0..2_000 {
+            assert_eq!(sk.get(key), model.get(&key));
+        }
+        assert!(sk.slots.len() <= 2_001);
+
+        let first = *model.keys().next().unwrap();
+        *sk.get_mut(first).unwrap() = 0;
+        assert_eq!(sk.get(first), Some(&0));
+        sk.clear();
+        assert!(sk.is_empty());
+        assert!(!sk.contains_key(0));
+        sk.insert(u64::MAX, 1);
+        sk.insert(0, 2);
+        assert_eq!(sk.get(u64::MAX), Some(&1));
+        assert_eq!(sk.get(0), Some(&2));
+    }
+}
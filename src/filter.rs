@@ -0,0 +1,69 @@
+use crate::{stable_hash, SkipList};
+use alloc::vec::Vec;
+use core::hash::Hash;
+use xorf::{Filter, Xor8};
+
+// Keys are hashed with the same fixed-key hasher as hashed tower levels, so a
+// filter built in one process answers for keys hashed in another.
+pub struct KeyFilter {
+    filter: Xor8,
+}
+
+impl KeyFilter {
+    pub fn contains<Q: Hash + ?Sized>(&self, key: &Q) -> bool {
+        self.filter.contains(&stable_hash(key))
+    }
+
+    pub fn as_xor8(&self) -> &Xor8 {
+        &self.filter
+    }
+
+    pub fn into_xor8(self) -> Xor8 {
+        self.filter
+    }
+}
+
+impl From<Xor8> for KeyFilter {
+    fn from(filter: Xor8) -> Self {
+        Self { filter }
+    }
+}
+
+impl<K: Ord + Hash, V> SkipList<K, V> {
+    pub fn build_key_filter(&self) -> KeyFilter {
+        let mut hashes: Vec<u64> = self.iter().map(|(k, _)| stable_hash(k)).collect();
+        hashes.sort_unstable();
+        hashes.dedup();
+        KeyFilter {
+            filter: Xor8::from(hashes.as_slice()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::KeyFilter;
+    use crate::SkipList;
+
+    #[test]
+    fn key_filter() {
+        let mut sk = SkipList::new();
+        for i in 0..10_000u64 {
+            sk.insert(format!("key-{}", i), i);
+        }
+        let filter = sk.build_key_filter();
+        for i in 0..10_000u64 {
+            assert!(filter.contains(&format!("key-{}", i)));
+        }
+        let false_positives = (10_000..20_000u64)
+            .filter(|i| filter.contains(&format!("key-{}", i)))
+            .count();
+        assert!(false_positives < 100, "{} false positives", false_positives);
+
+        let shipped = KeyFilter::from(filter.into_xor8());
+        assert!(shipped.contains("key-42"));
+
+        let empty = SkipList::<u64, ()>::new().build_key_filter();
+        assert!(!empty.contains(&1));
+    }
+}
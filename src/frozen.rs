@@ -0,0 +1,122 @@
+use crate::SkipList;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::ops::{Bound, RangeBounds};
+
+// Entries sorted in one Vec: no towers to chase, so scans are sequential reads
+// and lookups are binary searches.
+pub struct FrozenSkipList<K, V> {
+    entries: Vec<(K, V)>,
+}
+
+impl<K, V> SkipList<K, V> {
+    pub fn freeze(self) -> FrozenSkipList<K, V> {
+        let mut entries = Vec::with_capacity(self.size);
+        entries.extend(self);
+        FrozenSkipList { entries }
+    }
+}
+
+impl<K: Ord, V> FrozenSkipList<K, V> {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get_key_value(key).map(|(_, v)| v)
+    }
+
+    pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let i = self
+            .entries
+            .binary_search_by(|(k, _)| k.borrow().cmp(key))
+            .ok()?;
+        let (k, v) = &self.entries[i];
+        Some((k, v))
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get_key_value(key).is_some()
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&K, &V)> + ExactSizeIterator {
+        self.entries.iter().map(|(k, v)| (k, v))
+    }
+
+    pub fn range<Q, R>(
+        &self,
+        range: R,
+    ) -> impl DoubleEndedIterator<Item = (&K, &V)> + ExactSizeIterator
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let start = match range.start_bound() {
+            Bound::Included(q) => self.entries.partition_point(|(k, _)| k.borrow() < q),
+            Bound::Excluded(q) => self.entries.partition_point(|(k, _)| k.borrow() <= q),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(q) => self.entries.partition_point(|(k, _)| k.borrow() <= q),
+            Bound::Excluded(q) => self.entries.partition_point(|(k, _)| k.borrow() < q),
+            Bound::Unbounded => self.entries.len(),
+        };
+        self.entries[start..end.max(start)]
+            .iter()
+            .map(|(k, v)| (k, v))
+    }
+}
+
+// Hands out the entries in key order, for writing the list out.
+impl<K, V> IntoIterator for FrozenSkipList<K, V> {
+    type Item = (K, V);
+    type IntoIter = alloc::vec::IntoIter<(K, V)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::SkipList;
+
+    #[test]
+    fn frozen_matches_the_list() {
+        let mut sk = SkipList::new();
+        for i in (0..1000).rev() {
+            sk.insert(i * 2, i.to_string());
+        }
+        let frozen = sk.freeze();
+        assert_eq!(frozen.len(), 1000);
+        assert_eq!(frozen.get(&20).map(String::as_str), Some("10"));
+        assert_eq!(frozen.get(&21), None);
+        assert!(frozen.contains_key(&1998));
+        assert_eq!(
+            frozen.range(10..=16).map(|(k, _)| *k).collect::<Vec<_>>(),
+            [10, 12, 14, 16]
+        );
+        assert_eq!(frozen.range(11..12).count(), 0);
+        assert_eq!(frozen.iter().next_back(), Some((&1998, &"999".to_string())));
+
+        let flushed: Vec<_> = frozen.into_iter().map(|(k, _)| k).collect();
+        assert_eq!(flushed, (0..1000).map(|i| i * 2).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,241 @@
+use crate::{Iter, SkipList};
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::iter::FusedIterator;
+use core::mem;
+use core::slice;
+
+const DEFAULT_THRESHOLD: usize = 32;
+
+// Keeps its entries in a sorted Vec until there are more than threshold of
+// them and only then moves them into a SkipList, so the many maps that stay
+// tiny cost one allocation instead of one per entry. It moves back once it
+// has shrunk to half the threshold, which keeps an insert and remove right
+// at the threshold from converting every time.
+pub struct HybridSkipList<K, V> {
+    repr: Repr<K, V>,
+    threshold: usize,
+}
+
+enum Repr<K, V> {
+    Small(Vec<(K, V)>),
+    Large(SkipList<K, V>),
+}
+
+pub enum HybridIter<'a, K, V> {
+    Small(slice::Iter<'a, (K, V)>),
+    Large(Iter<'a, K, V>),
+}
+
+impl<K: Ord, V> HybridSkipList<K, V> {
+    pub fn new() -> Self {
+        Self::with_threshold(DEFAULT_THRESHOLD)
+    }
+
+    pub fn with_threshold(threshold: usize) -> Self {
+        Self {
+            repr: Repr::Small(Vec::new()),
+            threshold,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match &self.repr {
+            Repr::Small(entries) => entries.len(),
+            Repr::Large(list) => list.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Whether the entries are still in the sorted Vec.
+    pub fn is_small(&self) -> bool {
+        matches!(self.repr, Repr::Small(_))
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        match &self.repr {
+            Repr::Small(entries) => {
+                let i = search(entries, key).ok()?;
+                Some(&entries[i].1)
+            }
+            Repr::Large(list) => list.get(key),
+        }
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        match &mut self.repr {
+            Repr::Small(entries) => {
+                let i = search(entries, key).ok()?;
+                Some(&mut entries[i].1)
+            }
+            Repr::Large(list) => list.get_mut(key),
+        }
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    pub fn insert(&mut self, key: K, val: V) -> Option<V> {
+        let entries = match &mut self.repr {
+            Repr::Small(entries) => entries,
+            Repr::Large(list) => return list.insert_full(key, val).1,
+        };
+        match search(entries, &key) {
+            Ok(i) => Some(mem::replace(&mut entries[i].1, val)),
+            Err(i) => {
+                entries.insert(i, (key, val));
+                if entries.len() > self.threshold {
+                    let entries = mem::take(entries);
+                    self.repr = Repr::Large(SkipList::from_sorted_iter(entries));
+                }
+                None
+            }
+        }
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        match &mut self.repr {
+            Repr::Small(entries) => {
+                let i = search(entries, key).ok()?;
+                Some(entries.remove(i).1)
+            }
+            Repr::Large(list) => {
+                let val = list.remove(key)?;
+                if list.len() <= self.threshold / 2 {
+                    let entries = list.pop_first_k(list.len());
+                    self.repr = Repr::Small(entries);
+                }
+                Some(val)
+            }
+        }
+    }
+
+    pub fn first_key_value(&self) -> Option<(&K, &V)> {
+        self.iter().next()
+    }
+
+    pub fn last_key_value(&self) -> Option<(&K, &V)> {
+        self.iter().next_back()
+    }
+
+    pub fn iter(&self) -> HybridIter<'_, K, V> {
+        match &self.repr {
+            Repr::Small(entries) => HybridIter::Small(entries.iter()),
+            Repr::Large(list) => HybridIter::Large(list.iter()),
+        }
+    }
+
+    pub fn into_list(self) -> SkipList<K, V> {
+        match self.repr {
+            Repr::Small(entries) => SkipList::from_sorted_iter(entries),
+            Repr::Large(list) => list,
+        }
+    }
+}
+
+impl<K: Ord, V> Default for HybridSkipList<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn search<K: Borrow<Q>, Q: Ord + ?Sized, V>(entries: &[(K, V)], key: &Q) -> Result<usize, usize> {
+    entries.binary_search_by(|(k, _)| k.borrow().cmp(key))
+}
+
+impl<'a, K, V> Iterator for HybridIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        match self {
+            HybridIter::Small(it) => it.next().map(|(k, v)| (k, v)),
+            HybridIter::Large(it) => it.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            HybridIter::Small(it) => it.size_hint(),
+            HybridIter::Large(it) => it.size_hint(),
+        }
+    }
+}
+
+impl<K, V> DoubleEndedIterator for HybridIter<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match self {
+            HybridIter::Small(it) => it.next_back().map(|(k, v)| (k, v)),
+            HybridIter::Large(it) => it.next_back(),
+        }
+    }
+}
+
+impl<K, V> ExactSizeIterator for HybridIter<'_, K, V> {}
+
+impl<K, V> FusedIterator for HybridIter<'_, K, V> {}
+
+#[cfg(test)]
+mod tests {
+    use super::HybridSkipList;
+    use rand::prelude::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn switches_representation_by_size() {
+        let mut list = HybridSkipList::with_threshold(8);
+        let mut model = BTreeMap::new();
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut small = 0;
+        for _ in 0..2_000 {
+            let key = rng.gen_range(0..24u32);
+            if rng.gen_bool(0.5) {
+                let val = rng.gen::<u32>();
+                assert_eq!(list.insert(key, val), model.insert(key, val));
+            } else {
+                assert_eq!(list.remove(&key), model.remove(&key));
+            }
+            assert_eq!(list.len(), model.len());
+            assert!(list.iter().eq(model.iter()));
+            assert!(list.is_small() || list.len() > 4);
+            small += list.is_small() as usize;
+        }
+        assert!(small > 0 && small < 2_000);
+        if let Some(v) = list.get_mut(&3) {
+            *v = 0;
+            model.insert(3, 0);
+        }
+        assert_eq!(list.get(&3), model.get(&3));
+        assert_eq!(list.last_key_value(), model.last_key_value());
+        assert!(list.into_list().iter().eq(model.iter()));
+
+        let mut list = HybridSkipList::new();
+        for i in (0..32).rev() {
+            list.insert(i, ());
+        }
+        assert!(list.is_small());
+        list.insert(32, ());
+        assert!(!list.is_small());
+        assert_eq!(list.first_key_value(), Some((&0, &())));
+        assert!(list.contains_key(&17));
+    }
+}
@@ -0,0 +1,88 @@
+use crate::SkipList;
+
+pub trait IdKey: Ord + Copy {
+    const ZERO: Self;
+
+    fn successor(self) -> Option<Self>;
+}
+
+macro_rules! impl_id_key {
+    ($($t:ty),*) => {$(
+        impl IdKey for $t {
+            const ZERO: Self = 0;
+
+            fn successor(self) -> Option<Self> {
+                self.checked_add(1)
+            }
+        }
+    )*};
+}
+
+impl_id_key!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+impl<K: IdKey, V> SkipList<K, V> {
+    // There is no per-subtree fullness count to skip dense runs, so this seeks
+    // to `from` through the towers and then scans level 0 across the run of
+    // consecutive keys starting there: O(log n + run length).
+    pub fn first_absent(&self, from: K) -> Option<K> {
+        let mut expected = from;
+        for (key, _) in self.range(from..) {
+            if *key != expected {
+                break;
+            }
+            expected = expected.successor()?;
+        }
+        Some(expected)
+    }
+
+    pub fn allocate_id(&mut self, val: V) -> Option<K> {
+        let id = self.first_absent(K::ZERO)?;
+        self.insert(id, val);
+        Some(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::SkipList;
+
+    #[test]
+    fn first_absent() {
+        let mut sk = SkipList::new();
+        assert_eq!(sk.first_absent(0u32), Some(0));
+        for i in [0u32, 1, 2, 4, 5, 9] {
+            sk.insert(i, ());
+        }
+        assert_eq!(sk.first_absent(0), Some(3));
+        assert_eq!(sk.first_absent(3), Some(3));
+        assert_eq!(sk.first_absent(4), Some(6));
+        assert_eq!(sk.first_absent(9), Some(10));
+        assert_eq!(sk.first_absent(100), Some(100));
+
+        let mut full = SkipList::new();
+        for i in 250..=255u8 {
+            full.insert(i, ());
+        }
+        assert_eq!(full.first_absent(250), None);
+        assert_eq!(full.first_absent(249), Some(249));
+    }
+
+    #[test]
+    fn allocate_id() {
+        let mut ports = SkipList::new();
+        for _ in 0..5 {
+            ports.allocate_id("busy");
+        }
+        assert_eq!(ports.len(), 5);
+        ports.remove(&2u16);
+        assert_eq!(ports.allocate_id("again"), Some(2));
+        assert_eq!(ports.allocate_id("next"), Some(5));
+        assert_eq!(ports.get(&2), Some(&"again"));
+
+        let mut tiny = SkipList::<u8, ()>::new();
+        for _ in 0..256 {
+            assert!(tiny.allocate_id(()).is_some());
+        }
+        assert_eq!(tiny.allocate_id(()), None);
+    }
+}
@@ -0,0 +1,366 @@
+use alloc::vec::Vec;
+use core::ops::Range;
+
+const LEVELS: usize = 16;
+const NIL: u32 = u32::MAX;
+const HEAD: u32 = 0;
+
+// Intervals are ordered by start, then end, then insertion, in Vec slots as in
+// SkipListU64. Each link also records which node among those it covers (the
+// ones after its source, up to and including its target, or to the end of the
+// list for a link with no target) has the greatest end, so a query can skip
+// every link whose greatest end is at or before the point it asks about.
+// Slot 0 is the head and holds no interval.
+struct Slot<T, V> {
+    entry: Option<(Range<T>, V)>,
+    next: [u32; LEVELS],
+    max_end: [u32; LEVELS],
+}
+
+pub struct IntervalSkipList<T, V> {
+    slots: Vec<Slot<T, V>>,
+    free: Vec<u32>,
+    size: usize,
+    level: usize,
+}
+
+impl<T: Ord, V> IntervalSkipList<T, V> {
+    pub fn new() -> Self {
+        Self {
+            slots: alloc::vec![Slot {
+                entry: None,
+                next: [NIL; LEVELS],
+                max_end: [NIL; LEVELS],
+            }],
+            free: Vec::new(),
+            size: 0,
+            level: 1,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    // Equal intervals are all kept, in the order they were inserted.
+    pub fn insert(&mut self, interval: Range<T>, val: V) {
+        assert!(
+            interval.start < interval.end,
+            "interval start must be less than its end in IntervalSkipList"
+        );
+        let mut update = [HEAD; LEVELS];
+        self.find(
+            |r| (&r.start, &r.end) <= (&interval.start, &interval.end),
+            &mut update,
+        );
+
+        let height = ((crate::random_u32().trailing_zeros() as usize) + 1).min(LEVELS);
+        if height > self.level {
+            self.level = height;
+        }
+        let mut slot = Slot {
+            entry: Some((interval, val)),
+            next: [NIL; LEVELS],
+            max_end: [NIL; LEVELS],
+        };
+        for (l, prev) in update.iter().enumerate().take(height) {
+            slot.next[l] = self.slot(*prev).next[l];
+        }
+        let idx = match self.free.pop() {
+            Some(idx) => {
+                self.slots[idx as usize] = slot;
+                idx
+            }
+            None => {
+                let idx = self.slots.len() as u32;
+                assert!(idx != NIL, "IntervalSkipList is full");
+                self.slots.push(slot);
+                idx
+            }
+        };
+        for (l, prev) in update.iter().enumerate().take(height) {
+            self.slots[*prev as usize].next[l] = idx;
+        }
+        for (l, prev) in update.iter().enumerate().take(self.level) {
+            self.refresh(*prev, l);
+            if l < height {
+                self.refresh(idx, l);
+            }
+        }
+        self.size += 1;
+    }
+
+    // Removes the oldest entry with exactly these bounds.
+    pub fn remove(&mut self, interval: &Range<T>) -> Option<V> {
+        let mut update = [HEAD; LEVELS];
+        let idx = self.find(
+            |r| (&r.start, &r.end) < (&interval.start, &interval.end),
+            &mut update,
+        );
+        if idx == NIL || self.range(idx) != interval {
+            return None;
+        }
+        let next = self.slot(idx).next;
+        for (l, prev) in update.iter().enumerate().take(self.level) {
+            let prev = &mut self.slots[*prev as usize].next[l];
+            if *prev != idx {
+                break;
+            }
+            *prev = next[l];
+        }
+        for (l, prev) in update.iter().enumerate().take(self.level) {
+            self.refresh(*prev, l);
+        }
+        while self.level > 1 && self.slot(HEAD).next[self.level - 1] == NIL {
+            self.level -= 1;
+        }
+        self.size -= 1;
+        self.free.push(idx);
+        self.slots[idx as usize].entry.take().map(|(_, val)| val)
+    }
+
+    // Intervals containing point, by start.
+    pub fn find_overlapping_point(&self, point: &T) -> Vec<(&Range<T>, &V)> {
+        let mut found = Vec::new();
+        self.visit(
+            HEAD,
+            self.level - 1,
+            NIL,
+            &|start| start <= point,
+            point,
+            &mut found,
+        );
+        found
+    }
+
+    // Intervals sharing at least one point with range, by start.
+    pub fn find_overlapping(&self, range: &Range<T>) -> Vec<(&Range<T>, &V)> {
+        let mut found = Vec::new();
+        if range.start < range.end {
+            self.visit(
+                HEAD,
+                self.level - 1,
+                NIL,
+                &|start| start < &range.end,
+                &range.start,
+                &mut found,
+            );
+        }
+        found
+    }
+
+    pub fn iter(&self) -> Iter<'_, T, V> {
+        Iter {
+            list: self,
+            next: self.slot(HEAD).next[0],
+            len: self.size,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.slots.truncate(1);
+        self.slots[0].next = [NIL; LEVELS];
+        self.slots[0].max_end = [NIL; LEVELS];
+        self.free.clear();
+        self.size = 0;
+        self.level = 1;
+    }
+
+    fn slot(&self, idx: u32) -> &Slot<T, V> {
+        &self.slots[idx as usize]
+    }
+
+    fn range(&self, idx: u32) -> &Range<T> {
+        &self.slot(idx).entry.as_ref().unwrap().0
+    }
+
+    fn find<F: Fn(&Range<T>) -> bool>(&self, is_before: F, update: &mut [u32; LEVELS]) -> u32 {
+        let mut x = HEAD;
+        for l in (0..self.level).rev() {
+            loop {
+                let next = self.slot(x).next[l];
+                if next == NIL || !is_before(self.range(next)) {
+                    break;
+                }
+                x = next;
+            }
+            update[l] = x;
+        }
+        self.slot(x).next[0]
+    }
+
+    // The later-ending of two nodes, either of which may be NIL.
+    fn later_end(&self, a: u32, b: u32) -> u32 {
+        match (a, b) {
+            (NIL, _) => b,
+            (_, NIL) => a,
+            _ if self.range(b).end > self.range(a).end => b,
+            _ => a,
+        }
+    }
+
+    // Recomputes max_end for x's link on level l from the links below it,
+    // which must already be up to date.
+    fn refresh(&mut self, x: u32, l: usize) {
+        let end = self.slot(x).next[l];
+        let max_end = if l == 0 {
+            end
+        } else {
+            let mut best = NIL;
+            let mut y = x;
+            while y != end {
+                best = self.later_end(best, self.slot(y).max_end[l - 1]);
+                y = self.slot(y).next[l - 1];
+            }
+            best
+        };
+        self.slots[x as usize].max_end[l] = max_end;
+    }
+
+    // Walks level l from x until it reaches stop, descending into each link
+    // whose greatest end is past after. Returns false once a start fails
+    // starts_ok; every later start fails it too.
+    fn visit<'a, F: Fn(&T) -> bool>(
+        &'a self,
+        mut x: u32,
+        l: usize,
+        stop: u32,
+        starts_ok: &F,
+        after: &T,
+        found: &mut Vec<(&'a Range<T>, &'a V)>,
+    ) -> bool {
+        while x != stop {
+            let next = self.slot(x).next[l];
+            let max_end = self.slot(x).max_end[l];
+            if max_end == NIL {
+                break;
+            }
+            let reaches = self.range(max_end).end > *after;
+            if reaches && l > 0 {
+                if !self.visit(x, l - 1, next, starts_ok, after, found) {
+                    return false;
+                }
+            } else if next == NIL {
+                break;
+            } else {
+                let (range, val) = self.slot(next).entry.as_ref().unwrap();
+                if !starts_ok(&range.start) {
+                    return false;
+                }
+                if reaches {
+                    found.push((range, val));
+                }
+            }
+            x = next;
+        }
+        true
+    }
+}
+
+impl<T: Ord, V> Default for IntervalSkipList<T, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Iter<'a, T, V> {
+    list: &'a IntervalSkipList<T, V>,
+    next: u32,
+    len: usize,
+}
+
+impl<'a, T: Ord, V> Iterator for Iter<'a, T, V> {
+    type Item = (&'a Range<T>, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next == NIL {
+            return None;
+        }
+        let slot = self.list.slot(self.next);
+        self.next = slot.next[0];
+        self.len -= 1;
+        slot.entry.as_ref().map(|(range, val)| (range, val))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T: Ord, V> ExactSizeIterator for Iter<'_, T, V> {}
+
+#[cfg(test)]
+mod tests {
+    use super::IntervalSkipList;
+    use rand::prelude::*;
+    use std::ops::Range;
+
+    fn brute(intervals: &[(Range<u32>, usize)], q: &Range<u32>) -> Vec<usize> {
+        let mut hits: Vec<_> = intervals
+            .iter()
+            .filter(|(r, _)| r.start < q.end && q.start < r.end)
+            .collect();
+        hits.sort_by_key(|(r, i)| (r.start, r.end, *i));
+        hits.into_iter().map(|(_, i)| *i).collect()
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut list = IntervalSkipList::new();
+        let mut model = Vec::new();
+        for i in 0..2000 {
+            let start = rng.gen_range(0..10_000);
+            let len = if i % 10 == 0 {
+                rng.gen_range(1..5000)
+            } else {
+                rng.gen_range(1..50)
+            };
+            list.insert(start..start + len, i);
+            model.push((start..start + len, i));
+        }
+        for _ in 0..300 {
+            let victim = rng.gen_range(0..model.len());
+            let (range, i) = model.swap_remove(victim);
+            assert_eq!(list.remove(&range), Some(i));
+        }
+        assert_eq!(list.len(), model.len());
+        for _ in 0..200 {
+            let start = rng.gen_range(0..11_000);
+            let q = start..start + rng.gen_range(1..100);
+            let got: Vec<_> = list
+                .find_overlapping(&q)
+                .into_iter()
+                .map(|(_, i)| *i)
+                .collect();
+            assert_eq!(got, brute(&model, &q), "{:?}", q);
+            let got: Vec<_> = list
+                .find_overlapping_point(&start)
+                .into_iter()
+                .map(|(_, i)| *i)
+                .collect();
+            assert_eq!(got, brute(&model, &(start..start + 1)));
+        }
+    }
+
+    #[test]
+    fn equal_intervals_and_edges() {
+        let mut list = IntervalSkipList::new();
+        list.insert(10..20, "a");
+        list.insert(10..20, "b");
+        list.insert(20..30, "c");
+        assert_eq!(list.find_overlapping_point(&20), [(&(20..30), &"c")]);
+        assert_eq!(list.find_overlapping_point(&19).len(), 2);
+        assert!(list.find_overlapping(&(30..40)).is_empty());
+        assert!(list.find_overlapping(&(15..15)).is_empty());
+        assert_eq!(list.remove(&(10..20)), Some("a"));
+        assert_eq!(list.remove(&(10..25)), None);
+        assert_eq!(list.iter().map(|(_, v)| *v).collect::<Vec<_>>(), ["b", "c"]);
+        list.clear();
+        assert!(list.find_overlapping_point(&15).is_empty());
+    }
+}
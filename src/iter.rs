@@ -0,0 +1,1214 @@
+use crate::{key, Node, SkipList, MAX_LEVEL};
+use core::borrow::Borrow;
+use core::iter::FusedIterator;
+use core::marker::PhantomData;
+use core::ops::{Bound, RangeBounds};
+use core::ptr::NonNull;
+
+// Nodes are walked forwards along level 0 and backwards along prev. front and
+// back are the next nodes to hand out from either end.
+pub struct Iter<'a, K, V> {
+    front: Option<NonNull<Node<K, V>>>,
+    back: Option<NonNull<Node<K, V>>>,
+    len: usize,
+    _marker: PhantomData<&'a SkipList<K, V>>,
+}
+
+pub struct IterMut<'a, K, V> {
+    front: Option<NonNull<Node<K, V>>>,
+    back: Option<NonNull<Node<K, V>>>,
+    len: usize,
+    _marker: PhantomData<&'a mut SkipList<K, V>>,
+}
+
+pub struct IntoIter<K, V> {
+    list: SkipList<K, V>,
+}
+
+pub struct Keys<'a, K, V> {
+    inner: Iter<'a, K, V>,
+}
+
+pub struct Values<'a, K, V> {
+    inner: Iter<'a, K, V>,
+}
+
+pub struct ValuesMut<'a, K, V> {
+    inner: IterMut<'a, K, V>,
+}
+
+pub struct IntoKeys<K, V> {
+    inner: IntoIter<K, V>,
+}
+
+pub struct IntoValues<K, V> {
+    inner: IntoIter<K, V>,
+}
+
+// Both ends are resolved up front, so iterating needs no further comparisons.
+// The range is exhausted once front and back have met.
+pub struct Range<'a, K, V> {
+    front: Option<NonNull<Node<K, V>>>,
+    back: Option<NonNull<Node<K, V>>>,
+    _marker: PhantomData<&'a SkipList<K, V>>,
+}
+
+pub struct RangeMut<'a, K, V> {
+    front: Option<NonNull<Node<K, V>>>,
+    back: Option<NonNull<Node<K, V>>>,
+    _marker: PhantomData<&'a mut SkipList<K, V>>,
+}
+
+// Walks level 0 once. update[l] is the last kept node on level l, which is
+// exactly the predecessor unlink needs for the next node on any level.
+// Dropping the iterator early keeps whatever it has not reached yet.
+pub struct DrainFilter<'a, K, V, F> {
+    list: &'a mut SkipList<K, V>,
+    update: [Option<NonNull<Node<K, V>>>; MAX_LEVEL],
+    next: Option<NonNull<Node<K, V>>>,
+    pred: F,
+}
+
+// The list is emptied up front and the detached nodes are handed out from
+// either end like Iter does, so an early drop or a leaked Drain still leaves
+// the list empty and usable. Whatever is left is freed on drop.
+pub struct Drain<'a, K, V> {
+    list: &'a mut SkipList<K, V>,
+    front: Option<NonNull<Node<K, V>>>,
+    back: Option<NonNull<Node<K, V>>>,
+    len: usize,
+}
+
+// Each group's end is found with one search from the head for the first key
+// projecting past the group's bucket, so nothing is collected and a group
+// can be skipped without visiting its entries.
+pub struct ChunkBy<'a, K, V, F> {
+    list: &'a SkipList<K, V>,
+    next: Option<NonNull<Node<K, V>>>,
+    project: F,
+}
+
+impl<K, V> SkipList<K, V> {
+    // Takes every entry out in key order and keeps the head, the arena and
+    // the emptied nodes parked for reuse, with or without an arena, e.g. to
+    // flush a memtable and go on writing to the same list. clear gives them
+    // back.
+    pub fn drain(&mut self) -> Drain<'_, K, V> {
+        let len = self.size;
+        let back = self.last_node();
+        let front = unsafe { self.detach_all() };
+        Drain {
+            list: self,
+            front,
+            back,
+            len,
+        }
+    }
+
+    // Removes and yields the entries pred returns true for, in key order.
+    pub fn drain_filter<F>(&mut self, pred: F) -> DrainFilter<'_, K, V, F>
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        DrainFilter {
+            update: [Some(self.head); MAX_LEVEL],
+            next: unsafe { self.head.as_ref().tower[0] },
+            list: self,
+            pred,
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            front: unsafe { self.head.as_ref().tower[0] },
+            back: self.last_node(),
+            len: self.size,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut {
+            front: unsafe { self.head.as_ref().tower[0] },
+            back: self.last_node(),
+            len: self.size,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn keys(&self) -> Keys<'_, K, V> {
+        Keys { inner: self.iter() }
+    }
+
+    pub fn values(&self) -> Values<'_, K, V> {
+        Values { inner: self.iter() }
+    }
+
+    pub fn values_mut(&mut self) -> ValuesMut<'_, K, V> {
+        ValuesMut {
+            inner: self.iter_mut(),
+        }
+    }
+
+    pub fn into_keys(self) -> IntoKeys<K, V> {
+        IntoKeys {
+            inner: self.into_iter(),
+        }
+    }
+
+    pub fn into_values(self) -> IntoValues<K, V> {
+        IntoValues {
+            inner: self.into_iter(),
+        }
+    }
+
+    // Entries by position, with the same panics as slicing a Vec of len()
+    // entries.
+    pub fn index_range<R: RangeBounds<usize>>(&self, range: R) -> Range<'_, K, V> {
+        let start = match range.start_bound() {
+            Bound::Included(&i) => i,
+            Bound::Excluded(&i) => i.checked_add(1).expect("range start overflows usize"),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&i) => i.checked_add(1).expect("range end overflows usize"),
+            Bound::Excluded(&i) => i,
+            Bound::Unbounded => self.size,
+        };
+        if start > end {
+            panic!(
+                "range start index {} is greater than end index {}",
+                start, end
+            );
+        }
+        if end > self.size {
+            panic!(
+                "range end index {} out of range for SkipList of length {}",
+                end, self.size
+            );
+        }
+        self.range_between(self.node_at(start), self.node_at(end))
+    }
+
+    // front is the first node in the range and end the first one past it.
+    pub(crate) fn range_between(
+        &self,
+        front: Option<NonNull<Node<K, V>>>,
+        end: Option<NonNull<Node<K, V>>>,
+    ) -> Range<'_, K, V> {
+        let (front, back) = if front == end {
+            (None, None)
+        } else {
+            let back = match end {
+                Some(end) => unsafe { end.as_ref().prev },
+                None => self.last_node(),
+            };
+            (front, back)
+        };
+        Range {
+            front,
+            back,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<K: Ord, V> SkipList<K, V> {
+    // Panics as check_range describes.
+    pub fn range<Q, R>(&self, range: R) -> Range<'_, K, V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        check_range(&range);
+        let front = match range.start_bound() {
+            Bound::Included(key) => self.seek(key, false),
+            Bound::Excluded(key) => self.seek(key, true),
+            Bound::Unbounded => unsafe { self.head.as_ref().tower[0] },
+        };
+        // The first node past the range; the one before it is the last in it.
+        let end = match range.end_bound() {
+            Bound::Included(key) => self.seek(key, true),
+            Bound::Excluded(key) => self.seek(key, false),
+            Bound::Unbounded => None,
+        };
+        self.range_between(front, end)
+    }
+
+    pub fn range_mut<Q, R>(&mut self, range: R) -> RangeMut<'_, K, V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let Range { front, back, .. } = self.range(range);
+        RangeMut {
+            front,
+            back,
+            _marker: PhantomData,
+        }
+    }
+
+    // Keys starting with prefix, for keys whose Ord is the order of their
+    // bytes.
+    pub fn prefix_iter(&self, prefix: &[u8]) -> Range<'_, K, V>
+    where
+        K: AsRef<[u8]>,
+    {
+        let mut update: [Option<NonNull<Node<K, V>>>; MAX_LEVEL] = [None; MAX_LEVEL];
+        unsafe {
+            let front = self.find_by(|k| k.as_ref() < prefix, &mut update);
+            let end = match key::prefix_successor(prefix) {
+                Some(bound) => self.find_by(|k| k.as_ref() < &bound[..], &mut update),
+                None => None,
+            };
+            self.range_between(front, end)
+        }
+    }
+
+    // Consecutive runs of keys with the same bucket, as each bucket and the
+    // range of its entries. project must not decrease along key order, e.g.
+    // the day of a timestamp or the tenant in a (tenant, id) key. If it does,
+    // the groups are unspecified, and it may panic rather than loop.
+    pub fn chunk_by<B, F>(&self, project: F) -> ChunkBy<'_, K, V, F>
+    where
+        B: Ord,
+        F: FnMut(&K) -> B,
+    {
+        ChunkBy {
+            list: self,
+            next: unsafe { self.head.as_ref().tower[0] },
+            project,
+        }
+    }
+
+    // The first node at or past key, or strictly past it when skip_equal.
+    pub(crate) fn seek<Q>(&self, key: &Q, skip_equal: bool) -> Option<NonNull<Node<K, V>>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut update: [Option<NonNull<Node<K, V>>>; MAX_LEVEL] = [None; MAX_LEVEL];
+        unsafe {
+            match self.find_gt_or_eq_node(key, &mut update) {
+                Some(node_ptr) if skip_equal && node_ptr.as_ref().key.borrow() == key => {
+                    node_ptr.as_ref().tower[0]
+                }
+                x => x,
+            }
+        }
+    }
+}
+
+// Hands out the front node of a range and moves front on, or clears both
+// ends if that was the last one.
+fn take_front<K, V>(
+    front: &mut Option<NonNull<Node<K, V>>>,
+    back: &mut Option<NonNull<Node<K, V>>>,
+) -> Option<NonNull<Node<K, V>>> {
+    let node_ptr = (*front)?;
+    if *front == *back {
+        *front = None;
+        *back = None;
+    } else {
+        *front = unsafe { node_ptr.as_ref().tower[0] };
+    }
+    Some(node_ptr)
+}
+
+fn take_back<K, V>(
+    front: &mut Option<NonNull<Node<K, V>>>,
+    back: &mut Option<NonNull<Node<K, V>>>,
+) -> Option<NonNull<Node<K, V>>> {
+    let node_ptr = (*back)?;
+    if *front == *back {
+        *front = None;
+        *back = None;
+    } else {
+        *back = unsafe { node_ptr.as_ref().prev };
+    }
+    Some(node_ptr)
+}
+
+// The node n places after x, reached by taking the longest link on each
+// tower that doesn't overshoot, so a skip costs about as much as a search.
+// There must be at least n nodes after x.
+unsafe fn skip_forward<K, V>(mut x: NonNull<Node<K, V>>, mut n: usize) -> NonNull<Node<K, V>> {
+    while n > 0 {
+        let node = x.as_ref();
+        let (next, span) = (0..node.height())
+            .rev()
+            .find_map(|l| {
+                node.tower[l]
+                    .map(|next| (next, node.span(l)))
+                    .filter(|&(_, span)| span <= n)
+            })
+            .unwrap();
+        n -= span;
+        x = next;
+    }
+    x
+}
+
+// How many nodes come after x, following the highest link on each tower.
+unsafe fn count_after<K, V>(mut x: NonNull<Node<K, V>>) -> usize {
+    let mut count = 0;
+    loop {
+        let node = x.as_ref();
+        let Some((next, span)) = (0..node.height())
+            .rev()
+            .find_map(|l| node.tower[l].map(|next| (next, node.span(l))))
+        else {
+            return count;
+        };
+        count += span;
+        x = next;
+    }
+}
+
+// nth for the ranges, which don't keep a count: front is n places from back
+// exactly when n nodes fewer follow back than follow front.
+fn skip_front<K, V>(
+    front: &mut Option<NonNull<Node<K, V>>>,
+    back: &mut Option<NonNull<Node<K, V>>>,
+    n: usize,
+) {
+    let (Some(first), Some(last)) = (*front, *back) else {
+        return;
+    };
+    if n == 0 {
+        return;
+    }
+    unsafe {
+        if count_after(first) - count_after(last) < n {
+            *front = None;
+            *back = None;
+        } else {
+            *front = Some(skip_forward(first, n));
+        }
+    }
+}
+
+// nth for the iterators that know how many nodes are left.
+fn skip_counted<K, V>(
+    front: &mut Option<NonNull<Node<K, V>>>,
+    back: &mut Option<NonNull<Node<K, V>>>,
+    len: &mut usize,
+    n: usize,
+) {
+    if n >= *len {
+        *front = None;
+        *back = None;
+        *len = 0;
+    } else if n > 0 {
+        *front = front.map(|first| unsafe { skip_forward(first, n) });
+        *len -= n;
+    }
+}
+
+impl<'a, K, V> Iterator for Range<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        let node = unsafe { &*take_front(&mut self.front, &mut self.back)?.as_ptr() };
+        Some((&node.key, node.val()))
+    }
+
+    fn nth(&mut self, n: usize) -> Option<(&'a K, &'a V)> {
+        skip_front(&mut self.front, &mut self.back, n);
+        self.next()
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for Range<'a, K, V> {
+    fn next_back(&mut self) -> Option<(&'a K, &'a V)> {
+        let node = unsafe { &*take_back(&mut self.front, &mut self.back)?.as_ptr() };
+        Some((&node.key, node.val()))
+    }
+}
+
+impl<K, V> FusedIterator for Range<'_, K, V> {}
+
+impl<K, V> Clone for Range<'_, K, V> {
+    fn clone(&self) -> Self {
+        Range { ..*self }
+    }
+}
+
+impl<'a, K: Ord, V, B: Ord, F: FnMut(&K) -> B> Iterator for ChunkBy<'a, K, V, F> {
+    type Item = (B, Range<'a, K, V>);
+
+    fn next(&mut self) -> Option<(B, Range<'a, K, V>)> {
+        let front = self.next?;
+        let key = unsafe { &front.as_ref().key };
+        let bucket = (self.project)(key);
+        let mut update: [Option<NonNull<Node<K, V>>>; MAX_LEVEL] = [None; MAX_LEVEL];
+        let project = &mut self.project;
+        let end = unsafe { self.list.find_by(|k| project(k) <= bucket, &mut update) };
+        if let Some(end) = end {
+            assert!(
+                unsafe { &end.as_ref().key } > key,
+                "chunk_by projection decreases along key order"
+            );
+        }
+        self.next = end;
+        Some((bucket, self.list.range_between(Some(front), end)))
+    }
+}
+
+impl<K: Ord, V, B: Ord, F: FnMut(&K) -> B> FusedIterator for ChunkBy<'_, K, V, F> {}
+
+impl<'a, K, V> Iterator for RangeMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a mut V)> {
+        let node = unsafe { &mut *take_front(&mut self.front, &mut self.back)?.as_ptr() };
+        Some(node.entry_mut())
+    }
+
+    fn nth(&mut self, n: usize) -> Option<(&'a K, &'a mut V)> {
+        skip_front(&mut self.front, &mut self.back, n);
+        self.next()
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for RangeMut<'a, K, V> {
+    fn next_back(&mut self) -> Option<(&'a K, &'a mut V)> {
+        let node = unsafe { &mut *take_back(&mut self.front, &mut self.back)?.as_ptr() };
+        Some(node.entry_mut())
+    }
+}
+
+impl<K, V> FusedIterator for RangeMut<'_, K, V> {}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        let node = unsafe { &*take_front(&mut self.front, &mut self.back)?.as_ptr() };
+        self.len -= 1;
+        Some((&node.key, node.val()))
+    }
+
+    fn nth(&mut self, n: usize) -> Option<(&'a K, &'a V)> {
+        skip_counted(&mut self.front, &mut self.back, &mut self.len, n);
+        self.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for Iter<'a, K, V> {
+    fn next_back(&mut self) -> Option<(&'a K, &'a V)> {
+        let node = unsafe { &*take_back(&mut self.front, &mut self.back)?.as_ptr() };
+        self.len -= 1;
+        Some((&node.key, node.val()))
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+impl<K, V> FusedIterator for Iter<'_, K, V> {}
+
+impl<K, V> Clone for Iter<'_, K, V> {
+    fn clone(&self) -> Self {
+        Iter { ..*self }
+    }
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a mut V)> {
+        let node = unsafe { &mut *take_front(&mut self.front, &mut self.back)?.as_ptr() };
+        self.len -= 1;
+        Some(node.entry_mut())
+    }
+
+    fn nth(&mut self, n: usize) -> Option<(&'a K, &'a mut V)> {
+        skip_counted(&mut self.front, &mut self.back, &mut self.len, n);
+        self.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for IterMut<'a, K, V> {
+    fn next_back(&mut self) -> Option<(&'a K, &'a mut V)> {
+        let node = unsafe { &mut *take_back(&mut self.front, &mut self.back)?.as_ptr() };
+        self.len -= 1;
+        Some(node.entry_mut())
+    }
+}
+
+impl<K, V> ExactSizeIterator for IterMut<'_, K, V> {}
+
+impl<K, V> FusedIterator for IterMut<'_, K, V> {}
+
+// Panics like BTreeMap::range when start > end, or when start and end are
+// equal and both excluded.
+pub(crate) fn check_range<Q: Ord + ?Sized>(range: &impl RangeBounds<Q>) {
+    match (range.start_bound(), range.end_bound()) {
+        (Bound::Excluded(s), Bound::Excluded(e)) if s == e => {
+            panic!("range start and end are equal and excluded in SkipList")
+        }
+        (Bound::Included(s) | Bound::Excluded(s), Bound::Included(e) | Bound::Excluded(e))
+            if s > e =>
+        {
+            panic!("range start is greater than range end in SkipList")
+        }
+        _ => {}
+    }
+}
+
+// Takes nodes off the front: the first node sits right after the head on
+// every level it has, so unhooking it is a copy of its tower into the head's.
+// Whatever is left when the iterator is dropped goes with the list.
+impl<K, V> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<(K, V)> {
+        let list = &mut self.list;
+        unsafe {
+            let node_ptr = list.head.as_ref().tower[0]?;
+            let height = node_ptr.as_ref().height();
+            for l in 0..list.level {
+                if l < height {
+                    list.head.as_mut().tower[l] = node_ptr.as_ref().tower[l];
+                    list.head.as_mut().set_span(l, node_ptr.as_ref().span(l));
+                } else if list.head.as_ref().tower[l].is_some() {
+                    let span = list.head.as_ref().span(l);
+                    list.head.as_mut().set_span(l, span - 1);
+                }
+            }
+            if let Some(mut first) = list.head.as_ref().tower[0] {
+                first.as_mut().prev = None;
+            }
+            list.size -= 1;
+            Some(list.take_entry(node_ptr))
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.list.size, Some(self.list.size))
+    }
+}
+
+// The last node ends every level it is on, so its predecessors are the
+// last nodes before it found on the way down.
+impl<K, V> DoubleEndedIterator for IntoIter<K, V> {
+    fn next_back(&mut self) -> Option<(K, V)> {
+        let list = &mut self.list;
+        let last = list.last_node()?;
+        unsafe {
+            let mut x = list.head;
+            for l in (0..list.level).rev() {
+                while let Some(next) = x.as_ref().tower[l].filter(|next| *next != last) {
+                    x = next;
+                }
+                if x.as_ref().tower[l] == Some(last) {
+                    x.as_mut().tower[l] = None;
+                }
+            }
+            list.size -= 1;
+            Some(list.take_entry(last))
+        }
+    }
+}
+
+impl<K, V> ExactSizeIterator for IntoIter<K, V> {}
+
+impl<K, V> Iterator for Drain<'_, K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<(K, V)> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        unsafe {
+            let node_ptr = self.front?;
+            self.front = node_ptr.as_ref().tower[0];
+            Some(self.list.take_parked(node_ptr))
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<K, V> DoubleEndedIterator for Drain<'_, K, V> {
+    fn next_back(&mut self) -> Option<(K, V)> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        unsafe {
+            let node_ptr = self.back?;
+            self.back = node_ptr.as_ref().prev;
+            Some(self.list.take_parked(node_ptr))
+        }
+    }
+}
+
+impl<K, V> ExactSizeIterator for Drain<'_, K, V> {}
+
+impl<K, V> FusedIterator for Drain<'_, K, V> {}
+
+impl<K, V> Drop for Drain<'_, K, V> {
+    fn drop(&mut self) {
+        if self.len > 0 {
+            unsafe {
+                let after = self.back.unwrap().as_ref().tower[0];
+                self.list.free_chain(self.front, after, true);
+            }
+        }
+    }
+}
+
+impl<K, V, F: FnMut(&K, &mut V) -> bool> Iterator for DrainFilter<'_, K, V, F> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<(K, V)> {
+        unsafe {
+            while let Some(mut node_ptr) = self.next {
+                let node = node_ptr.as_mut();
+                self.next = node.tower[0];
+                let (key, val) = node.entry_mut();
+                if (self.pred)(key, val) {
+                    return Some(self.list.unlink(node_ptr, &self.update));
+                }
+                self.update[..node.height()].fill(Some(node_ptr));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.list.size))
+    }
+}
+
+impl<K, V, F: FnMut(&K, &mut V) -> bool> FusedIterator for DrainFilter<'_, K, V, F> {}
+
+impl<K, V> FusedIterator for IntoIter<K, V> {}
+
+impl<'a, K, V> IntoIterator for &'a SkipList<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Iter<'a, K, V> {
+        self.iter()
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a mut SkipList<K, V> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> IterMut<'a, K, V> {
+        self.iter_mut()
+    }
+}
+
+impl<K, V> IntoIterator for SkipList<K, V> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(self) -> IntoIter<K, V> {
+        IntoIter { list: self }
+    }
+}
+
+impl<'a, K, V> Iterator for Keys<'a, K, V> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<&'a K> {
+        self.inner.next().map(|(k, _)| k)
+    }
+
+    fn nth(&mut self, n: usize) -> Option<&'a K> {
+        self.inner.nth(n).map(|(k, _)| k)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for Keys<'a, K, V> {
+    fn next_back(&mut self) -> Option<&'a K> {
+        self.inner.next_back().map(|(k, _)| k)
+    }
+}
+
+impl<K, V> ExactSizeIterator for Keys<'_, K, V> {}
+
+impl<K, V> FusedIterator for Keys<'_, K, V> {}
+
+impl<K, V> Clone for Keys<'_, K, V> {
+    fn clone(&self) -> Self {
+        Keys {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for Values<'a, K, V> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<&'a V> {
+        self.inner.next().map(|(_, v)| v)
+    }
+
+    fn nth(&mut self, n: usize) -> Option<&'a V> {
+        self.inner.nth(n).map(|(_, v)| v)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for Values<'a, K, V> {
+    fn next_back(&mut self) -> Option<&'a V> {
+        self.inner.next_back().map(|(_, v)| v)
+    }
+}
+
+impl<K, V> ExactSizeIterator for Values<'_, K, V> {}
+
+impl<K, V> FusedIterator for Values<'_, K, V> {}
+
+impl<K, V> Clone for Values<'_, K, V> {
+    fn clone(&self) -> Self {
+        Values {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for ValuesMut<'a, K, V> {
+    type Item = &'a mut V;
+
+    fn next(&mut self) -> Option<&'a mut V> {
+        self.inner.next().map(|(_, v)| v)
+    }
+
+    fn nth(&mut self, n: usize) -> Option<&'a mut V> {
+        self.inner.nth(n).map(|(_, v)| v)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for ValuesMut<'a, K, V> {
+    fn next_back(&mut self) -> Option<&'a mut V> {
+        self.inner.next_back().map(|(_, v)| v)
+    }
+}
+
+impl<K, V> ExactSizeIterator for ValuesMut<'_, K, V> {}
+
+impl<K, V> FusedIterator for ValuesMut<'_, K, V> {}
+
+impl<K, V> Iterator for IntoKeys<K, V> {
+    type Item = K;
+
+    fn next(&mut self) -> Option<K> {
+        self.inner.next().map(|(k, _)| k)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K, V> DoubleEndedIterator for IntoKeys<K, V> {
+    fn next_back(&mut self) -> Option<K> {
+        self.inner.next_back().map(|(k, _)| k)
+    }
+}
+
+impl<K, V> ExactSizeIterator for IntoKeys<K, V> {}
+
+impl<K, V> FusedIterator for IntoKeys<K, V> {}
+
+impl<K, V> Iterator for IntoValues<K, V> {
+    type Item = V;
+
+    fn next(&mut self) -> Option<V> {
+        self.inner.next().map(|(_, v)| v)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K, V> DoubleEndedIterator for IntoValues<K, V> {
+    fn next_back(&mut self) -> Option<V> {
+        self.inner.next_back().map(|(_, v)| v)
+    }
+}
+
+impl<K, V> ExactSizeIterator for IntoValues<K, V> {}
+
+impl<K, V> FusedIterator for IntoValues<K, V> {}
+
+#[cfg(test)]
+mod tests {
+    use crate::SkipList;
+    use std::rc::Rc;
+
+    #[test]
+    fn chunk_by_groups_runs_of_buckets() {
+        let sk: SkipList<u64, u64> = [3, 7, 100, 101, 150, 420, 999, 1000]
+            .into_iter()
+            .map(|ts| (ts, ts * 2))
+            .collect();
+        let groups: Vec<_> = sk
+            .chunk_by(|ts| ts / 100)
+            .map(|(day, events)| (day, events.map(|(k, _)| *k).collect::<Vec<_>>()))
+            .collect();
+        assert_eq!(
+            groups,
+            [
+                (0, vec![3, 7]),
+                (1, vec![100, 101, 150]),
+                (4, vec![420]),
+                (9, vec![999]),
+                (10, vec![1000]),
+            ]
+        );
+        let mut chunks = sk.chunk_by(|ts| ts / 100);
+        let (_, mut skipped) = chunks.nth(1).unwrap();
+        assert_eq!(skipped.next_back(), Some((&150, &300)));
+        assert_eq!(chunks.count(), 3);
+        assert!(SkipList::<u64, ()>::new().chunk_by(|k| *k).next().is_none());
+    }
+
+    #[test]
+    fn drain_empties_and_keeps_the_list() {
+        let val = Rc::new(());
+        for mut sk in [SkipList::new(), SkipList::with_arena(1 << 16)] {
+            // Same-height towers, so every insert after the drain reuses a node.
+            sk.level_of = Some(|_, _| 1);
+            for i in 0..100 {
+                sk.insert(i, val.clone());
+            }
+            let mut drain = sk.drain();
+            assert_eq!(drain.len(), 100);
+            assert_eq!(drain.next().map(|(k, _)| k), Some(0));
+            assert_eq!(drain.next_back().map(|(k, _)| k), Some(99));
+            assert_eq!(drain.len(), 98);
+            drop(drain);
+            assert!(sk.is_empty());
+            assert_eq!(Rc::strong_count(&val), 1);
+            assert_eq!(sk.free.iter().map(Vec::len).sum::<usize>(), 100);
+
+            let used = sk.memory_usage();
+            for i in (0..100).rev() {
+                sk.insert(i, val.clone());
+            }
+            assert_eq!(sk.memory_usage(), used);
+            let keys: Vec<_> = sk.drain().rev().map(|(k, _)| k).collect();
+            assert!(keys.into_iter().eq((0..100).rev()));
+            sk.insert(7, val.clone());
+            assert_eq!(sk.len(), 1);
+            assert_eq!(sk.iter().count(), 1);
+        }
+        assert_eq!(SkipList::<u32, ()>::new().drain().next(), None);
+    }
+
+    #[test]
+    fn drain_filter() {
+        let mut sk = SkipList::new();
+        for i in 0..200 {
+            sk.insert(i, i);
+        }
+        let odd: Vec<_> = sk.drain_filter(|k, _| k % 2 == 1).collect();
+        assert_eq!(
+            odd,
+            (0..200)
+                .filter(|k| k % 2 == 1)
+                .map(|k| (k, k))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(sk.len(), 100);
+        for (i, (k, _)) in sk.iter().enumerate() {
+            assert_eq!(*k, i * 2);
+            assert_eq!(sk.rank(k), i);
+        }
+
+        // Stopping early keeps the rest, matching or not.
+        {
+            let mut drain = sk.drain_filter(|_, v| {
+                *v += 1;
+                true
+            });
+            assert_eq!(drain.next(), Some((0, 1)));
+            assert_eq!(drain.next(), Some((2, 3)));
+        }
+        assert_eq!(sk.len(), 98);
+        assert_eq!(sk.first_key_value(), Some((&4, &4)));
+        assert_eq!(sk.get_by_index(97), Some((&198, &198)));
+    }
+
+    #[test]
+    fn iter_and_iter_mut() {
+        let mut sk = SkipList::new();
+        for i in (0..100).rev() {
+            sk.insert(i, i * 10);
+        }
+        let it = sk.iter();
+        assert_eq!(it.len(), 100);
+        assert_eq!(
+            it.map(|(k, v)| (*k, *v)).collect::<Vec<_>>(),
+            (0..100).map(|i| (i, i * 10)).collect::<Vec<_>>()
+        );
+        for (k, v) in &mut sk {
+            *v += k;
+        }
+        assert_eq!(sk.get(&7), Some(&77));
+        assert_eq!((&sk).into_iter().nth(99), Some((&99, &1089)));
+        assert!(SkipList::<i32, i32>::new().iter().next().is_none());
+    }
+
+    #[test]
+    fn key_and_value_adapters() {
+        let mut sk: SkipList<i32, String> = (0..50).rev().map(|i| (i, i.to_string())).collect();
+        assert!(sk.keys().copied().eq(0..50));
+        assert_eq!(sk.keys().next_back(), Some(&49));
+        assert_eq!(sk.values().len(), 50);
+        assert_eq!(sk.values().nth(7).map(String::as_str), Some("7"));
+        for v in sk.values_mut() {
+            v.push('!');
+        }
+        assert_eq!(sk.get(&3).map(String::as_str), Some("3!"));
+        assert!(sk.clone().into_keys().rev().eq((0..50).rev()));
+        let mut vals = sk.into_values();
+        assert_eq!(vals.next_back().as_deref(), Some("49!"));
+        assert_eq!(vals.len(), 49);
+        assert_eq!(vals.next().as_deref(), Some("0!"));
+    }
+
+    // Runs the same random mix of nth and next_back on it and on a copy that
+    // steps with next, which is all the default nth does.
+    fn check_nth<T: PartialEq + std::fmt::Debug>(
+        mut it: impl DoubleEndedIterator<Item = T> + Clone,
+        rng: &mut impl rand::Rng,
+    ) {
+        let mut stepped = it.clone();
+        loop {
+            if rng.gen_bool(0.2) {
+                assert_eq!(it.next_back(), stepped.next_back());
+            }
+            let n = rng.gen_range(0..40);
+            let want = (0..n).find_map(|_| stepped.next().is_none().then_some(None));
+            let want = want.unwrap_or_else(|| stepped.next());
+            let got = it.nth(n);
+            assert_eq!(got, want);
+            if got.is_none() {
+                assert_eq!(it.next(), None);
+                assert_eq!(it.next_back(), None);
+                return;
+            }
+        }
+    }
+
+    #[test]
+    fn nth_skips_along_towers() {
+        use rand::prelude::*;
+        let mut rng = StdRng::seed_from_u64(14);
+        let mut sk = SkipList::new();
+        for i in 0..2_000 {
+            sk.insert(i, i * 2);
+        }
+        for _ in 0..700 {
+            sk.remove(&rng.gen_range(0..2_000));
+        }
+        for _ in 0..20 {
+            check_nth(sk.iter(), &mut rng);
+            check_nth(sk.keys(), &mut rng);
+            check_nth(sk.values(), &mut rng);
+            let lo = rng.gen_range(0..2_000);
+            check_nth(sk.range(lo..lo + rng.gen_range(0..300)), &mut rng);
+        }
+        let mut it = sk.iter();
+        assert_eq!(it.nth(100), sk.iter().nth(100));
+        assert_eq!(it.len(), sk.len() - 101);
+        assert_eq!(it.nth(sk.len()), None);
+        assert_eq!(it.len(), 0);
+
+        let key = *sk.keys().nth(500).unwrap();
+        assert_eq!(sk.iter_mut().nth(500).map(|(k, _)| *k), Some(key));
+        *sk.values_mut().nth(500).unwrap() = 0;
+        assert_eq!(sk.range_mut(..=key).nth(500), Some((&key, &mut 0)));
+        assert_eq!(sk.range(..).nth(sk.len()), None);
+        assert_eq!(sk.range(5..5).nth(3), None);
+    }
+
+    #[test]
+    fn prefix_iteration() {
+        let mut sk = SkipList::new();
+        for key in [
+            &b"a"[..],
+            b"ab",
+            b"ab\x00",
+            b"ab\xff",
+            b"ab\xff\xff",
+            b"ac",
+            b"b",
+            b"\xff",
+            b"\xff\x01",
+        ] {
+            sk.insert(key.to_vec(), ());
+        }
+        let with = |prefix: &[u8]| {
+            sk.prefix_iter(prefix)
+                .map(|(k, _)| k.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            with(b"ab"),
+            [&b"ab"[..], b"ab\x00", b"ab\xff", b"ab\xff\xff"]
+        );
+        assert_eq!(with(b"ab\xff"), [&b"ab\xff"[..], b"ab\xff\xff"]);
+        assert_eq!(with(b"\xff"), [&b"\xff"[..], b"\xff\x01"]);
+        assert_eq!(with(b"").len(), 9);
+        assert!(with(b"abc").is_empty());
+        assert_eq!(sk.prefix_iter(b"a").next_back().unwrap().0, b"ac");
+    }
+
+    #[test]
+    fn range_matches_btreemap() {
+        use std::collections::BTreeMap;
+        use std::ops::Bound::{self, Excluded, Included, Unbounded};
+
+        let mut sk = SkipList::new();
+        let mut model = BTreeMap::new();
+        for i in (0..60).step_by(3) {
+            sk.insert(i, i);
+            model.insert(i, i);
+        }
+        let bound = |kind, k| match kind {
+            0 => Included(k),
+            1 => Excluded(k),
+            _ => Unbounded,
+        };
+        for a in -2..62 {
+            for b in a..62 {
+                for (ka, kb) in [(0, 0), (0, 1), (1, 0), (1, 1), (2, 0), (0, 2), (2, 2)] {
+                    if a == b && ka == 1 && kb == 1 {
+                        continue;
+                    }
+                    let r: (Bound<i32>, Bound<i32>) = (bound(ka, a), bound(kb, b));
+                    assert!(sk.range(r).eq(model.range(r)), "{:?}", r);
+                }
+            }
+        }
+
+        for (_, v) in sk.range_mut(10..=20) {
+            *v = -1;
+        }
+        assert_eq!(
+            sk.iter()
+                .filter(|(_, v)| **v == -1)
+                .map(|(k, _)| *k)
+                .collect::<Vec<_>>(),
+            vec![12, 15, 18]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "range start is greater than range end")]
+    fn range_start_after_end_panics() {
+        use std::ops::Bound;
+        let sk: SkipList<i32, ()> = SkipList::new();
+        let _ = sk.range((Bound::Included(5), Bound::Excluded(3)));
+    }
+
+    #[test]
+    fn reverse_iteration() {
+        let mut sk = SkipList::new();
+        for i in 0..100 {
+            sk.insert(i, i);
+        }
+        assert_eq!(sk.last_key_value(), Some((&99, &99)));
+        assert_eq!(
+            sk.iter().rev().take(3).map(|(k, _)| *k).collect::<Vec<_>>(),
+            vec![99, 98, 97]
+        );
+        let mut it = sk.iter();
+        assert_eq!(it.next(), Some((&0, &0)));
+        assert_eq!(it.next_back(), Some((&99, &99)));
+        assert_eq!(it.len(), 98);
+        assert_eq!(it.by_ref().rev().take(97).count(), 97);
+        assert_eq!(it.next(), Some((&1, &1)));
+        assert_eq!(it.next(), None);
+        assert_eq!(it.next_back(), None);
+
+        assert_eq!(
+            sk.range(10..=15).rev().map(|(k, _)| *k).collect::<Vec<_>>(),
+            vec![15, 14, 13, 12, 11, 10]
+        );
+        let mut r = sk.range(40..43);
+        assert_eq!(r.next_back(), Some((&42, &42)));
+        assert_eq!(r.next(), Some((&40, &40)));
+        assert_eq!(r.next_back(), Some((&41, &41)));
+        assert_eq!(r.next(), None);
+        assert!(sk.range(200..).next_back().is_none());
+        for (_, v) in sk.range_mut(..5).rev() {
+            *v = -*v;
+        }
+        assert_eq!(sk.get(&4), Some(&-4));
+
+        for i in (0..100).step_by(3) {
+            sk.remove(&i);
+        }
+        sk.pop_first_k(2);
+        let forward: Vec<i32> = sk.iter().map(|(k, _)| *k).collect();
+        let mut backward: Vec<i32> = sk.iter().rev().map(|(k, _)| *k).collect();
+        backward.reverse();
+        assert_eq!(forward, backward);
+
+        let mut it = sk.into_iter();
+        assert_eq!(it.next_back().map(|(k, _)| k), Some(98));
+        assert_eq!(it.next_back().map(|(k, _)| k), Some(97));
+        assert_eq!(it.next().map(|(k, _)| k), Some(4));
+        assert_eq!(it.len(), 61);
+        let rest: Vec<i32> = it.rev().map(|(k, _)| k).collect();
+        assert_eq!(rest.len(), 61);
+        assert!(rest.windows(2).all(|w| w[0] > w[1]));
+        assert_eq!(SkipList::<i32, ()>::new().last_key_value(), None);
+    }
+
+    #[test]
+    fn into_iter() {
+        let val = Rc::new(());
+        let mut sk = SkipList::new();
+        for i in 0..50 {
+            sk.insert(i, val.clone());
+        }
+        let mut it = sk.into_iter();
+        assert_eq!(it.next().map(|(k, _)| k), Some(0));
+        assert_eq!(it.len(), 49);
+        let keys: Vec<i32> = it.by_ref().take(10).map(|(k, _)| k).collect();
+        assert_eq!(keys, (1..11).collect::<Vec<_>>());
+        assert_eq!(
+            it.list.iter().map(|(k, _)| *k).collect::<Vec<_>>(),
+            (11..50).collect::<Vec<_>>()
+        );
+        drop(it);
+        assert_eq!(Rc::strong_count(&val), 1);
+    }
+}
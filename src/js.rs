@@ -0,0 +1,54 @@
+use crate::SkipList;
+use js_sys::Array;
+use std::ops::Bound;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen(js_name = SkipList)]
+#[derive(Default)]
+pub struct JsSkipList {
+    inner: SkipList<String, JsValue>,
+}
+
+#[wasm_bindgen(js_class = SkipList)]
+impl JsSkipList {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> JsSkipList {
+        JsSkipList::default()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn size(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn insert(&mut self, key: String, val: JsValue) {
+        self.inner.insert(key, val);
+    }
+
+    pub fn get(&self, key: String) -> JsValue {
+        self.inner.get(&key).cloned().unwrap_or(JsValue::UNDEFINED)
+    }
+
+    pub fn has(&self, key: String) -> bool {
+        self.inner.get(&key).is_some()
+    }
+
+    pub fn delete(&mut self, key: String) -> bool {
+        self.inner.remove(&key).is_some()
+    }
+
+    // Entries with start <= key < end; a missing bound is open.
+    pub fn range(&self, start: Option<String>, end: Option<String>) -> Array {
+        let start = start.map_or(Bound::Unbounded, Bound::Included);
+        let end = end.map_or(Bound::Unbounded, Bound::Excluded);
+        self.inner.range((start, end)).map(pair).collect()
+    }
+
+    pub fn entries(&self) -> Array {
+        self.inner.iter().map(pair).collect()
+    }
+}
+
+fn pair((key, val): (&String, &JsValue)) -> JsValue {
+    Array::of2(&JsValue::from_str(key), val).into()
+}
@@ -0,0 +1,183 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+// A byte encoding that sorts the same way as Ord, so keys can be compared as
+// plain bytes once they leave the process. Every encoding is prefix-free,
+// which is what lets components be concatenated and inverted for descending
+// order.
+pub trait SkipKey: Ord {
+    fn encode_key(&self, out: &mut Vec<u8>);
+
+    fn to_key_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.encode_key(&mut out);
+        out
+    }
+}
+
+// Flips an encoded component so it sorts in descending order.
+pub fn invert(bytes: &mut [u8]) {
+    for b in bytes {
+        *b = !*b;
+    }
+}
+
+// The smallest byte string after every string that starts with prefix: the
+// prefix with its trailing ff bytes dropped and the last byte left bumped by
+// one. None when the prefix is empty or all ff, since then nothing is after.
+pub fn prefix_successor(prefix: &[u8]) -> Option<Vec<u8>> {
+    let last = prefix.iter().rposition(|b| *b != 0xff)?;
+    let mut bound = prefix[..=last].to_vec();
+    bound[last] += 1;
+    Some(bound)
+}
+
+pub fn cmp_ignore_case(a: &str, b: &str) -> Ordering {
+    a.chars()
+        .flat_map(char::to_lowercase)
+        .cmp(b.chars().flat_map(char::to_lowercase))
+}
+
+pub fn encode_ignore_case(s: &str, out: &mut Vec<u8>) {
+    let lower: String = s.chars().flat_map(char::to_lowercase).collect();
+    lower.encode_key(out);
+}
+
+macro_rules! unsigned {
+    ($($t:ty)*) => {$(
+        impl SkipKey for $t {
+            fn encode_key(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_be_bytes());
+            }
+        }
+    )*};
+}
+
+macro_rules! signed {
+    ($($t:ty => $u:ty)*) => {$(
+        impl SkipKey for $t {
+            fn encode_key(&self, out: &mut Vec<u8>) {
+                let flipped = (*self as $u) ^ (1 << (<$u>::BITS - 1));
+                out.extend_from_slice(&flipped.to_be_bytes());
+            }
+        }
+    )*};
+}
+
+unsigned!(u8 u16 u32 u64 u128 usize);
+signed!(i8 => u8 i16 => u16 i32 => u32 i64 => u64 i128 => u128 isize => usize);
+
+impl SkipKey for bool {
+    fn encode_key(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8);
+    }
+}
+
+impl SkipKey for char {
+    fn encode_key(&self, out: &mut Vec<u8>) {
+        (*self as u32).encode_key(out);
+    }
+}
+
+// Zero bytes are escaped as 00 ff and the string ends with 00 00, so a string
+// sorts before every string it is a prefix of.
+impl SkipKey for str {
+    fn encode_key(&self, out: &mut Vec<u8>) {
+        for &b in self.as_bytes() {
+            out.push(b);
+            if b == 0 {
+                out.push(0xff);
+            }
+        }
+        out.extend_from_slice(&[0, 0]);
+    }
+}
+
+impl SkipKey for String {
+    fn encode_key(&self, out: &mut Vec<u8>) {
+        self.as_str().encode_key(out);
+    }
+}
+
+impl<T: SkipKey + ?Sized> SkipKey for &T {
+    fn encode_key(&self, out: &mut Vec<u8>) {
+        (**self).encode_key(out);
+    }
+}
+
+impl<T: SkipKey> SkipKey for Option<T> {
+    fn encode_key(&self, out: &mut Vec<u8>) {
+        match self {
+            None => out.push(0),
+            Some(v) => {
+                out.push(1);
+                v.encode_key(out);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{prefix_successor, SkipKey};
+
+    #[test]
+    fn prefix_successors() {
+        assert_eq!(prefix_successor(b"ab"), Some(b"ac".to_vec()));
+        assert_eq!(prefix_successor(b"a\xff\xff"), Some(b"b".to_vec()));
+        assert_eq!(
+            prefix_successor(b"\x01\xfe\xff"),
+            Some(b"\x01\xff".to_vec())
+        );
+        assert_eq!(prefix_successor(b"\xff\xff"), None);
+        assert_eq!(prefix_successor(b""), None);
+    }
+
+    fn assert_sorted<T: SkipKey + std::fmt::Debug>(values: &[T]) {
+        for w in values.windows(2) {
+            assert!(w[0] < w[1], "{:?} < {:?}", w[0], w[1]);
+            assert!(
+                w[0].to_key_bytes() < w[1].to_key_bytes(),
+                "{:?} < {:?}",
+                w[0],
+                w[1]
+            );
+        }
+    }
+
+    #[test]
+    fn encoding_sorts_like_ord() {
+        assert_sorted(&[i64::MIN, -300, -1, 0, 1, 255, i64::MAX]);
+        assert_sorted(&[0u16, 1, 256, u16::MAX]);
+        assert_sorted(&["", "a", "a\0", "a\0b", "a\u{1}", "ab", "b"]);
+        assert_sorted(&[None, Some(false), Some(true)]);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derived_composite_key() {
+        #[derive(Debug, crate::SkipKey)]
+        struct Order<'a> {
+            #[skip_key(ignore_case)]
+            symbol: &'a str,
+            #[skip_key(desc)]
+            price: u32,
+            id: i64,
+        }
+
+        let order = |symbol, price, id| Order { symbol, price, id };
+        assert_sorted(&[
+            order("aapl", 200, 7),
+            order("AAPL", 150, -2),
+            order("aapl", 150, 3),
+            order("Aapl", 100, 0),
+            order("msft", 900, 1),
+        ]);
+        assert_eq!(order("MSFT", 1, 1), order("msft", 1, 1));
+        assert_eq!(
+            order("MSFT", 1, 1).to_key_bytes(),
+            order("msft", 1, 1).to_key_bytes()
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use crate::DuplicatePolicy;
+use alloc::vec::Vec;
+use core::iter::FusedIterator;
+
+// Merges sorted sources into one sorted stream, e.g. an active memtable and
+// the frozen ones behind it. Each source must be sorted by key; a key found
+// in several sources comes out once, with the policy folding the later
+// sources' values into the first one's in source order, so FirstWins keeps
+// the first source's value and LastWins the last's. Picking the next key
+// scans every source's head, which suits the handful of sources a read path
+// merges.
+pub struct MergeIter<K, V, I, P> {
+    sources: Vec<I>,
+    heads: Vec<Option<(K, V)>>,
+    policy: P,
+}
+
+impl<K: Ord, V, I: Iterator<Item = (K, V)>, P: DuplicatePolicy<K, V>> MergeIter<K, V, I, P> {
+    pub fn new<S, J>(sources: S, policy: P) -> Self
+    where
+        S: IntoIterator<Item = J>,
+        J: IntoIterator<IntoIter = I>,
+    {
+        let mut sources: Vec<I> = sources.into_iter().map(J::into_iter).collect();
+        let heads = sources.iter_mut().map(Iterator::next).collect();
+        Self {
+            sources,
+            heads,
+            policy,
+        }
+    }
+}
+
+impl<K: Ord, V, I: Iterator<Item = (K, V)>, P: DuplicatePolicy<K, V>> Iterator
+    for MergeIter<K, V, I, P>
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<(K, V)> {
+        // The first source holding the smallest key, so every source before
+        // it is already past that key.
+        let mut first: Option<(usize, &K)> = None;
+        for (i, head) in self.heads.iter().enumerate() {
+            if let Some((key, _)) = head {
+                if first.is_none_or(|(_, min)| key < min) {
+                    first = Some((i, key));
+                }
+            }
+        }
+        let (first, _) = first?;
+        let (key, mut val) = self.heads[first].take().unwrap();
+        self.heads[first] = self.sources[first].next();
+        for i in first..self.sources.len() {
+            while self.heads[i].as_ref().is_some_and(|(k, _)| *k == key) {
+                let (_, new) = self.heads[i].take().unwrap();
+                self.heads[i] = self.sources[i].next();
+                self.policy.merge(&key, &mut val, new);
+            }
+        }
+        Some((key, val))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let mut upper = Some(0usize);
+        let mut lower = 0;
+        for (head, source) in self.heads.iter().zip(&self.sources) {
+            if head.is_some() {
+                let (lo, hi) = source.size_hint();
+                lower = lower.max(lo + 1);
+                upper = upper.zip(hi).and_then(|(u, h)| u.checked_add(h + 1));
+            }
+        }
+        (lower, upper)
+    }
+}
+
+impl<K: Ord, V, I: Iterator<Item = (K, V)>, P: DuplicatePolicy<K, V>> FusedIterator
+    for MergeIter<K, V, I, P>
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MergeIter;
+    use crate::{FirstWins, LastWins, SkipList};
+
+    #[test]
+    fn merges_memtables_newest_first() {
+        let active: SkipList<u32, &str> = [(1, "a2"), (4, "d2")].into_iter().collect();
+        let older: SkipList<u32, &str> = [(1, "a1"), (2, "b1"), (4, "d1")].into_iter().collect();
+        let frozen = [(0, "z0"), (2, "b0"), (9, "i0")]
+            .into_iter()
+            .collect::<SkipList<u32, &str>>()
+            .freeze();
+        let sources = || -> [Box<dyn Iterator<Item = (&u32, &&str)> + '_>; 3] {
+            [
+                Box::new(active.iter()),
+                Box::new(older.iter()),
+                Box::new(frozen.iter()),
+            ]
+        };
+
+        let newest: Vec<_> = MergeIter::new(sources(), FirstWins).collect();
+        assert_eq!(
+            newest,
+            [
+                (&0, &"z0"),
+                (&1, &"a2"),
+                (&2, &"b1"),
+                (&4, &"d2"),
+                (&9, &"i0")
+            ]
+        );
+        let oldest = MergeIter::new(sources(), LastWins);
+        assert_eq!(oldest.size_hint(), (3, Some(8)));
+        assert!(oldest.map(|(_, v)| *v).eq(["z0", "a1", "b0", "d1", "i0"]));
+
+        let counted = MergeIter::new(
+            [vec![(1, 1), (1, 1), (3, 1)], vec![(1, 1), (2, 1), (3, 1)]],
+            |_: &u32, n: &mut u32, m: u32| *n += m,
+        );
+        assert!(counted.eq([(1, 3), (2, 1), (3, 2)]));
+        let empty: Vec<Vec<(u32, ())>> = vec![vec![], vec![]];
+        assert!(MergeIter::new(empty, FirstWins).next().is_none());
+    }
+}
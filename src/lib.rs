@@ -1,75 +1,591 @@
-use rand::prelude::*;
-use std::alloc::{alloc, dealloc, Layout};
-use std::cmp::Ord;
-use std::mem;
-use std::ops::Index;
-use std::ops::IndexMut;
-use std::ptr::NonNull;
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
-const MAX_LEVEL: usize = 20;
+extern crate alloc;
+
+use alloc::alloc::Layout;
+use alloc::boxed::Box;
+use alloc::collections::BTreeSet;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use builder::GlobalNodes;
+use core::borrow::Borrow;
+use core::cmp::Ord;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::marker::PhantomData;
+use core::mem;
+use core::ops::Index;
+use core::ops::IndexMut;
+use core::ops::{Bound, ControlFlow, RangeBounds};
+use core::ptr::{self, NonNull};
+use rand::{Rng, RngCore};
+use storage::Nodes;
+use sync::Lock;
+
+#[cfg(feature = "arrow")]
+mod arrow;
+
+mod allocator;
+mod any;
+mod arena;
+mod batch;
+pub mod builder;
+mod bulk;
+mod changes;
+mod comparator;
+mod cursor;
+mod dump;
+mod entry;
+pub mod fast;
+mod frozen;
+mod hybrid;
+mod ids;
+pub mod interval;
+mod iter;
+pub mod key;
+mod kmerge;
+mod lru;
+mod macros;
+mod merge;
+mod multimap;
+mod observe;
+pub mod persistent;
+mod prefix;
+mod queue;
+mod quota;
+pub mod set;
+mod setops;
+mod sparse;
+pub mod spatial;
+mod stats;
+mod storage;
+mod sync;
+mod topn;
+mod versioned;
+mod weight;
+
+pub use any::AnySkipList;
+pub use batch::WriteBatch;
+pub use builder::SkipListBuilder;
+pub use bulk::{CompactionFilter, Decision, DuplicateKey, DuplicatePolicy, FirstWins, LastWins};
+pub use changes::{Change, TrackedSkipList};
+pub use comparator::{Comparator, ComparatorSkipList};
+pub use cursor::{Cursor, CursorMut, RawCursor, StaleCursor, UnorderedKey};
+pub use entry::{Entry, OccupiedEntry, OccupiedError, VacantEntry};
+pub use frozen::FrozenSkipList;
+pub use hybrid::{HybridIter, HybridSkipList};
+pub use ids::IdKey;
+pub use iter::{
+    ChunkBy, Drain, DrainFilter, IntoIter, IntoKeys, IntoValues, Iter, IterMut, Keys, Range,
+    RangeMut, Values, ValuesMut,
+};
+pub use kmerge::MergeIter;
+pub use lru::{ByRecency, EvictionPolicy, Fifo, Lru, LruSkipList, SecondChance};
+pub use merge::{MergeOperator, MergeSkipList};
+pub use multimap::SkipMultiMap;
+pub use observe::{Event, ObservedSkipList, SubscriptionId};
+pub use prefix::PrefixSkipList;
+pub use queue::{QueueTicket, SkipQueue};
+pub use quota::{QuotaExceeded, QuotaOverlap, QuotaSkipList, QuotaUsage};
+pub use set::SkipSet;
+pub use setops::{Difference, Intersection, SymmetricDifference, Union};
+pub use sparse::{SparseIndex, SparseIndexBuilder};
+pub use stats::{CorruptionReport, SkipListStats};
+pub use storage::Storage;
+pub use versioned::VersionedSkipList;
+pub use weight::{InlineSize, WeighedSkipList, WeightOf};
+
+#[cfg(feature = "icu")]
+pub mod collation;
+
+#[cfg(feature = "js")]
+pub mod js;
+
+#[cfg(feature = "filter")]
+mod filter;
+
+#[cfg(feature = "failpoints")]
+pub mod failpoints;
+
+#[cfg(feature = "ord-checks")]
+mod ordcheck;
+
+#[cfg(feature = "search-stats")]
+mod trace;
+
+#[cfg(feature = "search-stats")]
+pub use trace::SearchStats;
+
+#[cfg(feature = "shadow")]
+pub mod shadow;
+
+#[cfg(feature = "std")]
+pub mod sharded;
+
+#[cfg(feature = "std")]
+mod snapshot;
+
+#[cfg(feature = "std")]
+pub use snapshot::{SnapshotCodec, SnapshotError};
+
+#[cfg(feature = "std")]
+mod spill;
+
+#[cfg(feature = "std")]
+pub use spill::{SpillIter, SpillSkipList};
+
+#[cfg(feature = "concurrent")]
+pub mod concurrent;
+
+#[cfg(feature = "concurrent")]
+pub mod compat;
+
+#[cfg(feature = "zeroize")]
+mod wipe;
+
+// Deadlines are Instants, which need std.
+#[cfg(feature = "std")]
+mod expiring;
+
+#[cfg(feature = "serde")]
+mod serde;
+
+// Lets the derive's ::rusty_skiplist paths resolve inside this crate too.
+#[cfg(feature = "derive")]
+extern crate self as rusty_skiplist;
+#[cfg(feature = "derive")]
+pub use rusty_skiplist_derive::SkipKey;
+
+// What the macros and the derive expand to, so they work without std.
+#[doc(hidden)]
+pub mod __private {
+    pub use alloc::vec;
+    pub use alloc::vec::Vec;
+}
+
+#[cfg(feature = "filter")]
+pub use filter::KeyFilter;
+
+#[cfg(feature = "arrow")]
+pub use arrow::{ArrowSchemaMapping, RecordBatches};
+
+#[cfg(feature = "std")]
+pub use expiring::ExpiringSkipList;
+
+// The most levels any list can be built with; sizes the predecessor arrays
+// that searches fill. Lists default to fewer.
+const MAX_LEVEL: usize = 32;
+const DEFAULT_MAX_LEVEL: usize = 20;
+const NODE_ALIGN: usize = mem::align_of::<usize>();
+const CACHE_LINE: usize = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum NodeLayout {
+    #[default]
+    Compact,
+    // Every node starts on its own cache line and is padded to a whole number
+    // of lines, so the key and the lowest tower levels share the first line and
+    // no two nodes ever share one.
+    CacheAligned,
+    // Cache-aligned as well, but a node keeps only its lowest n links, and
+    // their spans, in place. A taller tower keeps the rest in an array of its
+    // own that the node points to, so no node outgrows the lines its first n
+    // links fit in, at the cost of one more pointer hop above level n.
+    Inline(u8),
+}
+
+impl NodeLayout {
+    fn align(self) -> usize {
+        match self {
+            NodeLayout::Compact => NODE_ALIGN,
+            NodeLayout::CacheAligned | NodeLayout::Inline(_) => CACHE_LINE,
+        }
+    }
+
+    fn inline(self) -> usize {
+        match self {
+            NodeLayout::Inline(n) => n as usize,
+            _ => usize::MAX,
+        }
+    }
+}
+
+// A node could not be allocated; the list is left as it was.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AllocError;
+
+impl fmt::Display for AllocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("skiplist node allocation failed")
+    }
+}
+
+impl core::error::Error for AllocError {}
 
 struct Tower<K, V> {
     forward: [Option<NonNull<Node<K, V>>>; 0],
 }
 
+impl<K, V> Tower<K, V> {
+    // One of the node's own links, or one in its spill array, which the word
+    // after its own links points to.
+    unsafe fn link(
+        forward: *mut Option<NonNull<Node<K, V>>>,
+        index: usize,
+    ) -> *mut Option<NonNull<Node<K, V>>> {
+        let back = mem::offset_of!(Node<K, V>, tower) - mem::offset_of!(Node<K, V>, inline);
+        let inline = *(forward as *const u8).sub(back) as usize;
+        if index < inline {
+            forward.add(index)
+        } else {
+            (*(forward.add(inline) as *const *mut Option<NonNull<Node<K, V>>>)).add(index - inline)
+        }
+    }
+}
+
 impl<K, V> Index<usize> for Tower<K, V> {
     type Output = Option<NonNull<Node<K, V>>>;
 
     fn index(&self, index: usize) -> &Option<NonNull<Node<K, V>>> {
-        unsafe { self.forward.get_unchecked(index) }
+        unsafe { &*Tower::link(self.forward.as_ptr() as *mut _, index) }
     }
 }
 
 impl<K, V> IndexMut<usize> for Tower<K, V> {
     fn index_mut(&mut self, index: usize) -> &mut Option<NonNull<Node<K, V>>> {
-        unsafe { self.forward.get_unchecked_mut(index) }
+        unsafe { &mut *Tower::link(self.forward.as_mut_ptr(), index) }
+    }
+}
+
+// What a list's nodes look like beyond their height: the alignment its
+// NodeLayout asks for, how many links it keeps in place, and whether values
+// are boxed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Shape {
+    align: usize,
+    boxed: bool,
+    inline: usize,
+}
+
+impl Shape {
+    fn plain(align: usize) -> Shape {
+        Shape {
+            align,
+            boxed: false,
+            inline: usize::MAX,
+        }
     }
 }
 
+// The one-byte height, inline count and boxed flag sit right before the
+// tower, where they usually fill padding that the pointer alignment leaves
+// anyway. The allocation layout is recomputed from them on dealloc. prev
+// links level 0 backwards; the first node has none. The tower is the node's
+// first inline links, then, when it has more, a pointer to an array of the
+// rest followed by their spans. After that comes one span per inline level:
+// the number of level 0 steps that level's link covers, kept up to date only
+// while the link is set. The value comes last, so the key and the links stay
+// together however big it is; a boxed node keeps only a pointer to it there.
 #[repr(C)]
 pub struct Node<K, V> {
     key: K,
-    val: V,
-    layout: Layout,
+    prev: Option<NonNull<Node<K, V>>>,
+    height: u8,
+    inline: u8,
+    boxed: bool,
+    // The value is reached through val_ptr.
+    marker: PhantomData<V>,
     tower: Tower<K, V>,
 }
 
 impl<K, V> Node<K, V> {
-    pub fn alloc(height: usize) -> *mut Node<K, V> {
-        let size = mem::size_of::<K>()
-            + mem::size_of::<V>()
-            + mem::size_of::<Layout>()
-            + height * mem::size_of::<Option<NonNull<Node<K, V>>>>();
-        match Layout::from_size_align(size, 16) {
-            Ok(layout) => unsafe {
-                let ptr = alloc(layout) as *mut Node<K, V>;
-                if ptr.is_null() {
-                    return std::ptr::null_mut();
-                }
-                (*ptr).layout = layout;
-                for i in 0..height {
-                    (*ptr).tower[i] = None;
-                }
-                ptr
-            },
+    // The fields up to the tower, then the inline links, the spill pointer
+    // if the node has one, the inline spans and the value. Every step goes
+    // through Layout so that padding and overflow are accounted for instead
+    // of summed by hand.
+    fn layout(height: usize, shape: Shape) -> Layout {
+        let inline = height.min(shape.inline);
+        let layout = Layout::from_size_align(
+            mem::offset_of!(Node<K, V>, tower),
+            mem::align_of::<Node<K, V>>(),
+        )
+        .and_then(|header| {
+            let (mut layout, tower) =
+                header.extend(Layout::array::<Option<NonNull<Node<K, V>>>>(inline)?)?;
+            debug_assert_eq!(tower, mem::offset_of!(Node<K, V>, tower));
+            if inline < height {
+                layout = layout
+                    .extend(Layout::new::<*mut Option<NonNull<Node<K, V>>>>())?
+                    .0;
+            }
+            let (layout, spans) = layout.extend(Layout::array::<usize>(inline)?)?;
+            debug_assert_eq!(spans, Node::<K, V>::spans_offset(height, inline));
+            let (layout, val) = layout.extend(Node::<K, V>::slot(shape.boxed))?;
+            debug_assert_eq!(val, Node::<K, V>::val_offset(height, inline, shape.boxed));
+            layout.align_to(shape.align)
+        });
+        match layout {
+            Ok(layout) => layout.pad_to_align(),
             Err(why) => panic!("{}", why),
         }
     }
 
-    pub fn new(key: K, val: V, height: usize) -> Option<NonNull<Node<K, V>>> {
-        let ptr = Node::alloc(height);
-        if ptr.is_null() {
+    // The links past the inline ones, then their spans; None when the node
+    // keeps all of them.
+    fn spill_layout(height: usize, inline: usize) -> Option<Layout> {
+        if inline == height {
             return None;
         }
+        let layout = Layout::array::<Option<NonNull<Node<K, V>>>>(height - inline)
+            .and_then(|links| Ok(links.extend(Layout::array::<usize>(height - inline)?)?.0));
+        match layout {
+            Ok(layout) => Some(layout.pad_to_align()),
+            Err(why) => panic!("{}", why),
+        }
+    }
+
+    // The node and its spill array together.
+    fn footprint(height: usize, shape: Shape) -> usize {
+        let spill = Node::<K, V>::spill_layout(height, height.min(shape.inline));
+        Node::<K, V>::layout(height, shape).size() + spill.map_or(0, |spill| spill.size())
+    }
+
+    // Where span() and set_span() find the inline spans: straight after the
+    // links and the spill pointer, which leave them usize-aligned since both
+    // are pointer-sized.
+    fn spans_offset(height: usize, inline: usize) -> usize {
+        let words = if inline < height { inline + 1 } else { inline };
+        mem::offset_of!(Node<K, V>, tower) + words * mem::size_of::<Option<NonNull<Node<K, V>>>>()
+    }
+
+    fn slot(boxed: bool) -> Layout {
+        if boxed {
+            Layout::new::<*mut V>()
+        } else {
+            Layout::new::<V>()
+        }
+    }
+
+    fn val_offset(height: usize, inline: usize, boxed: bool) -> usize {
+        let end = Node::<K, V>::spans_offset(height, inline) + inline * mem::size_of::<usize>();
+        let align = Node::<K, V>::slot(boxed).align();
+        (end + align - 1) & !(align - 1)
+    }
+
+    pub fn alloc(height: usize) -> *mut Node<K, V> {
+        Node::alloc_aligned(height, NODE_ALIGN)
+    }
+
+    pub fn alloc_aligned(height: usize, align: usize) -> *mut Node<K, V> {
+        Node::alloc_in(height, Shape::plain(align), &GlobalNodes)
+    }
+
+    fn alloc_in(height: usize, shape: Shape, storage: &dyn Storage) -> *mut Node<K, V> {
+        assert!(height <= u8::MAX as usize);
+        let layout = Node::<K, V>::layout(height, shape);
+        let spill_layout = Node::<K, V>::spill_layout(height, height.min(shape.inline));
         unsafe {
-            (*ptr).key = key;
-            (*ptr).val = val;
+            #[cfg(feature = "failpoints")]
+            if failpoints::alloc_should_fail() {
+                return ptr::null_mut();
+            }
+            let ptr = storage.alloc(layout) as *mut Node<K, V>;
+            if ptr.is_null() {
+                return ptr::null_mut();
+            }
+            let mut spill = ptr::null_mut();
+            if let Some(spill_layout) = spill_layout {
+                spill = storage.alloc(spill_layout);
+                if spill.is_null() {
+                    storage.dealloc(ptr as *mut u8, layout);
+                    return ptr::null_mut();
+                }
+            }
+            Node::init(ptr, height, shape, spill);
+            ptr
+        }
+    }
+
+    // Sets up the header of freshly allocated node memory, and its spill
+    // array if the shape leaves it links to spill, through raw pointers,
+    // since key and value are still uninitialized and no reference to the
+    // node may exist yet; they are left for the caller to write.
+    unsafe fn init(ptr: *mut Node<K, V>, height: usize, shape: Shape, spill: *mut u8) {
+        let inline = height.min(shape.inline);
+        ptr::addr_of_mut!((*ptr).prev).write(None);
+        ptr::addr_of_mut!((*ptr).height).write(height as u8);
+        ptr::addr_of_mut!((*ptr).inline).write(inline as u8);
+        ptr::addr_of_mut!((*ptr).boxed).write(shape.boxed);
+        let tower = ptr::addr_of_mut!((*ptr).tower) as *mut Option<NonNull<Node<K, V>>>;
+        for i in 0..inline {
+            tower.add(i).write(None);
+        }
+        let spans = (ptr as *mut u8).add(Node::<K, V>::spans_offset(height, inline)) as *mut usize;
+        ptr::write_bytes(spans, 0, inline);
+        if inline < height {
+            let links = spill as *mut Option<NonNull<Node<K, V>>>;
+            (tower.add(inline) as *mut *mut Option<NonNull<Node<K, V>>>).write(links);
+            for i in 0..height - inline {
+                links.add(i).write(None);
+            }
+            ptr::write_bytes(links.add(height - inline) as *mut usize, 0, height - inline);
+        }
+    }
+
+    pub fn new(key: K, val: V, height: usize) -> Option<NonNull<Node<K, V>>> {
+        Node::new_aligned(key, val, height, NODE_ALIGN)
+    }
+
+    pub fn new_aligned(key: K, val: V, height: usize, align: usize) -> Option<NonNull<Node<K, V>>> {
+        Node::new_in(key, val, height, Shape::plain(align), &GlobalNodes)
+    }
+
+    fn new_in(
+        key: K,
+        val: V,
+        height: usize,
+        shape: Shape,
+        storage: &dyn Storage,
+    ) -> Option<NonNull<Node<K, V>>> {
+        let ptr: *mut Node<K, V> = Node::alloc_in(height, shape, storage);
+        if ptr.is_null() {
+            return None;
         }
+        unsafe { Node::write_entry(ptr, key, val) };
         NonNull::new(ptr)
     }
 
+    fn height(&self) -> usize {
+        self.height as usize
+    }
+
+    fn span(&self, level: usize) -> usize {
+        unsafe { *Node::span_ptr(self as *const Self as *mut Self, level) }
+    }
+
+    fn set_span(&mut self, level: usize, span: usize) {
+        unsafe { *Node::span_ptr(self, level) = span }
+    }
+
+    unsafe fn span_ptr(node: *mut Node<K, V>, level: usize) -> *mut usize {
+        let height = ptr::addr_of!((*node).height).read() as usize;
+        let inline = ptr::addr_of!((*node).inline).read() as usize;
+        if level < inline {
+            let offset = Node::<K, V>::spans_offset(height, inline);
+            ((node as *mut u8).add(offset) as *mut usize).add(level)
+        } else {
+            let spill = Node::spill_ptr(node);
+            (spill.add(height - inline) as *mut usize).add(level - inline)
+        }
+    }
+
+    // Only meaningful when the node has links past its inline ones.
+    unsafe fn spill_ptr(node: *mut Node<K, V>) -> *mut Option<NonNull<Node<K, V>>> {
+        let inline = ptr::addr_of!((*node).inline).read() as usize;
+        let tower = ptr::addr_of_mut!((*node).tower) as *mut Option<NonNull<Node<K, V>>>;
+        *(tower.add(inline) as *const *mut Option<NonNull<Node<K, V>>>)
+    }
+
+    fn val(&self) -> &V {
+        unsafe { &*Node::val_ptr(self as *const Self as *mut Self) }
+    }
+
+    fn val_mut(&mut self) -> &mut V {
+        unsafe { &mut *Node::val_ptr(self) }
+    }
+
+    fn entry_mut(&mut self) -> (&K, &mut V) {
+        let val = unsafe { &mut *Node::val_ptr(self) };
+        (&self.key, val)
+    }
+
+    // The value slot, found from the header alone; it holds the value, or a
+    // pointer to it in a boxed node.
+    unsafe fn slot_ptr(node: *mut Node<K, V>) -> *mut u8 {
+        let height = ptr::addr_of!((*node).height).read() as usize;
+        let inline = ptr::addr_of!((*node).inline).read() as usize;
+        let boxed = ptr::addr_of!((*node).boxed).read();
+        (node as *mut u8).add(Node::<K, V>::val_offset(height, inline, boxed))
+    }
+
+    unsafe fn val_ptr(node: *mut Node<K, V>) -> *mut V {
+        let slot = Node::slot_ptr(node);
+        if ptr::addr_of!((*node).boxed).read() {
+            *(slot as *const *mut V)
+        } else {
+            slot as *mut V
+        }
+    }
+
+    unsafe fn write_entry(node: *mut Node<K, V>, key: K, val: V) {
+        ptr::addr_of_mut!((*node).key).write(key);
+        let slot = Node::slot_ptr(node);
+        if ptr::addr_of!((*node).boxed).read() {
+            (slot as *mut *mut V).write(Box::into_raw(Box::new(val)));
+        } else {
+            (slot as *mut V).write(val);
+        }
+    }
+
+    // Moves the entry out, freeing the value's box if it has one.
+    unsafe fn take(node: *mut Node<K, V>) -> (K, V) {
+        let key = ptr::addr_of!((*node).key).read();
+        let val_ptr = Node::val_ptr(node);
+        let val = val_ptr.read();
+        if ptr::addr_of!((*node).boxed).read() {
+            #[cfg(feature = "zeroize")]
+            wipe::zero_bytes(val_ptr as *mut u8, mem::size_of::<V>());
+            drop(Box::from_raw(val_ptr as *mut mem::ManuallyDrop<V>));
+        }
+        (key, val)
+    }
+
+    // storage must be where the node came from.
+    unsafe fn release(node_ptr: NonNull<Node<K, V>>, align: usize, storage: &dyn Storage) {
+        let node = node_ptr.as_ref();
+        let (height, inline) = (node.height(), node.inline as usize);
+        let shape = Shape {
+            align,
+            boxed: node.boxed,
+            inline,
+        };
+        let layout = Node::<K, V>::layout(height, shape);
+        if let Some(spill_layout) = Node::<K, V>::spill_layout(height, inline) {
+            storage.dealloc(Node::spill_ptr(node_ptr.as_ptr()) as *mut u8, spill_layout);
+        }
+        #[cfg(feature = "zeroize")]
+        wipe::zero_entry_bytes(node_ptr);
+        storage.dealloc(node_ptr.as_ptr() as *mut u8, layout);
+    }
+
+    unsafe fn into_entry(
+        node_ptr: NonNull<Node<K, V>>,
+        align: usize,
+        storage: &dyn Storage,
+    ) -> (K, V) {
+        let entry = Node::take(node_ptr.as_ptr());
+        Node::release(node_ptr, align, storage);
+        entry
+    }
+
+    unsafe fn reuse(node_ptr: NonNull<Node<K, V>>, key: K, val: V) {
+        Node::write_entry(node_ptr.as_ptr(), key, val);
+    }
+
+    // Moves the entry out and leaves the node's memory to be reused.
+    unsafe fn read_entry(node_ptr: NonNull<Node<K, V>>) -> (K, V) {
+        let entry = Node::take(node_ptr.as_ptr());
+        #[cfg(feature = "zeroize")]
+        wipe::zero_entry_bytes(node_ptr);
+        entry
+    }
+
     pub fn new_uninit(height: usize) -> Option<NonNull<Node<K, V>>> {
-        let ptr = Node::alloc(height);
+        Node::new_uninit_aligned(height, NODE_ALIGN)
+    }
+
+    pub fn new_uninit_aligned(height: usize, align: usize) -> Option<NonNull<Node<K, V>>> {
+        let ptr = Node::alloc_aligned(height, align);
         if ptr.is_null() {
             return None;
         }
@@ -77,27 +593,201 @@ impl<K, V> Node<K, V> {
     }
 }
 
-fn rand_lvl() -> usize {
+// Tower heights are capped at max and each level is climbed with
+// probability climb / 2^32. When adaptive, a new tower is also capped at one
+// more than log2 of the list size, so small lists don't grow towers they
+// have no use for; the cap rises as the list does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Levels {
+    max: usize,
+    climb: u32,
+    adaptive: bool,
+}
+
+impl Default for Levels {
+    fn default() -> Self {
+        Levels {
+            max: DEFAULT_MAX_LEVEL,
+            climb: 1 << 31,
+            adaptive: true,
+        }
+    }
+}
+
+impl Levels {
+    // The whole number of nodes per link one level up closest to 1/p.
+    fn fanout(&self) -> usize {
+        (((1u64 << 32) + self.climb as u64 / 2) / self.climb as u64).max(2) as usize
+    }
+}
+
+fn rand_lvl(levels: Levels) -> usize {
+    #[cfg(feature = "std")]
+    return rng_lvl(&mut rand::thread_rng(), levels);
+    #[cfg(not(feature = "std"))]
+    return rng_lvl(&mut SharedRng, levels);
+}
+
+// Targets without 64-bit atomics count in 32 bits, which only wrap after
+// billions of lists.
+fn next_list_id() -> u64 {
+    use core::sync::atomic::Ordering;
+    #[cfg(target_has_atomic = "64")]
+    {
+        static NEXT: core::sync::atomic::AtomicU64 = core::sync::atomic::AtomicU64::new(0);
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    }
+    #[cfg(not(target_has_atomic = "64"))]
+    {
+        static NEXT: core::sync::atomic::AtomicU32 = core::sync::atomic::AtomicU32::new(0);
+        NEXT.fetch_add(1, Ordering::Relaxed) as u64
+    }
+}
+
+// One random word, for towers built from its trailing zeros.
+fn random_u32() -> u32 {
+    #[cfg(feature = "std")]
+    return rand::random();
+    #[cfg(not(feature = "std"))]
+    return SharedRng.next_u32();
+}
+
+// Without std there is no thread_rng, so every list draws from one global
+// Weyl sequence run through a 32-bit finalizer. It is not meant to be
+// unpredictable, only to flip fair coins for tower heights.
+#[cfg(not(feature = "std"))]
+pub(crate) struct SharedRng;
+
+#[cfg(not(feature = "std"))]
+impl RngCore for SharedRng {
+    fn next_u32(&mut self) -> u32 {
+        use core::sync::atomic::{AtomicU32, Ordering};
+        static STATE: AtomicU32 = AtomicU32::new(0x2545_f491);
+        let mut z = STATE
+            .fetch_add(0x9e37_79b9, Ordering::Relaxed)
+            .wrapping_add(0x9e37_79b9);
+        z = (z ^ (z >> 16)).wrapping_mul(0x85eb_ca6b);
+        z = (z ^ (z >> 13)).wrapping_mul(0xc2b2_ae35);
+        z ^ (z >> 16)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        (self.next_u32() as u64) << 32 | self.next_u32() as u64
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(4) {
+            chunk.copy_from_slice(&self.next_u32().to_le_bytes()[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+fn rng_lvl<R: Rng + ?Sized>(rng: &mut R, levels: Levels) -> usize {
+    #[cfg(feature = "failpoints")]
+    if let Some(level) = failpoints::forced_level(levels.max) {
+        return level;
+    }
+    let mut level = 1;
+    while level < levels.max && rng.gen::<u32>() < levels.climb {
+        level += 1;
+    }
+    level
+}
+
+// stable_hash has no per-process keys, so the level of a key is the same in
+// every list and every process built from the same binary. The hash seeds a
+// splitmix64 sequence that stands in for the coin flips.
+fn hash_lvl<K: Hash>(key: &K, levels: Levels) -> usize {
+    let mut state = stable_hash(key);
     let mut level = 1;
-    while level < MAX_LEVEL && (random::<usize>() % 2 == 0) {
+    while level < levels.max {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        if (z >> 32) as u32 >= levels.climb {
+            break;
+        }
         level += 1;
     }
     level
 }
 
+fn stable_hash<K: Hash + ?Sized>(key: &K) -> u64 {
+    let mut hasher = Fnv(0xcbf2_9ce4_8422_2325);
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+// FNV-1a, which needs nothing from std; hash_lvl mixes the result further.
+struct Fnv(u64);
+
+impl Hasher for Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ *byte as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
 pub struct SkipList<K, V> {
     head: NonNull<Node<K, V>>,
     size: usize,
     level: usize,
+    layout: NodeLayout,
+    // Whether new nodes keep their value in a box, as the builder's
+    // box_values_over decides.
+    boxed: bool,
+    // Heights derived from the key, as hashed levels do; None draws them
+    // at random.
+    level_of: Option<fn(&K, Levels) -> usize>,
+    levels: Levels,
+    // free[h - 1] parks emptied nodes of height h for reuse by insert.
+    free: Vec<Vec<NonNull<Node<K, V>>>>,
+    #[cfg(feature = "zeroize")]
+    wipe: Option<wipe::Wipe<K, V>>,
+    // Where nodes are allocated and freed: the global allocator, an arena,
+    // or other storage. Shared with lists split off this one, since they keep
+    // its nodes.
+    nodes: Nodes,
+    // Overrides level_of when set; shared with split-off lists as well, so
+    // a seeded list stays reproducible across splits.
+    rng: Option<Arc<Lock<dyn RngCore + Send>>>,
+    // Bumped by every change to which nodes are linked, so a RawCursor can
+    // tell whether the node it points at may have gone.
+    generation: u64,
+    // Unique to this list, since the head's address may be handed to a new
+    // list once this one is dropped.
+    id: u64,
+    #[cfg(feature = "search-stats")]
+    search: trace::SearchCounters,
+    // The list owns its keys and values through the node pointers.
+    marker: PhantomData<(K, V)>,
 }
 
+// Nodes are only reached through the list that owns them, so the list can move
+// or be shared across threads whenever its keys and values can. The storage
+// and rng it may share with split-off lists are Sync or behind a lock.
+unsafe impl<K: Send, V: Send> Send for SkipList<K, V> {}
+unsafe impl<K: Sync, V: Sync> Sync for SkipList<K, V> {}
+
 impl<K: Ord, V> SkipList<K, V> {
     pub fn new() -> Self {
-        Self {
-            head: Node::new_uninit(MAX_LEVEL).unwrap(),
-            size: 0,
-            level: 1,
-        }
+        Self::with_node_layout(NodeLayout::Compact)
+    }
+
+    pub fn with_node_layout(layout: NodeLayout) -> Self {
+        Self::empty(layout)
     }
 
     pub fn insert(&mut self, key: K, val: V) {
@@ -107,135 +797,2439 @@ impl<K: Ord, V> SkipList<K, V> {
             if let Some(mut node_ptr) = self.find_gt_or_eq_node(&key, &mut update) {
                 let node = node_ptr.as_mut();
                 if node.key == key {
-                    node.val = val;
+                    #[cfg(feature = "zeroize")]
+                    self.wipe_val(node.val_mut());
+                    *node.val_mut() = val;
                     return;
                 }
             }
         }
 
-        let level = rand_lvl();
-        if level > self.level {
-            for i in self.level..level {
-                update[i] = Some(self.head);
-            }
-            self.level = level;
-        }
+        self.link(key, val, &mut update);
+    }
 
-        let x = Node::new(key, val, level);
+    pub fn get_mut_or_default(&mut self, key: K) -> &mut V
+    where
+        V: Default,
+    {
+        let mut update: [Option<NonNull<Node<K, V>>>; MAX_LEVEL] = [None; MAX_LEVEL];
+        unsafe {
+            let mut node_ptr = match self.find_gt_or_eq_node(&key, &mut update) {
+                Some(node_ptr) if node_ptr.as_ref().key == key => node_ptr,
+                _ => self.link(key, V::default(), &mut update),
+            };
+            node_ptr.as_mut().val_mut()
+        }
+    }
 
-        for i in 0..level {
-            unsafe {
-                x.unwrap().as_mut().tower[i] = update[i].unwrap().as_ref().tower[i];
-                update[i].unwrap().as_mut().tower[i] = x;
+    // Returns the position the key ends up at together with the value it
+    // replaced.
+    pub fn insert_full(&mut self, key: K, val: V) -> (usize, Option<V>) {
+        let mut update: [Option<NonNull<Node<K, V>>>; MAX_LEVEL] = [None; MAX_LEVEL];
+        unsafe {
+            let (next, index) = self.find_by_ranked(|k| *k < key, &mut update);
+            if let Some(mut node_ptr) = next {
+                if node_ptr.as_ref().key == key {
+                    return (index, Some(mem::replace(node_ptr.as_mut().val_mut(), val)));
+                }
             }
+            self.link(key, val, &mut update);
+            (index, None)
         }
-
-        self.size += 1;
     }
 
-    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+    // Same as insert_full without the rank, except that a failed node
+    // allocation is returned as an error rather than a panic.
+    pub fn insert_fallible(&mut self, key: K, val: V) -> Result<Option<V>, AllocError> {
         let mut update: [Option<NonNull<Node<K, V>>>; MAX_LEVEL] = [None; MAX_LEVEL];
         unsafe {
-            if let Some(mut node_ptr) = self.find_gt_or_eq_node(key, &mut update) {
-                return if node_ptr.as_ref().key == *key {
-                    Some(&mut node_ptr.as_mut().val)
-                } else {
-                    None
-                };
+            if let Some(mut node_ptr) = self.find_gt_or_eq_node(&key, &mut update) {
+                if node_ptr.as_ref().key == key {
+                    return Ok(Some(mem::replace(node_ptr.as_mut().val_mut(), val)));
+                }
             }
         }
-        None
+        self.try_link(key, val, &mut update)?;
+        Ok(None)
+    }
+
+    // Allocates a node for key and splices it in after update[l] on each of its
+    // levels.
+    fn link(
+        &mut self,
+        key: K,
+        val: V,
+        update: &mut [Option<NonNull<Node<K, V>>>; MAX_LEVEL],
+    ) -> NonNull<Node<K, V>> {
+        match self.try_link(key, val, update) {
+            Ok(x) => x,
+            Err(why) => panic!("{}", why),
+        }
+    }
+
+    fn try_link(
+        &mut self,
+        key: K,
+        val: V,
+        update: &mut [Option<NonNull<Node<K, V>>>; MAX_LEVEL],
+    ) -> Result<NonNull<Node<K, V>>, AllocError> {
+        #[cfg(feature = "ord-checks")]
+        unsafe {
+            let prev = update[0].filter(|p| *p != self.head);
+            let next = update[0].and_then(|p| p.as_ref().tower[0]);
+            ordcheck::check_neighbours(
+                prev.map(|p| &(*p.as_ptr()).key),
+                &key,
+                next.map(|n| &(*n.as_ptr()).key),
+            );
+        }
+        self.try_splice(key, val, update)
     }
 
-    pub fn get(&self, key: &K) -> Option<&V> {
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
         let mut update: [Option<NonNull<Node<K, V>>>; MAX_LEVEL] = [None; MAX_LEVEL];
         unsafe {
             if let Some(mut node_ptr) = self.find_gt_or_eq_node(key, &mut update) {
-                return if node_ptr.as_ref().key == *key {
-                    Some(&node_ptr.as_mut().val)
+                return if node_ptr.as_ref().key.borrow() == key {
+                    Some(node_ptr.as_mut().val_mut())
                 } else {
                     None
                 };
             }
-            None
         }
+        None
     }
 
-    pub fn len(&self) -> usize {
-        self.size
+    // Only ever overwrites: a missing key is left missing and val dropped.
+    // There's no tower to draw or link, and the search skips the update
+    // array, so this is the cheaper call when the key is known to be there.
+    pub fn replace<Q>(&mut self, key: &Q, val: V) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let node_ptr = self.find_node(key)?;
+        unsafe { Some(mem::replace((*node_ptr.as_ptr()).val_mut(), val)) }
     }
 
-    unsafe fn find_gt_or_eq_node(
-        &self,
-        key: &K,
-        update: &mut [Option<NonNull<Node<K, V>>>; MAX_LEVEL],
-    ) -> Option<NonNull<Node<K, V>>> {
-        let mut x = Some(self.head);
-        for i in (0..self.level).rev() {
-            while let Some(node_ptr) = x.unwrap().as_ref().tower[i] {
-                if node_ptr.as_ref().key < *key {
-                    x = x.unwrap().as_ref().tower[i];
-                } else {
-                    break;
-                }
-            }
-            update[i] = x;
+    // Returns false and leaves both values alone unless both keys are present.
+    pub fn swap_values<Q>(&mut self, a: &Q, b: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let (Some(x), Some(y)) = (self.find_node(a), self.find_node(b)) else {
+            return false;
+        };
+        if x != y {
+            unsafe { ptr::swap(Node::val_ptr(x.as_ptr()), Node::val_ptr(y.as_ptr())) };
         }
+        true
+    }
 
-        return x.unwrap().as_ref().tower[0];
+    // None if any key is missing or two of them name the same entry.
+    pub fn get_many_mut<Q, const N: usize>(&mut self, keys: [&Q; N]) -> Option<[&mut V; N]>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut nodes: [Option<NonNull<Node<K, V>>>; N] = [None; N];
+        for (i, key) in keys.iter().enumerate() {
+            let node_ptr = self.find_node(*key)?;
+            if nodes[..i].contains(&Some(node_ptr)) {
+                return None;
+            }
+            nodes[i] = Some(node_ptr);
+        }
+        Some(nodes.map(|node_ptr| unsafe { (*node_ptr.unwrap().as_ptr()).val_mut() }))
     }
-}
 
-impl<K, V> Drop for SkipList<K, V> {
-    fn drop(&mut self) {
+    fn find_node<Q>(&self, key: &Q) -> Option<NonNull<Node<K, V>>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut update: [Option<NonNull<Node<K, V>>>; MAX_LEVEL] = [None; MAX_LEVEL];
         unsafe {
-            let mut x = self.head.as_mut().tower[0];
-            while let Some(node_ptr) = x {
-                let t = node_ptr.as_ref().tower[0];
-                dealloc(node_ptr.as_ptr() as *mut u8, node_ptr.as_ref().layout);
-                x = t;
-            }
-            dealloc(self.head.as_ptr() as *mut u8, self.head.as_ref().layout);
+            self.find_gt_or_eq_node(key, &mut update)
+                .filter(|node_ptr| node_ptr.as_ref().key.borrow() == key)
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::SkipList;
-    #[test]
-    fn it_works() {
-        let mut sk = SkipList::new();
-        assert_eq!(sk.len(), 0);
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.find_node(key).is_some()
+    }
+
+    // The number of keys less than key, which is its index when present.
+    pub fn rank<Q>(&self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.rank_by(|k| k.borrow() < key)
+    }
+
+    // How many keys fall in range, exact and in O(log n): the difference of
+    // two ranks read off the spans, whatever the size of the range.
+    pub fn range_len<Q, R>(&self, range: R) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let (start, end) = self.index_bounds(&range);
+        end.saturating_sub(start)
+    }
+
+    // First entry with a key >= key.
+    pub fn lower_bound<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.seek(key, false)
+            .map(|node_ptr| unsafe { (&(*node_ptr.as_ptr()).key, (*node_ptr.as_ptr()).val()) })
+    }
+
+    // First entry with a key > key.
+    pub fn upper_bound<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.seek(key, true)
+            .map(|node_ptr| unsafe { (&(*node_ptr.as_ptr()).key, (*node_ptr.as_ptr()).val()) })
+    }
+
+    // The neighbours of key whether or not it is present: the first entry
+    // after it and the last one before it.
+    pub fn next_after<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.upper_bound(key)
+    }
+
+    pub fn prev_before<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut update: [Option<NonNull<Node<K, V>>>; MAX_LEVEL] = [None; MAX_LEVEL];
+        unsafe {
+            self.find_gt_or_eq_node(key, &mut update);
+            let prev = update[0].filter(|prev| *prev != self.head)?;
+            Some((&(*prev.as_ptr()).key, (*prev.as_ptr()).val()))
+        }
+    }
+
+    // Where the value lives. The address is stable until the entry is
+    // removed, with three exceptions, after which no address handed out
+    // before can be relied on:
+    // - rebalance, in any of its forms, which may move any entry into a
+    //   new node to give it the height it should have;
+    // - appending this list into another;
+    // - clearing or dropping the list.
+    // Inserts and removals of other keys, split_off and appends into this
+    // list leave it alone. Reading through it is up to the caller, like any
+    // raw pointer.
+    pub fn get_raw<Q>(&self, key: &Q) -> Option<NonNull<V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let node_ptr = self.find_node(key)?;
+        unsafe { NonNull::new(Node::val_ptr(node_ptr.as_ptr())) }
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut update: [Option<NonNull<Node<K, V>>>; MAX_LEVEL] = [None; MAX_LEVEL];
+        unsafe {
+            if let Some(mut node_ptr) = self.find_gt_or_eq_node(key, &mut update) {
+                return if node_ptr.as_ref().key.borrow() == key {
+                    Some(node_ptr.as_mut().val())
+                } else {
+                    None
+                };
+            }
+            None
+        }
+    }
+
+    // The stored key alongside its value, e.g. to get back the shared copy of
+    // an interned key from a borrowed lookup.
+    pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.find_node(key).map(|node_ptr| unsafe {
+            let node = &*node_ptr.as_ptr();
+            (&node.key, node.val())
+        })
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.remove_entry(key).map(|(_, val)| val)
+    }
+
+    // Also hands back the key as stored, which may differ from the one asked
+    // with, e.g. a String looked up by &str.
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut update: [Option<NonNull<Node<K, V>>>; MAX_LEVEL] = [None; MAX_LEVEL];
+        unsafe {
+            let node_ptr = self.find_gt_or_eq_node(key, &mut update)?;
+            if node_ptr.as_ref().key.borrow() != key {
+                return None;
+            }
+            Some(self.unlink(node_ptr, &update))
+        }
+    }
+
+    pub fn remove_by_index(&mut self, index: usize) -> Option<(K, V)> {
+        if index >= self.size {
+            return None;
+        }
+        let mut update: [Option<NonNull<Node<K, V>>>; MAX_LEVEL] = [None; MAX_LEVEL];
+        unsafe {
+            self.find_index_predecessors(index, &mut update);
+            let node_ptr = update[0].unwrap().as_ref().tower[0]?;
+            Some(self.unlink(node_ptr, &update))
+        }
+    }
+
+    pub fn pop_at(&mut self, index: usize) -> Option<(K, V)> {
+        self.remove_by_index(index)
+    }
+
+    pub fn split_at_index(&mut self, index: usize) -> SkipList<K, V> {
+        if index >= self.size {
+            return self.empty_like();
+        }
+        let mut update: [Option<NonNull<Node<K, V>>>; MAX_LEVEL] = [None; MAX_LEVEL];
+        unsafe {
+            let ranks = self.find_index_predecessors(index, &mut update);
+            self.split_after(&update, &ranks, self.size - index)
+        }
+    }
+
+    // Moves the entries with keys >= key into a new list. The cut is found
+    // by one ranked search, so this is O(log n) however many entries move.
+    pub fn split_off<Q>(&mut self, key: &Q) -> SkipList<K, V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let index = self.rank(key);
+        self.split_at_index(index)
+    }
+
+    // Drops every entry in range and returns how many went. Each level is
+    // rewired once, from the last node before the range to the first one
+    // after it, so the cost beyond freeing the nodes is a few ranked
+    // searches. Panics on the same bounds range does.
+    pub fn remove_range<Q, R>(&mut self, range: R) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        self.generation += 1;
+        let (start, end) = self.index_bounds(&range);
+        if start >= end {
+            return 0;
+        }
+        let removed = end - start;
+        let mut before: [Option<NonNull<Node<K, V>>>; MAX_LEVEL] = [None; MAX_LEVEL];
+        let mut last: [Option<NonNull<Node<K, V>>>; MAX_LEVEL] = [None; MAX_LEVEL];
+        unsafe {
+            let before_ranks = self.find_index_predecessors(start, &mut before);
+            let last_ranks = self.find_index_predecessors(end, &mut last);
+            let first = before[0].unwrap().as_ref().tower[0];
+            for l in 0..self.level {
+                let mut prev = before[l].unwrap();
+                let last = last[l].unwrap();
+                // last is in the range unless the level has no node in it.
+                let next = if last_ranks[l] > start {
+                    last.as_ref().tower[l]
+                } else {
+                    prev.as_ref().tower[l]
+                };
+                if next.is_some() {
+                    let span = last_ranks[l] + last.as_ref().span(l) - before_ranks[l];
+                    prev.as_mut().set_span(l, span - removed);
+                }
+                prev.as_mut().tower[l] = next;
+            }
+            if let Some(mut next) = before[0].unwrap().as_ref().tower[0] {
+                next.as_mut().prev = before[0].filter(|prev| *prev != self.head);
+            }
+            self.size -= removed;
+            self.shrink_level();
+            let after = before[0].unwrap().as_ref().tower[0];
+            self.free_chain(first, after, false);
+        }
+        removed
+    }
+
+    // The positions of the first entry in range and of the first one past
+    // it, found with two ranked searches. Panics on the same bounds range
+    // does.
+    fn index_bounds<Q, R>(&self, range: &R) -> (usize, usize)
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        iter::check_range(range);
+        let start = match range.start_bound() {
+            Bound::Included(q) => self.rank_by(|k| k.borrow() < q),
+            Bound::Excluded(q) => self.rank_by(|k| k.borrow() <= q),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(q) => self.rank_by(|k| k.borrow() <= q),
+            Bound::Excluded(q) => self.rank_by(|k| k.borrow() < q),
+            Bound::Unbounded => self.size,
+        };
+        (start, end)
+    }
+
+    // Frees every node, parked ones included, but keeps the head for the
+    // entries inserted next. Arena nodes are parked instead, since the arena
+    // only gives memory back when it is dropped.
+    pub fn clear(&mut self) {
+        unsafe {
+            if self.nodes.storage().frees_nodes() {
+                for node_ptr in self.free.drain(..).flatten() {
+                    Node::release(node_ptr, self.layout.align(), self.nodes.storage());
+                }
+            }
+            let first = self.detach_all();
+            self.free_chain(first, None, false);
+        }
+    }
+
+    pub fn clear_retain_capacity(&mut self) {
+        unsafe {
+            let first = self.detach_all();
+            self.free_chain(first, None, true);
+        }
+    }
+
+    // Returns the keys that start chunks 2..=n of an n-way split by position,
+    // so [first, p[0]), [p[0], p[1]), ..., [p[n-2], last] hold about len/n
+    // entries each. Fewer keys come back when the list has fewer than n entries.
+    pub fn partition_points(&self, n: usize) -> Vec<K>
+    where
+        K: Clone,
+    {
+        let n = n.min(self.size);
+        (1..n)
+            .map(|chunk| self.get_by_index(chunk * self.size / n).unwrap().0.clone())
+            .collect()
+    }
+
+    pub fn try_for_each<B, F>(&self, f: F) -> ControlFlow<B>
+    where
+        F: FnMut(&K, &V) -> ControlFlow<B>,
+    {
+        self.try_for_each_range(.., f)
+    }
+
+    pub fn try_for_each_range<R, B, F>(&self, range: R, mut f: F) -> ControlFlow<B>
+    where
+        R: RangeBounds<K>,
+        F: FnMut(&K, &V) -> ControlFlow<B>,
+    {
+        for (key, val) in self.range(range) {
+            f(key, val)?;
+        }
+        ControlFlow::Continue(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    pub fn node_layout(&self) -> NodeLayout {
+        self.layout
+    }
+
+    // Last entry with a key <= key.
+    pub(crate) fn floor_entry(&self, key: &K) -> Option<(&K, &V)> {
+        let mut update: [Option<NonNull<Node<K, V>>>; MAX_LEVEL] = [None; MAX_LEVEL];
+        unsafe {
+            if let Some(node_ptr) = self.find_gt_or_eq_node(key, &mut update) {
+                let node = &*node_ptr.as_ptr();
+                if node.key == *key {
+                    return Some((&node.key, node.val()));
+                }
+            }
+            let prev = update[0].unwrap();
+            if prev == self.head {
+                return None;
+            }
+            let node = &*prev.as_ptr();
+            Some((&node.key, node.val()))
+        }
+    }
+
+    pub fn truncate(&mut self, len: usize) {
+        self.generation += 1;
+        if len >= self.size {
+            return;
+        }
+        let mut update: [Option<NonNull<Node<K, V>>>; MAX_LEVEL] = [None; MAX_LEVEL];
+        unsafe {
+            self.find_index_predecessors(len, &mut update);
+            let x = update[0].unwrap().as_ref().tower[0];
+            for (l, prev) in update.iter().enumerate().take(self.level) {
+                prev.unwrap().as_mut().tower[l] = None;
+            }
+            self.size = len;
+            self.shrink_level();
+            self.free_chain(x, None, false);
+        }
+    }
+
+    // Drops every entry with a key < bound and returns how many went, e.g.
+    // to move a log index up to its low watermark. The head's tower is
+    // pointed past the cut in one ranked search and the prefix is freed
+    // along level 0, so nothing after the bound is touched.
+    pub fn truncate_before<Q>(&mut self, bound: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.generation += 1;
+        let k = self.rank_by(|key| key.borrow() < bound);
+        if k == 0 {
+            return 0;
+        }
+        let mut update: [Option<NonNull<Node<K, V>>>; MAX_LEVEL] = [None; MAX_LEVEL];
+        unsafe {
+            let ranks = self.find_index_predecessors(k, &mut update);
+            let first = self.head.as_ref().tower[0];
+            for (l, prev) in update.iter().enumerate().take(self.level) {
+                let prev = prev.unwrap();
+                self.head.as_mut().tower[l] = prev.as_ref().tower[l];
+                if prev.as_ref().tower[l].is_some() {
+                    let span = ranks[l] + prev.as_ref().span(l) - k;
+                    self.head.as_mut().set_span(l, span);
+                }
+            }
+            let after = self.head.as_ref().tower[0];
+            if let Some(mut after) = after {
+                after.as_mut().prev = None;
+            }
+            self.size -= k;
+            self.shrink_level();
+            self.free_chain(first, after, false);
+        }
+        k
+    }
+
+    pub fn pop_first(&mut self) -> Option<(K, V)> {
+        let node_ptr = unsafe { self.head.as_ref().tower[0]? };
+        let update = [Some(self.head); MAX_LEVEL];
+        unsafe { Some(self.unlink(node_ptr, &update)) }
+    }
+
+    pub fn pop_last(&mut self) -> Option<(K, V)> {
+        let index = self.size.checked_sub(1)?;
+        self.remove_by_index(index)
+    }
+
+    // Both cut the towers once at the k-th boundary and hand the detached
+    // entries back in the order k single pops would have returned them.
+    pub fn pop_first_k(&mut self, k: usize) -> Vec<(K, V)> {
+        self.generation += 1;
+        let k = k.min(self.size);
+        if k == 0 {
+            return Vec::new();
+        }
+        let mut update: [Option<NonNull<Node<K, V>>>; MAX_LEVEL] = [None; MAX_LEVEL];
+        unsafe {
+            let ranks = self.find_index_predecessors(k, &mut update);
+            let mut x = self.head.as_ref().tower[0];
+            for (l, prev) in update.iter().enumerate().take(self.level) {
+                let prev = prev.unwrap();
+                self.head.as_mut().tower[l] = prev.as_ref().tower[l];
+                if prev.as_ref().tower[l].is_some() {
+                    let span = ranks[l] + prev.as_ref().span(l) - k;
+                    self.head.as_mut().set_span(l, span);
+                }
+            }
+            if let Some(mut first) = self.head.as_ref().tower[0] {
+                first.as_mut().prev = None;
+            }
+            self.size -= k;
+            self.shrink_level();
+            let mut entries = Vec::with_capacity(k);
+            for _ in 0..k {
+                let node_ptr = x.unwrap();
+                x = node_ptr.as_ref().tower[0];
+                entries.push(self.take_entry(node_ptr));
+            }
+            entries
+        }
+    }
+
+    pub fn pop_last_k(&mut self, k: usize) -> Vec<(K, V)> {
+        self.generation += 1;
+        let k = k.min(self.size);
+        if k == 0 {
+            return Vec::new();
+        }
+        let mut update: [Option<NonNull<Node<K, V>>>; MAX_LEVEL] = [None; MAX_LEVEL];
+        unsafe {
+            self.find_index_predecessors(self.size - k, &mut update);
+            let mut x = update[0].unwrap().as_ref().tower[0];
+            for (l, prev) in update.iter().enumerate().take(self.level) {
+                prev.unwrap().as_mut().tower[l] = None;
+            }
+            self.size -= k;
+            self.shrink_level();
+            let mut entries = Vec::with_capacity(k);
+            while let Some(node_ptr) = x {
+                x = node_ptr.as_ref().tower[0];
+                entries.push(self.take_entry(node_ptr));
+            }
+            entries.reverse();
+            entries
+        }
+    }
+
+    // Cuts every level after update[l] and moves the tail, which holds len
+    // entries, into a new list. ranks are as from find_index_predecessors.
+    unsafe fn split_after(
+        &mut self,
+        update: &[Option<NonNull<Node<K, V>>>; MAX_LEVEL],
+        ranks: &[usize; MAX_LEVEL],
+        len: usize,
+    ) -> SkipList<K, V> {
+        self.generation += 1;
+        let mut other = self.empty_like();
+        let kept = self.size - len;
+        for (l, prev) in update.iter().enumerate().take(self.level) {
+            let mut prev = prev.unwrap();
+            other.head.as_mut().tower[l] = prev.as_ref().tower[l];
+            if prev.as_ref().tower[l].is_some() {
+                let span = ranks[l] + prev.as_ref().span(l) - kept;
+                other.head.as_mut().set_span(l, span);
+            }
+            prev.as_mut().tower[l] = None;
+        }
+        if let Some(mut first) = other.head.as_ref().tower[0] {
+            first.as_mut().prev = None;
+        }
+        other.level = self.level;
+        other.size = len;
+        self.size -= len;
+        self.shrink_level();
+        other.shrink_level();
+        other
+    }
+
+    unsafe fn find_gt_or_eq_node<Q>(
+        &self,
+        key: &Q,
+        update: &mut [Option<NonNull<Node<K, V>>>; MAX_LEVEL],
+    ) -> Option<NonNull<Node<K, V>>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.find_by(|k| k.borrow() < key, update)
+    }
+}
+
+impl<K, V> SkipList<K, V> {
+    fn empty(layout: NodeLayout) -> Self {
+        Self::empty_with(layout, Levels::default())
+    }
+
+    fn empty_with(layout: NodeLayout, levels: Levels) -> Self {
+        Self::empty_in(layout, levels, Nodes::Global)
+    }
+
+    fn empty_in(layout: NodeLayout, levels: Levels, nodes: Nodes) -> Self {
+        let head = Node::alloc_in(
+            levels.max,
+            Shape::plain(layout.align()),
+            nodes.head_storage(),
+        );
+        Self {
+            head: NonNull::new(head).expect("skiplist node allocation failed"),
+            size: 0,
+            level: 1,
+            layout,
+            boxed: false,
+            level_of: None,
+            levels,
+            free: Vec::new(),
+            #[cfg(feature = "zeroize")]
+            wipe: None,
+            nodes,
+            rng: None,
+            generation: 0,
+            id: next_list_id(),
+            #[cfg(feature = "search-stats")]
+            search: Default::default(),
+            marker: PhantomData,
+        }
+    }
+
+    fn empty_like(&self) -> Self {
+        let mut other = Self::empty_in(self.layout, self.levels, self.nodes.clone());
+        other.boxed = self.boxed;
+        other.level_of = self.level_of;
+        other.rng = self.rng.clone();
+        #[cfg(feature = "zeroize")]
+        {
+            other.wipe = self.wipe;
+        }
+        other
+    }
+
+    fn shape(&self) -> Shape {
+        Shape {
+            align: self.layout.align(),
+            boxed: self.boxed,
+            inline: self.layout.inline(),
+        }
+    }
+
+    fn alloc_node(&mut self, key: K, val: V, level: usize) -> NonNull<Node<K, V>> {
+        match self.try_alloc_node(key, val, level) {
+            Ok(x) => x,
+            Err(why) => panic!("{}", why),
+        }
+    }
+
+    // Takes a node of the given height from the free list, or else from the
+    // list's storage.
+    fn try_alloc_node(
+        &mut self,
+        key: K,
+        val: V,
+        level: usize,
+    ) -> Result<NonNull<Node<K, V>>, AllocError> {
+        let x = match self.free.get_mut(level - 1).and_then(Vec::pop) {
+            Some(node_ptr) => unsafe {
+                Node::reuse(node_ptr, key, val);
+                Some(node_ptr)
+            },
+            None => Node::new_in(key, val, level, self.shape(), self.nodes.storage()),
+        };
+        x.ok_or(AllocError)
+    }
+
+    // Links x in after the last node on each of its levels, for building a
+    // list front to back. tails[l] is the last node on level l and its rank,
+    // and starts out as (head, 0) on every level.
+    unsafe fn push_back_node(
+        &mut self,
+        mut x: NonNull<Node<K, V>>,
+        tails: &mut [(NonNull<Node<K, V>>, usize); MAX_LEVEL],
+    ) {
+        self.generation += 1;
+        let rank = self.size + 1;
+        let height = x.as_ref().height();
+        x.as_mut().prev = Some(tails[0].0).filter(|prev| *prev != self.head);
+        for (l, (tail, tail_rank)) in tails.iter_mut().enumerate().take(height) {
+            tail.as_mut().tower[l] = Some(x);
+            tail.as_mut().set_span(l, rank - *tail_rank);
+            *tail = x;
+            *tail_rank = rank;
+        }
+        self.size = rank;
+        self.level = self.level.max(height);
+    }
+
+    // Does the work of link without consulting the key's Ord.
+    fn splice(
+        &mut self,
+        key: K,
+        val: V,
+        update: &mut [Option<NonNull<Node<K, V>>>; MAX_LEVEL],
+    ) -> NonNull<Node<K, V>> {
+        match self.try_splice(key, val, update) {
+            Ok(x) => x,
+            Err(why) => panic!("{}", why),
+        }
+    }
+
+    fn insert_levels(&self) -> Levels {
+        if !self.levels.adaptive {
+            return self.levels;
+        }
+        let cap = (usize::BITS - (self.size + 1).leading_zeros()) as usize;
+        Levels {
+            max: self.levels.max.min(cap),
+            ..self.levels
+        }
+    }
+
+    // Nothing is touched until the node has been allocated, so a failure
+    // leaves the list as it was.
+    fn try_splice(
+        &mut self,
+        key: K,
+        val: V,
+        update: &mut [Option<NonNull<Node<K, V>>>; MAX_LEVEL],
+    ) -> Result<NonNull<Node<K, V>>, AllocError> {
+        self.generation += 1;
+        let levels = self.insert_levels();
+        let level = match (&self.rng, self.level_of) {
+            (Some(rng), _) => rng.with(|rng| rng_lvl(rng, levels)),
+            (None, Some(level_of)) => level_of(&key, levels),
+            (None, None) => rand_lvl(levels),
+        };
+        let mut x = self.try_alloc_node(key, val, level)?;
+
+        if level > self.level {
+            update[self.level..level].fill(Some(self.head));
+            self.level = level;
+        }
+
+        // behind[i] is how far update[i] is behind update[0] along level 0,
+        // found by retracing the search's steps on the level below.
+        let mut behind = 0;
+        for (i, prev) in update.iter().enumerate().take(self.level) {
+            unsafe {
+                let mut prev = prev.unwrap();
+                if i > 0 {
+                    let mut y = prev;
+                    while y != update[i - 1].unwrap() {
+                        behind += y.as_ref().span(i - 1);
+                        y = y.as_ref().tower[i - 1].unwrap();
+                    }
+                }
+                if i >= level {
+                    if prev.as_ref().tower[i].is_some() {
+                        let span = prev.as_ref().span(i);
+                        prev.as_mut().set_span(i, span + 1);
+                    }
+                    continue;
+                }
+                x.as_mut().tower[i] = prev.as_ref().tower[i];
+                if x.as_ref().tower[i].is_some() {
+                    x.as_mut().set_span(i, prev.as_ref().span(i) - behind);
+                }
+                prev.as_mut().tower[i] = Some(x);
+                prev.as_mut().set_span(i, behind + 1);
+            }
+        }
+        unsafe {
+            x.as_mut().prev = update[0].filter(|prev| *prev != self.head);
+            if let Some(mut next) = x.as_ref().tower[0] {
+                next.as_mut().prev = Some(x);
+            }
+        }
+
+        self.size += 1;
+        Ok(x)
+    }
+
+    // update[l] must be the last node before node_ptr on every level.
+    unsafe fn unlink(
+        &mut self,
+        node_ptr: NonNull<Node<K, V>>,
+        update: &[Option<NonNull<Node<K, V>>>; MAX_LEVEL],
+    ) -> (K, V) {
+        self.generation += 1;
+        for (i, prev) in update.iter().enumerate().take(self.level) {
+            let mut prev = prev.unwrap();
+            if prev.as_ref().tower[i] == Some(node_ptr) {
+                let span = prev.as_ref().span(i) + node_ptr.as_ref().span(i);
+                prev.as_mut().tower[i] = node_ptr.as_ref().tower[i];
+                prev.as_mut().set_span(i, span - 1);
+            } else if prev.as_ref().tower[i].is_some() {
+                let span = prev.as_ref().span(i);
+                prev.as_mut().set_span(i, span - 1);
+            }
+        }
+        if let Some(mut next) = node_ptr.as_ref().tower[0] {
+            next.as_mut().prev = node_ptr.as_ref().prev;
+        }
+        self.shrink_level();
+        self.size -= 1;
+        self.take_entry(node_ptr)
+    }
+
+    fn shrink_level(&mut self) {
+        unsafe {
+            while self.level > 1 && self.head.as_ref().tower[self.level - 1].is_none() {
+                self.level -= 1;
+            }
+        }
+    }
+
+    // Fills update[l] with the last node on level l whose position is below
+    // index and returns how many nodes come up to and including each of them,
+    // the head counting as zero.
+    unsafe fn find_index_predecessors(
+        &self,
+        index: usize,
+        update: &mut [Option<NonNull<Node<K, V>>>; MAX_LEVEL],
+    ) -> [usize; MAX_LEVEL] {
+        let mut ranks = [0; MAX_LEVEL];
+        let mut x = self.head;
+        let mut rank = 0;
+        for l in (0..self.level).rev() {
+            while let Some(next) = x.as_ref().tower[l] {
+                if rank + x.as_ref().span(l) > index {
+                    break;
+                }
+                rank += x.as_ref().span(l);
+                x = next;
+            }
+            update[l] = Some(x);
+            ranks[l] = rank;
+        }
+        ranks
+    }
+
+    // Counts the nodes whose key is_before the target.
+    fn rank_by(&self, mut is_before: impl FnMut(&K) -> bool) -> usize {
+        #[cfg(feature = "search-stats")]
+        let mut comparisons = 0;
+        #[cfg(feature = "search-stats")]
+        let mut is_before = |k: &K| {
+            comparisons += 1;
+            is_before(k)
+        };
+        let mut x = self.head;
+        let mut rank = 0;
+        unsafe {
+            for l in (0..self.level).rev() {
+                while let Some(next) = x.as_ref().tower[l] {
+                    if !is_before(&next.as_ref().key) {
+                        break;
+                    }
+                    rank += x.as_ref().span(l);
+                    x = next;
+                }
+            }
+        }
+        #[cfg(feature = "search-stats")]
+        self.search.record(self.level, comparisons);
+        rank
+    }
+
+    pub fn get_by_index(&self, index: usize) -> Option<(&K, &V)> {
+        self.node_at(index).map(|node_ptr| unsafe {
+            let node = &*node_ptr.as_ptr();
+            (&node.key, node.val())
+        })
+    }
+
+    // Each entry equally likely, found by index through the spans.
+    pub fn random_entry<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<(&K, &V)> {
+        if self.size == 0 {
+            return None;
+        }
+        self.get_by_index(rng.gen_range(0..self.size))
+    }
+
+    // k distinct entries, or all of them if there are fewer, in key order.
+    // Every k-subset is equally likely: the indices are drawn with Floyd's
+    // algorithm, so it takes k draws and k lookups whatever the length.
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R, k: usize) -> Vec<(&K, &V)> {
+        let k = k.min(self.size);
+        let mut picked = BTreeSet::new();
+        for j in self.size - k..self.size {
+            let i = rng.gen_range(0..=j);
+            if !picked.insert(i) {
+                picked.insert(j);
+            }
+        }
+        picked
+            .into_iter()
+            .filter_map(|i| self.get_by_index(i))
+            .collect()
+    }
+
+    fn node_at(&self, index: usize) -> Option<NonNull<Node<K, V>>> {
+        if index >= self.size {
+            return None;
+        }
+        let mut update: [Option<NonNull<Node<K, V>>>; MAX_LEVEL] = [None; MAX_LEVEL];
+        unsafe {
+            self.find_index_predecessors(index, &mut update);
+            update[0].unwrap().as_ref().tower[0]
+        }
+    }
+
+    // Recomputes every span with one pass along level 0, for edits that
+    // splice out many nodes at once.
+    fn rebuild_spans(&mut self) {
+        let mut last = [self.head; MAX_LEVEL];
+        let mut last_rank = [0; MAX_LEVEL];
+        let mut rank = 0;
+        unsafe {
+            let mut x = self.head.as_ref().tower[0];
+            while let Some(node_ptr) = x {
+                rank += 1;
+                for l in 0..node_ptr.as_ref().height() {
+                    last[l].as_mut().set_span(l, rank - last_rank[l]);
+                    last[l] = node_ptr;
+                    last_rank[l] = rank;
+                }
+                x = node_ptr.as_ref().tower[0];
+            }
+        }
+    }
+
+    // Fills update[l] with the last node on level l whose key is_before the
+    // target and returns the node after it on level 0.
+    unsafe fn find_by(
+        &self,
+        mut is_before: impl FnMut(&K) -> bool,
+        update: &mut [Option<NonNull<Node<K, V>>>; MAX_LEVEL],
+    ) -> Option<NonNull<Node<K, V>>> {
+        #[cfg(feature = "search-stats")]
+        let mut comparisons = 0;
+        #[cfg(feature = "search-stats")]
+        let mut is_before = |k: &K| {
+            comparisons += 1;
+            is_before(k)
+        };
+        let mut x = Some(self.head);
+        for i in (0..self.level).rev() {
+            while let Some(node_ptr) = x.unwrap().as_ref().tower[i] {
+                if is_before(&node_ptr.as_ref().key) {
+                    x = x.unwrap().as_ref().tower[i];
+                } else {
+                    break;
+                }
+            }
+            update[i] = x;
+        }
+        #[cfg(feature = "search-stats")]
+        self.search.record(self.level, comparisons);
+
+        x.unwrap().as_ref().tower[0]
+    }
+
+    // find_by that also adds up the spans it steps over, so the position of
+    // the node it returns comes out of the same descent.
+    unsafe fn find_by_ranked(
+        &self,
+        mut is_before: impl FnMut(&K) -> bool,
+        update: &mut [Option<NonNull<Node<K, V>>>; MAX_LEVEL],
+    ) -> (Option<NonNull<Node<K, V>>>, usize) {
+        #[cfg(feature = "search-stats")]
+        let mut comparisons = 0;
+        #[cfg(feature = "search-stats")]
+        let mut is_before = |k: &K| {
+            comparisons += 1;
+            is_before(k)
+        };
+        let mut x = self.head;
+        let mut rank = 0;
+        for l in (0..self.level).rev() {
+            while let Some(next) = x.as_ref().tower[l] {
+                if !is_before(&next.as_ref().key) {
+                    break;
+                }
+                rank += x.as_ref().span(l);
+                x = next;
+            }
+            update[l] = Some(x);
+        }
+        #[cfg(feature = "search-stats")]
+        self.search.record(self.level, comparisons);
+
+        (x.as_ref().tower[0], rank)
+    }
+
+    pub fn first_key_value(&self) -> Option<(&K, &V)> {
+        unsafe {
+            let node = &*self.head.as_ref().tower[0]?.as_ptr();
+            Some((&node.key, node.val()))
+        }
+    }
+
+    pub fn last_key_value(&self) -> Option<(&K, &V)> {
+        self.last_node().map(|node_ptr| unsafe {
+            let node = &*node_ptr.as_ptr();
+            (&node.key, node.val())
+        })
+    }
+
+    // Moves the entry out of an unlinked node and gives the node up: back to
+    // the storage, or onto the free list when the storage, like an arena,
+    // only returns its memory all at once.
+    unsafe fn take_entry(&mut self, node_ptr: NonNull<Node<K, V>>) -> (K, V) {
+        if self.nodes.storage().frees_nodes() {
+            return Node::into_entry(node_ptr, self.layout.align(), self.nodes.storage());
+        }
+        let entry = Node::read_entry(node_ptr);
+        self.park(node_ptr, node_ptr.as_ref().height());
+        entry
+    }
+
+    // take_entry, but keeping the node for reuse whatever the list
+    // allocates from.
+    unsafe fn take_parked(&mut self, node_ptr: NonNull<Node<K, V>>) -> (K, V) {
+        let entry = Node::read_entry(node_ptr);
+        self.park(node_ptr, node_ptr.as_ref().height());
+        entry
+    }
+
+    unsafe fn free_node(&mut self, node_ptr: NonNull<Node<K, V>>) {
+        drop(self.take_entry(node_ptr));
+    }
+
+    // Empties the head and hands back the first node, so the list is already
+    // valid and empty before any entry is dropped.
+    unsafe fn detach_all(&mut self) -> Option<NonNull<Node<K, V>>> {
+        self.generation += 1;
+        let first = self.head.as_ref().tower[0];
+        for i in 0..self.level {
+            self.head.as_mut().tower[i] = None;
+        }
+        self.size = 0;
+        self.level = 1;
+        first
+    }
+
+    // Frees the nodes along level 0 from x up to but not including until,
+    // which must already be unlinked; park keeps the nodes for reuse. Should
+    // a key or value panic on drop, the rest are still dropped before the
+    // panic goes on, the way a Vec drops its elements.
+    unsafe fn free_chain(
+        &mut self,
+        mut x: Option<NonNull<Node<K, V>>>,
+        until: Option<NonNull<Node<K, V>>>,
+        park: bool,
+    ) {
+        struct Rest<'a, K, V> {
+            sk: &'a mut SkipList<K, V>,
+            x: Option<NonNull<Node<K, V>>>,
+            until: Option<NonNull<Node<K, V>>>,
+            park: bool,
+        }
+
+        impl<K, V> Drop for Rest<'_, K, V> {
+            fn drop(&mut self) {
+                unsafe { self.sk.free_chain(self.x, self.until, self.park) }
+            }
+        }
+
+        while x != until {
+            let node_ptr = x.unwrap();
+            x = node_ptr.as_ref().tower[0];
+            let rest = Rest {
+                sk: self,
+                x,
+                until,
+                park,
+            };
+            #[cfg(feature = "zeroize")]
+            rest.sk.wipe_node(node_ptr);
+            if park {
+                // Parked before the entry is dropped, so a panic there
+                // doesn't lose the node.
+                let height = node_ptr.as_ref().height();
+                let entry = Node::read_entry(node_ptr);
+                rest.sk.park(node_ptr, height);
+                drop(entry);
+            } else {
+                rest.sk.free_node(node_ptr);
+            }
+            mem::forget(rest);
+        }
+    }
+
+    fn park(&mut self, node_ptr: NonNull<Node<K, V>>, height: usize) {
+        if self.free.len() < height {
+            self.free.resize_with(height, Vec::new);
+        }
+        self.free[height - 1].push(node_ptr);
+    }
+
+    // Runs to the end of every level from the top down.
+    fn last_node(&self) -> Option<NonNull<Node<K, V>>> {
+        let mut x = self.head;
+        unsafe {
+            for l in (0..self.level).rev() {
+                while let Some(next) = x.as_ref().tower[l] {
+                    x = next;
+                }
+            }
+        }
+        (x != self.head).then_some(x)
+    }
+}
+
+impl<K: Ord, V> SkipList<K, V> {
+    // Draws tower heights from rng instead of the thread-local generator,
+    // e.g. a seeded StdRng for reproducible layouts or a cheaper SmallRng.
+    pub fn with_rng<R: RngCore + Send + 'static>(rng: R) -> Self {
+        let mut sk = Self::new();
+        sk.rng = Some(Arc::new(Lock::new(rng)));
+        sk
+    }
+}
+
+impl<K: Ord + Hash, V> SkipList<K, V> {
+    pub fn with_hashed_levels() -> Self {
+        let mut sk = Self::new();
+        // A size-dependent cap would give a key different levels in lists
+        // of different sizes.
+        sk.levels.adaptive = false;
+        sk.level_of = Some(hash_lvl::<K>);
+        sk
+    }
+}
+
+impl<K: Ord, V> Default for SkipList<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Copies the towers as they are, so the clone has the same shape and settings
+// as the original; an arena list's clone allocates from the same arena.
+impl<K: Clone, V: Clone> Clone for SkipList<K, V> {
+    fn clone(&self) -> Self {
+        let mut sk = self.empty_like();
+        let mut tails = [(sk.head, 0); MAX_LEVEL];
+        let mut x = unsafe { self.head.as_ref().tower[0] };
+        while let Some(node_ptr) = x {
+            unsafe {
+                let node = node_ptr.as_ref();
+                let copy = sk.alloc_node(node.key.clone(), node.val().clone(), node.height());
+                sk.push_back_node(copy, &mut tails);
+                x = node.tower[0];
+            }
+        }
+        sk
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for SkipList<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+// Equality and hashing go by the entries in order; tower shape and settings
+// don't take part.
+impl<K: PartialEq, V: PartialEq> PartialEq for SkipList<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.size == other.size && self.iter().eq(other.iter())
+    }
+}
+
+impl<K: Eq, V: Eq> Eq for SkipList<K, V> {}
+
+// Lexicographic over the entries in order, as BTreeMap compares.
+impl<K: PartialOrd, V: PartialOrd> PartialOrd for SkipList<K, V> {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        self.iter().partial_cmp(other.iter())
+    }
+}
+
+impl<K: Ord, V: Ord> Ord for SkipList<K, V> {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+        self.iter().cmp(other.iter())
+    }
+}
+
+// Panics if the key is missing.
+impl<K, Q, V> Index<&Q> for SkipList<K, V>
+where
+    K: Borrow<Q> + Ord,
+    Q: Ord + ?Sized,
+{
+    type Output = V;
+
+    fn index(&self, key: &Q) -> &V {
+        self.get(key).expect("no entry found for key")
+    }
+}
+
+impl<K: Hash, V: Hash> Hash for SkipList<K, V> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.size);
+        for entry in self.iter() {
+            entry.hash(state);
+        }
+    }
+}
+
+impl<K, V> Drop for SkipList<K, V> {
+    fn drop(&mut self) {
+        // Gives back the head and the parked nodes after the entries, even
+        // when dropping one of them panics.
+        struct Release<'a, K, V>(&'a mut SkipList<K, V>);
+
+        impl<K, V> Drop for Release<'_, K, V> {
+            fn drop(&mut self) {
+                let sk = &mut *self.0;
+                unsafe {
+                    if sk.nodes.storage().frees_nodes() {
+                        for node_ptr in sk.free.iter().flatten() {
+                            Node::release(*node_ptr, sk.layout.align(), sk.nodes.storage());
+                        }
+                    }
+                    Node::release(sk.head, sk.layout.align(), sk.nodes.head_storage());
+                }
+            }
+        }
+
+        unsafe {
+            let first = self.head.as_ref().tower[0];
+            let release = Release(self);
+            release.0.free_chain(first, None, false);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Node, NodeLayout, Shape, SkipList, CACHE_LINE, NODE_ALIGN};
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+    use std::mem;
+    use std::ops::{Bound, ControlFlow};
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    use std::sync::atomic::{AtomicIsize, AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::{cmp, fmt};
+    #[test]
+    fn it_works() {
+        let mut sk = SkipList::new();
+        assert_eq!(sk.len(), 0);
 
         for i in 0..10 {
-            sk.insert(i, i);
-        }
-        assert_eq!(sk.len(), 10);
-        for i in 0..10 {
+            sk.insert(i, i);
+        }
+        assert_eq!(sk.len(), 10);
+        for i in 0..10 {
             let k = i;
             let mut v = i;
             assert_eq!(sk.get_mut(&k), Some(&mut v));
         }
 
-        for i in 0..10 {
-            sk.insert(i, i + 1);
+        for i in 0..10 {
+            sk.insert(i, i + 1);
+        }
+        assert_eq!(sk.len(), 10);
+        for i in 0..10 {
+            let k = i;
+            let v = i + 1;
+            assert_eq!(sk.get(&k), Some(&v));
+        }
+
+        for i in 0..20 {
+            sk.insert(i, i + 1);
+        }
+        assert_eq!(sk.len(), 20);
+        for i in 0..20 {
+            let k = i;
+            let v = i + 1;
+            assert_eq!(sk.get(&k), Some(&v));
+        }
+    }
+
+    #[test]
+    fn cache_aligned_nodes() {
+        let mut sk = SkipList::with_node_layout(NodeLayout::CacheAligned);
+        assert_eq!(sk.node_layout(), NodeLayout::CacheAligned);
+        for i in 0..100u32 {
+            sk.insert(i, i);
+        }
+        unsafe {
+            assert_eq!(sk.head.as_ptr() as usize % CACHE_LINE, 0);
+            let mut x = sk.head.as_ref().tower[0];
+            while let Some(node_ptr) = x {
+                let node = node_ptr.as_ref();
+                assert_eq!(node_ptr.as_ptr() as usize % CACHE_LINE, 0);
+                let layout = Node::<u32, u32>::layout(node.height(), sk.shape());
+                assert_eq!(layout.size() % CACHE_LINE, 0);
+                x = node.tower[0];
+            }
+        }
+        for i in 0..100u32 {
+            assert_eq!(sk.get(&i), Some(&i));
+        }
+    }
+
+    #[test]
+    fn inline_links_spill_the_rest() {
+        for arena in [false, true] {
+            let builder = crate::SkipListBuilder::new().node_layout(NodeLayout::Inline(1));
+            let mut sk: SkipList<u32, u32> = if arena {
+                builder.arena(512).build()
+            } else {
+                builder.build()
+            };
+            let mut model = std::collections::BTreeMap::new();
+            for i in 0..2000u32 {
+                let k = i.wrapping_mul(2_654_435_761) % 5000;
+                sk.insert(k, i);
+                model.insert(k, i);
+                if i % 3 == 0 {
+                    let k = i.wrapping_mul(40_503) % 5000;
+                    assert_eq!(sk.remove(&k), model.remove(&k));
+                }
+            }
+            sk.check_invariants();
+            assert!(sk.level > 1);
+            unsafe {
+                let mut x = sk.head.as_ref().tower[0];
+                while let Some(node_ptr) = x {
+                    let node = node_ptr.as_ref();
+                    assert_eq!(node_ptr.as_ptr() as usize % CACHE_LINE, 0);
+                    let layout = Node::<u32, u32>::layout(node.height(), sk.shape());
+                    assert_eq!(layout.size(), CACHE_LINE);
+                    x = node.tower[0];
+                }
+            }
+            assert!(sk.iter().eq(model.iter()));
+            assert!(sk.iter().rev().eq(model.iter().rev()));
+            for (i, (k, _)) in model.iter().enumerate().step_by(37) {
+                assert_eq!(sk.rank(k), i);
+                assert_eq!(sk.get_by_index(i).map(|(k, _)| *k), Some(*k));
+            }
+            let mut tail = sk.split_at_index(model.len() / 2);
+            tail.check_invariants();
+            sk.append(&mut tail);
+            sk.check_invariants();
+            assert!(sk.clone().iter().eq(model.iter()));
+        }
+    }
+
+    pub(crate) fn levels<K: Ord + Clone, V>(sk: &SkipList<K, V>) -> Vec<Vec<K>> {
+        let mut levels = Vec::new();
+        unsafe {
+            for i in 0..sk.level {
+                let mut keys = Vec::new();
+                let mut x = sk.head.as_ref().tower[i];
+                while let Some(node_ptr) = x {
+                    keys.push(node_ptr.as_ref().key.clone());
+                    x = node_ptr.as_ref().tower[i];
+                }
+                levels.push(keys);
+            }
+        }
+        levels
+    }
+
+    #[test]
+    fn get_many_mut_needs_distinct_keys() {
+        let mut sk = SkipList::new();
+        for i in 0..10 {
+            sk.insert(i.to_string(), i);
+        }
+        let [a, b, c] = sk.get_many_mut(["1", "5", "9"]).unwrap();
+        std::mem::swap(a, c);
+        *b += 100;
+        assert_eq!(sk.get("1"), Some(&9));
+        assert_eq!(sk.get("5"), Some(&105));
+        assert_eq!(sk.get("9"), Some(&1));
+        assert!(sk.get_many_mut(["1", "1"]).is_none());
+        assert!(sk.get_many_mut(["1", "x"]).is_none());
+        assert_eq!(sk.get_many_mut::<str, 0>([]), Some([]));
+    }
+
+    #[test]
+    fn random_entries_are_uniform() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut sk = SkipList::new();
+        assert_eq!(sk.random_entry(&mut rng), None);
+        assert!(sk.sample(&mut rng, 3).is_empty());
+        for i in 0..10 {
+            sk.insert(i, i * 10);
+        }
+        let mut hits = [0; 10];
+        for _ in 0..100_000 {
+            let (k, v) = sk.random_entry(&mut rng).unwrap();
+            assert_eq!(*v, k * 10);
+            hits[*k] += 1;
+        }
+        assert!(
+            hits.iter().all(|h| (9_000..11_000).contains(h)),
+            "{:?}",
+            hits
+        );
+
+        let mut hits = [0; 10];
+        for _ in 0..20_000 {
+            let picked = sk.sample(&mut rng, 3);
+            assert_eq!(picked.len(), 3);
+            assert!(picked.windows(2).all(|w| w[0].0 < w[1].0));
+            for (k, _) in picked {
+                hits[*k] += 1;
+            }
+        }
+        assert!(
+            hits.iter().all(|h| (5_400..6_600).contains(h)),
+            "{:?}",
+            hits
+        );
+        assert!(sk.sample(&mut rng, 50).into_iter().eq(sk.iter()));
+    }
+
+    #[test]
+    fn seeded_rng_is_reproducible() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let build = || {
+            let mut sk = SkipList::with_rng(StdRng::seed_from_u64(7));
+            for i in 0..200 {
+                sk.insert(i, ());
+            }
+            let tail = sk.split_at_index(100);
+            (sk, tail)
+        };
+        let (mut a, mut a_tail) = build();
+        let (mut b, mut b_tail) = build();
+        for i in 200..300 {
+            a.insert(i, ());
+            a_tail.insert(i, ());
+            b.insert(i, ());
+            b_tail.insert(i, ());
         }
-        assert_eq!(sk.len(), 10);
-        for i in 0..10 {
-            let k = i;
-            let v = i + 1;
-            assert_eq!(sk.get(&k), Some(&v));
+        assert_eq!(levels(&a), levels(&b));
+        assert_eq!(levels(&a_tail), levels(&b_tail));
+        assert!(levels(&a).len() > 1);
+    }
+
+    #[test]
+    fn hashed_levels_are_canonical() {
+        let mut a = SkipList::with_hashed_levels();
+        let mut b = SkipList::with_hashed_levels();
+        for i in 0..1000u64 {
+            a.insert(i, i);
         }
+        for i in (0..1000u64).rev() {
+            b.insert(i, i * 2);
+        }
+        assert_eq!(a.len(), b.len());
+        assert!(levels(&a).len() > 1);
+        assert_eq!(levels(&a), levels(&b));
+        for i in 0..1000u64 {
+            assert_eq!(b.get(&i), Some(&(i * 2)));
+        }
+    }
 
+    #[test]
+    fn range_bounds() {
+        let mut sk = SkipList::new();
         for i in 0..20 {
-            sk.insert(i, i + 1);
+            sk.insert(i * 2, i);
+        }
+        let keys = |r: Vec<(&i32, &i32)>| r.into_iter().map(|(k, _)| *k).collect::<Vec<_>>();
+        assert_eq!(keys(sk.range(4..10).collect()), vec![4, 6, 8]);
+        assert_eq!(keys(sk.range(3..=10).collect()), vec![4, 6, 8, 10]);
+        assert_eq!(keys(sk.range(34..).collect()), vec![34, 36, 38]);
+        assert_eq!(keys(sk.range(..3).collect()), vec![0, 2]);
+        assert_eq!(
+            keys(
+                sk.range((Bound::Excluded(4), Bound::Excluded(10)))
+                    .collect()
+            ),
+            vec![6, 8]
+        );
+        assert_eq!(sk.range(100..).count(), 0);
+        assert_eq!(sk.iter().count(), 20);
+    }
+
+    #[test]
+    fn remove_unlinks_and_shrinks() {
+        let mut sk = SkipList::new();
+        for i in 0..100 {
+            sk.insert(i, i.to_string());
+        }
+        for i in (0..100).step_by(2) {
+            assert_eq!(sk.remove(&i), Some(i.to_string()));
+        }
+        assert_eq!(sk.remove(&0), None);
+        assert_eq!(sk.len(), 50);
+        for (lvl, keys) in levels(&sk).iter().enumerate() {
+            assert!(!keys.is_empty(), "level {} is empty", lvl);
+            assert!(keys.iter().all(|k| k % 2 == 1));
+        }
+        for i in 0..100 {
+            assert_eq!(sk.get(&i).is_some(), i % 2 == 1);
+        }
+        for i in (1..100).step_by(2) {
+            assert_eq!(sk.remove(&i), Some(i.to_string()));
+        }
+        assert!(sk.is_empty());
+        assert_eq!(sk.level, 1);
+    }
+
+    #[test]
+    fn clear_frees_nodes_and_keeps_head() {
+        let mut sk = SkipList::new();
+        for i in 0..500 {
+            sk.insert(i, i.to_string());
+        }
+        sk.remove(&3);
+        sk.clear_retain_capacity();
+        for i in 0..500 {
+            sk.insert(i, i.to_string());
+        }
+        let head = sk.head;
+        let empty = SkipList::<i32, String>::new().memory_usage();
+        sk.clear();
+        assert!(sk.is_empty());
+        assert_eq!(sk.level, 1);
+        assert_eq!(sk.head, head);
+        assert_eq!(sk.memory_usage(), empty);
+        assert!(!sk.contains_key(&7));
+        sk.insert(7, String::new());
+        assert!(sk.contains_key(&7));
+        assert_eq!(sk.iter().count(), 1);
+    }
+
+    #[test]
+    fn clear_retain_capacity_reuses_nodes() {
+        use std::rc::Rc;
+
+        let val = Rc::new(());
+        let mut sk = SkipList::new();
+        for i in 0..100 {
+            sk.insert(i, val.clone());
+        }
+        assert_eq!(Rc::strong_count(&val), 101);
+
+        sk.clear_retain_capacity();
+        assert!(sk.is_empty());
+        assert_eq!(sk.get(&0), None);
+        assert_eq!(Rc::strong_count(&val), 1);
+        let parked = |sk: &SkipList<i32, Rc<()>>| sk.free.iter().map(Vec::len).sum::<usize>();
+        assert_eq!(parked(&sk), 100);
+
+        for i in 0..100 {
+            sk.insert(i, val.clone());
+        }
+        assert!(parked(&sk) < 100);
+        assert_eq!(sk.len(), 100);
+        for i in 0..100 {
+            assert!(sk.get(&i).is_some());
+        }
+        drop(sk);
+        assert_eq!(Rc::strong_count(&val), 1);
+    }
+
+    // Panics from drop while its key is 13, after counting the drop.
+    struct Brittle(u32, Arc<AtomicUsize>);
+
+    // Counts the node allocations still outstanding.
+    struct Live(Arc<AtomicIsize>);
+
+    unsafe impl GlobalAlloc for Live {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            self.0.fetch_sub(1, Ordering::Relaxed);
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    impl Drop for Brittle {
+        fn drop(&mut self) {
+            self.1.fetch_add(1, Ordering::Relaxed);
+            if self.0 == 13 {
+                panic!("dropping 13");
+            }
+        }
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    struct Touchy(u32);
+
+    impl PartialOrd for Touchy {
+        fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Touchy {
+        fn cmp(&self, other: &Self) -> cmp::Ordering {
+            assert!(self.0 != 13 && other.0 != 13, "comparing 13");
+            self.0.cmp(&other.0)
+        }
+    }
+
+    // Every link and span still agrees with level 0.
+    fn assert_whole<K: Ord + fmt::Debug, V>(sk: &SkipList<K, V>) {
+        let keys: Vec<_> = sk.iter().map(|(k, _)| k).collect();
+        assert_eq!(keys.len(), sk.len());
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(sk.get_by_index(i).map(|(k, _)| k), Some(*key));
+            assert!(sk.contains_key(*key));
+        }
+    }
+
+    #[test]
+    fn panics_in_user_code_leave_the_list_whole() {
+        type Op = fn(&mut SkipList<u32, Brittle>);
+        let ops: [(Op, usize); 5] = [
+            (|sk| sk.clear(), 0),
+            (|sk| sk.clear_retain_capacity(), 0),
+            (|sk| sk.truncate(10), 10),
+            (
+                |sk| {
+                    sk.remove_range(5..50);
+                },
+                55,
+            ),
+            // Stops at 13, having let go of the even keys below it.
+            (|sk| sk.retain(|k, _| k % 2 == 0), 93),
+        ];
+        for (op, left) in ops {
+            let drops = Arc::new(AtomicUsize::new(0));
+            let live = Arc::new(AtomicIsize::new(0));
+            let mut sk = SkipList::new_in(Live(live.clone()));
+            for i in 0..100 {
+                sk.insert(i, Brittle(i, drops.clone()));
+            }
+            assert!(catch_unwind(AssertUnwindSafe(|| op(&mut sk))).is_err());
+            assert_whole(&sk);
+            assert_eq!(sk.len(), left);
+            assert_eq!(drops.load(Ordering::Relaxed), 100 - left);
+            sk.insert(200, Brittle(200, drops.clone()));
+            assert_whole(&sk);
+            drop(sk);
+            assert_eq!(drops.load(Ordering::Relaxed), 101);
+            assert_eq!(live.load(Ordering::Relaxed), 0);
+        }
+
+        // Dropping the list still drops every entry past the one that panics,
+        // and frees the head and any parked nodes along with them.
+        let drops = Arc::new(AtomicUsize::new(0));
+        let live = Arc::new(AtomicIsize::new(0));
+        let mut sk = SkipList::new_in(Live(live.clone()));
+        for i in 100..140 {
+            sk.insert(i, Brittle(i, drops.clone()));
+        }
+        sk.clear_retain_capacity();
+        for i in 0..20 {
+            sk.insert(i, Brittle(i, drops.clone()));
+        }
+        assert!(catch_unwind(AssertUnwindSafe(move || drop(sk))).is_err());
+        assert_eq!(drops.load(Ordering::Relaxed), 60);
+        assert_eq!(live.load(Ordering::Relaxed), 0);
+
+        // A comparison that panics does so before anything is relinked.
+        let mut sk: SkipList<_, _> = (0..100)
+            .filter(|i| *i != 13)
+            .map(|i| (Touchy(i), i))
+            .collect();
+        assert!(catch_unwind(AssertUnwindSafe(|| sk.insert(Touchy(13), 13))).is_err());
+        assert!(catch_unwind(AssertUnwindSafe(|| sk.remove(&Touchy(13)))).is_err());
+        assert_whole(&sk);
+        assert_eq!(sk.len(), 99);
+    }
+
+    #[test]
+    fn partition_points_balance() {
+        let mut sk = SkipList::new();
+        for i in 0..100 {
+            sk.insert(i, ());
+        }
+        assert_eq!(sk.partition_points(4), vec![25, 50, 75]);
+        assert_eq!(sk.partition_points(3), vec![33, 66]);
+        assert_eq!(sk.partition_points(1), Vec::<i32>::new());
+        assert_eq!(sk.partition_points(0), Vec::<i32>::new());
+        assert_eq!(sk.partition_points(1000).len(), 99);
+
+        let mut small = SkipList::new();
+        small.insert(1, ());
+        small.insert(2, ());
+        assert_eq!(small.partition_points(5), vec![2]);
+        assert!(SkipList::<i32, ()>::new().partition_points(3).is_empty());
+    }
+
+    #[test]
+    fn split_at_index() {
+        let mut sk = SkipList::with_node_layout(NodeLayout::CacheAligned);
+        for i in 0..100 {
+            sk.insert(i, i);
+        }
+        let mut tail = sk.split_at_index(60);
+        assert_eq!(sk.len(), 60);
+        assert_eq!(tail.len(), 40);
+        assert_eq!(tail.node_layout(), NodeLayout::CacheAligned);
+        for i in 0..100 {
+            assert_eq!(sk.get(&i).is_some(), i < 60);
+            assert_eq!(tail.get(&i).is_some(), i >= 60);
+        }
+        for keys in levels(&sk) {
+            assert!(!keys.is_empty() && keys.iter().all(|k| *k < 60));
+        }
+        for keys in levels(&tail) {
+            assert!(!keys.is_empty() && keys.iter().all(|k| *k >= 60));
+        }
+        tail.insert(7, 7);
+        assert_eq!(tail.iter().next(), Some((&7, &7)));
+
+        let rest = sk.split_at_index(0);
+        assert!(sk.is_empty());
+        assert_eq!(rest.len(), 60);
+        assert!(sk.split_at_index(10).is_empty());
+    }
+
+    #[test]
+    fn truncate() {
+        use std::rc::Rc;
+
+        let val = Rc::new(());
+        let mut sk = SkipList::new();
+        for i in (0..50).rev() {
+            sk.insert(i, val.clone());
+        }
+        sk.truncate(100);
+        assert_eq!(sk.len(), 50);
+        sk.truncate(20);
+        assert_eq!(sk.len(), 20);
+        assert_eq!(Rc::strong_count(&val), 21);
+        for i in 0..50 {
+            assert_eq!(sk.get(&i).is_some(), i < 20);
+        }
+        for keys in levels(&sk) {
+            assert!(!keys.is_empty() && keys.iter().all(|k| *k < 20));
+        }
+        sk.insert(30, val.clone());
+        assert_eq!(sk.len(), 21);
+        sk.truncate(0);
+        assert!(sk.is_empty());
+        assert_eq!(sk.level, 1);
+        assert_eq!(Rc::strong_count(&val), 1);
+    }
+
+    #[test]
+    fn remove_range_matches_btreemap() {
+        use std::collections::BTreeMap;
+
+        for (a, b) in [
+            (10, 20),
+            (0, 5),
+            (95, 200),
+            (-5, 0),
+            (33, 34),
+            (50, 50),
+            (0, 100),
+        ] {
+            let mut sk = SkipList::new();
+            let mut model = BTreeMap::new();
+            for i in 0..100 {
+                sk.insert(i, i);
+                model.insert(i, i);
+            }
+            let gone: Vec<i32> = model.range(a..b).map(|(k, _)| *k).collect();
+            assert_eq!(sk.remove_range(a..b), gone.len());
+            for k in gone {
+                model.remove(&k);
+            }
+            assert!(sk.iter().eq(model.iter()), "{}..{}", a, b);
+            assert!(sk.iter().rev().eq(model.iter().rev()));
+            for (i, k) in model.keys().enumerate() {
+                assert_eq!(sk.rank(k), i);
+                assert_eq!(sk.get_by_index(i).map(|(k, _)| *k), Some(*k));
+            }
+            sk.insert(a, -1);
+            assert_eq!(sk.get(&a), Some(&-1));
         }
+
+        let mut sk: SkipList<i32, String> = (0..1000).map(|i| (i, i.to_string())).collect();
+        assert_eq!(
+            sk.remove_range((Bound::Excluded(10), Bound::Included(990))),
+            980
+        );
         assert_eq!(sk.len(), 20);
+        assert_eq!(sk.remove_range(..), 20);
+        assert!(sk.is_empty());
+        assert_eq!(sk.level, 1);
+    }
+
+    #[test]
+    fn pop_first_and_last_k() {
+        let mut sk = SkipList::new();
+        for i in 0..50 {
+            sk.insert(i, i.to_string());
+        }
+        let first: Vec<i32> = sk.pop_first_k(10).into_iter().map(|(k, _)| k).collect();
+        assert_eq!(first, (0..10).collect::<Vec<_>>());
+        let last: Vec<(i32, String)> = sk.pop_last_k(3);
+        assert_eq!(
+            last,
+            vec![
+                (49, "49".to_string()),
+                (48, "48".to_string()),
+                (47, "47".to_string())
+            ]
+        );
+        assert_eq!(sk.len(), 37);
+        assert_eq!(sk.get(&9), None);
+        assert_eq!(sk.get(&10).map(String::as_str), Some("10"));
+        assert_eq!(levels(&sk)[0], (10..47).collect::<Vec<_>>());
+        for ks in levels(&sk) {
+            assert!(ks.iter().all(|k| (10..47).contains(k)));
+        }
+
+        assert!(sk.pop_first_k(0).is_empty());
+        assert_eq!(sk.pop_last_k(100).len(), 37);
+        assert!(sk.is_empty());
+        assert_eq!(sk.level, 1);
+        assert!(sk.pop_first_k(1).is_empty());
+    }
+
+    #[test]
+    fn swap_values() {
+        let mut sk = SkipList::new();
+        for i in 0..10 {
+            sk.insert(i, vec![i; 100]);
+        }
+        assert!(sk.swap_values(&2, &7));
+        assert_eq!(sk.get(&2).unwrap()[0], 7);
+        assert_eq!(sk.get(&7).unwrap()[0], 2);
+        assert!(sk.swap_values(&3, &3));
+        assert_eq!(sk.get(&3).unwrap()[0], 3);
+        assert!(!sk.swap_values(&3, &30));
+        assert_eq!(sk.get(&3).unwrap()[0], 3);
+    }
+
+    #[test]
+    fn get_mut_or_default() {
+        let mut counts = SkipList::new();
+        for word in "a b a c b a".split(' ') {
+            *counts.get_mut_or_default(word) += 1;
+        }
+        assert_eq!(counts.len(), 3);
+        assert_eq!(counts.get(&"a"), Some(&3));
+        assert_eq!(counts.get(&"b"), Some(&2));
+        assert_eq!(counts.get(&"c"), Some(&1));
+    }
+
+    #[test]
+    fn borrowed_lookups() {
+        let mut sk: SkipList<String, usize> = SkipList::new();
+        for word in ["apple", "banana", "cherry", "date"] {
+            sk.insert(word.to_string(), word.len());
+        }
+        assert_eq!(sk.get("banana"), Some(&6));
+        assert!(sk.contains_key("date"));
+        assert!(!sk.contains_key("fig"));
+        *sk.get_mut("apple").unwrap() += 1;
+        assert_eq!(sk.get("apple"), Some(&6));
+        assert!(sk.swap_values("apple", "date"));
+        assert_eq!(
+            sk.range::<str, _>((Bound::Excluded("apple"), Bound::Included("cherry")))
+                .map(|(k, _)| k.as_str())
+                .collect::<Vec<_>>(),
+            vec!["banana", "cherry"]
+        );
+        assert_eq!(sk.remove("cherry"), Some(6));
+        assert_eq!(sk.remove("cherry"), None);
+        assert_eq!(sk.len(), 3);
+    }
+
+    #[test]
+    fn get_key_value_returns_stored_key() {
+        use std::rc::Rc;
+
+        let mut interned: SkipList<Rc<str>, u32> = SkipList::new();
+        let banana: Rc<str> = Rc::from("banana");
+        interned.insert(Rc::clone(&banana), 1);
+        interned.insert(Rc::from("apple"), 2);
+        let (key, val) = interned.get_key_value("banana").unwrap();
+        assert!(Rc::ptr_eq(key, &banana));
+        assert_eq!(*val, 1);
+        assert_eq!(interned.get_key_value("fig"), None);
+    }
+
+    fn check_positions(sk: &SkipList<u32, u32>, model: &[u32]) {
+        assert_eq!(sk.len(), model.len());
+        for (i, key) in model.iter().enumerate() {
+            assert_eq!(sk.get_by_index(i), Some((key, &(key * 2))));
+            assert_eq!(sk.rank(key), i);
+            assert_eq!(sk.rank(&(key + 1)), i + 1);
+        }
+        assert_eq!(sk.get_by_index(model.len()), None);
+    }
+
+    #[test]
+    fn positions_follow_edits() {
+        let mut sk = SkipList::new();
+        let mut model = Vec::new();
+        for i in 0..300u32 {
+            let key = i.wrapping_mul(7919) % 1000 * 2;
+            sk.insert(key, key * 2);
+            if let Err(pos) = model.binary_search(&key) {
+                model.insert(pos, key);
+            }
+        }
+        check_positions(&sk, &model);
+        for key in model.clone().iter().step_by(3) {
+            sk.remove(key);
+            model.retain(|k| k != key);
+        }
+        check_positions(&sk, &model);
+        sk.pop_first_k(17);
+        model.drain(..17);
+        check_positions(&sk, &model);
+        let tail = sk.split_at_index(100);
+        let tail_model = model.split_off(100);
+        check_positions(&sk, &model);
+        check_positions(&tail, &tail_model);
+        let mut it = tail.into_iter();
+        it.next();
+        let rest: SkipList<_, _> = SkipList::from_unsorted_vec(it.collect());
+        check_positions(&rest, &tail_model[1..]);
+        sk.retain(|k, _| k % 3 != 0);
+        model.retain(|k| k % 3 != 0);
+        check_positions(&sk, &model);
+        let (index, old) = sk.insert_full(1, 2);
+        assert_eq!((index, old), (model.partition_point(|k| *k < 1), None));
+    }
+
+    #[test]
+    fn split_off() {
+        let mut sk = SkipList::new();
+        for i in 0..100 {
+            sk.insert(i.to_string(), i);
+        }
+        let tail = sk.split_off("50");
+        assert_eq!(sk.len() + tail.len(), 100);
+        assert!(sk.iter().all(|(k, _)| k.as_str() < "50"));
+        assert!(tail.iter().all(|(k, _)| k.as_str() >= "50"));
+        assert_eq!(tail.first_key_value(), Some((&"50".to_string(), &50)));
+        assert_eq!(
+            tail.rank("6"),
+            tail.iter().filter(|(k, _)| k.as_str() < "6").count()
+        );
+
+        let mut tail2 = tail;
+        let rest = tail2.split_off("zzz");
+        assert!(rest.is_empty());
+        let all = sk.split_off("");
+        assert!(sk.is_empty());
+        assert_eq!(all.len(), 46);
+    }
+
+    #[test]
+    fn pop_ends() {
+        let mut sk = SkipList::new();
+        assert_eq!(sk.pop_first(), None);
+        assert_eq!(sk.pop_last(), None);
+        for i in 0..100 {
+            sk.insert(i, i * 2);
+        }
+        assert_eq!(sk.first_key_value(), Some((&0, &0)));
+        assert_eq!(sk.last_key_value(), Some((&99, &198)));
+        for i in 0..50 {
+            assert_eq!(sk.pop_first(), Some((i, i * 2)));
+            assert_eq!(sk.pop_last(), Some((99 - i, (99 - i) * 2)));
+            if let Some((k, _)) = sk.first_key_value() {
+                assert_eq!(sk.get_by_index(0), Some((k, &(k * 2))));
+                assert_eq!(sk.rank(&(99 - i)), sk.len());
+            }
+        }
+        assert!(sk.is_empty());
+        assert_eq!(sk.level, 1);
+        assert_eq!(sk.first_key_value(), None);
+    }
+
+    #[test]
+    fn index_range() {
+        let mut sk = SkipList::new();
         for i in 0..20 {
-            let k = i;
-            let v = i + 1;
-            assert_eq!(sk.get(&k), Some(&v));
+            sk.insert(i, i);
+        }
+        let keys: Vec<_> = sk.index_range(5..9).map(|(k, _)| *k).collect();
+        assert_eq!(keys, [5, 6, 7, 8]);
+        let keys: Vec<_> = sk.index_range(17..).rev().map(|(k, _)| *k).collect();
+        assert_eq!(keys, [19, 18, 17]);
+        assert_eq!(sk.index_range(20..).count(), 0);
+        assert_eq!(sk.index_range(..=0).count(), 1);
+        assert_eq!(sk.partition_points(4), [5, 10, 15]);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn index_range_past_end() {
+        let sk: SkipList<u32, ()> = SkipList::new();
+        let _ = sk.index_range(0..1);
+    }
+
+    #[test]
+    fn remove_by_index() {
+        let mut sk = SkipList::new();
+        for i in 0..10 {
+            sk.insert(i, i * 10);
+        }
+        assert_eq!(sk.remove_by_index(10), None);
+        assert_eq!(sk.remove_by_index(5), Some((5, 50)));
+        assert_eq!(sk.remove_by_index(5), Some((6, 60)));
+        assert_eq!(sk.pop_at(0), Some((0, 0)));
+        assert_eq!(sk.remove_by_index(sk.len() - 1), Some((9, 90)));
+        assert_eq!(sk.len(), 6);
+        let keys: Vec<_> = sk.iter().map(|(k, _)| *k).collect();
+        assert_eq!(keys, vec![1, 2, 3, 4, 7, 8]);
+        for i in [1, 2, 3, 4, 7, 8] {
+            assert_eq!(sk.get(&i), Some(&(i * 10)));
+        }
+        while sk.pop_at(0).is_some() {}
+        assert!(sk.is_empty());
+        assert_eq!(sk.level, 1);
+    }
+
+    #[test]
+    fn node_header_is_slim() {
+        let ptr = mem::size_of::<usize>();
+        // Key and value are apart, so each is padded out to a pointer.
+        let layout = Node::<u32, u32>::layout(1, Shape::plain(NODE_ALIGN));
+        assert_eq!(layout.size(), ptr + ptr + ptr + ptr + ptr + ptr);
+        let layout = Node::<u64, u64>::layout(2, Shape::plain(NODE_ALIGN));
+        assert_eq!(layout.size(), 16 + ptr + ptr + 2 * ptr + 2 * ptr);
+        let layout = Node::<u128, u8>::layout(3, Shape::plain(CACHE_LINE));
+        assert_eq!(layout.align(), CACHE_LINE);
+        assert!(layout.size() >= Node::<u128, u8>::spans_offset(3, 3) + 3 * ptr);
+        assert_eq!(layout.size() % CACHE_LINE, 0);
+
+        let mut sk = SkipList::new();
+        for i in 0..1000u32 {
+            sk.insert(i, i);
+        }
+        unsafe {
+            let mut x = sk.head.as_ref().tower[0];
+            while let Some(node_ptr) = x {
+                let node = node_ptr.as_ref();
+                assert!(node.height() >= 1 && node.height() <= sk.level);
+                x = node.tower[0];
+            }
+        }
+    }
+
+    #[test]
+    fn try_for_each_stops_early() {
+        let mut sk = SkipList::new();
+        for i in 0..100 {
+            sk.insert(i, i * i);
+        }
+        let mut visited = 0;
+        let found = sk.try_for_each_range(10.., |k, v| {
+            visited += 1;
+            if v % 7 == 0 {
+                ControlFlow::Break(*k)
+            } else {
+                ControlFlow::Continue(())
+            }
+        });
+        assert_eq!(found, ControlFlow::Break(14));
+        assert_eq!(visited, 5);
+
+        let mut sum = 0;
+        let res: ControlFlow<()> = sk.try_for_each(|_, v| {
+            sum += v;
+            ControlFlow::Continue(())
+        });
+        assert_eq!(res, ControlFlow::Continue(()));
+        assert_eq!(sum, (0..100).map(|i| i * i).sum::<i32>());
+        assert_eq!(
+            sk.try_for_each_range(50..50, |_, _| ControlFlow::Break(())),
+            ControlFlow::Continue(())
+        );
+    }
+
+    #[test]
+    fn insert_full_reports_rank() {
+        let mut sk = SkipList::new();
+        assert_eq!(sk.insert_full(50, "a"), (0, None));
+        assert_eq!(sk.insert_full(10, "b"), (0, None));
+        assert_eq!(sk.insert_full(30, "c"), (1, None));
+        assert_eq!(sk.insert_full(90, "d"), (3, None));
+        assert_eq!(sk.insert_full(30, "e"), (1, Some("c")));
+        assert_eq!(sk.insert_full(90, "f"), (3, Some("d")));
+        assert_eq!(sk.len(), 4);
+        assert_eq!(sk.get(&30), Some(&"e"));
+        for (i, k) in [10, 30, 50, 90].into_iter().enumerate() {
+            assert_eq!(sk.insert_full(k, "z").0, i);
+        }
+    }
+
+    #[test]
+    fn lower_and_upper_bound() {
+        let mut sk = SkipList::new();
+        for i in 0..10 {
+            sk.insert(format!("k{}", i * 2 % 10), i);
+        }
+        assert_eq!(sk.lower_bound("k4"), Some((&"k4".to_string(), &7)));
+        assert_eq!(sk.upper_bound("k4"), Some((&"k6".to_string(), &8)));
+        assert_eq!(sk.lower_bound("k5"), Some((&"k6".to_string(), &8)));
+        assert_eq!(sk.upper_bound("k5"), Some((&"k6".to_string(), &8)));
+        assert_eq!(sk.lower_bound(""), sk.first_key_value());
+        assert_eq!(sk.upper_bound("k8"), None);
+        assert_eq!(sk.lower_bound("k9"), None);
+    }
+
+    #[test]
+    fn truncate_before_drops_the_prefix() {
+        let mut sk: SkipList<_, _> = (0..1_000).map(|i| (i * 2, i)).collect();
+        assert_eq!(sk.truncate_before(&0), 0);
+        assert_eq!(sk.truncate_before(&301), 151);
+        sk.check_invariants();
+        assert_eq!(sk.first_key_value(), Some((&302, &151)));
+        assert_eq!(sk.len(), 849);
+        assert_eq!(sk.get_by_index(10), Some((&322, &161)));
+        assert_eq!(sk.truncate_before(&302), 0);
+        sk.insert(1, 0);
+        assert_eq!(sk.truncate_before(&2_000), 850);
+        assert!(sk.is_empty());
+        sk.check_invariants();
+        sk.insert(5, 5);
+        assert!(sk.iter().eq([(&5, &5)]));
+    }
+
+    #[test]
+    fn replace_never_inserts() {
+        let mut sk: SkipList<_, _> = (0..10).map(|i| (i, i * 10)).collect();
+        let generation = sk.generation();
+        assert_eq!(sk.replace(&4, 44), Some(40));
+        assert_eq!(sk.replace(&4, 45), Some(44));
+        assert_eq!(sk.replace(&10, 100), None);
+        assert_eq!(sk.len(), 10);
+        assert!(!sk.contains_key(&10));
+        assert_eq!(sk.get(&4), Some(&45));
+        assert_eq!(sk.generation(), generation);
+        assert_eq!(SkipList::<u32, u32>::new().replace(&1, 1), None);
+        sk.check_invariants();
+    }
+
+    #[test]
+    fn value_addresses_survive_other_writes() {
+        let mut sk = SkipList::new();
+        for i in (0..2_000).step_by(2) {
+            sk.insert(i, i.to_string());
+        }
+        let pinned: Vec<_> = (0..2_000)
+            .step_by(200)
+            .map(|i| (i, sk.get_raw(&i).unwrap()))
+            .collect();
+        for i in (1..2_000).step_by(2) {
+            sk.insert(i, i.to_string());
+        }
+        for i in (2..2_000).step_by(4) {
+            sk.remove(&i);
+        }
+        let tail = sk.split_off(&1_000);
+        sk.append(&mut SkipList::from_iter([(-1, String::new())]));
+        for (i, ptr) in pinned {
+            let holder = if i < 1_000 { &sk } else { &tail };
+            assert_eq!(holder.get_raw(&i), Some(ptr));
+            assert_eq!(unsafe { ptr.as_ref() }, &i.to_string());
+        }
+        assert_eq!(sk.get_raw(&2), None);
+
+        // rebalance is one of the exceptions: it moves the nodes whose height
+        // it changes.
+        let before: Vec<_> = sk.keys().map(|k| sk.get_raw(k).unwrap()).collect();
+        sk.rebalance();
+        let moved = sk
+            .keys()
+            .zip(&before)
+            .filter(|&(k, ptr)| sk.get_raw(k) != Some(*ptr))
+            .count();
+        assert!(moved > 0 && moved < before.len(), "{}", moved);
+        assert!(sk.iter().all(|(k, v)| *v == k.to_string() || *k == -1));
+    }
+
+    #[test]
+    fn range_len_counts_exactly() {
+        use rand::prelude::*;
+        use std::collections::BTreeMap;
+
+        let mut sk = SkipList::new();
+        let mut model = BTreeMap::new();
+        let mut rng = StdRng::seed_from_u64(11);
+        for _ in 0..3_000 {
+            let key = rng.gen_range(0..5_000u32);
+            sk.insert(key, ());
+            model.insert(key, ());
+        }
+        for _ in 0..500 {
+            let (a, b) = (rng.gen_range(0..5_100u32), rng.gen_range(0..5_100u32));
+            let (a, b) = (a.min(b), a.max(b));
+            assert_eq!(sk.range_len(a..b), model.range(a..b).count());
+            assert_eq!(sk.range_len(a..=b), model.range(a..=b).count());
+            assert_eq!(
+                sk.range_len((Bound::Excluded(a), Bound::Included(b))),
+                model
+                    .range((Bound::Excluded(a), Bound::Included(b)))
+                    .count()
+            );
+            assert_eq!(sk.range_len(..b), model.range(..b).count());
+            assert_eq!(sk.range_len(a..), model.range(a..).count());
+        }
+        assert_eq!(sk.range_len(..), sk.len());
+        assert_eq!(SkipList::<u32, ()>::new().range_len(3..9), 0);
+    }
+
+    #[test]
+    fn next_after_and_prev_before() {
+        let sk: SkipList<u32, u32> = (1..=5).map(|i| (i * 10, i)).collect();
+        assert_eq!(sk.next_after(&30), Some((&40, &4)));
+        assert_eq!(sk.prev_before(&30), Some((&20, &2)));
+        assert_eq!(sk.next_after(&35), Some((&40, &4)));
+        assert_eq!(sk.prev_before(&35), Some((&30, &3)));
+        assert_eq!(sk.prev_before(&10), None);
+        assert_eq!(sk.prev_before(&0), None);
+        assert_eq!(sk.prev_before(&99), Some((&50, &5)));
+        assert_eq!(sk.next_after(&50), None);
+        assert_eq!(sk.next_after(&0), Some((&10, &1)));
+        assert_eq!(SkipList::<u32, u32>::new().prev_before(&1), None);
+    }
+
+    #[test]
+    fn btreemap_parity() {
+        use std::collections::BTreeMap;
+        let pairs = [(3, 'c'), (1, 'a'), (2, 'b'), (5, 'e'), (4, 'd')];
+        let mut sk = SkipList::from(pairs);
+        let mut model = BTreeMap::from(pairs);
+        assert_eq!(sk[&2], model[&2]);
+        {
+            let mut first = sk.first_entry().unwrap();
+            let mut model_first = model.first_entry().unwrap();
+            assert_eq!(first.key(), model_first.key());
+            *first.get_mut() = 'A';
+            *model_first.get_mut() = 'A';
+        }
+        let (last, model_last) = (sk.last_entry().unwrap(), model.last_entry().unwrap());
+        assert_eq!(last.remove_entry(), model_last.remove_entry());
+        assert_eq!(sk.remove_entry(&3), model.remove_entry(&3));
+        assert_eq!(sk.remove_entry(&3), None);
+        sk.extend([(&7, &'g'), (&0, &'z')]);
+        model.extend([(&7, &'g'), (&0, &'z')]);
+        assert!(sk.iter().eq(model.iter()));
+        assert_eq!(sk.last_entry().map(|e| *e.key()), Some(7));
+        // Ordered like BTreeMaps of the same entries.
+        let other = SkipList::from([(0, 'z'), (1, 'B')]);
+        let model_other = BTreeMap::from([(0, 'z'), (1, 'B')]);
+        assert_eq!(sk.cmp(&other), model.cmp(&model_other));
+        assert!(
+            SkipList::<u8, u8>::new() < other.clone().into_iter().map(|(k, _)| (k, 0)).collect()
+        );
+        assert!(SkipList::<u8, u8>::new().first_entry().is_none());
+        assert!(SkipList::<u8, u8>::new().last_entry().is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "no entry found for key")]
+    fn index_by_missing_key_panics() {
+        let sk = SkipList::from([(1, 1)]);
+        let _ = sk[&2];
+    }
+
+    #[test]
+    fn clone_keeps_shape() {
+        let mut sk = SkipList::new();
+        for i in 0..500 {
+            sk.insert(i.to_string(), vec![i]);
+        }
+        let mut copy = sk.clone();
+        assert_eq!(copy, sk);
+        assert_eq!(copy.level, sk.level);
+        unsafe {
+            let heights = |sk: &SkipList<String, Vec<i32>>| {
+                let mut heights = Vec::new();
+                let mut x = sk.head.as_ref().tower[0];
+                while let Some(node_ptr) = x {
+                    heights.push(node_ptr.as_ref().height());
+                    x = node_ptr.as_ref().tower[0];
+                }
+                heights
+            };
+            assert_eq!(heights(&copy), heights(&sk));
+        }
+        assert_eq!(copy.get_by_index(321), sk.get_by_index(321));
+        copy.get_mut("7").unwrap().push(0);
+        assert_ne!(copy, sk);
+        assert_eq!(sk.get("7"), Some(&vec![7]));
+    }
+
+    #[test]
+    fn debug_eq_hash() {
+        let hash = |sk: &SkipList<u32, char>| {
+            let mut h = DefaultHasher::new();
+            sk.hash(&mut h);
+            h.finish()
+        };
+        let a: SkipList<u32, char> = [(2, 'b'), (1, 'a')].into_iter().collect();
+        let b = SkipList::from_sorted_iter([(1, 'a'), (2, 'b')]);
+        assert_eq!(format!("{:?}", a), "{1: 'a', 2: 'b'}");
+        assert_eq!(a, b);
+        assert_eq!(hash(&a), hash(&b));
+        assert_ne!(hash(&a), hash(&SkipList::new()));
+        assert_eq!(SkipList::<u32, char>::default(), SkipList::new());
+    }
+
+    #[test]
+    fn send_and_sync() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        fn shareable<T: Send + Sync>(_: &T) {}
+        let mut sk = SkipList::with_rng(StdRng::seed_from_u64(1));
+        for i in 0..1000 {
+            sk.insert(i, i.to_string());
         }
+        shareable(&sk);
+        let sk = std::thread::spawn(move || {
+            sk.insert(1000, "x".to_string());
+            sk
+        })
+        .join()
+        .unwrap();
+        let sk = &sk;
+        std::thread::scope(|s| {
+            for t in 0..4 {
+                s.spawn(move || assert_eq!(sk.get(&(t * 250)), Some(&(t * 250).to_string())));
+            }
+        });
+        assert_eq!(sk.len(), 1001);
     }
 }
@@ -0,0 +1,432 @@
+use crate::{Node, SkipList, MAX_LEVEL};
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::cell::Cell;
+use core::iter::FusedIterator;
+use core::marker::PhantomData;
+use core::ptr::NonNull;
+
+type Meta<K, V, P> = <P as EvictionPolicy<K, V>>::Meta;
+type NodePtr<K, V, P> = NonNull<Node<K, Slot<K, V, P>>>;
+type Link<K, V, P> = Option<NodePtr<K, V, P>>;
+type Update<K, V, P> = [Link<K, V, P>; MAX_LEVEL];
+
+struct Slot<K, V, P: EvictionPolicy<K, V>> {
+    val: V,
+    meta: Cell<P::Meta>,
+    newer: Cell<Link<K, V, P>>,
+    older: Cell<Link<K, V, P>>,
+}
+
+// Decides what a cache gives up. Entries wait in a queue, new ones at the
+// front, and eviction looks at the back: the policy may take the entry there
+// or send it round to the front again. What it knows of each entry on top of
+// the key and value is its Meta, which the cache keeps alongside.
+//
+// get only borrows the cache, so the policy is only ever borrowed too; one
+// that keeps state of its own across entries has to put it in a Cell.
+pub trait EvictionPolicy<K, V> {
+    type Meta: Copy;
+
+    fn inserted(&self, key: &K, val: &V) -> Self::Meta;
+
+    // A get or an insert over the key. True moves the entry to the front.
+    fn accessed(&self, key: &K, meta: &mut Self::Meta) -> bool;
+
+    // The entry is at the back; false keeps it, at the front. Once every
+    // entry has been kept in a row, evict stops short rather than go round
+    // for good.
+    fn evict(&self, key: &K, val: &V, meta: &mut Self::Meta) -> bool;
+}
+
+// Least recently used goes first.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Lru;
+
+// Oldest insert goes first, whatever got used since.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Fifo;
+
+// CLOCK: oldest insert goes first unless it was used since it last came up,
+// which buys it one more round. Near LRU without reordering on every get.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SecondChance;
+
+impl<K, V> EvictionPolicy<K, V> for Lru {
+    type Meta = ();
+
+    fn inserted(&self, _: &K, _: &V) {}
+
+    fn accessed(&self, _: &K, _: &mut ()) -> bool {
+        true
+    }
+
+    fn evict(&self, _: &K, _: &V, _: &mut ()) -> bool {
+        true
+    }
+}
+
+impl<K, V> EvictionPolicy<K, V> for Fifo {
+    type Meta = ();
+
+    fn inserted(&self, _: &K, _: &V) {}
+
+    fn accessed(&self, _: &K, _: &mut ()) -> bool {
+        false
+    }
+
+    fn evict(&self, _: &K, _: &V, _: &mut ()) -> bool {
+        true
+    }
+}
+
+impl<K, V> EvictionPolicy<K, V> for SecondChance {
+    type Meta = bool;
+
+    fn inserted(&self, _: &K, _: &V) -> bool {
+        false
+    }
+
+    fn accessed(&self, _: &K, used: &mut bool) -> bool {
+        *used = true;
+        false
+    }
+
+    fn evict(&self, _: &K, _: &V, used: &mut bool) -> bool {
+        !core::mem::take(used)
+    }
+}
+
+// A sorted map that also keeps its entries in an eviction queue, for a cache
+// that has to answer range queries and evict. The queue is threaded through
+// the nodes themselves, which never move while their entries are in it, so
+// evict takes from the back without scanning. Under the default Lru policy
+// get and insert move an entry to the front and the queue is in order of use.
+pub struct LruSkipList<K, V, P: EvictionPolicy<K, V> = Lru> {
+    list: SkipList<K, Slot<K, V, P>>,
+    newest: Cell<Link<K, V, P>>,
+    oldest: Cell<Link<K, V, P>>,
+    policy: P,
+}
+
+pub struct ByRecency<'a, K, V, P: EvictionPolicy<K, V> = Lru> {
+    next: Link<K, V, P>,
+    len: usize,
+    _marker: PhantomData<&'a LruSkipList<K, V, P>>,
+}
+
+unsafe impl<K: Send, V: Send, P: EvictionPolicy<K, V> + Send> Send for LruSkipList<K, V, P> where
+    Meta<K, V, P>: Send
+{
+}
+
+impl<K: Ord, V> LruSkipList<K, V> {
+    pub fn new() -> Self {
+        Self::with_policy(Lru)
+    }
+
+    // Removes up to n of the least recently used entries and hands them
+    // back, least recent first.
+    pub fn evict_lru(&mut self, n: usize) -> Vec<(K, V)> {
+        self.evict(n)
+    }
+}
+
+impl<K: Ord, V, P: EvictionPolicy<K, V>> LruSkipList<K, V, P> {
+    pub fn with_policy(policy: P) -> Self {
+        Self {
+            list: SkipList::new(),
+            newest: Cell::new(None),
+            oldest: Cell::new(None),
+            policy,
+        }
+    }
+
+    pub fn policy(&self) -> &P {
+        &self.policy
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    // Counts as a use.
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let node_ptr = self.list.find_node(key)?;
+        unsafe {
+            self.accessed(node_ptr);
+            Some(&(*node_ptr.as_ptr()).val().val)
+        }
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let node_ptr = self.list.find_node(key)?;
+        unsafe {
+            self.accessed(node_ptr);
+            Some(&mut (*node_ptr.as_ptr()).val_mut().val)
+        }
+    }
+
+    // Like get, but keeps it from the policy.
+    pub fn peek<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.list.get(key).map(|slot| &slot.val)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.list.contains_key(key)
+    }
+
+    pub fn insert(&mut self, key: K, val: V) -> Option<V> {
+        let mut update: Update<K, V, P> = [None; MAX_LEVEL];
+        unsafe {
+            if let Some(node_ptr) = self.list.find_gt_or_eq_node(&key, &mut update) {
+                if node_ptr.as_ref().key == key {
+                    self.accessed(node_ptr);
+                    let slot = (*node_ptr.as_ptr()).val_mut();
+                    return Some(core::mem::replace(&mut slot.val, val));
+                }
+            }
+            let slot = Slot {
+                meta: Cell::new(self.policy.inserted(&key, &val)),
+                val,
+                newer: Cell::new(None),
+                older: Cell::new(None),
+            };
+            let node_ptr = self.list.link(key, slot, &mut update);
+            self.push_front(node_ptr);
+        }
+        None
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let node_ptr = self.list.find_node(key)?;
+        unsafe { Some(self.take(node_ptr).1) }
+    }
+
+    // Removes up to n entries the policy gives up and hands them back in the
+    // order it did.
+    pub fn evict(&mut self, n: usize) -> Vec<(K, V)> {
+        let mut evicted = Vec::with_capacity(n.min(self.len()));
+        let mut kept = 0;
+        while evicted.len() < n && kept <= self.len() {
+            let Some(node_ptr) = self.oldest.get() else {
+                break;
+            };
+            let node = unsafe { node_ptr.as_ref() };
+            let mut meta = node.val().meta.get();
+            let evict = self.policy.evict(&node.key, &node.val().val, &mut meta);
+            node.val().meta.set(meta);
+            if evict {
+                evicted.push(unsafe { self.take(node_ptr) });
+                kept = 0;
+            } else {
+                unsafe { self.touch(node_ptr) };
+                kept += 1;
+            }
+        }
+        evicted
+    }
+
+    // In key order, without counting as uses.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&K, &V)> + ExactSizeIterator {
+        self.list.iter().map(|(key, slot)| (key, &slot.val))
+    }
+
+    // Front of the queue first, so most recently used first under Lru;
+    // without counting as uses.
+    pub fn iter_by_recency(&self) -> ByRecency<'_, K, V, P> {
+        ByRecency {
+            next: self.newest.get(),
+            len: self.len(),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn into_list(self) -> SkipList<K, V> {
+        self.list
+            .into_iter()
+            .map(|(key, slot)| (key, slot.val))
+            .collect()
+    }
+
+    unsafe fn take(&mut self, node_ptr: NodePtr<K, V, P>) -> (K, V) {
+        self.detach(node_ptr);
+        let mut update: Update<K, V, P> = [None; MAX_LEVEL];
+        self.list
+            .find_gt_or_eq_node(&node_ptr.as_ref().key, &mut update);
+        let (key, slot) = self.list.unlink(node_ptr, &update);
+        (key, slot.val)
+    }
+
+    unsafe fn accessed(&self, node_ptr: NodePtr<K, V, P>) {
+        let node = node_ptr.as_ref();
+        let mut meta = node.val().meta.get();
+        let promote = self.policy.accessed(&node.key, &mut meta);
+        node.val().meta.set(meta);
+        if promote {
+            self.touch(node_ptr);
+        }
+    }
+
+    unsafe fn touch(&self, node_ptr: NodePtr<K, V, P>) {
+        if self.newest.get() != Some(node_ptr) {
+            self.detach(node_ptr);
+            self.push_front(node_ptr);
+        }
+    }
+
+    unsafe fn detach(&self, node_ptr: NodePtr<K, V, P>) {
+        let slot = node_ptr.as_ref().val();
+        let (newer, older) = (slot.newer.take(), slot.older.take());
+        match newer {
+            Some(newer) => newer.as_ref().val().older.set(older),
+            None => self.newest.set(older),
+        }
+        match older {
+            Some(older) => older.as_ref().val().newer.set(newer),
+            None => self.oldest.set(newer),
+        }
+    }
+
+    unsafe fn push_front(&self, node_ptr: NodePtr<K, V, P>) {
+        let slot = node_ptr.as_ref().val();
+        let older = self.newest.replace(Some(node_ptr));
+        slot.older.set(older);
+        match older {
+            Some(older) => older.as_ref().val().newer.set(Some(node_ptr)),
+            None => self.oldest.set(Some(node_ptr)),
+        }
+    }
+}
+
+impl<K: Ord, V, P: EvictionPolicy<K, V> + Default> Default for LruSkipList<K, V, P> {
+    fn default() -> Self {
+        Self::with_policy(P::default())
+    }
+}
+
+impl<'a, K, V, P: EvictionPolicy<K, V>> Iterator for ByRecency<'a, K, V, P> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        let node = unsafe { &*self.next?.as_ptr() };
+        self.next = node.val().older.get();
+        self.len -= 1;
+        Some((&node.key, &node.val().val))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<K, V, P: EvictionPolicy<K, V>> ExactSizeIterator for ByRecency<'_, K, V, P> {}
+
+impl<K, V, P: EvictionPolicy<K, V>> FusedIterator for ByRecency<'_, K, V, P> {}
+
+#[cfg(test)]
+mod tests {
+    use super::{EvictionPolicy, Fifo, LruSkipList, SecondChance};
+
+    fn recency<P: EvictionPolicy<u32, &'static str>>(
+        cache: &LruSkipList<u32, &'static str, P>,
+    ) -> Vec<u32> {
+        cache.iter_by_recency().map(|(k, _)| *k).collect()
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let mut cache = LruSkipList::new();
+        for (k, v) in [(3, "c"), (1, "a"), (4, "d"), (2, "b")] {
+            assert_eq!(cache.insert(k, v), None);
+        }
+        assert_eq!(recency(&cache), [2, 4, 1, 3]);
+        assert_eq!(cache.get(&3), Some(&"c"));
+        assert_eq!(cache.peek(&1), Some(&"a"));
+        assert_eq!(cache.insert(4, "D"), Some("d"));
+        assert_eq!(recency(&cache), [4, 3, 2, 1]);
+        assert!(cache.iter().map(|(k, _)| *k).eq(1..=4));
+
+        assert_eq!(cache.evict_lru(2), [(1, "a"), (2, "b")]);
+        assert_eq!(cache.remove(&4), Some("D"));
+        assert_eq!(cache.remove(&4), None);
+        cache.insert(5, "e");
+        *cache.get_mut(&3).unwrap() = "C";
+        assert_eq!(recency(&cache), [3, 5]);
+        assert_eq!(cache.iter_by_recency().len(), 2);
+        assert_eq!(cache.evict_lru(10), [(5, "e"), (3, "C")]);
+        assert!(cache.is_empty());
+        assert_eq!(cache.iter_by_recency().next(), None);
+
+        cache.insert(7, "g");
+        cache.insert(6, "f");
+        let list = cache.into_list();
+        assert!(list.iter().eq([(&6, &"f"), (&7, &"g")]));
+    }
+
+    #[test]
+    fn policies_pick_the_victims() {
+        let mut fifo = LruSkipList::with_policy(Fifo);
+        for (k, v) in [(1, "a"), (2, "b"), (3, "c")] {
+            fifo.insert(k, v);
+        }
+        fifo.get(&1);
+        assert_eq!(recency(&fifo), [3, 2, 1]);
+        assert_eq!(fifo.evict(1), [(1, "a")]);
+
+        let mut clock = LruSkipList::with_policy(SecondChance);
+        for (k, v) in [(1, "a"), (2, "b"), (3, "c"), (4, "d")] {
+            clock.insert(k, v);
+        }
+        clock.get(&1);
+        clock.insert(2, "B");
+        assert_eq!(clock.evict(2), [(3, "c"), (4, "d")]);
+        assert_eq!(recency(&clock), [2, 1]);
+        // Their rounds are spent, so the oldest goes now.
+        assert_eq!(clock.evict(1), [(1, "a")]);
+
+        // Never gives up a pinned entry: evict goes round once and stops.
+        struct Pinned;
+        impl EvictionPolicy<u32, &'static str> for Pinned {
+            type Meta = ();
+            fn inserted(&self, _: &u32, _: &&'static str) {}
+            fn accessed(&self, _: &u32, _: &mut ()) -> bool {
+                true
+            }
+            fn evict(&self, _: &u32, val: &&'static str, _: &mut ()) -> bool {
+                !val.starts_with('!')
+            }
+        }
+        let mut pinned = LruSkipList::with_policy(Pinned);
+        for (k, v) in [(1, "!a"), (2, "b"), (3, "!c")] {
+            pinned.insert(k, v);
+        }
+        assert_eq!(pinned.evict(3), [(2, "b")]);
+        assert_eq!(pinned.len(), 2);
+        assert!(pinned.policy().evict(&0, &"x", &mut ()));
+    }
+}
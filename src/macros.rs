@@ -0,0 +1,54 @@
+// Both go through from_unsorted_vec: sorting literals that are already in
+// order is a single linear pass, and later duplicates win as with insert.
+#[macro_export]
+macro_rules! skiplist {
+    () => {
+        $crate::SkipList::new()
+    };
+    ($($key:expr => $val:expr),+ $(,)?) => {
+        $crate::SkipList::from_unsorted_vec($crate::__private::vec![$(($key, $val)),+])
+    };
+}
+
+#[macro_export]
+macro_rules! skipset {
+    () => {
+        $crate::SkipList::<_, ()>::new()
+    };
+    ($($key:expr),+ $(,)?) => {
+        $crate::SkipList::from_unsorted_vec($crate::__private::vec![$(($key, ())),+])
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::SkipList;
+
+    #[test]
+    fn skiplist_macro() {
+        let sk = skiplist! {
+            1 => "one",
+            2 => "two",
+            3 => "three",
+        };
+        assert_eq!(sk.len(), 3);
+        assert_eq!(sk.get(&2), Some(&"two"));
+
+        let sk = skiplist! { "b" => 1, "a" => 2, "b" => 3 };
+        assert_eq!(sk.iter().collect::<Vec<_>>(), vec![(&"a", &2), (&"b", &3)]);
+
+        let empty: SkipList<u8, u8> = skiplist! {};
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn skipset_macro() {
+        let set = skipset! { 5, 1, 3, 1 };
+        assert_eq!(
+            set.iter().map(|(k, _)| *k).collect::<Vec<_>>(),
+            vec![1, 3, 5]
+        );
+        let empty: SkipList<i32, ()> = skipset! {};
+        assert!(empty.is_empty());
+    }
+}
@@ -0,0 +1,139 @@
+use crate::{Entry, Iter, Range, SkipList};
+use core::borrow::Borrow;
+use core::ops::RangeBounds;
+
+// Folds an operand into the value already under key, or into nothing when
+// the key is new, and returns the value to keep.
+pub trait MergeOperator<K, V, O> {
+    fn merge(&self, key: &K, existing: Option<&V>, operand: O) -> V;
+}
+
+impl<K, V, O, F: Fn(&K, Option<&V>, O) -> V> MergeOperator<K, V, O> for F {
+    fn merge(&self, key: &K, existing: Option<&V>, operand: O) -> V {
+        self(key, existing, operand)
+    }
+}
+
+// A list with a merge operator registered, so read-modify-write updates
+// such as bumping a counter or adding to a set are one call with a single
+// search instead of a get followed by an insert.
+pub struct MergeSkipList<K, V, M> {
+    list: SkipList<K, V>,
+    op: M,
+}
+
+impl<K: Ord, V, M> MergeSkipList<K, V, M> {
+    pub fn new(op: M) -> Self {
+        Self::from_list(SkipList::new(), op)
+    }
+
+    pub fn from_list(list: SkipList<K, V>, op: M) -> Self {
+        Self { list, op }
+    }
+
+    pub fn merge<O>(&mut self, key: K, operand: O) -> &V
+    where
+        M: MergeOperator<K, V, O>,
+    {
+        match self.list.entry(key) {
+            Entry::Occupied(mut entry) => {
+                let val = self.op.merge(entry.key(), Some(entry.get()), operand);
+                entry.insert(val);
+                entry.into_mut()
+            }
+            Entry::Vacant(entry) => {
+                let val = self.op.merge(entry.key(), None, operand);
+                entry.insert(val)
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    pub fn list(&self) -> &SkipList<K, V> {
+        &self.list
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.list.get(key)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.list.contains_key(key)
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        self.list.iter()
+    }
+
+    pub fn range<Q, R>(&self, range: R) -> Range<'_, K, V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        self.list.range(range)
+    }
+
+    // Bypasses the operator.
+    pub fn insert(&mut self, key: K, val: V) -> Option<V> {
+        self.list.insert_full(key, val).1
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.list.remove(key)
+    }
+
+    pub fn into_list(self) -> SkipList<K, V> {
+        self.list
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MergeSkipList;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn merges_counters_and_sets() {
+        let mut counts =
+            MergeSkipList::new(|_: &&str, old: Option<&u64>, n: u64| old.copied().unwrap_or(0) + n);
+        for word in "a b a c b a".split(' ') {
+            counts.merge(word, 1);
+        }
+        assert_eq!(*counts.merge("c", 10), 11);
+        assert!(counts.iter().eq([(&"a", &3), (&"b", &2), (&"c", &11)]));
+
+        let union = |_: &u32, old: Option<&BTreeSet<u32>>, add: &[u32]| {
+            let mut set = old.cloned().unwrap_or_default();
+            set.extend(add);
+            set
+        };
+        let mut sets = MergeSkipList::new(union);
+        sets.merge(1, &[3, 1][..]);
+        sets.merge(1, &[2, 3][..]);
+        sets.insert(2, BTreeSet::new());
+        sets.merge(2, &[9][..]);
+        assert!(sets.get(&1).unwrap().iter().eq(&[1, 2, 3]));
+        assert!(sets.get(&2).unwrap().iter().eq(&[9]));
+        assert_eq!(sets.len(), 2);
+    }
+}
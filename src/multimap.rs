@@ -0,0 +1,201 @@
+use crate::iter::{Iter, IterMut, Range};
+use crate::{Node, SkipList, MAX_LEVEL};
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::ptr::NonNull;
+
+// Equal keys sit side by side in the order they were inserted: insert searches
+// past every key <= the new one, so it lands after the equals already there.
+// Entries are linked with splice, since ord-checks would reject the repeats.
+pub struct SkipMultiMap<K, V> {
+    list: SkipList<K, V>,
+}
+
+impl<K: Ord, V> SkipMultiMap<K, V> {
+    pub fn new() -> Self {
+        Self {
+            list: SkipList::new(),
+        }
+    }
+
+    // Counts every entry, repeats included.
+    pub fn len(&self) -> usize {
+        self.list.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.size == 0
+    }
+
+    pub fn insert(&mut self, key: K, val: V) {
+        let mut update: [Option<NonNull<Node<K, V>>>; MAX_LEVEL] = [None; MAX_LEVEL];
+        unsafe { self.list.find_by(|k| *k <= key, &mut update) };
+        self.list.splice(key, val, &mut update);
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.first(key).is_some()
+    }
+
+    // The value inserted first under key.
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.first(key)
+            .map(|node_ptr| unsafe { (*node_ptr.as_ptr()).val() })
+    }
+
+    // Every entry under key, oldest first.
+    pub fn get_all<Q>(&self, key: &Q) -> Range<'_, K, V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut update: [Option<NonNull<Node<K, V>>>; MAX_LEVEL] = [None; MAX_LEVEL];
+        unsafe {
+            // With no key equal, front is end and the range is empty.
+            let front = self.list.find_gt_or_eq_node(key, &mut update);
+            let end = self.list.find_by(|k| k.borrow() <= key, &mut update);
+            self.list.range_between(front, end)
+        }
+    }
+
+    pub fn count<Q>(&self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get_all(key).count()
+    }
+
+    // Removes the oldest entry under key.
+    pub fn remove_one<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut update: [Option<NonNull<Node<K, V>>>; MAX_LEVEL] = [None; MAX_LEVEL];
+        unsafe {
+            let node_ptr = self.list.find_gt_or_eq_node(key, &mut update)?;
+            if node_ptr.as_ref().key.borrow() != key {
+                return None;
+            }
+            Some(self.list.unlink(node_ptr, &update))
+        }
+    }
+
+    // Removes every entry under key and returns their values, oldest first.
+    // The predecessors stay the same for each, so the search runs once.
+    pub fn remove_all<Q>(&mut self, key: &Q) -> Vec<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut update: [Option<NonNull<Node<K, V>>>; MAX_LEVEL] = [None; MAX_LEVEL];
+        let mut vals = Vec::new();
+        unsafe {
+            let mut next = self.list.find_gt_or_eq_node(key, &mut update);
+            while let Some(node_ptr) = next.filter(|n| n.as_ref().key.borrow() == key) {
+                next = node_ptr.as_ref().tower[0];
+                vals.push(self.list.unlink(node_ptr, &update).1);
+            }
+        }
+        vals
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        self.list.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        self.list.iter_mut()
+    }
+
+    pub fn clear(&mut self) {
+        self.list.clear();
+    }
+
+    fn first<Q>(&self, key: &Q) -> Option<NonNull<Node<K, V>>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut update: [Option<NonNull<Node<K, V>>>; MAX_LEVEL] = [None; MAX_LEVEL];
+        unsafe {
+            self.list
+                .find_gt_or_eq_node(key, &mut update)
+                .filter(|node_ptr| node_ptr.as_ref().key.borrow() == key)
+        }
+    }
+}
+
+impl<K: Ord, V> Default for SkipMultiMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord, V> FromIterator<(K, V)> for SkipMultiMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(entries: I) -> Self {
+        let mut map = Self::new();
+        map.extend(entries);
+        map
+    }
+}
+
+impl<K: Ord, V> Extend<(K, V)> for SkipMultiMap<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, entries: I) {
+        for (key, val) in entries {
+            self.insert(key, val);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SkipMultiMap;
+
+    #[test]
+    fn equal_keys_keep_insertion_order() {
+        let mut m = SkipMultiMap::new();
+        for (i, ts) in [5, 3, 5, 1, 5, 3].into_iter().enumerate() {
+            m.insert(ts, i);
+        }
+        assert_eq!(m.len(), 6);
+        assert_eq!(
+            m.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>(),
+            [(1, 3), (3, 1), (3, 5), (5, 0), (5, 2), (5, 4)]
+        );
+        assert_eq!(m.get(&5), Some(&0));
+        assert_eq!(
+            m.get_all(&5).map(|(_, v)| *v).collect::<Vec<_>>(),
+            [0, 2, 4]
+        );
+        assert_eq!(m.get_all(&5).next_back(), Some((&5, &4)));
+        assert_eq!(m.count(&3), 2);
+        assert_eq!(m.get_all(&4).count(), 0);
+        assert_eq!(m.get_all(&4).next_back(), None);
+        assert!(!m.contains_key(&4));
+    }
+
+    #[test]
+    fn remove_one_and_all() {
+        let mut m: SkipMultiMap<u32, u32> = (0..300).map(|i| (i % 7, i)).collect();
+        assert_eq!(m.remove_one(&3), Some((3, 3)));
+        assert_eq!(m.get(&3), Some(&10));
+        let all = m.remove_all(&3);
+        assert_eq!(all.len(), 42);
+        assert_eq!(all[..2], [10, 17]);
+        assert_eq!(m.remove_one(&3), None);
+        assert!(m.remove_all(&3).is_empty());
+        assert_eq!(m.len(), 300 - 43);
+        assert_eq!(m.count(&4), 43);
+        assert_eq!(m.list.rank(&5), 43 * 2 + 43 + 43);
+    }
+}
@@ -0,0 +1,246 @@
+use crate::{Entry, Iter, Range, SkipList};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::ops::RangeBounds;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event<'a, K, V> {
+    Insert { key: &'a K, new: &'a V },
+    Update { key: &'a K, old: &'a V, new: &'a V },
+    Remove { key: &'a K, old: &'a V },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(u64);
+
+type Subscriber<K, V> = Box<dyn FnMut(&Event<'_, K, V>) + Send>;
+
+// A list that tells its subscribers about every change as it is made, in
+// the order they subscribed, e.g. to append to a replication log or keep a
+// secondary index in step. Reads go straight to the list.
+pub struct ObservedSkipList<K, V> {
+    list: SkipList<K, V>,
+    subscribers: Vec<(SubscriptionId, Subscriber<K, V>)>,
+    next_id: u64,
+}
+
+impl<K: Ord, V> ObservedSkipList<K, V> {
+    pub fn new() -> Self {
+        Self::from_list(SkipList::new())
+    }
+
+    // Whatever the list already holds is not announced.
+    pub fn from_list(list: SkipList<K, V>) -> Self {
+        Self {
+            list,
+            subscribers: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    pub fn subscribe<F: FnMut(&Event<'_, K, V>) + Send + 'static>(
+        &mut self,
+        f: F,
+    ) -> SubscriptionId {
+        let id = SubscriptionId(self.next_id);
+        self.next_id += 1;
+        self.subscribers.push((id, Box::new(f)));
+        id
+    }
+
+    // False if id was never handed out or is already unsubscribed.
+    pub fn unsubscribe(&mut self, id: SubscriptionId) -> bool {
+        let before = self.subscribers.len();
+        self.subscribers.retain(|(sub, _)| *sub != id);
+        self.subscribers.len() != before
+    }
+
+    pub fn list(&self) -> &SkipList<K, V> {
+        &self.list
+    }
+
+    pub fn into_list(self) -> SkipList<K, V> {
+        self.list
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.list.get(key)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.list.contains_key(key)
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        self.list.iter()
+    }
+
+    pub fn range<Q, R>(&self, range: R) -> Range<'_, K, V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        self.list.range(range)
+    }
+
+    pub fn insert(&mut self, key: K, val: V) -> Option<V> {
+        let subscribers = &mut self.subscribers;
+        match self.list.entry(key) {
+            Entry::Occupied(mut entry) => {
+                let old = entry.insert(val);
+                notify(
+                    subscribers,
+                    &Event::Update {
+                        key: entry.key(),
+                        old: &old,
+                        new: entry.get(),
+                    },
+                );
+                Some(old)
+            }
+            Entry::Vacant(entry) => {
+                notify(
+                    subscribers,
+                    &Event::Insert {
+                        key: entry.key(),
+                        new: &val,
+                    },
+                );
+                entry.insert(val);
+                None
+            }
+        }
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let (key, old) = self.list.remove_entry(key)?;
+        notify(
+            &mut self.subscribers,
+            &Event::Remove {
+                key: &key,
+                old: &old,
+            },
+        );
+        Some(old)
+    }
+
+    // Announces a Remove for every entry, in key order.
+    pub fn clear(&mut self) {
+        while let Some((key, old)) = self.list.pop_first() {
+            notify(
+                &mut self.subscribers,
+                &Event::Remove {
+                    key: &key,
+                    old: &old,
+                },
+            );
+        }
+    }
+}
+
+impl<K: Ord, V> Default for ObservedSkipList<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn notify<K, V>(subscribers: &mut [(SubscriptionId, Subscriber<K, V>)], event: &Event<'_, K, V>) {
+    for (_, f) in subscribers {
+        f(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Event, ObservedSkipList};
+    use std::sync::mpsc;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Debug, PartialEq)]
+    enum Logged {
+        Insert(u32, String),
+        Update(u32, String, String),
+        Remove(u32, String),
+    }
+
+    #[test]
+    fn subscribers_see_every_change() {
+        let mut list = ObservedSkipList::new();
+        let (tx, rx) = mpsc::channel();
+        let log = list.subscribe(move |event: &Event<'_, u32, String>| {
+            let logged = match *event {
+                Event::Insert { key, new } => Logged::Insert(*key, new.clone()),
+                Event::Update { key, old, new } => Logged::Update(*key, old.clone(), new.clone()),
+                Event::Remove { key, old } => Logged::Remove(*key, old.clone()),
+            };
+            tx.send(logged).unwrap();
+        });
+        // A secondary index from value to key.
+        let index = Arc::new(Mutex::new(std::collections::BTreeMap::new()));
+        let by_value = Arc::clone(&index);
+        list.subscribe(move |event| {
+            let mut index = by_value.lock().unwrap();
+            match *event {
+                Event::Insert { key, new } => {
+                    index.insert(new.clone(), *key);
+                }
+                Event::Update { key, old, new } => {
+                    index.remove(old);
+                    index.insert(new.clone(), *key);
+                }
+                Event::Remove { old, .. } => {
+                    index.remove(old);
+                }
+            }
+        });
+
+        assert_eq!(list.insert(1, "a".to_string()), None);
+        assert_eq!(list.insert(2, "b".to_string()), None);
+        assert_eq!(list.insert(1, "c".to_string()), Some("a".to_string()));
+        assert_eq!(list.remove(&2), Some("b".to_string()));
+        assert_eq!(list.remove(&2), None);
+        assert!(list.unsubscribe(log));
+        assert!(!list.unsubscribe(log));
+        list.insert(3, "d".to_string());
+
+        let logged: Vec<_> = rx.try_iter().collect();
+        assert_eq!(
+            logged,
+            [
+                Logged::Insert(1, "a".into()),
+                Logged::Insert(2, "b".into()),
+                Logged::Update(1, "a".into(), "c".into()),
+                Logged::Remove(2, "b".into()),
+            ]
+        );
+        assert_eq!(
+            index.lock().unwrap().iter().collect::<Vec<_>>(),
+            [(&"c".to_string(), &1), (&"d".to_string(), &3)]
+        );
+        list.clear();
+        assert!(list.is_empty());
+        assert!(index.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,84 @@
+use core::cmp::Ordering;
+
+// Spot checks the Ord laws on a key and the neighbours it is about to be
+// linked between. A broken Ord (NaN-carrying floats, comparisons on mutable
+// state) otherwise goes unnoticed and quietly leaves the list out of order.
+pub(crate) fn check_neighbours<K: Ord>(prev: Option<&K>, key: &K, next: Option<&K>) {
+    if key.cmp(key) != Ordering::Equal {
+        violation("a key compares unequal to itself");
+    }
+    if let Some(prev) = prev {
+        check_less(prev, key);
+    }
+    if let Some(next) = next {
+        check_less(key, next);
+    }
+    if let (Some(prev), Some(next)) = (prev, next) {
+        if prev.cmp(next) != Ordering::Less {
+            violation("ordering is not transitive");
+        }
+    }
+}
+
+fn check_less<K: Ord>(a: &K, b: &K) {
+    match (a.cmp(b), b.cmp(a)) {
+        (Ordering::Less, Ordering::Greater) => {}
+        (Ordering::Less, _) => violation("ordering is not antisymmetric"),
+        _ => violation("search placed a key next to one it does not precede"),
+    }
+    if a == b {
+        violation("PartialEq disagrees with Ord");
+    }
+}
+
+fn violation(what: &str) -> ! {
+    panic!("inconsistent Ord implementation for skiplist key: {}", what)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::SkipList;
+    use std::cmp::Ordering;
+
+    // Orders like f64 but treats NaN as smaller than everything, itself
+    // included.
+    #[derive(Clone, Copy, Debug)]
+    struct Loose(f64);
+
+    impl PartialEq for Loose {
+        fn eq(&self, other: &Self) -> bool {
+            self.cmp(other) == Ordering::Equal
+        }
+    }
+
+    impl Eq for Loose {}
+
+    impl PartialOrd for Loose {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Loose {
+        fn cmp(&self, other: &Self) -> Ordering {
+            self.0.partial_cmp(&other.0).unwrap_or(Ordering::Less)
+        }
+    }
+
+    #[test]
+    fn consistent_keys_pass() {
+        let mut sk = SkipList::new();
+        for x in [3.0, 1.0, 2.0, -0.5] {
+            sk.insert(Loose(x), ());
+        }
+        assert_eq!(sk.len(), 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "compares unequal to itself")]
+    fn nan_key_is_reported() {
+        let mut sk = SkipList::new();
+        sk.insert(Loose(1.0), ());
+        sk.insert(Loose(f64::NAN), ());
+    }
+}
@@ -0,0 +1,375 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::iter::FusedIterator;
+use core::slice;
+
+const MAX_LEVEL: usize = 32;
+
+// A skip list whose towers are drawn as a tree: a node on level l holds the
+// run of level l - 1 nodes from one tower that reaches level l up to the
+// next, and level 1 nodes hold the entries. An insert or remove copies the
+// nodes on the way down to its key, plus the runs its tower splits or
+// joins, and shares every other node with the version it started from, so
+// keeping an old version costs no more than the Arc clone that keeps it.
+//
+// The first node on each level belongs to the head tower, which no key
+// owns, and is the only one that may be empty.
+pub struct SkipList<K, V> {
+    root: Arc<Node<K, V>>,
+    level: usize,
+    len: usize,
+    // Tower heights come from a splitmix64 sequence carried from version to
+    // version, so building the same versions always gives the same shape.
+    seed: u64,
+}
+
+enum Node<K, V> {
+    Entries(Vec<Arc<(K, V)>>),
+    Runs(Vec<Arc<Node<K, V>>>),
+}
+
+// What a level hands back up after an insert: its new node, or the two it
+// was split into by the new key's tower.
+enum Put<K, V> {
+    One(Node<K, V>),
+    Split(Node<K, V>, Node<K, V>),
+}
+
+pub struct Iter<'a, K, V> {
+    runs: Vec<slice::Iter<'a, Arc<Node<K, V>>>>,
+    entries: slice::Iter<'a, Arc<(K, V)>>,
+    len: usize,
+}
+
+impl<K: Ord, V> SkipList<K, V> {
+    pub fn new() -> Self {
+        Self {
+            root: Arc::new(Node::Entries(Vec::new())),
+            level: 1,
+            len: 0,
+            seed: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut node = &*self.root;
+        loop {
+            match node {
+                Node::Runs(runs) => node = &runs[run_for(runs, key)],
+                Node::Entries(entries) => {
+                    let i = entries.binary_search_by(|e| e.0.borrow().cmp(key)).ok()?;
+                    return Some(&entries[i].1);
+                }
+            }
+        }
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    // A new version with key mapped to val; self is left as it was.
+    pub fn insert(&self, key: K, val: V) -> Self {
+        let (height, seed) = next_height(self.seed);
+        let mut root = self.root.clone();
+        let mut level = self.level;
+        // The head tower has to stand taller than any key's.
+        while height >= level {
+            root = Arc::new(Node::Runs(alloc::vec![root]));
+            level += 1;
+        }
+        let (put, replaced) = root.put(level, (key, val), height);
+        let Put::One(root) = put else {
+            unreachable!("the head tower is taller than the new one");
+        };
+        Self {
+            root: Arc::new(root),
+            level,
+            len: self.len + !replaced as usize,
+            seed,
+        }
+    }
+
+    // A new version without key, or a clone of self if it has no such key.
+    pub fn remove<Q>(&self, key: &Q) -> Self
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        match self.root.remove(key) {
+            Some(root) => Self {
+                root: Arc::new(root),
+                level: self.level,
+                len: self.len - 1,
+                seed: self.seed,
+            },
+            None => self.clone(),
+        }
+    }
+
+    pub fn first_key_value(&self) -> Option<(&K, &V)> {
+        self.iter().next()
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        let mut iter = Iter {
+            runs: Vec::new(),
+            entries: [].iter(),
+            len: self.len,
+        };
+        iter.enter(&self.root);
+        iter
+    }
+}
+
+impl<K: Ord, V> Node<K, V> {
+    fn first_key(&self) -> Option<&K> {
+        match self {
+            Node::Entries(entries) => entries.first().map(|e| &e.0),
+            Node::Runs(runs) => runs.first()?.first_key(),
+        }
+    }
+
+    // Adds entry below this node on level, where a tower of height splits
+    // every level up to its own. The flag is whether the key was already
+    // there, in which case its value is replaced and its tower kept.
+    fn put(&self, level: usize, entry: (K, V), height: usize) -> (Put<K, V>, bool) {
+        match self {
+            Node::Entries(entries) => {
+                let i = entries.partition_point(|e| e.0 < entry.0);
+                let mut entries = entries.clone();
+                if entries.get(i).is_some_and(|e| e.0 == entry.0) {
+                    entries[i] = Arc::new(entry);
+                    return (Put::One(Node::Entries(entries)), true);
+                }
+                if height >= 1 {
+                    let mut right = entries.split_off(i);
+                    right.insert(0, Arc::new(entry));
+                    (
+                        Put::Split(Node::Entries(entries), Node::Entries(right)),
+                        false,
+                    )
+                } else {
+                    entries.insert(i, Arc::new(entry));
+                    (Put::One(Node::Entries(entries)), false)
+                }
+            }
+            Node::Runs(runs) => {
+                let i = run_for(runs, &entry.0);
+                let (put, replaced) = runs[i].put(level - 1, entry, height);
+                let mut runs = runs.clone();
+                let put = match put {
+                    Put::One(run) => {
+                        runs[i] = Arc::new(run);
+                        Put::One(Node::Runs(runs))
+                    }
+                    Put::Split(left, right) if height >= level => {
+                        runs[i] = Arc::new(left);
+                        let mut after = runs.split_off(i + 1);
+                        after.insert(0, Arc::new(right));
+                        Put::Split(Node::Runs(runs), Node::Runs(after))
+                    }
+                    Put::Split(left, right) => {
+                        runs[i] = Arc::new(left);
+                        runs.insert(i + 1, Arc::new(right));
+                        Put::One(Node::Runs(runs))
+                    }
+                };
+                (put, replaced)
+            }
+        }
+    }
+
+    // None if key isn't below this node. A run the key's tower started is
+    // joined onto the run before it, level by level.
+    fn remove<Q>(&self, key: &Q) -> Option<Node<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        match self {
+            Node::Entries(entries) => {
+                let i = entries.binary_search_by(|e| e.0.borrow().cmp(key)).ok()?;
+                let mut entries = entries.clone();
+                entries.remove(i);
+                Some(Node::Entries(entries))
+            }
+            Node::Runs(runs) => {
+                let i = run_for(runs, key);
+                let run = runs[i].remove(key)?;
+                let mut runs = runs.clone();
+                // The first run only starts with key when this node does too,
+                // and then the level above does the joining.
+                if i > 0 && runs[i].first_key().is_some_and(|k| k.borrow() == key) {
+                    runs[i - 1] = Arc::new(runs[i - 1].join(&run));
+                    runs.remove(i);
+                } else {
+                    runs[i] = Arc::new(run);
+                }
+                Some(Node::Runs(runs))
+            }
+        }
+    }
+
+    // Appends next, a node on the same level whose tower is gone, so the
+    // last run here and next's first run become one on every level below.
+    fn join(&self, next: &Node<K, V>) -> Node<K, V> {
+        match (self, next) {
+            (Node::Entries(left), Node::Entries(right)) => {
+                Node::Entries(left.iter().chain(right).cloned().collect())
+            }
+            (Node::Runs(left), Node::Runs(right)) => {
+                let (last, left) = left.split_last().unwrap();
+                let (first, right) = right.split_first().unwrap();
+                let mut runs = left.to_vec();
+                runs.push(Arc::new(last.join(first)));
+                runs.extend_from_slice(right);
+                Node::Runs(runs)
+            }
+            _ => unreachable!("joined nodes are on the same level"),
+        }
+    }
+}
+
+// The run holding key: the last one starting at or before it, falling back
+// to the first, which covers everything before the second.
+fn run_for<K: Ord + Borrow<Q>, Q: Ord + ?Sized, V>(runs: &[Arc<Node<K, V>>], key: &Q) -> usize {
+    runs[1..].partition_point(|run| run.first_key().is_some_and(|k| k.borrow() <= key))
+}
+
+// Levels above the entries: each one a quarter as likely as the one below,
+// giving runs of four on average.
+fn next_height(seed: u64) -> (usize, u64) {
+    let seed = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = seed;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^= z >> 31;
+    let height = (z.trailing_zeros() as usize / 2).min(MAX_LEVEL - 1);
+    (height, seed)
+}
+
+impl<K, V> Clone for SkipList<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root.clone(),
+            level: self.level,
+            len: self.len,
+            seed: self.seed,
+        }
+    }
+}
+
+impl<K: Ord, V> Default for SkipList<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord, V> FromIterator<(K, V)> for SkipList<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        iter.into_iter()
+            .fold(Self::new(), |list, (key, val)| list.insert(key, val))
+    }
+}
+
+impl<'a, K, V> Iter<'a, K, V> {
+    fn enter(&mut self, node: &'a Node<K, V>) {
+        match node {
+            Node::Entries(entries) => self.entries = entries.iter(),
+            Node::Runs(runs) => self.runs.push(runs.iter()),
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        loop {
+            if let Some(entry) = self.entries.next() {
+                self.len -= 1;
+                return Some((&entry.0, &entry.1));
+            }
+            match self.runs.last_mut()?.next() {
+                Some(run) => self.enter(run),
+                None => {
+                    self.runs.pop();
+                }
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+impl<K, V> FusedIterator for Iter<'_, K, V> {}
+
+#[cfg(test)]
+mod tests {
+    use super::SkipList;
+    use rand::prelude::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn versions_stay_as_they_were() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut list = SkipList::new();
+        let mut model = BTreeMap::new();
+        let mut versions = Vec::new();
+        for step in 0..4_000 {
+            let key = rng.gen_range(0..600u32);
+            if rng.gen_bool(0.6) {
+                list = list.insert(key, step);
+                model.insert(key, step);
+            } else {
+                list = list.remove(&key);
+                model.remove(&key);
+            }
+            if step % 500 == 0 {
+                versions.push((list.clone(), model.clone()));
+            }
+        }
+        versions.push((list.clone(), model));
+        for (list, model) in &versions {
+            assert_eq!(list.len(), model.len());
+            assert!(list.iter().eq(model.iter()));
+            assert_eq!(list.iter().len(), model.len());
+            for key in 0..600 {
+                assert_eq!(list.get(&key), model.get(&key));
+            }
+        }
+
+        // Entries away from the change are the same allocations.
+        let next = list.insert(10_000, 0);
+        let (k, v) = list.first_key_value().unwrap();
+        assert!(std::ptr::eq(next.get(k).unwrap(), v));
+        assert!(!list.contains_key(&10_000) && next.contains_key(&10_000));
+        let empty = SkipList::<u32, ()>::new();
+        assert!(empty.remove(&1).is_empty());
+        assert!(empty.iter().next().is_none());
+        let built: SkipList<_, _> = (0..100).rev().map(|i| (i, i)).collect();
+        assert!(built.iter().map(|(k, _)| *k).eq(0..100));
+    }
+}
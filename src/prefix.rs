@@ -0,0 +1,254 @@
+use crate::SkipList;
+use alloc::vec::Vec;
+use core::mem;
+use core::ops::Bound;
+
+const DEFAULT_RESTART_INTERVAL: usize = 16;
+
+// Byte-string keys stored as suffixes of the key before them. Entries are
+// grouped into blocks of up to twice the restart interval; the list is keyed
+// by each block's first key, its restart point, which is the only key kept
+// whole. The rest keep how many leading bytes they share with the key ahead
+// of them and the bytes after that, all in one buffer per block, so long
+// common prefixes such as URLs or paths cost a few bytes per entry.
+//
+// A lookup finds the block through the towers and decodes at most one
+// block's keys. A write decodes and re-encodes the block it lands in,
+// splitting it in two once it passes twice the interval.
+pub struct PrefixSkipList<V> {
+    blocks: SkipList<Vec<u8>, Block<V>>,
+    restart_interval: usize,
+    len: usize,
+}
+
+struct Block<V> {
+    // Suffix bytes of every entry after the first, back to back.
+    suffixes: Vec<u8>,
+    // For each entry: the bytes shared with the previous key and where its
+    // suffix ends in suffixes. The first entry is the restart key itself.
+    ends: Vec<(usize, usize)>,
+    vals: Vec<V>,
+}
+
+impl<V> PrefixSkipList<V> {
+    pub fn new() -> Self {
+        Self::with_restart_interval(DEFAULT_RESTART_INTERVAL)
+    }
+
+    pub fn with_restart_interval(restart_interval: usize) -> Self {
+        Self {
+            blocks: SkipList::new(),
+            restart_interval: restart_interval.max(1),
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Key bytes actually held: restart keys plus suffixes.
+    pub fn key_bytes(&self) -> usize {
+        self.blocks
+            .iter()
+            .map(|(restart, block)| restart.len() + block.suffixes.len())
+            .sum()
+    }
+
+    pub fn get(&self, key: &[u8]) -> Option<&V> {
+        let (restart, block) = self.block_for(key)?;
+        let mut found = None;
+        block.decode(restart, |i, k| {
+            if k == key {
+                found = Some(&block.vals[i]);
+            }
+            k < key
+        });
+        found
+    }
+
+    pub fn contains_key(&self, key: &[u8]) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn insert(&mut self, key: Vec<u8>, val: V) -> Option<V> {
+        let mut entries = self.take_block(&key);
+        let old = match entries.binary_search_by(|(k, _)| k.as_slice().cmp(&key)) {
+            Ok(i) => Some(mem::replace(&mut entries[i].1, val)),
+            Err(i) => {
+                entries.insert(i, (key, val));
+                self.len += 1;
+                None
+            }
+        };
+        self.put_blocks(entries);
+        old
+    }
+
+    pub fn remove(&mut self, key: &[u8]) -> Option<V> {
+        self.block_for(key)?;
+        let mut entries = self.take_block(key);
+        let old = match entries.binary_search_by(|(k, _)| k.as_slice().cmp(key)) {
+            Ok(i) => {
+                self.len -= 1;
+                Some(entries.remove(i).1)
+            }
+            Err(_) => None,
+        };
+        self.put_blocks(entries);
+        old
+    }
+
+    // Keys come out rebuilt, in order.
+    pub fn iter(&self) -> impl Iterator<Item = (Vec<u8>, &V)> + '_ {
+        self.blocks.iter().flat_map(|(restart, block)| {
+            let mut key = Vec::new();
+            block
+                .ends
+                .iter()
+                .zip(&block.vals)
+                .enumerate()
+                .map(move |(i, ((shared, end), val))| {
+                    if i == 0 {
+                        key.clone_from(restart);
+                    } else {
+                        key.truncate(*shared);
+                        key.extend_from_slice(&block.suffixes[block.ends[i - 1].1..*end]);
+                    }
+                    (key.clone(), val)
+                })
+        })
+    }
+
+    // The block whose range holds key: the last one starting at or before it.
+    fn block_for(&self, key: &[u8]) -> Option<(&Vec<u8>, &Block<V>)> {
+        self.blocks
+            .range::<[u8], _>((Bound::Unbounded, Bound::Included(key)))
+            .next_back()
+    }
+
+    // Takes out the block that key belongs in, or the first block when key
+    // sorts before every restart point, and returns its entries decoded.
+    fn take_block(&mut self, key: &[u8]) -> Vec<(Vec<u8>, V)> {
+        let restart = match self.block_for(key) {
+            Some((restart, _)) => restart.clone(),
+            None => match self.blocks.first_key_value() {
+                Some((restart, _)) => restart.clone(),
+                None => return Vec::new(),
+            },
+        };
+        let block = self.blocks.remove(&restart).unwrap();
+        block.into_entries(restart)
+    }
+
+    // A block that has grown past twice the interval goes back as two halves.
+    fn put_blocks(&mut self, mut entries: Vec<(Vec<u8>, V)>) {
+        if entries.len() > 2 * self.restart_interval {
+            let upper = entries.split_off(entries.len() / 2);
+            self.put_block(upper);
+        }
+        self.put_block(entries);
+    }
+
+    fn put_block(&mut self, entries: Vec<(Vec<u8>, V)>) {
+        if !entries.is_empty() {
+            let (restart, block) = Block::encode(entries.into_iter());
+            self.blocks.insert(restart, block);
+        }
+    }
+}
+
+impl<V> Block<V> {
+    fn encode(entries: impl Iterator<Item = (Vec<u8>, V)>) -> (Vec<u8>, Block<V>) {
+        let mut block = Block {
+            suffixes: Vec::new(),
+            ends: Vec::new(),
+            vals: Vec::new(),
+        };
+        let mut restart = Vec::new();
+        let mut prev = Vec::new();
+        for (i, (key, val)) in entries.enumerate() {
+            let shared = if i == 0 {
+                restart.clone_from(&key);
+                key.len()
+            } else {
+                prev.iter().zip(&key).take_while(|(a, b)| a == b).count()
+            };
+            block.suffixes.extend_from_slice(&key[shared..]);
+            block.ends.push((shared, block.suffixes.len()));
+            block.vals.push(val);
+            prev = key;
+        }
+        (restart, block)
+    }
+
+    // Calls f with each entry's index and key in order until it returns false.
+    fn decode(&self, restart: &[u8], mut f: impl FnMut(usize, &[u8]) -> bool) {
+        let mut key = restart.to_vec();
+        let mut start = 0;
+        for (i, (shared, end)) in self.ends.iter().enumerate() {
+            key.truncate(*shared);
+            key.extend_from_slice(&self.suffixes[start..*end]);
+            start = *end;
+            if !f(i, &key) {
+                return;
+            }
+        }
+    }
+
+    fn into_entries(self, restart: Vec<u8>) -> Vec<(Vec<u8>, V)> {
+        let mut keys = Vec::with_capacity(self.vals.len());
+        self.decode(&restart, |_, key| {
+            keys.push(key.to_vec());
+            true
+        });
+        keys.into_iter().zip(self.vals).collect()
+    }
+}
+
+impl<V> Default for PrefixSkipList<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PrefixSkipList;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn matches_btreemap_with_fewer_key_bytes() {
+        let mut list = PrefixSkipList::new();
+        let mut model = BTreeMap::new();
+        for i in (0..2000u32).rev() {
+            let key = format!("https://example.com/users/{}/profile", i % 500 * 7).into_bytes();
+            assert_eq!(list.insert(key.clone(), i), model.insert(key, i));
+        }
+        for i in (0..3500).step_by(3) {
+            let key = format!("https://example.com/users/{}/profile", i).into_bytes();
+            assert_eq!(list.remove(&key), model.remove(&key));
+        }
+        assert_eq!(list.remove(b"a"), None);
+        assert_eq!(list.insert(b"a".to_vec(), 1), None);
+        model.insert(b"a".to_vec(), 1);
+        assert_eq!(list.len(), model.len());
+        assert!(list.iter().eq(model.iter().map(|(k, v)| (k.clone(), v))));
+        for (k, v) in &model {
+            assert_eq!(list.get(k), Some(v));
+        }
+        assert_eq!(list.get(b"https://example.com/users/"), None);
+        let raw: usize = model.keys().map(Vec::len).sum();
+        assert!(
+            list.key_bytes() * 3 < raw,
+            "{} of {}",
+            list.key_bytes(),
+            raw
+        );
+        assert!(PrefixSkipList::<()>::new().iter().next().is_none());
+    }
+}
@@ -0,0 +1,139 @@
+use crate::{Node, SkipList, MAX_LEVEL};
+use core::ptr::NonNull;
+
+// What push hands back: the item's place in the queue, for taking it out
+// again before its turn.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct QueueTicket<P> {
+    priority: P,
+    seq: u64,
+}
+
+impl<P> QueueTicket<P> {
+    pub fn priority(&self) -> &P {
+        &self.priority
+    }
+}
+
+// Items are keyed by priority and then by a counter bumped on every push, so
+// items of equal priority leave in the order they came in, from either end.
+pub struct SkipQueue<P, T> {
+    list: SkipList<QueueTicket<P>, T>,
+    seq: u64,
+}
+
+type Link<P, T> = Option<NonNull<Node<QueueTicket<P>, T>>>;
+
+impl<P: Ord, T> SkipQueue<P, T> {
+    pub fn new() -> Self {
+        Self {
+            list: SkipList::new(),
+            seq: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    pub fn push(&mut self, priority: P, item: T) -> QueueTicket<P>
+    where
+        P: Clone,
+    {
+        let ticket = QueueTicket {
+            priority,
+            seq: self.seq,
+        };
+        self.seq += 1;
+        self.list.insert(ticket.clone(), item);
+        ticket
+    }
+
+    pub fn peek_min(&self) -> Option<(&P, &T)> {
+        let (ticket, item) = self.list.first_key_value()?;
+        Some((&ticket.priority, item))
+    }
+
+    pub fn pop_min(&mut self) -> Option<(P, T)> {
+        let (ticket, item) = self.list.pop_first()?;
+        Some((ticket.priority, item))
+    }
+
+    pub fn peek_max(&self) -> Option<(&P, &T)> {
+        let mut update = [None; MAX_LEVEL];
+        let node = unsafe { &*self.oldest_of_max(&mut update)?.as_ptr() };
+        Some((&node.key.priority, node.val()))
+    }
+
+    pub fn pop_max(&mut self) -> Option<(P, T)> {
+        let mut update = [None; MAX_LEVEL];
+        let node_ptr = self.oldest_of_max(&mut update)?;
+        let (ticket, item) = unsafe { self.list.unlink(node_ptr, &update) };
+        Some((ticket.priority, item))
+    }
+
+    // Takes the item out wherever it is in the queue; None once it has been
+    // popped or removed.
+    pub fn remove(&mut self, ticket: &QueueTicket<P>) -> Option<T> {
+        self.list.remove(ticket)
+    }
+
+    // Lowest priority first, and first in first among equals.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&P, &T)> + '_ {
+        self.list
+            .iter()
+            .map(|(ticket, item)| (&ticket.priority, item))
+    }
+
+    // The last node holds the highest priority, and the first node with that
+    // priority is the one that has waited longest.
+    fn oldest_of_max(&self, update: &mut [Link<P, T>; MAX_LEVEL]) -> Link<P, T> {
+        let last = self.list.last_node()?;
+        let max = unsafe { &(*last.as_ptr()).key.priority };
+        unsafe { self.list.find_by(|ticket| ticket.priority < *max, update) }
+    }
+}
+
+impl<P: Ord, T> Default for SkipQueue<P, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SkipQueue;
+
+    #[test]
+    fn equal_priorities_are_first_in_first_out() {
+        let mut q = SkipQueue::new();
+        q.push(2, "b1");
+        q.push(1, "a1");
+        let b2 = q.push(2, "b2");
+        q.push(3, "c1");
+        q.push(2, "b3");
+        q.push(3, "c2");
+        assert_eq!(q.len(), 6);
+        assert_eq!(q.peek_min(), Some((&1, &"a1")));
+        assert_eq!(q.peek_max(), Some((&3, &"c1")));
+        assert_eq!(q.pop_max(), Some((3, "c1")));
+        assert_eq!(q.pop_max(), Some((3, "c2")));
+        assert_eq!(q.remove(&b2), Some("b2"));
+        assert_eq!(q.remove(&b2), None);
+        assert_eq!(*b2.priority(), 2);
+        assert_eq!(
+            q.iter().map(|(_, item)| *item).collect::<Vec<_>>(),
+            ["a1", "b1", "b3"]
+        );
+        assert_eq!(q.pop_max(), Some((2, "b1")));
+        assert_eq!(q.pop_min(), Some((1, "a1")));
+        assert_eq!(q.pop_min(), Some((2, "b3")));
+        assert_eq!(q.pop_min(), None);
+        assert_eq!(q.pop_max(), None);
+        assert!(q.is_empty());
+    }
+}
@@ -0,0 +1,208 @@
+use crate::SkipList;
+use core::cell::Cell;
+use core::error::Error;
+use core::fmt;
+use core::ops::{Bound, Range};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QuotaUsage {
+    pub used: usize,
+    pub limit: usize,
+}
+
+// The rejected entry is handed back so the caller still owns it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QuotaExceeded<K, V> {
+    pub key: K,
+    pub val: V,
+    pub limit: usize,
+}
+
+impl<K: fmt::Debug, V> fmt::Display for QuotaExceeded<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "inserting {:?} would exceed the quota of {} entries",
+            self.key, self.limit
+        )
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> Error for QuotaExceeded<K, V> {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QuotaOverlap<K>(pub Range<K>);
+
+impl<K: fmt::Debug> fmt::Display for QuotaOverlap<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "quota range {:?} overlaps an existing quota", self.0)
+    }
+}
+
+impl<K: fmt::Debug> Error for QuotaOverlap<K> {}
+
+struct Quota<K> {
+    end: K,
+    limit: usize,
+    used: Cell<usize>,
+}
+
+// Quotas cover disjoint half-open key ranges and are kept keyed by their
+// start, so the quota for a key is the floor entry if the key is below its
+// end. Usage is updated on the same insert or remove that changes the
+// entries, so the two cannot drift apart.
+pub struct QuotaSkipList<K, V> {
+    entries: SkipList<K, V>,
+    quotas: SkipList<K, Quota<K>>,
+}
+
+impl<K: Ord, V> QuotaSkipList<K, V> {
+    pub fn new() -> Self {
+        Self {
+            entries: SkipList::new(),
+            quotas: SkipList::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Entries already in range count towards the new quota, even if that
+    // puts it over the limit; only later inserts are refused.
+    pub fn set_quota(&mut self, range: Range<K>, limit: usize) -> Result<(), QuotaOverlap<K>> {
+        let overlaps = self
+            .quotas
+            .floor_entry(&range.start)
+            .is_some_and(|(_, q)| q.end > range.start)
+            || self
+                .quotas
+                .range((Bound::Included(&range.start), Bound::Excluded(&range.end)))
+                .next()
+                .is_some();
+        if overlaps {
+            return Err(QuotaOverlap(range));
+        }
+        let used = self
+            .entries
+            .range((Bound::Included(&range.start), Bound::Excluded(&range.end)))
+            .count();
+        self.quotas.insert(
+            range.start,
+            Quota {
+                end: range.end,
+                limit,
+                used: Cell::new(used),
+            },
+        );
+        Ok(())
+    }
+
+    pub fn remove_quota(&mut self, start: &K) -> bool {
+        self.quotas.remove(start).is_some()
+    }
+
+    pub fn usage(&self, key: &K) -> Option<QuotaUsage> {
+        self.quota_of(key).map(|q| QuotaUsage {
+            used: q.used.get(),
+            limit: q.limit,
+        })
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.entries.get(key)
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.entries.get_mut(key)
+    }
+
+    // Replacing the value of an existing key never counts against a quota.
+    pub fn insert(&mut self, key: K, val: V) -> Result<Option<V>, QuotaExceeded<K, V>> {
+        if let Some(old) = self.entries.get_mut(&key) {
+            return Ok(Some(core::mem::replace(old, val)));
+        }
+        if let Some(q) = self.quota_of(&key) {
+            if q.used.get() >= q.limit {
+                let limit = q.limit;
+                return Err(QuotaExceeded { key, val, limit });
+            }
+            q.used.set(q.used.get() + 1);
+        }
+        self.entries.insert(key, val);
+        Ok(None)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let val = self.entries.remove(key)?;
+        if let Some(q) = self.quota_of(key) {
+            q.used.set(q.used.get() - 1);
+        }
+        Some(val)
+    }
+
+    fn quota_of(&self, key: &K) -> Option<&Quota<K>> {
+        self.quotas
+            .floor_entry(key)
+            .map(|(_, q)| q)
+            .filter(|q| *key < q.end)
+    }
+}
+
+impl<K: Ord, V> Default for QuotaSkipList<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{QuotaExceeded, QuotaOverlap, QuotaSkipList, QuotaUsage};
+
+    #[test]
+    fn per_tenant_quotas() {
+        let mut sk = QuotaSkipList::new();
+        sk.insert("acme/0".to_string(), 0).unwrap();
+        sk.set_quota("acme/".to_string().."acme0".to_string(), 2)
+            .unwrap();
+        assert_eq!(
+            sk.usage(&"acme/9".to_string()),
+            Some(QuotaUsage { used: 1, limit: 2 })
+        );
+
+        sk.insert("acme/1".to_string(), 1).unwrap();
+        assert_eq!(
+            sk.insert("acme/2".to_string(), 2),
+            Err(QuotaExceeded {
+                key: "acme/2".to_string(),
+                val: 2,
+                limit: 2
+            })
+        );
+        assert_eq!(sk.insert("acme/1".to_string(), 10), Ok(Some(1)));
+        for i in 0..10 {
+            sk.insert(format!("globex/{}", i), i).unwrap();
+        }
+        assert_eq!(sk.usage(&"globex/1".to_string()), None);
+
+        assert_eq!(sk.remove(&"acme/0".to_string()), Some(0));
+        assert_eq!(sk.usage(&"acme/".to_string()).unwrap().used, 1);
+        sk.insert("acme/2".to_string(), 2).unwrap();
+        assert_eq!(sk.len(), 12);
+
+        assert_eq!(
+            sk.set_quota("a".to_string().."b".to_string(), 5),
+            Err(QuotaOverlap("a".to_string().."b".to_string()))
+        );
+        assert!(sk
+            .set_quota("acme0".to_string().."b".to_string(), 5)
+            .is_ok());
+        assert!(sk.remove_quota(&"acme/".to_string()));
+        sk.insert("acme/3".to_string(), 3).unwrap();
+        assert_eq!(sk.usage(&"acme/3".to_string()), None);
+    }
+}
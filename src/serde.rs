@@ -0,0 +1,80 @@
+use crate::SkipList;
+use alloc::vec::Vec;
+use core::fmt;
+use core::marker::PhantomData;
+use serde::de::{Deserialize, Deserializer, MapAccess, Visitor};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+
+impl<K: Serialize, V: Serialize> Serialize for SkipList<K, V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.size))?;
+        for (key, val) in self.iter() {
+            map.serialize_entry(key, val)?;
+        }
+        map.end()
+    }
+}
+
+// Entries are gathered first and loaded with from_unsorted_vec: a map written
+// by Serialize arrives in key order, so the sort is one linear pass and the
+// load appends at the tail finger. Repeated keys keep the last value, as
+// BTreeMap does.
+impl<'de, K, V> Deserialize<'de> for SkipList<K, V>
+where
+    K: Ord + Deserialize<'de>,
+    V: Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(SkipListVisitor(PhantomData))
+    }
+}
+
+struct SkipListVisitor<K, V>(PhantomData<fn() -> SkipList<K, V>>);
+
+impl<'de, K, V> Visitor<'de> for SkipListVisitor<K, V>
+where
+    K: Ord + Deserialize<'de>,
+    V: Deserialize<'de>,
+{
+    type Value = SkipList<K, V>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a map")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+        let mut entries = Vec::with_capacity(access.size_hint().unwrap_or(0).min(4096));
+        while let Some(entry) = access.next_entry()? {
+            entries.push(entry);
+        }
+        Ok(SkipList::from_unsorted_vec(entries))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::SkipList;
+
+    #[test]
+    fn round_trip() {
+        let mut sk = SkipList::new();
+        for i in (0..50).rev() {
+            sk.insert(format!("k{:02}", i), i);
+        }
+        let json = serde_json::to_string(&sk).unwrap();
+        assert!(json.starts_with(r#"{"k00":0,"k01":1,"#));
+        let back: SkipList<String, i32> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.len(), 50);
+        assert!(back.iter().eq(sk.iter()));
+        assert_eq!(back.get_by_index(10), Some((&"k10".to_string(), &10)));
+    }
+
+    #[test]
+    fn unsorted_and_repeated_input() {
+        let sk: SkipList<u32, char> =
+            serde_json::from_str(r#"{"3":"c","1":"a","3":"d","2":"b"}"#).unwrap();
+        let entries: Vec<_> = sk.iter().map(|(k, v)| (*k, *v)).collect();
+        assert_eq!(entries, [(1, 'a'), (2, 'b'), (3, 'd')]);
+        assert!(serde_json::from_str::<SkipList<u32, char>>("[1]").is_err());
+    }
+}
@@ -0,0 +1,304 @@
+use crate::{iter, setops, SkipList};
+use core::borrow::Borrow;
+use core::iter::FusedIterator;
+use core::ops::RangeBounds;
+
+// A SkipList with () values; () takes no room in the node, so a set costs
+// the same as the keys and towers alone.
+pub struct SkipSet<K> {
+    list: SkipList<K, ()>,
+}
+
+impl<K: Ord> SkipSet<K> {
+    pub fn new() -> Self {
+        Self {
+            list: SkipList::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    // Returns false if the key was already there, leaving the stored key as is.
+    pub fn insert(&mut self, key: K) -> bool {
+        if self.list.contains_key(&key) {
+            return false;
+        }
+        self.list.insert(key, ());
+        true
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.list.remove(key).is_some()
+    }
+
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.list.contains_key(key)
+    }
+
+    pub fn first(&self) -> Option<&K> {
+        self.list.first_key_value().map(|(key, _)| key)
+    }
+
+    pub fn last(&self) -> Option<&K> {
+        self.list.last_key_value().map(|(key, _)| key)
+    }
+
+    pub fn pop_first(&mut self) -> Option<K> {
+        self.list.pop_first().map(|(key, _)| key)
+    }
+
+    pub fn pop_last(&mut self) -> Option<K> {
+        self.list.pop_last().map(|(key, _)| key)
+    }
+
+    pub fn retain<F: FnMut(&K) -> bool>(&mut self, mut keep: F) {
+        self.list.retain(|key, _| keep(key));
+    }
+
+    pub fn iter(&self) -> Iter<'_, K> {
+        Iter {
+            inner: self.list.iter(),
+        }
+    }
+
+    pub fn range<Q, R>(&self, range: R) -> Range<'_, K>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        Range {
+            inner: self.list.range(range),
+        }
+    }
+
+    pub fn union<'a>(&'a self, other: &'a SkipSet<K>) -> Union<'a, K> {
+        self.list.union_keys(&other.list)
+    }
+
+    pub fn intersection<'a>(&'a self, other: &'a SkipSet<K>) -> Intersection<'a, K> {
+        self.list.intersection_keys(&other.list)
+    }
+
+    pub fn difference<'a>(&'a self, other: &'a SkipSet<K>) -> Difference<'a, K> {
+        self.list.difference_keys(&other.list)
+    }
+
+    pub fn symmetric_difference<'a>(&'a self, other: &'a SkipSet<K>) -> SymmetricDifference<'a, K> {
+        self.list.symmetric_difference_keys(&other.list)
+    }
+
+    pub fn is_subset(&self, other: &SkipSet<K>) -> bool {
+        self.len() <= other.len() && self.difference(other).next().is_none()
+    }
+
+    pub fn is_superset(&self, other: &SkipSet<K>) -> bool {
+        other.is_subset(self)
+    }
+
+    pub fn is_disjoint(&self, other: &SkipSet<K>) -> bool {
+        self.intersection(other).next().is_none()
+    }
+}
+
+impl<K: Ord> Default for SkipSet<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord> FromIterator<K> for SkipSet<K> {
+    fn from_iter<I: IntoIterator<Item = K>>(keys: I) -> Self {
+        Self {
+            list: SkipList::from_unsorted_vec(keys.into_iter().map(|key| (key, ())).collect()),
+        }
+    }
+}
+
+impl<K: Ord> Extend<K> for SkipSet<K> {
+    fn extend<I: IntoIterator<Item = K>>(&mut self, keys: I) {
+        for key in keys {
+            self.insert(key);
+        }
+    }
+}
+
+pub struct Iter<'a, K> {
+    inner: iter::Iter<'a, K, ()>,
+}
+
+impl<'a, K> Iterator for Iter<'a, K> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<&'a K> {
+        self.inner.next().map(|(key, _)| key)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K> DoubleEndedIterator for Iter<'a, K> {
+    fn next_back(&mut self) -> Option<&'a K> {
+        self.inner.next_back().map(|(key, _)| key)
+    }
+}
+
+impl<K> ExactSizeIterator for Iter<'_, K> {}
+
+impl<K> FusedIterator for Iter<'_, K> {}
+
+impl<K> Clone for Iter<'_, K> {
+    fn clone(&self) -> Self {
+        Iter {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+pub struct Range<'a, K> {
+    inner: iter::Range<'a, K, ()>,
+}
+
+impl<'a, K> Iterator for Range<'a, K> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<&'a K> {
+        self.inner.next().map(|(key, _)| key)
+    }
+}
+
+impl<'a, K> DoubleEndedIterator for Range<'a, K> {
+    fn next_back(&mut self) -> Option<&'a K> {
+        self.inner.next_back().map(|(key, _)| key)
+    }
+}
+
+impl<K> FusedIterator for Range<'_, K> {}
+
+pub struct IntoIter<K> {
+    inner: iter::IntoIter<K, ()>,
+}
+
+impl<K> Iterator for IntoIter<K> {
+    type Item = K;
+
+    fn next(&mut self) -> Option<K> {
+        self.inner.next().map(|(key, _)| key)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K> DoubleEndedIterator for IntoIter<K> {
+    fn next_back(&mut self) -> Option<K> {
+        self.inner.next_back().map(|(key, _)| key)
+    }
+}
+
+impl<K> ExactSizeIterator for IntoIter<K> {}
+
+impl<K> FusedIterator for IntoIter<K> {}
+
+impl<'a, K: Ord> IntoIterator for &'a SkipSet<K> {
+    type Item = &'a K;
+    type IntoIter = Iter<'a, K>;
+
+    fn into_iter(self) -> Iter<'a, K> {
+        self.iter()
+    }
+}
+
+impl<K> IntoIterator for SkipSet<K> {
+    type Item = K;
+    type IntoIter = IntoIter<K>;
+
+    fn into_iter(self) -> IntoIter<K> {
+        IntoIter {
+            inner: self.list.into_iter(),
+        }
+    }
+}
+
+pub type Union<'a, K> = setops::Union<'a, K, (), ()>;
+pub type Intersection<'a, K> = setops::Intersection<'a, K, (), ()>;
+pub type Difference<'a, K> = setops::Difference<'a, K, (), ()>;
+pub type SymmetricDifference<'a, K> = setops::SymmetricDifference<'a, K, (), ()>;
+
+#[cfg(test)]
+mod tests {
+    use super::SkipSet;
+
+    fn set(keys: &[u32]) -> SkipSet<u32> {
+        keys.iter().copied().collect()
+    }
+
+    #[test]
+    fn insert_remove_contains() {
+        let mut s = SkipSet::new();
+        assert!(s.insert("b".to_string()));
+        assert!(s.insert("a".to_string()));
+        assert!(!s.insert("b".to_string()));
+        assert_eq!(s.len(), 2);
+        assert!(s.contains("a"));
+        assert!(s.remove("a"));
+        assert!(!s.remove("a"));
+        assert_eq!(s.first().map(String::as_str), Some("b"));
+        assert_eq!(s.pop_last().as_deref(), Some("b"));
+        assert!(s.is_empty());
+    }
+
+    #[test]
+    fn iterators() {
+        let s = set(&[5, 1, 9, 3, 7]);
+        assert_eq!(s.iter().copied().collect::<Vec<_>>(), [1, 3, 5, 7, 9]);
+        assert_eq!(s.iter().next_back(), Some(&9));
+        assert_eq!(s.range(3..8).copied().collect::<Vec<_>>(), [3, 5, 7]);
+        assert_eq!(s.into_iter().collect::<Vec<_>>(), [1, 3, 5, 7, 9]);
+    }
+
+    #[test]
+    fn set_operations() {
+        let a = set(&[1, 2, 3, 5, 8]);
+        let b = set(&[2, 3, 4, 8, 9]);
+        assert_eq!(
+            a.union(&b).copied().collect::<Vec<_>>(),
+            [1, 2, 3, 4, 5, 8, 9]
+        );
+        assert_eq!(a.intersection(&b).copied().collect::<Vec<_>>(), [2, 3, 8]);
+        assert_eq!(a.difference(&b).copied().collect::<Vec<_>>(), [1, 5]);
+        assert_eq!(
+            a.symmetric_difference(&b).copied().collect::<Vec<_>>(),
+            [1, 4, 5, 9]
+        );
+        assert!(set(&[2, 8]).is_subset(&a));
+        assert!(!b.is_subset(&a));
+        assert!(a.is_superset(&set(&[])));
+        assert!(set(&[4, 9]).is_disjoint(&a));
+    }
+
+    #[test]
+    fn retain_and_extend() {
+        let mut s = set(&[1, 2, 3, 4, 5, 6]);
+        s.retain(|k| k % 2 == 0);
+        s.extend([4, 10, 8]);
+        assert_eq!(s.iter().copied().collect::<Vec<_>>(), [2, 4, 6, 8, 10]);
+    }
+}
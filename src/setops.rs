@@ -0,0 +1,383 @@
+use crate::{Node, SkipList, MAX_LEVEL};
+use core::cmp::Ordering;
+use core::ptr::NonNull;
+
+impl<K: Ord, V> SkipList<K, V> {
+    pub fn retain_intersection<W>(&mut self, other: &SkipList<K, W>) {
+        let mut cursor = other.seeker();
+        self.retain(|key, _| cursor.contains(key));
+    }
+
+    pub fn remove_all_in<W>(&mut self, other: &SkipList<K, W>) {
+        let mut cursor = other.seeker();
+        self.retain(|key, _| !cursor.contains(key));
+    }
+
+    // One pass along level 0. update[l] tracks the last kept node on level l,
+    // so a dropped node is spliced out of every level it is on as it is met.
+    // Spans are redone in a second pass at the end, which the guard runs even
+    // if keep or a drop panics partway, so the list is left whole with only
+    // some of the entries gone.
+    pub fn retain<F: FnMut(&K, &mut V) -> bool>(&mut self, mut keep: F) {
+        self.generation += 1;
+        struct Respan<'a, K, V>(&'a mut SkipList<K, V>);
+
+        impl<K, V> Drop for Respan<'_, K, V> {
+            fn drop(&mut self) {
+                self.0.shrink_level();
+                self.0.rebuild_spans();
+            }
+        }
+
+        let mut update: [NonNull<Node<K, V>>; MAX_LEVEL] = [self.head; MAX_LEVEL];
+        let sk = Respan(self);
+        unsafe {
+            let mut x = sk.0.head.as_ref().tower[0];
+            while let Some(mut node_ptr) = x {
+                let node = node_ptr.as_mut();
+                x = node.tower[0];
+                let height = node.height();
+                let (key, val) = node.entry_mut();
+                if keep(key, val) {
+                    update[..height].fill(node_ptr);
+                    continue;
+                }
+                for (l, prev) in update.iter_mut().enumerate().take(height) {
+                    prev.as_mut().tower[l] = node.tower[l];
+                }
+                if let Some(mut next) = node.tower[0] {
+                    next.as_mut().prev = Some(update[0]).filter(|prev| *prev != sk.0.head);
+                }
+                sk.0.size -= 1;
+                #[cfg(feature = "zeroize")]
+                sk.0.wipe_node(node_ptr);
+                sk.0.free_node(node_ptr);
+            }
+        }
+    }
+
+    // Keys in either list, in order, each once.
+    pub fn union_keys<'a, W>(&'a self, other: &'a SkipList<K, W>) -> Union<'a, K, V, W> {
+        Union(self.seeker(), other.seeker())
+    }
+
+    pub fn intersection_keys<'a, W>(
+        &'a self,
+        other: &'a SkipList<K, W>,
+    ) -> Intersection<'a, K, V, W> {
+        Intersection(self.seeker(), other.seeker())
+    }
+
+    // Keys in self but not in other.
+    pub fn difference_keys<'a, W>(&'a self, other: &'a SkipList<K, W>) -> Difference<'a, K, V, W> {
+        Difference(self.seeker(), other.seeker())
+    }
+
+    pub fn symmetric_difference_keys<'a, W>(
+        &'a self,
+        other: &'a SkipList<K, W>,
+    ) -> SymmetricDifference<'a, K, V, W> {
+        SymmetricDifference(self.seeker(), other.seeker())
+    }
+
+    fn seeker(&self) -> Seeker<'_, K, V> {
+        Seeker {
+            list: self,
+            preds: [self.head; MAX_LEVEL],
+        }
+    }
+}
+
+// A forward-only cursor for keys asked in increasing order. preds[l] is the
+// last node on level l before where the cursor stands, so a seek climbs only
+// as high as the first level whose link reaches the key and searches down
+// from there: O(log d) for a jump over d entries, and a run of seeks costs
+// O(n + m) overall and far less when they skip large parts of the list.
+struct Seeker<'a, K, V> {
+    list: &'a SkipList<K, V>,
+    preds: [NonNull<Node<K, V>>; MAX_LEVEL],
+}
+
+impl<'a, K: Ord, V> Seeker<'a, K, V> {
+    fn peek(&self) -> Option<&'a K> {
+        unsafe { self.preds[0].as_ref().tower[0].map(|next| &(*next.as_ptr()).key) }
+    }
+
+    // Steps past the entry the cursor stands at.
+    fn advance(&mut self) {
+        unsafe {
+            if let Some(next) = self.preds[0].as_ref().tower[0] {
+                self.preds[..next.as_ref().height()].fill(next);
+            }
+        }
+    }
+
+    // Moves on to the first entry at or after key and returns its key.
+    fn seek(&mut self, key: &K) -> Option<&'a K> {
+        unsafe {
+            let behind = |x: NonNull<Node<K, V>>, l: usize| {
+                x.as_ref().tower[l].is_some_and(|next| next.as_ref().key < *key)
+            };
+            let mut top = 0;
+            while top < self.list.level && behind(self.preds[top], top) {
+                top += 1;
+            }
+            if top > 0 {
+                let mut x = self.preds[top - 1];
+                for l in (0..top).rev() {
+                    while behind(x, l) {
+                        x = x.as_ref().tower[l].unwrap();
+                    }
+                    self.preds[l] = x;
+                }
+            }
+        }
+        self.peek()
+    }
+
+    fn contains(&mut self, key: &K) -> bool {
+        self.seek(key) == Some(key)
+    }
+}
+
+// The merges below advance whichever side has the smaller key, or both when
+// they are equal. Intersection and difference seek instead of stepping, so
+// runs of the other list that can't match are jumped over on the towers.
+pub struct Union<'a, K, V, W>(Seeker<'a, K, V>, Seeker<'a, K, W>);
+
+impl<'a, K: Ord, V, W> Iterator for Union<'a, K, V, W> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<&'a K> {
+        match (self.0.peek(), self.1.peek()) {
+            (None, None) => None,
+            (Some(a), None) => {
+                self.0.advance();
+                Some(a)
+            }
+            (None, Some(b)) => {
+                self.1.advance();
+                Some(b)
+            }
+            (Some(a), Some(b)) => match a.cmp(b) {
+                Ordering::Less => {
+                    self.0.advance();
+                    Some(a)
+                }
+                Ordering::Greater => {
+                    self.1.advance();
+                    Some(b)
+                }
+                Ordering::Equal => {
+                    self.0.advance();
+                    self.1.advance();
+                    Some(a)
+                }
+            },
+        }
+    }
+}
+
+pub struct Intersection<'a, K, V, W>(Seeker<'a, K, V>, Seeker<'a, K, W>);
+
+impl<'a, K: Ord, V, W> Iterator for Intersection<'a, K, V, W> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<&'a K> {
+        let mut a = self.0.peek()?;
+        loop {
+            let b = self.1.seek(a)?;
+            if a == b {
+                self.0.advance();
+                self.1.advance();
+                return Some(a);
+            }
+            a = self.0.seek(b)?;
+        }
+    }
+}
+
+pub struct Difference<'a, K, V, W>(Seeker<'a, K, V>, Seeker<'a, K, W>);
+
+impl<'a, K: Ord, V, W> Iterator for Difference<'a, K, V, W> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<&'a K> {
+        loop {
+            let a = self.0.peek()?;
+            self.0.advance();
+            if !self.1.contains(a) {
+                return Some(a);
+            }
+        }
+    }
+}
+
+pub struct SymmetricDifference<'a, K, V, W>(Seeker<'a, K, V>, Seeker<'a, K, W>);
+
+impl<'a, K: Ord, V, W> Iterator for SymmetricDifference<'a, K, V, W> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<&'a K> {
+        loop {
+            match (self.0.peek(), self.1.peek()) {
+                (None, None) => return None,
+                (Some(a), b) if b.is_none_or(|b| a < b) => {
+                    self.0.advance();
+                    return Some(a);
+                }
+                (a, Some(b)) if a.is_none_or(|a| b < a) => {
+                    self.1.advance();
+                    return Some(b);
+                }
+                _ => {
+                    self.0.advance();
+                    self.1.advance();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::SkipList;
+    use std::cell::Cell;
+    use std::cmp::Ordering;
+    use std::collections::BTreeSet;
+    use std::rc::Rc;
+
+    thread_local! {
+        static COMPARED: Cell<usize> = const { Cell::new(0) };
+    }
+
+    #[derive(PartialEq, Eq, Debug)]
+    struct Counted(u32);
+
+    impl PartialOrd for Counted {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Counted {
+        fn cmp(&self, other: &Self) -> Ordering {
+            COMPARED.with(|c| c.set(c.get() + 1));
+            self.0.cmp(&other.0)
+        }
+    }
+
+    fn keys<V>(sk: &SkipList<i32, V>) -> Vec<i32> {
+        sk.iter().map(|(k, _)| *k).collect()
+    }
+
+    #[test]
+    fn retain() {
+        let mut sk = SkipList::new();
+        for i in 0..100 {
+            sk.insert(i, i * 10);
+        }
+        sk.retain(|k, v| {
+            *v += 1;
+            k % 10 == 0
+        });
+        assert_eq!(keys(&sk), (0..100).step_by(10).collect::<Vec<_>>());
+        assert_eq!(sk.get(&30), Some(&301));
+        assert_eq!(sk.get_by_index(5), Some((&50, &501)));
+    }
+
+    #[test]
+    fn retain_intersection() {
+        let val = Rc::new(());
+        let mut a = SkipList::new();
+        for i in 0..1000 {
+            a.insert(i, val.clone());
+        }
+        let mut b = SkipList::new();
+        for i in (0..2000).step_by(7) {
+            b.insert(i, "x");
+        }
+        a.retain_intersection(&b);
+        let expected: Vec<i32> = (0..1000).step_by(7).collect();
+        assert_eq!(keys(&a), expected);
+        assert!(a.iter().rev().map(|(k, _)| k).eq(expected.iter().rev()));
+        assert_eq!(a.len(), expected.len());
+        assert_eq!(Rc::strong_count(&val), expected.len() + 1);
+        for i in 0..1000 {
+            assert_eq!(a.get(&i).is_some(), i % 7 == 0);
+        }
+
+        a.retain_intersection(&SkipList::<i32, ()>::new());
+        assert!(a.is_empty());
+        assert_eq!(a.level, 1);
+        assert_eq!(Rc::strong_count(&val), 1);
+    }
+
+    #[test]
+    fn remove_all_in() {
+        let mut a = SkipList::new();
+        for i in 0..100 {
+            a.insert(i, i);
+        }
+        let mut b = SkipList::new();
+        for i in [-5, 0, 3, 50, 51, 99, 150] {
+            b.insert(i, ());
+        }
+        a.remove_all_in(&b);
+        assert_eq!(a.len(), 95);
+        assert_eq!(
+            keys(&a),
+            (0..100)
+                .filter(|i| ![0, 3, 50, 51, 99].contains(i))
+                .collect::<Vec<_>>()
+        );
+        for (lvl, ks) in crate::tests::levels(&a).iter().enumerate() {
+            assert!(!ks.is_empty(), "level {} is empty", lvl);
+            assert!(ks.windows(2).all(|w| w[0] < w[1]));
+        }
+    }
+
+    #[test]
+    fn key_set_operations() {
+        let mut a = SkipList::new();
+        let mut b = SkipList::new();
+        for i in 0..3000u32 {
+            if i % 3 == 0 || i % 7 == 0 {
+                a.insert(i, i as u64);
+            }
+            if i % 5 == 0 || (1000..1200).contains(&i) {
+                b.insert(i, "x");
+            }
+        }
+        let sa: BTreeSet<u32> = a.iter().map(|(k, _)| *k).collect();
+        let sb: BTreeSet<u32> = b.iter().map(|(k, _)| *k).collect();
+        assert!(a.union_keys(&b).eq(sa.union(&sb)));
+        assert!(a.intersection_keys(&b).eq(sa.intersection(&sb)));
+        assert!(a.difference_keys(&b).eq(sa.difference(&sb)));
+        assert!(b.difference_keys(&a).eq(sb.difference(&sa)));
+        assert!(a
+            .symmetric_difference_keys(&b)
+            .eq(sa.symmetric_difference(&sb)));
+        let empty = SkipList::<u32, ()>::new();
+        assert_eq!(a.intersection_keys(&empty).count(), 0);
+        assert!(a.union_keys(&empty).eq(sa.iter()));
+    }
+
+    #[test]
+    fn intersection_skips_on_the_towers() {
+        let mut big = SkipList::new();
+        for i in 0..100_000 {
+            big.insert(Counted(i), ());
+        }
+        let mut small = SkipList::new();
+        for i in (0..100_000).step_by(10_000) {
+            small.insert(Counted(i + 1), ());
+        }
+        COMPARED.with(|c| c.set(0));
+        assert_eq!(small.intersection_keys(&big).count(), 10);
+        assert_eq!(big.intersection_keys(&small).count(), 10);
+        let compared = COMPARED.with(Cell::get);
+        // Stepping through big would take 100k comparisons each way.
+        assert!(compared < 4_000, "{}", compared);
+    }
+}
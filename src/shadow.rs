@@ -0,0 +1,229 @@
+use crate::SkipList;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Debug;
+use std::ops::ControlFlow;
+
+// Mirrors every operation into a BTreeMap and panics as soon as the two
+// disagree, either in what an operation returned or in the full contents
+// and tower structure checked after every mutation. Each mutation costs
+// O(n) on top, so this is meant for tests and fuzzing only.
+pub struct ShadowSkipList<K, V> {
+    list: SkipList<K, V>,
+    model: BTreeMap<K, V>,
+}
+
+impl<K, V> ShadowSkipList<K, V>
+where
+    K: Ord + Clone + Debug,
+    V: Clone + PartialEq + Debug,
+{
+    pub fn new() -> Self {
+        Self {
+            list: SkipList::new(),
+            model: BTreeMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        assert_eq!(self.list.len(), self.model.len(), "len");
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let got = self.list.get(key);
+        assert_eq!(got, self.model.get(key), "get({:?})", key);
+        got
+    }
+
+    pub fn insert(&mut self, key: K, val: V) {
+        self.model.insert(key.clone(), val.clone());
+        self.list.insert(key, val);
+        self.check();
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let got = self.list.remove(key);
+        assert_eq!(got, self.model.remove(key), "remove({:?})", key);
+        self.check();
+        got
+    }
+
+    // Applies f to the value in both structures; f should be deterministic.
+    pub fn modify<F: FnMut(&mut V)>(&mut self, key: &K, mut f: F) -> bool {
+        let found = match (self.list.get_mut(key), self.model.get_mut(key)) {
+            (Some(a), Some(b)) => {
+                f(a);
+                f(b);
+                true
+            }
+            (None, None) => false,
+            (a, b) => panic!("get_mut({:?}): {:?} != {:?}", key, a, b),
+        };
+        self.check();
+        found
+    }
+
+    pub fn pop_first_k(&mut self, k: usize) -> Vec<(K, V)> {
+        let got = self.list.pop_first_k(k);
+        let expected: Vec<_> = (0..k).map_while(|_| self.model.pop_first()).collect();
+        assert_eq!(got, expected, "pop_first_k({})", k);
+        self.check();
+        got
+    }
+
+    pub fn pop_last_k(&mut self, k: usize) -> Vec<(K, V)> {
+        let got = self.list.pop_last_k(k);
+        let expected: Vec<_> = (0..k).map_while(|_| self.model.pop_last()).collect();
+        assert_eq!(got, expected, "pop_last_k({})", k);
+        self.check();
+        got
+    }
+
+    pub fn truncate(&mut self, len: usize) {
+        self.list.truncate(len);
+        while self.model.len() > len {
+            self.model.pop_last();
+        }
+        self.check();
+    }
+
+    pub fn clear(&mut self) {
+        self.list.clear_retain_capacity();
+        self.model.clear();
+        self.check();
+    }
+
+    pub fn try_for_each<B, F>(&self, mut f: F) -> ControlFlow<B>
+    where
+        F: FnMut(&K, &V) -> ControlFlow<B>,
+    {
+        let mut model = self.model.iter();
+        self.list.try_for_each(|k, v| {
+            assert_eq!(Some((k, v)), model.next(), "iteration");
+            f(k, v)
+        })
+    }
+
+    pub fn into_inner(self) -> SkipList<K, V> {
+        self.list
+    }
+
+    pub fn check(&self) {
+        assert_eq!(self.list.len(), self.model.len(), "len");
+        assert!(
+            self.list.iter().eq(self.model.iter()),
+            "contents differ: {:?} != {:?}",
+            self.list.iter().collect::<Vec<_>>(),
+            self.model
+        );
+        self.list.check_towers();
+    }
+}
+
+impl<K, V> Default for ShadowSkipList<K, V>
+where
+    K: Ord + Clone + Debug,
+    V: Clone + PartialEq + Debug,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord + Debug, V> SkipList<K, V> {
+    // Every level must be strictly increasing and only hold nodes tall
+    // enough for it, the list level must be the highest non-empty one, prev
+    // must mirror level 0, and every span must match the positions of the
+    // nodes its link joins.
+    fn check_towers(&self) {
+        unsafe {
+            let mut before = None;
+            let mut ranks = HashMap::from([(self.head, 0)]);
+            let mut x = self.head.as_ref().tower[0];
+            while let Some(node_ptr) = x {
+                ranks.insert(node_ptr, ranks.len());
+                assert!(
+                    node_ptr.as_ref().prev == before,
+                    "bad prev at {:?}",
+                    node_ptr.as_ref().key
+                );
+                before = x;
+                x = node_ptr.as_ref().tower[0];
+            }
+            for l in 0..self.level {
+                let mut x = self.head.as_ref().tower[l];
+                let mut from = self.head;
+                let mut prev: Option<&K> = None;
+                assert!(x.is_some() || l == 0, "level {} is empty", l);
+                while let Some(node_ptr) = x {
+                    let node = &*node_ptr.as_ptr();
+                    assert!(node.height() > l, "node {:?} on level {}", node.key, l);
+                    if let Some(prev) = prev {
+                        assert!(
+                            *prev < node.key,
+                            "{:?} before {:?} on level {}",
+                            prev,
+                            node.key,
+                            l
+                        );
+                    }
+                    assert_eq!(
+                        from.as_ref().span(l),
+                        ranks[&node_ptr] - ranks[&from],
+                        "span into {:?} on level {}",
+                        node.key,
+                        l
+                    );
+                    from = node_ptr;
+                    prev = Some(&node.key);
+                    x = node.tower[l];
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ShadowSkipList;
+    use rand::prelude::*;
+    use std::ops::ControlFlow;
+
+    #[test]
+    fn random_ops_agree() {
+        let mut rng = thread_rng();
+        let mut sk = ShadowSkipList::new();
+        for _ in 0..2_000 {
+            let key = rng.gen_range(0..200);
+            match rng.gen_range(0..10) {
+                0..=4 => sk.insert(key, key.to_string()),
+                5 | 6 => {
+                    sk.remove(&key);
+                }
+                7 => {
+                    sk.modify(&key, |v| v.push('!'));
+                }
+                8 => {
+                    sk.pop_first_k(rng.gen_range(0..4));
+                }
+                _ => {
+                    sk.pop_last_k(rng.gen_range(0..4));
+                }
+            }
+            sk.get(&key);
+        }
+        let mut seen = 0;
+        let _ = sk.try_for_each(|_, _| {
+            seen += 1;
+            ControlFlow::<()>::Continue(())
+        });
+        assert_eq!(seen, sk.len());
+        sk.truncate(10);
+        sk.clear();
+        assert!(sk.is_empty());
+    }
+}
@@ -0,0 +1,452 @@
+use crate::{stable_hash, FirstWins, Iter, MergeIter, Range, SkipList};
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::fmt;
+use core::hash::Hash;
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut, RangeBounds};
+use core::time::Duration;
+use std::sync::{
+    Mutex, MutexGuard, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError,
+};
+use std::thread;
+use std::time::Instant;
+
+const DEFAULT_SHARDS: usize = 16;
+
+// What guards each shard. RwLock is the default; Mutex is a smaller lock
+// that lets one reader in at a time. Another lock crate plugs in by
+// implementing this for its lock, and picking it is a matter of the map's
+// third type parameter, so the map itself stays the same type to write code
+// against. A poisoned std lock is taken over as it is: a panicking writer
+// leaves its shard a valid SkipList either way.
+pub trait ShardLock<T> {
+    type Read<'a>: Deref<Target = T>
+    where
+        Self: 'a;
+    type Write<'a>: DerefMut<Target = T>
+    where
+        Self: 'a;
+
+    fn new(val: T) -> Self;
+    fn read(&self) -> Self::Read<'_>;
+    fn write(&self) -> Self::Write<'_>;
+    fn try_read(&self) -> Option<Self::Read<'_>>;
+    fn try_write(&self) -> Option<Self::Write<'_>>;
+    fn into_inner(self) -> T;
+
+    // Locks that can park with a deadline should override these; the
+    // default keeps retrying, yielding in between, until time's up.
+    fn try_read_for(&self, timeout: Duration) -> Option<Self::Read<'_>> {
+        retry_for(timeout, || self.try_read())
+    }
+
+    fn try_write_for(&self, timeout: Duration) -> Option<Self::Write<'_>> {
+        retry_for(timeout, || self.try_write())
+    }
+}
+
+// A timed lock acquisition ran out of time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Timeout;
+
+impl fmt::Display for Timeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("timed out waiting for a shard lock")
+    }
+}
+
+impl core::error::Error for Timeout {}
+
+impl<T> ShardLock<T> for RwLock<T> {
+    type Read<'a>
+        = RwLockReadGuard<'a, T>
+    where
+        T: 'a;
+    type Write<'a>
+        = RwLockWriteGuard<'a, T>
+    where
+        T: 'a;
+
+    fn new(val: T) -> Self {
+        RwLock::new(val)
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, T> {
+        RwLock::read(self).unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, T> {
+        RwLock::write(self).unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn try_read(&self) -> Option<RwLockReadGuard<'_, T>> {
+        taken(RwLock::try_read(self))
+    }
+
+    fn try_write(&self) -> Option<RwLockWriteGuard<'_, T>> {
+        taken(RwLock::try_write(self))
+    }
+
+    fn into_inner(self) -> T {
+        RwLock::into_inner(self).unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T> ShardLock<T> for Mutex<T> {
+    type Read<'a>
+        = MutexGuard<'a, T>
+    where
+        T: 'a;
+    type Write<'a>
+        = MutexGuard<'a, T>
+    where
+        T: 'a;
+
+    fn new(val: T) -> Self {
+        Mutex::new(val)
+    }
+
+    fn read(&self) -> MutexGuard<'_, T> {
+        self.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self) -> MutexGuard<'_, T> {
+        self.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn try_read(&self) -> Option<MutexGuard<'_, T>> {
+        taken(self.try_lock())
+    }
+
+    fn try_write(&self) -> Option<MutexGuard<'_, T>> {
+        taken(self.try_lock())
+    }
+
+    fn into_inner(self) -> T {
+        Mutex::into_inner(self).unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+// Spreads its keys over a fixed number of SkipLists by hash, each behind its
+// own RwLock, so writers only contend when they land on the same shard. It's
+// the simple alternative to concurrent::SkipMap: every read and write is an
+// ordinary SkipList call under a lock, and nothing has to be pinned.
+// Ordered reads go through read, which locks every shard and merges them.
+pub struct SkipMap<K, V, L = RwLock<SkipList<K, V>>> {
+    shards: Vec<L>,
+    _marker: PhantomData<fn() -> SkipList<K, V>>,
+}
+
+// All shards held for reading, so everything read through it sees the same
+// state.
+pub struct ReadGuard<'a, K, V, L: ShardLock<SkipList<K, V>> + 'a = RwLock<SkipList<K, V>>> {
+    shards: Vec<L::Read<'a>>,
+}
+
+impl<K: Ord + Hash, V> SkipMap<K, V> {
+    pub fn new() -> Self {
+        Self::with_shards(DEFAULT_SHARDS)
+    }
+
+    pub fn with_shards(shards: usize) -> Self {
+        Self::with_lock(shards)
+    }
+}
+
+impl<K: Ord + Hash, V, L: ShardLock<SkipList<K, V>>> SkipMap<K, V, L> {
+    // with_shards for a lock other than RwLock, named by the type:
+    // SkipMap::<K, V, Mutex<_>>::with_lock(shards).
+    pub fn with_lock(shards: usize) -> Self {
+        assert!(shards > 0, "a sharded map needs at least one shard");
+        Self {
+            shards: (0..shards).map(|_| L::new(SkipList::new())).collect(),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn shards(&self) -> usize {
+        self.shards.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.read().len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|shard| shard.read().is_empty())
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + Hash + ?Sized,
+        V: Clone,
+    {
+        self.get_with(key, V::clone)
+    }
+
+    // Runs f on the value under the shard's read lock, for values that are
+    // expensive or impossible to clone.
+    pub fn get_with<Q, R>(&self, key: &Q, f: impl FnOnce(&V) -> R) -> Option<R>
+    where
+        K: Borrow<Q>,
+        Q: Ord + Hash + ?Sized,
+    {
+        self.shard(key).read().get(key).map(f)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + Hash + ?Sized,
+    {
+        self.shard(key).read().contains_key(key)
+    }
+
+    pub fn insert(&self, key: K, val: V) -> Option<V> {
+        let mut shard = self.shard(&key).write();
+        shard.insert_full(key, val).1
+    }
+
+    pub fn remove<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + Hash + ?Sized,
+    {
+        self.shard(key).write().remove(key)
+    }
+
+    // get and insert, but giving up with Timeout instead of waiting longer
+    // than timeout for the key's shard. The time bounds the wait for the
+    // lock only, not the SkipList call made under it.
+    pub fn try_get_for<Q>(&self, key: &Q, timeout: Duration) -> Result<Option<V>, Timeout>
+    where
+        K: Borrow<Q>,
+        Q: Ord + Hash + ?Sized,
+        V: Clone,
+    {
+        let shard = self.shard(key).try_read_for(timeout).ok_or(Timeout)?;
+        Ok(shard.get(key).cloned())
+    }
+
+    pub fn try_insert_for(&self, key: K, val: V, timeout: Duration) -> Result<Option<V>, Timeout> {
+        let mut shard = self.shard(&key).try_write_for(timeout).ok_or(Timeout)?;
+        Ok(shard.insert_full(key, val).1)
+    }
+
+    // Writers wait until it's dropped. It locks the shards in order and a
+    // writer only ever holds one, so taking it can't deadlock.
+    pub fn read(&self) -> ReadGuard<'_, K, V, L> {
+        ReadGuard {
+            shards: self.shards.iter().map(L::read).collect(),
+        }
+    }
+
+    // Clones the entries in range out from under the locks, in key order.
+    pub fn range<Q, R>(&self, range: R) -> Vec<(K, V)>
+    where
+        K: Borrow<Q> + Clone,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+        V: Clone,
+    {
+        self.read()
+            .range(range)
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect()
+    }
+
+    pub fn into_list(self) -> SkipList<K, V> {
+        let mut lists: Vec<SkipList<K, V>> = self.shards.into_iter().map(L::into_inner).collect();
+        let mut merged = lists.pop().unwrap();
+        for mut list in lists {
+            merged.append(&mut list);
+        }
+        merged
+    }
+
+    fn shard<Q: Hash + ?Sized>(&self, key: &Q) -> &L {
+        &self.shards[(stable_hash(key) % self.shards.len() as u64) as usize]
+    }
+}
+
+impl<K: Ord + Hash, V, L: ShardLock<SkipList<K, V>>> Default for SkipMap<K, V, L> {
+    fn default() -> Self {
+        Self::with_lock(DEFAULT_SHARDS)
+    }
+}
+
+impl<'a, K: Ord, V, L: ShardLock<SkipList<K, V>>> ReadGuard<'a, K, V, L> {
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|shard| shard.is_empty())
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + Hash + ?Sized,
+    {
+        let i = (stable_hash(key) % self.shards.len() as u64) as usize;
+        self.shards[i].get(key)
+    }
+
+    // A key lives in exactly one shard, so the merge never sees duplicates.
+    pub fn iter(&self) -> MergeIter<&K, &V, Iter<'_, K, V>, FirstWins> {
+        MergeIter::new(self.shards.iter().map(|shard| shard.iter()), FirstWins)
+    }
+
+    pub fn range<Q, R>(&self, range: R) -> MergeIter<&K, &V, Range<'_, K, V>, FirstWins>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let bounds = (range.start_bound(), range.end_bound());
+        MergeIter::new(
+            self.shards.iter().map(|shard| shard.range(bounds)),
+            FirstWins,
+        )
+    }
+}
+
+fn taken<G>(attempt: Result<G, TryLockError<G>>) -> Option<G> {
+    match attempt {
+        Ok(guard) => Some(guard),
+        Err(TryLockError::Poisoned(poisoned)) => Some(poisoned.into_inner()),
+        Err(TryLockError::WouldBlock) => None,
+    }
+}
+
+fn retry_for<G>(timeout: Duration, mut attempt: impl FnMut() -> Option<G>) -> Option<G> {
+    // A timeout too long to add up is as good as none.
+    let deadline = Instant::now().checked_add(timeout);
+    loop {
+        if let Some(guard) = attempt() {
+            return Some(guard);
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return None;
+        }
+        thread::yield_now();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SkipMap, Timeout};
+    use std::sync::{mpsc, Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn shards_merge_back_into_key_order() {
+        let map = Arc::new(SkipMap::with_shards(4));
+        let writers: Vec<_> = (0..4u32)
+            .map(|t| {
+                let map = map.clone();
+                thread::spawn(move || {
+                    for i in 0..500 {
+                        assert_eq!(map.insert(i * 4 + t, t), None);
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        assert_eq!(map.len(), 2_000);
+        assert_eq!(map.get(&13), Some(1));
+        assert_eq!(map.insert(13, 9), Some(1));
+        assert_eq!(map.remove(&14), Some(2));
+        assert_eq!(map.get_with(&13, |v| v + 1), Some(10));
+        assert!(!map.contains_key(&14));
+
+        let guard = map.read();
+        assert!(guard
+            .iter()
+            .map(|(k, _)| *k)
+            .eq((0..2_000).filter(|&k| k != 14)));
+        assert_eq!(guard.get(&13), Some(&9));
+        assert!(guard
+            .range(10..17)
+            .map(|(k, _)| *k)
+            .eq([10, 11, 12, 13, 15, 16]));
+        assert_eq!(guard.len(), 1_999);
+        drop(guard);
+        assert_eq!(map.range(..=2), [(0, 0), (1, 1), (2, 2)]);
+
+        let list = Arc::into_inner(map).unwrap().into_list();
+        list.check_invariants();
+        assert_eq!(list.len(), 1_999);
+        assert!(SkipMap::<u32, ()>::new().is_empty());
+    }
+
+    #[test]
+    fn shards_under_a_mutex() {
+        let map = Arc::new(SkipMap::<u32, u32, Mutex<_>>::with_lock(3));
+        let writers: Vec<_> = (0..3u32)
+            .map(|t| {
+                let map = map.clone();
+                thread::spawn(move || {
+                    for i in 0..300 {
+                        map.insert(i * 3 + t, t);
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        assert_eq!(map.get(&7), Some(1));
+        assert_eq!(map.remove(&7), Some(1));
+        assert!(map
+            .read()
+            .iter()
+            .map(|(k, _)| *k)
+            .eq((0..900).filter(|&k| k != 7)));
+        assert_eq!(Arc::into_inner(map).unwrap().into_list().len(), 899);
+    }
+
+    #[test]
+    fn timed_calls_give_up_on_a_held_shard() {
+        let map = SkipMap::<u32, u32, Mutex<_>>::with_lock(1);
+        let short = Duration::from_millis(10);
+        assert_eq!(map.try_insert_for(1, 10, short), Ok(None));
+        assert_eq!(map.try_get_for(&1, short), Ok(Some(10)));
+
+        let guard = map.read();
+        assert_eq!(map.try_get_for(&1, short), Err(Timeout));
+        assert_eq!(map.try_insert_for(2, 20, Duration::ZERO), Err(Timeout));
+        drop(guard);
+        // Gets in once the holder lets go in time.
+        let (held, wait) = mpsc::channel();
+        thread::scope(|s| {
+            s.spawn(|| {
+                let guard = map.read();
+                held.send(()).unwrap();
+                thread::sleep(short);
+                drop(guard);
+            });
+            wait.recv().unwrap();
+            assert_eq!(
+                map.try_insert_for(1, 11, Duration::from_secs(10)),
+                Ok(Some(10))
+            );
+        });
+
+        // Readers share an RwLock shard, so only writers wait on one.
+        let map = SkipMap::with_shards(1);
+        map.insert(1, 10);
+        let guard = map.read();
+        assert_eq!(map.try_get_for(&1, short), Ok(Some(10)));
+        assert_eq!(map.try_insert_for(1, 11, short), Err(Timeout));
+        drop(guard);
+        assert_eq!(map.try_insert_for(1, 11, Duration::MAX), Ok(Some(10)));
+        assert_eq!(Timeout.to_string(), "timed out waiting for a shard lock");
+    }
+}
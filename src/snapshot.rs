@@ -0,0 +1,362 @@
+use crate::{CompactionFilter, Decision, Fnv, SkipList};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::error::Error;
+use core::fmt;
+use core::hash::Hasher;
+use std::io::{self, Read, Write};
+
+const MAGIC: &[u8; 4] = b"RSKL";
+const VERSION: u8 = 1;
+
+// How keys and values are laid out in a snapshot. decode gets back exactly
+// the bytes encode wrote, since every record carries its own length.
+pub trait SnapshotCodec: Sized {
+    fn encode(&self, out: &mut Vec<u8>);
+
+    fn decode(bytes: &[u8]) -> Option<Self>;
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    NotASnapshot,
+    Version(u8),
+    // A key or value its codec couldn't read back.
+    Record { entry: u64 },
+    Unsorted { entry: u64 },
+    Checksum { expected: u64, found: u64 },
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Io(err) => write!(f, "reading the snapshot failed: {}", err),
+            SnapshotError::NotASnapshot => f.write_str("not a skiplist snapshot"),
+            SnapshotError::Version(v) => write!(f, "unsupported snapshot version {}", v),
+            SnapshotError::Record { entry } => {
+                write!(f, "entry {} of the snapshot doesn't decode", entry)
+            }
+            SnapshotError::Unsorted { entry } => {
+                write!(f, "entry {} of the snapshot is out of order", entry)
+            }
+            SnapshotError::Checksum { expected, found } => write!(
+                f,
+                "snapshot checksum is {:#018x}, expected {:#018x}",
+                found, expected
+            ),
+        }
+    }
+}
+
+impl Error for SnapshotError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SnapshotError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(err: io::Error) -> Self {
+        SnapshotError::Io(err)
+    }
+}
+
+// The format is the magic and a version byte, then the entry count, then
+// each entry as a length-prefixed key and value, and last an FNV-1a
+// checksum of everything after the version. Lengths and counts are little
+// endian, u32 and u64. Entries are written in key order, which is what
+// lets read_from rebuild the towers in one pass instead of inserting.
+impl<K: Ord + SnapshotCodec, V: SnapshotCodec> SkipList<K, V> {
+    pub fn write_to<W: Write>(&self, w: W) -> io::Result<()> {
+        write_entries(w, self.size, self.iter(), |_, _| {})
+    }
+
+    // A snapshot of what filter keeps, with its replacements in place of the
+    // values they stand for; the list itself is left as it is. The count goes
+    // ahead of the entries, so every decision is made before writing starts.
+    pub fn write_filtered_to<W: Write, F: CompactionFilter<K, V>>(
+        &self,
+        w: W,
+        mut filter: F,
+    ) -> io::Result<()> {
+        let kept: Vec<(&K, &V, Option<V>)> = self
+            .iter()
+            .filter_map(|(key, val)| match filter.filter(key, val) {
+                Decision::Keep => Some((key, val, None)),
+                Decision::Drop => None,
+                Decision::Replace(new) => Some((key, val, Some(new))),
+            })
+            .collect();
+        let entries = kept
+            .iter()
+            .map(|(key, val, new)| (*key, new.as_ref().unwrap_or(val)));
+        write_entries(w, kept.len(), entries, |_, _| {})
+    }
+
+    pub fn read_from<R: Read>(mut r: R) -> Result<Self, SnapshotError> {
+        let mut header = [0; 5];
+        r.read_exact(&mut header).map_err(|err| match err.kind() {
+            io::ErrorKind::UnexpectedEof => SnapshotError::NotASnapshot,
+            _ => SnapshotError::Io(err),
+        })?;
+        if header[..4] != MAGIC[..] {
+            return Err(SnapshotError::NotASnapshot);
+        }
+        if header[4] != VERSION {
+            return Err(SnapshotError::Version(header[4]));
+        }
+        let mut sum = Fnv(0xcbf2_9ce4_8422_2325);
+        let count = u64::from_le_bytes(read_array(&mut r, &mut sum)?);
+        // The count isn't trusted until the checksum says so.
+        let mut entries: Vec<(K, V)> = Vec::with_capacity(count.min(1 << 16) as usize);
+        let mut buf = Vec::new();
+        // A record that doesn't decode or sort is only reported once the
+        // checksum has passed, as damage shows up as a bad checksum first.
+        let mut bad = None;
+        for entry in 0..count {
+            let key = read_record(&mut r, &mut sum, &mut buf)?;
+            let key = K::decode(key);
+            let val = read_record(&mut r, &mut sum, &mut buf)?;
+            if bad.is_some() {
+                continue;
+            }
+            let (Some(key), Some(val)) = (key, V::decode(val)) else {
+                bad = Some(SnapshotError::Record { entry });
+                continue;
+            };
+            if entries.last().is_some_and(|(last, _)| *last >= key) {
+                bad = Some(SnapshotError::Unsorted { entry });
+                continue;
+            }
+            entries.push((key, val));
+        }
+        let expected = u64::from_le_bytes(read_array(&mut r, &mut Fnv(0))?);
+        let found = sum.finish();
+        if found != expected {
+            return Err(SnapshotError::Checksum { expected, found });
+        }
+        if let Some(bad) = bad {
+            return Err(bad);
+        }
+        Ok(Self::from_sorted_iter(entries))
+    }
+}
+
+// The byte offset of the first entry, after the magic, version and count.
+pub(crate) const FIRST_ENTRY: u64 = 13;
+
+// at is told where each entry starts, for indexes over the written file.
+pub(crate) fn write_entries<'a, K: SnapshotCodec + 'a, V: SnapshotCodec + 'a>(
+    mut w: impl Write,
+    count: usize,
+    entries: impl Iterator<Item = (&'a K, &'a V)>,
+    mut at: impl FnMut(&K, u64),
+) -> io::Result<()> {
+    let mut sum = Fnv(0xcbf2_9ce4_8422_2325);
+    w.write_all(MAGIC)?;
+    w.write_all(&[VERSION])?;
+    let mut buf = Vec::new();
+    buf.extend_from_slice(&(count as u64).to_le_bytes());
+    sum.write(&buf);
+    w.write_all(&buf)?;
+    buf.clear();
+    let mut offset = FIRST_ENTRY;
+    for (key, val) in entries {
+        at(key, offset);
+        put_record(&mut buf, key)?;
+        put_record(&mut buf, val)?;
+        sum.write(&buf);
+        w.write_all(&buf)?;
+        offset += buf.len() as u64;
+        buf.clear();
+    }
+    sum.write(&buf);
+    w.write_all(&buf)?;
+    w.write_all(&sum.finish().to_le_bytes())
+}
+
+// A record whose length doesn't fit the u32 prefix can't be written at all.
+fn put_record<T: SnapshotCodec>(buf: &mut Vec<u8>, item: &T) -> io::Result<()> {
+    let at = buf.len();
+    buf.extend_from_slice(&[0; 4]);
+    item.encode(buf);
+    let len = u32::try_from(buf.len() - at - 4)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "snapshot record over 4 GiB"))?;
+    buf[at..at + 4].copy_from_slice(&len.to_le_bytes());
+    Ok(())
+}
+
+// One entry read from wherever r stands, without the checksum: for readers
+// that seek into a file they wrote themselves.
+pub(crate) fn read_entry<K: SnapshotCodec, V: SnapshotCodec>(
+    r: &mut impl Read,
+    buf: &mut Vec<u8>,
+) -> io::Result<(K, V)> {
+    let undecodable =
+        || io::Error::new(io::ErrorKind::InvalidData, "snapshot record doesn't decode");
+    let sum = &mut Fnv(0);
+    let key = K::decode(read_record(r, sum, buf)?).ok_or_else(undecodable)?;
+    let val = V::decode(read_record(r, sum, buf)?).ok_or_else(undecodable)?;
+    Ok((key, val))
+}
+
+fn read_array<const N: usize>(r: &mut impl Read, sum: &mut Fnv) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    r.read_exact(&mut bytes)?;
+    sum.write(&bytes);
+    Ok(bytes)
+}
+
+// Reads through take, so a corrupt length runs into the end of the input
+// rather than allocating whatever it says.
+fn read_record<'a>(r: &mut impl Read, sum: &mut Fnv, buf: &'a mut Vec<u8>) -> io::Result<&'a [u8]> {
+    let len = u32::from_le_bytes(read_array(r, sum)?) as u64;
+    buf.clear();
+    if r.by_ref().take(len).read_to_end(buf)? as u64 != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    sum.write(buf);
+    Ok(buf)
+}
+
+macro_rules! fixed {
+    ($($t:ty)*) => {$(
+        impl SnapshotCodec for $t {
+            fn encode(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_le_bytes());
+            }
+
+            fn decode(bytes: &[u8]) -> Option<Self> {
+                Some(<$t>::from_le_bytes(bytes.try_into().ok()?))
+            }
+        }
+    )*};
+}
+
+fixed!(u8 u16 u32 u64 u128 i8 i16 i32 i64 i128 f32 f64);
+
+impl SnapshotCodec for bool {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8);
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [0] => Some(false),
+            [1] => Some(true),
+            _ => None,
+        }
+    }
+}
+
+impl SnapshotCodec for () {
+    fn encode(&self, _: &mut Vec<u8>) {}
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        bytes.is_empty().then_some(())
+    }
+}
+
+// A tag byte, then the value if there is one.
+impl<T: SnapshotCodec> SnapshotCodec for Option<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(self.is_some() as u8);
+        if let Some(val) = self {
+            val.encode(out);
+        }
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        match bytes.split_first()? {
+            (0, []) => Some(None),
+            (1, rest) => T::decode(rest).map(Some),
+            _ => None,
+        }
+    }
+}
+
+impl SnapshotCodec for String {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self.as_bytes());
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+}
+
+impl SnapshotCodec for Vec<u8> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self);
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        Some(bytes.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SnapshotError;
+    use crate::{Decision, SkipList};
+
+    #[test]
+    fn snapshots_round_trip_and_catch_damage() {
+        let sk: SkipList<u64, String> = (0..500).map(|i| (i * 3, format!("v{}", i))).collect();
+        let mut bytes = Vec::new();
+        sk.write_to(&mut bytes).unwrap();
+        let back = SkipList::<u64, String>::read_from(&bytes[..]).unwrap();
+        back.check_invariants();
+        assert!(back.iter().eq(sk.iter()));
+        assert_eq!(back.rank(&300), 100);
+
+        let mut empty = Vec::new();
+        SkipList::<String, ()>::new().write_to(&mut empty).unwrap();
+        assert!(SkipList::<String, ()>::read_from(&empty[..])
+            .unwrap()
+            .is_empty());
+
+        let mut flipped = bytes.clone();
+        flipped[40] ^= 1;
+        assert!(matches!(
+            SkipList::<u64, String>::read_from(&flipped[..]),
+            Err(SnapshotError::Checksum { .. })
+        ));
+        assert!(matches!(
+            SkipList::<u64, String>::read_from(&bytes[..bytes.len() - 3]),
+            Err(SnapshotError::Io(_))
+        ));
+        assert!(matches!(
+            SkipList::<u64, String>::read_from(&b"RSK"[..]),
+            Err(SnapshotError::NotASnapshot)
+        ));
+        let mut newer = bytes.clone();
+        newer[4] = 9;
+        let err = SkipList::<u64, String>::read_from(&newer[..]).unwrap_err();
+        assert_eq!(err.to_string(), "unsupported snapshot version 9");
+        // A filtered snapshot drops and replaces without touching the list.
+        let mut filtered = Vec::new();
+        sk.write_filtered_to(&mut filtered, |k: &u64, _: &String| match k % 2 {
+            0 => Decision::Drop,
+            _ if *k < 30 => Decision::Replace(String::from("new")),
+            _ => Decision::Keep,
+        })
+        .unwrap();
+        let back = SkipList::<u64, String>::read_from(&filtered[..]).unwrap();
+        back.check_invariants();
+        assert_eq!(back.len(), 250);
+        assert_eq!(back.get(&3).map(String::as_str), Some("new"));
+        assert_eq!(back.get(&33).map(String::as_str), Some("v11"));
+        assert!(!back.contains_key(&6));
+        assert_eq!(sk.len(), 500);
+
+        // Keys of the wrong width don't decode.
+        assert!(matches!(
+            SkipList::<u32, String>::read_from(&bytes[..]),
+            Err(SnapshotError::Record { entry: 0 })
+        ));
+    }
+}
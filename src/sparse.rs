@@ -0,0 +1,99 @@
+use crate::SkipList;
+use alloc::vec::Vec;
+
+// Builds a SparseIndex from the records of an external file sorted by key.
+// Every record is pushed with its byte offset and every `every`-th one, starting
+// with the first, is kept as a sample.
+pub struct SparseIndexBuilder<K> {
+    every: usize,
+    until_sample: usize,
+    samples: Vec<(K, u64)>,
+}
+
+impl<K: Ord> SparseIndexBuilder<K> {
+    pub fn new(every: usize) -> Self {
+        Self {
+            every: every.max(1),
+            until_sample: 0,
+            samples: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, key: K, offset: u64) {
+        if self.until_sample == 0 {
+            self.samples.push((key, offset));
+            self.until_sample = self.every;
+        }
+        self.until_sample -= 1;
+    }
+
+    pub fn finish(self) -> SparseIndex<K> {
+        SparseIndex {
+            index: SkipList::from_unsorted_vec(self.samples),
+        }
+    }
+}
+
+pub struct SparseIndex<K> {
+    index: SkipList<K, u64>,
+}
+
+impl<K: Ord> SparseIndex<K> {
+    pub fn from_samples<I: IntoIterator<Item = (K, u64)>>(samples: I) -> Self {
+        Self {
+            index: SkipList::from_unsorted_vec(samples.into_iter().collect()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    // Offset of the last sample with a key <= key, which is where a scan for
+    // key has to start. Keys before the first sample start at the first sample.
+    pub fn seek_offset(&self, key: &K) -> u64 {
+        match self.index.floor_entry(key) {
+            Some((_, offset)) => *offset,
+            None => self.index.iter().next().map_or(0, |(_, o)| *o),
+        }
+    }
+}
+
+impl<K: Ord> FromIterator<(K, u64)> for SparseIndex<K> {
+    fn from_iter<I: IntoIterator<Item = (K, u64)>>(samples: I) -> Self {
+        Self::from_samples(samples)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SparseIndex, SparseIndexBuilder};
+
+    #[test]
+    fn seek_offset() {
+        let mut builder = SparseIndexBuilder::new(4);
+        for i in 0..100u64 {
+            builder.push(i * 10 + 5, 1000 + i * 64);
+        }
+        let index = builder.finish();
+        assert_eq!(index.len(), 25);
+
+        assert_eq!(index.seek_offset(&0), 1000);
+        assert_eq!(index.seek_offset(&5), 1000);
+        assert_eq!(index.seek_offset(&44), 1000);
+        assert_eq!(index.seek_offset(&45), 1000 + 4 * 64);
+        assert_eq!(index.seek_offset(&46), 1000 + 4 * 64);
+        assert_eq!(index.seek_offset(&10_000), 1000 + 96 * 64);
+
+        let empty: SparseIndex<u32> = SparseIndex::from_samples(Vec::new());
+        assert!(empty.is_empty());
+        assert_eq!(empty.seek_offset(&7), 0);
+
+        let collected: SparseIndex<&str> = [("b", 10), ("d", 20)].into_iter().collect();
+        assert_eq!(collected.seek_offset(&"c"), 10);
+    }
+}
@@ -0,0 +1,184 @@
+use crate::SkipList;
+use alloc::vec;
+use alloc::vec::Vec;
+
+// Interleaves x into the even bits and y into the odd bits, so points close
+// in the plane tend to get keys close in the list.
+pub fn encode(x: u32, y: u32) -> u64 {
+    spread(x) | (spread(y) << 1)
+}
+
+pub fn decode(z: u64) -> (u32, u32) {
+    (compact(z), compact(z >> 1))
+}
+
+fn spread(v: u32) -> u64 {
+    let mut v = v as u64;
+    v = (v | (v << 16)) & 0x0000_ffff_0000_ffff;
+    v = (v | (v << 8)) & 0x00ff_00ff_00ff_00ff;
+    v = (v | (v << 4)) & 0x0f0f_0f0f_0f0f_0f0f;
+    v = (v | (v << 2)) & 0x3333_3333_3333_3333;
+    (v | (v << 1)) & 0x5555_5555_5555_5555
+}
+
+fn compact(z: u64) -> u32 {
+    let mut v = z & 0x5555_5555_5555_5555;
+    v = (v | (v >> 1)) & 0x3333_3333_3333_3333;
+    v = (v | (v >> 2)) & 0x0f0f_0f0f_0f0f_0f0f;
+    v = (v | (v >> 4)) & 0x00ff_00ff_00ff_00ff;
+    v = (v | (v >> 8)) & 0x0000_ffff_0000_ffff;
+    ((v | (v >> 16)) & 0x0000_0000_ffff_ffff) as u32
+}
+
+// Inclusive on all four sides.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rect {
+    pub min_x: u32,
+    pub min_y: u32,
+    pub max_x: u32,
+    pub max_y: u32,
+}
+
+impl Rect {
+    pub fn new(x0: u32, y0: u32, x1: u32, y1: u32) -> Self {
+        Self {
+            min_x: x0.min(x1),
+            min_y: y0.min(y1),
+            max_x: x0.max(x1),
+            max_y: y0.max(y1),
+        }
+    }
+
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        self.min_x <= x && x <= self.max_x && self.min_y <= y && y <= self.max_y
+    }
+
+    pub fn z_intervals(&self) -> ZIntervals {
+        ZIntervals {
+            rect: *self,
+            stack: vec![(0, 0, 32)],
+            pending: None,
+        }
+    }
+}
+
+// Yields, in increasing order, the maximal runs [lo, hi] of Z-order keys whose
+// points lie inside the rectangle. Quadrants are visited in Z order, ones
+// fully inside become a run and ones half inside are split, so the runs are
+// produced lazily and adjacent quadrants are merged on the fly.
+pub struct ZIntervals {
+    rect: Rect,
+    stack: Vec<(u64, u64, u32)>,
+    pending: Option<(u64, u64)>,
+}
+
+impl Iterator for ZIntervals {
+    type Item = (u64, u64);
+
+    fn next(&mut self) -> Option<(u64, u64)> {
+        while let Some((x0, y0, k)) = self.stack.pop() {
+            let side = 1u64 << k;
+            let (x1, y1) = (x0 + side - 1, y0 + side - 1);
+            let r = &self.rect;
+            let (min_x, min_y) = (r.min_x as u64, r.min_y as u64);
+            let (max_x, max_y) = (r.max_x as u64, r.max_y as u64);
+            if x1 < min_x || x0 > max_x || y1 < min_y || y0 > max_y {
+                continue;
+            }
+            if min_x <= x0 && x1 <= max_x && min_y <= y0 && y1 <= max_y {
+                let lo = encode(x0 as u32, y0 as u32);
+                let hi = lo
+                    | if k == 32 {
+                        u64::MAX
+                    } else {
+                        (1u64 << (2 * k)) - 1
+                    };
+                match self.pending {
+                    Some((start, end)) if end + 1 == lo => self.pending = Some((start, hi)),
+                    Some(run) => {
+                        self.pending = Some((lo, hi));
+                        return Some(run);
+                    }
+                    None => self.pending = Some((lo, hi)),
+                }
+                continue;
+            }
+            let half = side / 2;
+            for (dx, dy) in [(half, half), (0, half), (half, 0), (0, 0)] {
+                self.stack.push((x0 + dx, y0 + dy, k - 1));
+            }
+        }
+        self.pending.take()
+    }
+}
+
+impl<V> SkipList<u64, V> {
+    pub fn range_2d(&self, rect: Rect) -> impl Iterator<Item = ((u32, u32), &V)> + '_ {
+        rect.z_intervals()
+            .flat_map(move |(lo, hi)| self.range(lo..=hi))
+            .map(|(z, v)| (decode(*z), v))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, encode, Rect};
+    use crate::SkipList;
+
+    #[test]
+    fn encode_roundtrip() {
+        assert_eq!(encode(0, 0), 0);
+        assert_eq!(encode(1, 0), 1);
+        assert_eq!(encode(0, 1), 2);
+        assert_eq!(encode(3, 3), 15);
+        assert_eq!(encode(u32::MAX, u32::MAX), u64::MAX);
+        for (x, y) in [(0, 0), (12345, 678), (u32::MAX, 0), (7, u32::MAX)] {
+            assert_eq!(decode(encode(x, y)), (x, y));
+        }
+    }
+
+    #[test]
+    fn z_intervals_cover_rect_exactly() {
+        let rect = Rect::new(3, 2, 9, 6);
+        let runs: Vec<_> = rect.z_intervals().collect();
+        assert!(runs.windows(2).all(|w| w[0].1 + 1 < w[1].0));
+        let covered: Vec<u64> = runs.iter().flat_map(|&(lo, hi)| lo..=hi).collect();
+        let mut expected: Vec<u64> = (3..=9)
+            .flat_map(|x| (2..=6).map(move |y| encode(x, y)))
+            .collect();
+        expected.sort();
+        assert_eq!(covered, expected);
+
+        assert_eq!(
+            Rect::new(0, 0, 7, 7).z_intervals().collect::<Vec<_>>(),
+            vec![(0, 63)]
+        );
+        assert_eq!(
+            Rect::new(0, 0, u32::MAX, u32::MAX)
+                .z_intervals()
+                .collect::<Vec<_>>(),
+            vec![(0, u64::MAX)]
+        );
+        assert_eq!(Rect::new(5, 5, 5, 5).z_intervals().count(), 1);
+    }
+
+    #[test]
+    fn range_2d() {
+        let mut sk = SkipList::new();
+        for x in 0..32 {
+            for y in 0..32 {
+                sk.insert(encode(x, y), x * 100 + y);
+            }
+        }
+        let rect = Rect::new(10, 4, 20, 9);
+        let mut found: Vec<_> = sk.range_2d(rect).collect();
+        assert_eq!(found.len(), 11 * 6);
+        assert!(found
+            .iter()
+            .all(|((x, y), v)| rect.contains(*x, *y) && **v == x * 100 + y));
+        found.sort();
+        found.dedup();
+        assert_eq!(found.len(), 11 * 6);
+        assert_eq!(sk.range_2d(Rect::new(100, 100, 200, 200)).count(), 0);
+    }
+}
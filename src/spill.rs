@@ -0,0 +1,378 @@
+use crate::snapshot::{read_entry, write_entries, FIRST_ENTRY};
+use crate::{
+    FirstWins, InlineSize, MergeIter, SkipList, SnapshotCodec, SparseIndex, SparseIndexBuilder,
+    WeighedSkipList, WeightOf,
+};
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::mem;
+use core::ops::{Bound, RangeBounds};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Take, Write};
+use std::path::{Path, PathBuf};
+
+// Every how many entries of a run the index keeps a key, so a lookup reads
+// at most this many records off disk.
+const SAMPLE_EVERY: usize = 16;
+
+// Holds more than fits in memory by keeping the newest writes in a weighed
+// SkipList and moving all of it into a sorted run on disk once it weighs more
+// than the budget, the way an LSM memtable is flushed. Runs are snapshot
+// files, with an in-memory sparse index each. Reads look in memory first,
+// then the runs from newest to oldest, and iteration merges them all.
+//
+// A remove is kept as a tombstone until the next spill carries it to disk, so
+// it still hides the key in older runs. Anything read from a run is decoded
+// afresh, so reads hand out owned values, and a failing disk shows up as an
+// io::Error. Runs are never merged with each other; the files go when the
+// list is dropped.
+pub struct SpillSkipList<K, V, W = InlineSize> {
+    mem: WeighedSkipList<K, Option<V>, Tombstones<W>>,
+    budget: usize,
+    dir: PathBuf,
+    // Oldest first.
+    runs: Vec<Run<K>>,
+    spilled: u64,
+}
+
+struct Run<K> {
+    path: PathBuf,
+    index: SparseIndex<K>,
+    // Where the entries end and the checksum starts.
+    end: u64,
+}
+
+// Weighs a tombstone as a bare key.
+struct Tombstones<W>(W);
+
+impl<K, V, W: WeightOf<K, V>> WeightOf<K, Option<V>> for Tombstones<W> {
+    fn weight(&self, key: &K, val: &Option<V>) -> usize {
+        match val {
+            Some(val) => self.0.weight(key, val),
+            None => mem::size_of::<K>(),
+        }
+    }
+}
+
+type Source<'a, K, V> = Box<dyn Iterator<Item = (K, Option<V>)> + 'a>;
+
+// Yields Err once, and then nothing, if reading a run fails partway.
+pub struct SpillIter<'a, K, V> {
+    merged: MergeIter<K, Option<V>, Source<'a, K, V>, FirstWins>,
+    failed: Rc<RefCell<Option<io::Error>>>,
+    done: bool,
+}
+
+// The entries of one run from a start offset on, up to the end of a range.
+struct RunIter<K, V> {
+    file: Take<BufReader<File>>,
+    buf: Vec<u8>,
+    start: Bound<K>,
+    end: Bound<K>,
+    failed: Rc<RefCell<Option<io::Error>>>,
+    _marker: core::marker::PhantomData<V>,
+}
+
+impl<K, V> SpillSkipList<K, V>
+where
+    K: Ord + Clone + SnapshotCodec,
+    V: SnapshotCodec,
+{
+    pub fn new(dir: impl AsRef<Path>, budget: usize) -> io::Result<Self> {
+        Self::with_weigher(dir, budget, InlineSize)
+    }
+}
+
+impl<K, V, W> SpillSkipList<K, V, W>
+where
+    K: Ord + Clone + SnapshotCodec,
+    V: SnapshotCodec,
+    W: WeightOf<K, V>,
+{
+    // budget is in whatever weigh counts, as for WeighedSkipList. Runs are
+    // written into dir, which is made if it's missing.
+    pub fn with_weigher(dir: impl AsRef<Path>, budget: usize, weigh: W) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            mem: WeighedSkipList::new(Tombstones(weigh)),
+            budget,
+            dir,
+            runs: Vec::new(),
+            spilled: 0,
+        })
+    }
+
+    // How much of the budget the entries in memory take up.
+    pub fn memory_bytes(&self) -> usize {
+        self.mem.approximate_bytes()
+    }
+
+    pub fn runs(&self) -> usize {
+        self.runs.len()
+    }
+
+    // Doesn't say what it replaced: finding out would mean reading the runs.
+    pub fn insert(&mut self, key: K, val: V) -> io::Result<()> {
+        self.mem.insert(key, Some(val));
+        self.spill_if_over()
+    }
+
+    pub fn remove(&mut self, key: &K) -> io::Result<()> {
+        if self.runs.is_empty() {
+            self.mem.remove(key);
+            return Ok(());
+        }
+        self.mem.insert(key.clone(), None);
+        self.spill_if_over()
+    }
+
+    pub fn get(&self, key: &K) -> io::Result<Option<V>>
+    where
+        V: Clone,
+    {
+        if let Some(slot) = self.mem.get(key) {
+            return Ok(slot.clone());
+        }
+        for run in self.runs.iter().rev() {
+            if let Some(slot) = run.get::<V>(key)? {
+                return Ok(slot);
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn contains_key(&self, key: &K) -> io::Result<bool>
+    where
+        V: Clone,
+    {
+        Ok(self.get(key)?.is_some())
+    }
+
+    pub fn iter(&self) -> io::Result<SpillIter<'_, K, V>>
+    where
+        V: Clone,
+    {
+        self.range(..)
+    }
+
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> io::Result<SpillIter<'_, K, V>>
+    where
+        V: Clone,
+    {
+        let (start, end) = (range.start_bound().cloned(), range.end_bound().cloned());
+        let failed = Rc::new(RefCell::new(None));
+        let mut sources: Vec<Source<'_, K, V>> = Vec::with_capacity(self.runs.len() + 1);
+        sources.push(Box::new(
+            self.mem
+                .range((start.as_ref(), end.as_ref()))
+                .map(|(k, v)| (k.clone(), v.clone())),
+        ));
+        for run in self.runs.iter().rev() {
+            let iter = run.iter(start.clone(), end.clone(), failed.clone())?;
+            sources.push(Box::new(iter));
+        }
+        Ok(SpillIter {
+            merged: MergeIter::new(sources, FirstWins),
+            failed,
+            done: false,
+        })
+    }
+
+    // Writes everything in memory out as the newest run and empties memory.
+    // Tombstones only go along when there's an older run for them to shadow.
+    // Nothing is dropped from memory unless the whole run made it to disk.
+    pub fn spill(&mut self) -> io::Result<()> {
+        if self.mem.is_empty() {
+            return Ok(());
+        }
+        let path = self.dir.join(format!("run-{:06}.rskl", self.spilled));
+        let keep_tombstones = !self.runs.is_empty();
+        let list: &SkipList<K, Option<V>> = self.mem.list();
+        let entries: Vec<_> = list
+            .iter()
+            .filter(|(_, val)| keep_tombstones || val.is_some())
+            .collect();
+        let mut index = SparseIndexBuilder::new(SAMPLE_EVERY);
+        let mut end = FIRST_ENTRY;
+        let written = File::create(&path).and_then(|file| {
+            let mut w = BufWriter::new(file);
+            write_entries(&mut w, entries.len(), entries.iter().copied(), |key, at| {
+                index.push(key.clone(), at);
+            })?;
+            w.flush()?;
+            end = w.get_ref().metadata()?.len() - 8;
+            w.get_ref().sync_all()
+        });
+        if let Err(err) = written {
+            let _ = fs::remove_file(&path);
+            return Err(err);
+        }
+        self.spilled += 1;
+        self.runs.push(Run {
+            path,
+            index: index.finish(),
+            end,
+        });
+        self.mem.drain();
+        Ok(())
+    }
+
+    fn spill_if_over(&mut self) -> io::Result<()> {
+        if self.mem.approximate_bytes() > self.budget {
+            self.spill()?;
+        }
+        Ok(())
+    }
+}
+
+impl<K: Ord + SnapshotCodec> Run<K> {
+    fn reader(&self, key: Option<&K>) -> io::Result<Take<BufReader<File>>> {
+        let at = match key {
+            Some(key) if !self.index.is_empty() => self.index.seek_offset(key),
+            _ => FIRST_ENTRY,
+        };
+        let mut file = BufReader::new(File::open(&self.path)?);
+        file.seek(SeekFrom::Start(at))?;
+        Ok(file.take(self.end - at))
+    }
+
+    // Some(None) is a tombstone, None means the run doesn't have the key.
+    fn get<V: SnapshotCodec>(&self, key: &K) -> io::Result<Option<Option<V>>> {
+        let mut file = self.reader(Some(key))?;
+        let mut buf = Vec::new();
+        while file.limit() > 0 {
+            let (k, v): (K, Option<V>) = read_entry(&mut file, &mut buf)?;
+            if k >= *key {
+                return Ok((k == *key).then_some(v));
+            }
+        }
+        Ok(None)
+    }
+
+    fn iter<V>(
+        &self,
+        start: Bound<K>,
+        end: Bound<K>,
+        failed: Rc<RefCell<Option<io::Error>>>,
+    ) -> io::Result<RunIter<K, V>> {
+        let from = match &start {
+            Bound::Included(key) | Bound::Excluded(key) => Some(key),
+            Bound::Unbounded => None,
+        };
+        Ok(RunIter {
+            file: self.reader(from)?,
+            buf: Vec::new(),
+            start,
+            end,
+            failed,
+            _marker: core::marker::PhantomData,
+        })
+    }
+}
+
+impl<K: Ord + SnapshotCodec, V: SnapshotCodec> Iterator for RunIter<K, V> {
+    type Item = (K, Option<V>);
+
+    fn next(&mut self) -> Option<(K, Option<V>)> {
+        while self.file.limit() > 0 {
+            let (key, val) = match read_entry(&mut self.file, &mut self.buf) {
+                Ok(entry) => entry,
+                Err(err) => {
+                    *self.failed.borrow_mut() = Some(err);
+                    break;
+                }
+            };
+            if !(self.start.as_ref(), Bound::Unbounded).contains(&key) {
+                continue;
+            }
+            if (Bound::Unbounded, self.end.as_ref()).contains(&key) {
+                return Some((key, val));
+            }
+            break;
+        }
+        self.file.set_limit(0);
+        None
+    }
+}
+
+impl<K: Ord, V> Iterator for SpillIter<'_, K, V> {
+    type Item = io::Result<(K, V)>;
+
+    fn next(&mut self) -> Option<io::Result<(K, V)>> {
+        while !self.done {
+            let next = self.merged.next();
+            if let Some(err) = self.failed.borrow_mut().take() {
+                self.done = true;
+                return Some(Err(err));
+            }
+            match next {
+                Some((key, Some(val))) => return Some(Ok((key, val))),
+                Some((_, None)) => {}
+                None => self.done = true,
+            }
+        }
+        None
+    }
+}
+
+impl<K, V, W> Drop for SpillSkipList<K, V, W> {
+    fn drop(&mut self) {
+        for run in &self.runs {
+            let _ = fs::remove_file(&run.path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SpillSkipList;
+    use rand::prelude::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn spilled_runs_read_back_under_memory() {
+        let dir = std::env::temp_dir().join(format!("rskl-spill-{}", std::process::id()));
+        let mut sk = SpillSkipList::new(&dir, 4_096).unwrap();
+        let mut model = BTreeMap::new();
+        let mut rng = StdRng::seed_from_u64(229);
+        for i in 0..5_000u32 {
+            let key = rng.gen_range(0..2_000u32);
+            if i % 5 == 4 {
+                sk.remove(&key).unwrap();
+                model.remove(&key);
+            } else {
+                sk.insert(key, i as u64).unwrap();
+                model.insert(key, i as u64);
+            }
+            assert!(sk.memory_bytes() <= 4_096);
+        }
+        assert!(sk.runs() > 5, "{}", sk.runs());
+
+        for key in 0..2_010 {
+            assert_eq!(sk.get(&key).unwrap(), model.get(&key).copied(), "{}", key);
+        }
+        let all: Vec<_> = sk.iter().unwrap().map(Result::unwrap).collect();
+        assert!(all.iter().map(|(k, v)| (k, v)).eq(model.iter()));
+        let some: Vec<_> = sk.range(500..=900).unwrap().map(Result::unwrap).collect();
+        assert!(some.iter().map(|(k, v)| (k, v)).eq(model.range(500..=900)));
+
+        // A spill of what's left still reads the same.
+        sk.spill().unwrap();
+        assert_eq!(sk.memory_bytes(), 0);
+        assert!(sk
+            .range(..100)
+            .unwrap()
+            .map(Result::unwrap)
+            .eq(model.range(..100).map(|(k, v)| (*k, *v))));
+
+        let runs: Vec<_> = std::fs::read_dir(&dir).unwrap().collect();
+        assert_eq!(runs.len(), sk.runs());
+        drop(sk);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+        std::fs::remove_dir(&dir).unwrap();
+    }
+}
@@ -0,0 +1,510 @@
+use crate::{stable_hash, Node, Shape, SkipList, MAX_LEVEL};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::error::Error;
+use core::fmt;
+use core::hash::Hash;
+use core::mem;
+use core::ops::RangeBounds;
+use core::ptr::NonNull;
+
+// The first thing verify found wrong. Entries count from 1 along level 0.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CorruptionReport {
+    Height {
+        entry: usize,
+        height: usize,
+        level: usize,
+    },
+    Prev {
+        entry: usize,
+    },
+    Order {
+        entry: usize,
+    },
+    Skipped {
+        level: usize,
+        entry: usize,
+    },
+    Span {
+        level: usize,
+        entry: usize,
+        span: usize,
+        expected: usize,
+    },
+    Size {
+        size: usize,
+        linked: usize,
+    },
+    RunsOn {
+        level: usize,
+    },
+    EmptyLevel {
+        level: usize,
+    },
+    Parked {
+        height: usize,
+        expected: usize,
+    },
+    Checksum {
+        expected: u64,
+        found: u64,
+    },
+}
+
+impl fmt::Display for CorruptionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            CorruptionReport::Height {
+                entry,
+                height,
+                level,
+            } => write!(
+                f,
+                "entry {} has height {} with the list at level {}",
+                entry, height, level
+            ),
+            CorruptionReport::Prev { entry } => {
+                write!(f, "entry {} has the wrong prev link", entry)
+            }
+            CorruptionReport::Order { entry } => write!(f, "entry {} is out of order", entry),
+            CorruptionReport::Skipped { level, entry } => {
+                write!(f, "level {} skips entry {}", level, entry)
+            }
+            CorruptionReport::Span {
+                level,
+                entry,
+                span,
+                expected,
+            } => write!(
+                f,
+                "wrong span on level {} before entry {}: {} instead of {}",
+                level, entry, span, expected
+            ),
+            CorruptionReport::Size { size, linked } => write!(
+                f,
+                "size {} does not match the entries linked (at least {})",
+                size, linked
+            ),
+            CorruptionReport::RunsOn { level } => {
+                write!(f, "level {} runs on past its last entry", level)
+            }
+            CorruptionReport::EmptyLevel { level } => write!(f, "level {} is empty", level),
+            CorruptionReport::Parked { height, expected } => write!(
+                f,
+                "a node of height {} is parked with those of height {}",
+                height, expected
+            ),
+            CorruptionReport::Checksum { expected, found } => {
+                write!(f, "key checksum is {:#x}, expected {:#x}", found, expected)
+            }
+        }
+    }
+}
+
+impl Error for CorruptionReport {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SkipListStats {
+    pub len: usize,
+    pub level: usize,
+    // per_level[l] is how many nodes have a link on level l, so per_level[0]
+    // is len and each level should hold about half of the one below.
+    pub per_level: Vec<usize>,
+    // heights[h - 1] is how many nodes have exactly h levels.
+    pub heights: Vec<usize>,
+    // Mean number of keys compared when looking up each present key.
+    pub avg_search_path: f64,
+}
+
+impl<K, V> SkipList<K, V> {
+    // Bytes held in nodes: the head, every entry's node and boxed value, and
+    // emptied nodes parked for reuse. Heap memory owned by keys and values
+    // isn't counted; WeighedSkipList::memory_usage adds it.
+    pub fn memory_usage(&self) -> usize {
+        let shape = self.shape();
+        let head = Shape::plain(shape.align);
+        let mut total = Node::<K, V>::layout(self.levels.max, head).size();
+        let mut x = unsafe { self.head.as_ref().tower[0] };
+        while let Some(node_ptr) = x {
+            unsafe {
+                let node = node_ptr.as_ref();
+                total += Node::<K, V>::footprint(node.height(), shape);
+                if node.boxed {
+                    total += mem::size_of::<V>();
+                }
+                x = node.tower[0];
+            }
+        }
+        for (h, parked) in self.free.iter().enumerate() {
+            total += parked.len() * Node::<K, V>::footprint(h + 1, shape);
+        }
+        total
+    }
+}
+
+impl<K: Ord, V> SkipList<K, V> {
+    // Walks the whole list and repeats the search for every key, so it costs
+    // O(n log n).
+    pub fn stats(&self) -> SkipListStats {
+        let mut per_level = vec![0; self.level];
+        let mut heights = vec![0; self.level];
+        let mut compared = 0;
+        let mut x = unsafe { self.head.as_ref().tower[0] };
+        while let Some(node_ptr) = x {
+            unsafe {
+                let node = node_ptr.as_ref();
+                heights[node.height() - 1] += 1;
+                for count in &mut per_level[..node.height()] {
+                    *count += 1;
+                }
+                compared += self.search_path(&node.key);
+                x = node.tower[0];
+            }
+        }
+        SkipListStats {
+            len: self.size,
+            level: self.level,
+            per_level,
+            heights,
+            avg_search_path: if self.size == 0 {
+                0.0
+            } else {
+                compared as f64 / self.size as f64
+            },
+        }
+    }
+
+    // Spans make the count exact, in O(log n) like the byte estimate.
+    pub fn estimate_range_len<Q, R>(&self, range: R) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        self.range_len(range)
+    }
+
+    // The entries in range times the expected node size for the list's
+    // tower heights, and the boxes of boxed values, without visiting them.
+    // Heap memory owned by keys and values isn't counted, as in memory_usage.
+    pub fn estimate_range_bytes<Q, R>(&self, range: R) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let mut per_entry = self.expected_node_size();
+        if self.boxed {
+            per_entry += mem::size_of::<V>() as f64;
+        }
+        (self.estimate_range_len(range) as f64 * per_entry) as usize
+    }
+
+    // The mean node size under the list's tower height distribution.
+    pub(crate) fn expected_node_size(&self) -> f64 {
+        let climb = self.levels.climb as f64 / (1u64 << 32) as f64;
+        let shape = self.shape();
+        let mut expected = 0.0;
+        let mut reach = 1.0;
+        for h in 1..=self.levels.max {
+            let stop = if h == self.levels.max {
+                1.0
+            } else {
+                1.0 - climb
+            };
+            expected += reach * stop * Node::<K, V>::footprint(h, shape) as f64;
+            reach *= climb;
+        }
+        expected
+    }
+
+    // Panics with what verify reports. Costs O(n); meant for tests and
+    // fuzzers that check the list against a model after every operation.
+    pub fn check_invariants(&self) {
+        if let Err(report) = self.verify() {
+            panic!("{}", report);
+        }
+    }
+
+    // Checks that keys rise strictly along level 0, every node is linked on
+    // exactly the levels of its tower, spans and prev match the positions on
+    // level 0, and size and level agree with what is linked, stopping at the
+    // first thing wrong. Walks no further than size entries, so a cycle in
+    // the links is reported rather than followed, though links into memory
+    // that holds no node can't be caught.
+    pub fn verify(&self) -> Result<(), CorruptionReport> {
+        let mut pred = [self.head; MAX_LEVEL];
+        let mut pred_rank = [0; MAX_LEVEL];
+        let mut rank = 0;
+        let mut prev: Option<NonNull<Node<K, V>>> = None;
+        unsafe {
+            let mut x = self.head.as_ref().tower[0];
+            while let Some(node_ptr) = x {
+                rank += 1;
+                if rank > self.size {
+                    return Err(CorruptionReport::Size {
+                        size: self.size,
+                        linked: rank,
+                    });
+                }
+                let node = node_ptr.as_ref();
+                let height = node.height();
+                if !(1..=self.level).contains(&height) {
+                    return Err(CorruptionReport::Height {
+                        entry: rank,
+                        height,
+                        level: self.level,
+                    });
+                }
+                if node.prev != prev {
+                    return Err(CorruptionReport::Prev { entry: rank });
+                }
+                if prev.is_some_and(|prev| prev.as_ref().key >= node.key) {
+                    return Err(CorruptionReport::Order { entry: rank });
+                }
+                for l in 0..height {
+                    if pred[l].as_ref().tower[l] != Some(node_ptr) {
+                        return Err(CorruptionReport::Skipped {
+                            level: l,
+                            entry: rank,
+                        });
+                    }
+                    let span = pred[l].as_ref().span(l);
+                    if span != rank - pred_rank[l] {
+                        return Err(CorruptionReport::Span {
+                            level: l,
+                            entry: rank,
+                            span,
+                            expected: rank - pred_rank[l],
+                        });
+                    }
+                    pred[l] = node_ptr;
+                    pred_rank[l] = rank;
+                }
+                prev = x;
+                x = node.tower[0];
+            }
+            if rank != self.size {
+                return Err(CorruptionReport::Size {
+                    size: self.size,
+                    linked: rank,
+                });
+            }
+            for (l, last) in pred.iter().enumerate().take(self.levels.max) {
+                if last.as_ref().tower[l].is_some() {
+                    return Err(CorruptionReport::RunsOn { level: l });
+                }
+            }
+            if self.level > 1 && pred[self.level - 1] == self.head {
+                return Err(CorruptionReport::EmptyLevel {
+                    level: self.level - 1,
+                });
+            }
+        }
+        for (h, parked) in self.free.iter().enumerate() {
+            for node_ptr in parked {
+                let height = unsafe { node_ptr.as_ref().height() };
+                if height != h + 1 {
+                    return Err(CorruptionReport::Parked {
+                        height,
+                        expected: h + 1,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Folds the hash of every key in order, so storing it alongside a list
+    // kept in shared or persistent memory lets verify_checksum spot keys that
+    // changed underneath it. The same keys always give the same checksum.
+    pub fn key_checksum(&self) -> u64
+    where
+        K: Hash,
+    {
+        self.keys().fold(0xcbf2_9ce4_8422_2325, |sum, key| {
+            (sum.rotate_left(5) ^ stable_hash(key)).wrapping_mul(0x0100_0000_01b3)
+        })
+    }
+
+    pub fn verify_checksum(&self, expected: u64) -> Result<(), CorruptionReport>
+    where
+        K: Hash,
+    {
+        self.verify()?;
+        let found = self.key_checksum();
+        if found != expected {
+            return Err(CorruptionReport::Checksum { expected, found });
+        }
+        Ok(())
+    }
+
+    // The keys find_gt_or_eq_node would compare on its way to key.
+    fn search_path(&self, key: &K) -> usize {
+        let mut compared = 0;
+        let mut x = self.head;
+        unsafe {
+            for l in (0..self.level).rev() {
+                while let Some(next) = x.as_ref().tower[l] {
+                    compared += 1;
+                    if next.as_ref().key < *key {
+                        x = next;
+                    } else {
+                        break;
+                    }
+                }
+            }
+        }
+        compared
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CorruptionReport, Node, SkipList, SkipListBuilder};
+
+    #[test]
+    fn invariants_hold_against_a_model() {
+        use rand::prelude::*;
+        use std::collections::BTreeMap;
+        let mut rng = StdRng::seed_from_u64(11);
+        let mut sk = SkipList::with_arena(1 << 16);
+        let mut model = BTreeMap::new();
+        for _ in 0..3_000 {
+            let key = rng.gen_range(0..300u32);
+            match rng.gen_range(0..6) {
+                0..=2 => {
+                    sk.insert(key, key);
+                    model.insert(key, key);
+                }
+                3 => assert_eq!(sk.remove(&key), model.remove(&key)),
+                4 => {
+                    let end = key + rng.gen_range(0..10);
+                    sk.remove_range(key..end);
+                    model.retain(|k, _| !(key..end).contains(k));
+                }
+                _ => assert_eq!(sk.pop_last(), model.pop_last()),
+            }
+            sk.check_invariants();
+            assert!(sk.iter().eq(model.iter()));
+        }
+        sk.rebalance();
+        sk.check_invariants();
+        sk.clear_retain_capacity();
+        sk.check_invariants();
+    }
+
+    #[test]
+    fn verify_reports_damage_and_checksums() {
+        let sk: SkipList<u32, ()> = (0..100).map(|i| (i, ())).collect();
+        assert_eq!(sk.verify(), Ok(()));
+        let sum = sk.key_checksum();
+        assert_eq!(sk.verify_checksum(sum), Ok(()));
+        assert_eq!(sk.clone().key_checksum(), sum);
+        unsafe {
+            let node = sk.find_node(&50).unwrap();
+            (*node.as_ptr()).key = 5;
+        }
+        assert_eq!(sk.verify(), Err(CorruptionReport::Order { entry: 51 }));
+        unsafe {
+            let node = sk.node_at(50).unwrap();
+            (*node.as_ptr()).key = 50;
+        }
+        assert_eq!(sk.verify(), Ok(()));
+        let mut last = sk.find_node(&49).unwrap();
+        let next = unsafe { last.as_ref().tower[0] };
+        unsafe { last.as_mut().tower[0] = None };
+        assert_eq!(
+            sk.verify(),
+            Err(CorruptionReport::Size {
+                size: 100,
+                linked: 50
+            })
+        );
+        unsafe { last.as_mut().tower[0] = sk.find_node(&2) };
+        assert!(sk.verify().is_err());
+        unsafe { last.as_mut().tower[0] = next };
+        assert_eq!(sk.verify(), Ok(()));
+
+        let sk: SkipList<u32, ()> = (0..100).map(|i| (i, ())).collect();
+        let mut other: SkipList<u32, ()> = (0..100).map(|i| (i, ())).collect();
+        other.remove(&7);
+        other.insert(107, ());
+        match sk.verify_checksum(other.key_checksum()) {
+            Err(err @ CorruptionReport::Checksum { .. }) => {
+                assert!(err.to_string().starts_with("key checksum is"));
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "wrong span on level 0 before entry 2")]
+    fn check_invariants_catches_bad_spans() {
+        let sk: SkipList<u32, ()> = (0..4).map(|i| (i, ())).collect();
+        unsafe {
+            let first = sk.head.as_ref().tower[0].unwrap();
+            (*first.as_ptr()).set_span(0, 5);
+        }
+        sk.check_invariants();
+    }
+
+    #[test]
+    fn levels_halve() {
+        let mut sk = SkipList::new();
+        for i in 0..20_000u32 {
+            sk.insert(i, ());
+        }
+        let stats = sk.stats();
+        assert_eq!(stats.len, 20_000);
+        assert_eq!(stats.per_level[0], 20_000);
+        assert_eq!(stats.heights.iter().sum::<usize>(), 20_000);
+        assert_eq!(stats.per_level.len(), stats.level);
+        for l in 1..6 {
+            let ratio = stats.per_level[l] as f64 / stats.per_level[l - 1] as f64;
+            assert!((0.4..0.6).contains(&ratio), "level {}: {}", l, ratio);
+        }
+        // About 2 log2(n) for p = 1/2.
+        assert!(stats.avg_search_path > 10.0 && stats.avg_search_path < 45.0);
+
+        let flat: SkipList<u32, ()> = SkipListBuilder::new().max_level(1).build();
+        assert_eq!(flat.stats().avg_search_path, 0.0);
+    }
+
+    #[test]
+    fn range_estimates() {
+        let mut sk = SkipList::new();
+        for i in 0..20_000u64 {
+            sk.insert(i, i);
+        }
+        assert_eq!(sk.estimate_range_len(100..200), 100);
+        assert_eq!(sk.estimate_range_len(..=9), 10);
+        assert_eq!(sk.estimate_range_len(30_000..), 0);
+        let all = sk.estimate_range_bytes(..);
+        let actual = sk.memory_usage();
+        let ratio = all as f64 / actual as f64;
+        assert!((0.95..1.05).contains(&ratio), "{} vs {}", all, actual);
+        let half = sk.estimate_range_bytes(..10_000);
+        assert!((all / 2).abs_diff(half) <= 1, "{} vs {}", half, all);
+    }
+
+    #[test]
+    fn memory_usage_counts_nodes() {
+        let mut sk = SkipList::new();
+        let empty = sk.memory_usage();
+        sk.insert(1u64, 1u64);
+        let one = Node::<u64, u64>::layout(
+            unsafe { sk.head.as_ref().tower[0].unwrap().as_ref().height() },
+            sk.shape(),
+        );
+        assert_eq!(sk.memory_usage(), empty + one.size());
+        for i in 2..1000 {
+            sk.insert(i, i);
+        }
+        let per_node = (sk.memory_usage() - empty) as f64 / 1000.0;
+        // Header plus two links and spans on average.
+        assert!(per_node > 40.0 && per_node < 80.0, "{}", per_node);
+    }
+}
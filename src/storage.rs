@@ -0,0 +1,180 @@
+use crate::arena::Arena;
+use crate::builder::{AllocNodes, GlobalNodes};
+use crate::sync::Lock;
+use crate::{AllocError, Node, Shape, SkipList};
+use alloc::alloc::{alloc, dealloc, GlobalAlloc, Layout};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::ptr::NonNull;
+
+// Where a list gets the memory for its nodes. The list hands each node back
+// through dealloc once it's done with it, unless frees_nodes says nodes are
+// only freed all together, as an arena's are; then emptied nodes are kept for
+// reuse instead, and the memory is the storage's to free when it's dropped.
+// Lists split off one share its storage, so it works through &self. The
+// safety contract is GlobalAlloc's.
+#[allow(clippy::missing_safety_doc)]
+pub trait Storage: Send + Sync {
+    // Null when there's no memory to be had.
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8;
+
+    // ptr came from alloc with the same layout.
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout);
+
+    fn frees_nodes(&self) -> bool {
+        true
+    }
+
+    // Sets aside size bytes aligned to align, so that allocations adding up
+    // to that many don't fail. Storage that goes to an allocator for every
+    // node has nothing to set aside.
+    fn reserve(&self, size: usize, align: usize) -> Result<(), AllocError> {
+        let _ = (size, align);
+        Ok(())
+    }
+}
+
+impl Storage for GlobalNodes {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        dealloc(ptr, layout)
+    }
+}
+
+impl<A: GlobalAlloc + Send + Sync> Storage for AllocNodes<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.0.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.0.dealloc(ptr, layout)
+    }
+}
+
+impl Storage for Lock<Arena> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.with(|arena| arena.alloc(layout))
+            .map_or(core::ptr::null_mut(), |ptr| ptr.as_ptr())
+    }
+
+    unsafe fn dealloc(&self, _: *mut u8, _: Layout) {}
+
+    fn frees_nodes(&self) -> bool {
+        false
+    }
+
+    fn reserve(&self, size: usize, align: usize) -> Result<(), AllocError> {
+        self.with(|arena| arena.reserve(size, align))
+            .ok_or(AllocError)
+    }
+}
+
+// A list's storage as the list keeps it. The global allocator needs no Arc,
+// and the arena stays typed so its chunks can be looked at.
+#[derive(Clone)]
+pub(crate) enum Nodes {
+    Global,
+    Arena(Arc<Lock<Arena>>),
+    Custom(Arc<dyn Storage>),
+}
+
+impl Nodes {
+    pub(crate) fn storage(&self) -> &dyn Storage {
+        match self {
+            Nodes::Global => &GlobalNodes,
+            Nodes::Arena(arena) => &**arena,
+            Nodes::Custom(storage) => &**storage,
+        }
+    }
+
+    // Arena chunks are sized for entries, so an arena list's head, which
+    // lives as long as the list does, comes from the global allocator.
+    pub(crate) fn head_storage(&self) -> &dyn Storage {
+        match self {
+            Nodes::Arena(_) => &GlobalNodes,
+            _ => self.storage(),
+        }
+    }
+
+    // Whether a node from one list can be freed by the other.
+    pub(crate) fn same(&self, other: &Nodes) -> bool {
+        match (self, other) {
+            (Nodes::Global, Nodes::Global) => true,
+            (Nodes::Arena(a), Nodes::Arena(b)) => Arc::ptr_eq(a, b),
+            (Nodes::Custom(a), Nodes::Custom(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl<K, V> SkipList<K, V> {
+    // Moves an empty list, head and all, onto other storage.
+    pub(crate) fn set_nodes(&mut self, nodes: Nodes) {
+        debug_assert!(self.size == 0 && self.free.iter().all(Vec::is_empty));
+        let align = self.layout.align();
+        let head = Node::alloc_in(self.levels.max, Shape::plain(align), nodes.head_storage());
+        let head = NonNull::new(head).expect("skiplist node allocation failed");
+        unsafe { Node::release(self.head, align, self.nodes.head_storage()) };
+        self.head = head;
+        self.nodes = nodes;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Storage;
+    use crate::{SkipList, SkipListBuilder};
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::sync::{Arc, Mutex};
+
+    // Hands out memory from a fixed budget, and counts the blocks out.
+    #[derive(Default)]
+    struct Budget {
+        left: Mutex<(usize, usize)>,
+    }
+
+    struct Metered(Arc<Budget>);
+
+    impl Storage for Metered {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let mut left = self.0.left.lock().unwrap();
+            if left.0 < layout.size() {
+                return std::ptr::null_mut();
+            }
+            *left = (left.0 - layout.size(), left.1 + 1);
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            let mut left = self.0.left.lock().unwrap();
+            *left = (left.0 + layout.size(), left.1 - 1);
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[test]
+    fn nodes_come_from_the_storage() {
+        let budget = Arc::new(Budget::default());
+        *budget.left.lock().unwrap() = (16 * 1024, 0);
+        let mut sk: SkipList<u32, u32> = SkipListBuilder::new()
+            .storage(Metered(budget.clone()))
+            .build();
+        let mut n = 0;
+        while sk.insert_fallible(n, n).is_ok() {
+            n += 1;
+        }
+        assert!(n > 100, "{}", n);
+        assert_eq!(budget.left.lock().unwrap().1, n as usize + 1);
+        assert!((0..50).all(|i| sk.remove(&i).is_some()));
+        assert_eq!(sk.insert_fallible(n, n), Ok(None));
+
+        let tail = sk.split_at_index(sk.len() / 2);
+        sk.check_invariants();
+        tail.check_invariants();
+        drop((sk, tail));
+        assert_eq!(*budget.left.lock().unwrap(), (16 * 1024, 0));
+    }
+}
@@ -0,0 +1,69 @@
+// Guards the arena and seeded rng that split-off lists share. With std this is
+// a Mutex; without it, a spin lock, which is plenty for a lock that is only
+// contended when lists sharing one are used from several threads at once.
+#[cfg(feature = "std")]
+pub(crate) struct Lock<T: ?Sized>(std::sync::Mutex<T>);
+
+#[cfg(feature = "std")]
+impl<T> Lock<T> {
+    pub(crate) fn new(value: T) -> Self {
+        Lock(std::sync::Mutex::new(value))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: ?Sized> Lock<T> {
+    pub(crate) fn with<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        f(&mut self
+            .0
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner))
+    }
+}
+
+#[cfg(not(feature = "std"))]
+pub(crate) struct Lock<T: ?Sized> {
+    locked: core::sync::atomic::AtomicBool,
+    value: core::cell::UnsafeCell<T>,
+}
+
+#[cfg(not(feature = "std"))]
+unsafe impl<T: ?Sized + Send> Send for Lock<T> {}
+#[cfg(not(feature = "std"))]
+unsafe impl<T: ?Sized + Send> Sync for Lock<T> {}
+
+#[cfg(not(feature = "std"))]
+impl<T> Lock<T> {
+    pub(crate) fn new(value: T) -> Self {
+        Lock {
+            locked: core::sync::atomic::AtomicBool::new(false),
+            value: core::cell::UnsafeCell::new(value),
+        }
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl<T: ?Sized> Lock<T> {
+    pub(crate) fn with<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        use core::sync::atomic::Ordering;
+
+        // Unlocks on the way out, unwinding included.
+        struct Unlock<'a>(&'a core::sync::atomic::AtomicBool);
+
+        impl Drop for Unlock<'_> {
+            fn drop(&mut self) {
+                self.0.store(false, Ordering::Release);
+            }
+        }
+
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            core::hint::spin_loop();
+        }
+        let _unlock = Unlock(&self.locked);
+        f(unsafe { &mut *self.value.get() })
+    }
+}
@@ -0,0 +1,109 @@
+use crate::SkipList;
+use alloc::collections::BinaryHeap;
+use alloc::vec::Vec;
+use core::cmp::{Ordering, Reverse};
+use core::ops::RangeBounds;
+
+impl<K: Ord, V> SkipList<K, V> {
+    // Returns up to n entries in range with the largest by(key, val), largest
+    // first. Equal scores go to the smaller key. Wrap the score in Reverse to
+    // get the n smallest instead.
+    pub fn top_n_in<R, T, F>(&self, range: R, n: usize, mut by: F) -> Vec<(&K, &V)>
+    where
+        R: RangeBounds<K>,
+        T: Ord,
+        F: FnMut(&K, &V) -> T,
+    {
+        if n == 0 {
+            return Vec::new();
+        }
+        // A min-heap of the best n seen so far: its top is the entry to evict.
+        let mut heap: BinaryHeap<Reverse<Ranked<T, (&K, &V)>>> = BinaryHeap::with_capacity(n + 1);
+        for (pos, (key, val)) in self.range(range).enumerate() {
+            let score = by(key, val);
+            if heap.len() == n {
+                let Reverse(worst) = heap.peek().unwrap();
+                if score <= worst.score {
+                    continue;
+                }
+                heap.pop();
+            }
+            heap.push(Reverse(Ranked {
+                score,
+                pos,
+                entry: (key, val),
+            }));
+        }
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(r)| r.entry)
+            .collect()
+    }
+}
+
+struct Ranked<T, E> {
+    score: T,
+    pos: usize,
+    entry: E,
+}
+
+impl<T: Ord, E> Ord for Ranked<T, E> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.score
+            .cmp(&other.score)
+            .then_with(|| other.pos.cmp(&self.pos))
+    }
+}
+
+impl<T: Ord, E> PartialOrd for Ranked<T, E> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Ord, E> PartialEq for Ranked<T, E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T: Ord, E> Eq for Ranked<T, E> {}
+
+#[cfg(test)]
+mod tests {
+    use crate::SkipList;
+    use std::cmp::Reverse;
+
+    #[test]
+    fn top_n_in() {
+        let mut offers = SkipList::new();
+        for price in 0..100u32 {
+            offers.insert(price, (price * 37) % 101);
+        }
+        let got: Vec<u32> = offers
+            .top_n_in(20..60, 5, |_, qty| *qty)
+            .into_iter()
+            .map(|(_, q)| *q)
+            .collect();
+        let mut expected: Vec<u32> = (20..60).map(|p| (p * 37) % 101).collect();
+        expected.sort_unstable_by(|a, b| b.cmp(a));
+        assert_eq!(got, expected[..5]);
+
+        let cheapest: Vec<u32> = offers
+            .top_n_in(.., 3, |price, _| Reverse(*price))
+            .into_iter()
+            .map(|(p, _)| *p)
+            .collect();
+        assert_eq!(cheapest, vec![0, 1, 2]);
+
+        let ties: Vec<u32> = offers
+            .top_n_in(10..20, 3, |_, _| 0)
+            .into_iter()
+            .map(|(p, _)| *p)
+            .collect();
+        assert_eq!(ties, vec![10, 11, 12]);
+
+        assert!(offers.top_n_in(.., 0, |_, q| *q).is_empty());
+        assert_eq!(offers.top_n_in(95.., 10, |_, q| *q).len(), 5);
+    }
+}
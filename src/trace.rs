@@ -0,0 +1,146 @@
+use crate::SkipList;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+// Totals over every search since the list was made or last reset. Each
+// search walks down from the top level, so levels is searches times the
+// height at the time; comparisons is how many keys were looked at on the way.
+// On a healthy list the mean stays within a small multiple of log2(len), and
+// max_comparisons is where a skewed tower layout shows up first.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SearchStats {
+    pub searches: u64,
+    pub levels: u64,
+    pub comparisons: u64,
+    pub max_comparisons: u64,
+}
+
+impl SearchStats {
+    pub fn comparisons_per_search(&self) -> f64 {
+        if self.searches == 0 {
+            return 0.0;
+        }
+        self.comparisons as f64 / self.searches as f64
+    }
+}
+
+// Searches only borrow the list, and a shared list may be searched from
+// several threads at once, hence the atomics.
+#[derive(Default)]
+pub(crate) struct SearchCounters {
+    searches: AtomicU64,
+    levels: AtomicU64,
+    comparisons: AtomicU64,
+    max_comparisons: AtomicU64,
+}
+
+impl SearchCounters {
+    pub(crate) fn record(&self, levels: usize, comparisons: u64) {
+        self.searches.fetch_add(1, Ordering::Relaxed);
+        self.levels.fetch_add(levels as u64, Ordering::Relaxed);
+        self.comparisons.fetch_add(comparisons, Ordering::Relaxed);
+        self.max_comparisons
+            .fetch_max(comparisons, Ordering::Relaxed);
+    }
+}
+
+impl<K, V> SkipList<K, V> {
+    pub fn search_stats(&self) -> SearchStats {
+        let counters = &self.search;
+        SearchStats {
+            searches: counters.searches.load(Ordering::Relaxed),
+            levels: counters.levels.load(Ordering::Relaxed),
+            comparisons: counters.comparisons.load(Ordering::Relaxed),
+            max_comparisons: counters.max_comparisons.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn reset_search_stats(&self) {
+        let counters = &self.search;
+        counters.searches.store(0, Ordering::Relaxed);
+        counters.levels.store(0, Ordering::Relaxed);
+        counters.comparisons.store(0, Ordering::Relaxed);
+        counters.max_comparisons.store(0, Ordering::Relaxed);
+    }
+}
+
+impl<K, V> SkipList<K, V> {
+    // How far across the list the searches since the last reset ranged, for
+    // rebalance_tuned. In the layout rebalance builds, a search makes about
+    // (b + 1) / 2 comparisons on each of log_b(n) levels, b being 1/p, so
+    // working that back from what searches cost now gives the reach, which
+    // is less than len when they keep to a few hot keys. None when nobody
+    // has searched.
+    pub(crate) fn searched_reach(&self) -> Option<usize> {
+        let stats = self.search_stats();
+        if stats.searches == 0 {
+            return None;
+        }
+        let now = self.levels.fanout() as f64;
+        let levels = stats.comparisons_per_search() / ((now + 1.0) / 2.0);
+        Some(now.powf(levels).min(usize::MAX as f64).ceil() as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::SkipList;
+
+    #[test]
+    fn searches_stay_logarithmic() {
+        let mut sk: SkipList<_, _> = (0..4_096u32).map(|i| (i, i)).collect();
+        sk.reset_search_stats();
+        for i in (0..4_096).step_by(4) {
+            assert_eq!(sk.get(&i), Some(&i));
+        }
+        assert_eq!(sk.rank(&100), 100);
+        let stats = sk.search_stats();
+        assert_eq!(stats.searches, 1_025);
+        assert!(stats.levels >= stats.searches);
+        assert!(stats.comparisons_per_search() < 48.0, "{stats:?}");
+        assert!(stats.max_comparisons >= 12);
+
+        // The position insert_full returns comes out of its one search.
+        sk.reset_search_stats();
+        assert_eq!(sk.insert_full(5_000, 0), (4_096, None));
+        assert_eq!(sk.insert_full(7, 0), (7, Some(7)));
+        assert_eq!(sk.search_stats().searches, 2);
+
+        sk.reset_search_stats();
+        assert_eq!(sk.search_stats(), Default::default());
+        assert_eq!(sk.search_stats().comparisons_per_search(), 0.0);
+    }
+
+    #[test]
+    fn rebalance_tuned_follows_the_searches() {
+        let mut sk = SkipList::from_sorted_iter((0..4_096u32).map(|i| (i, i)));
+        assert_eq!(sk.rebalance_tuned(), 0.5);
+
+        // Searches across the whole list do best with four nodes per link.
+        for i in (0..4_096).step_by(3) {
+            sk.get(&i);
+        }
+        let before = sk.search_stats().comparisons_per_search();
+        assert_eq!(sk.rebalance_tuned(), 0.25);
+        sk.check_invariants();
+        assert_eq!(sk.search_stats(), Default::default());
+        for i in (0..4_096).step_by(3) {
+            assert_eq!(sk.get(&i), Some(&i));
+        }
+        assert!(sk.search_stats().comparisons_per_search() < before);
+        let heights = sk.stats().heights;
+        assert_eq!(heights[0], 4_096 - 1_024);
+
+        // Ones that keep to the front are cheap whatever p is, so fewer links
+        // win.
+        for _ in 0..100 {
+            for i in 0..4 {
+                sk.get(&i);
+            }
+        }
+        let p = sk.rebalance_tuned();
+        assert!(p < 0.25, "{}", p);
+        sk.check_invariants();
+        sk.insert(5_000, 0);
+        assert_eq!(sk.len(), 4_097);
+    }
+}
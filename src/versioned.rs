@@ -0,0 +1,130 @@
+use crate::{SkipList, MAX_LEVEL};
+use core::borrow::Borrow;
+use core::cmp::Reverse;
+
+// Every version is its own entry keyed by (key, seq), newest first within a
+// key, so the version visible at seq is the first entry at or after
+// (key, seq). Deletes are versions too, with no value.
+pub struct VersionedSkipList<K, V> {
+    list: SkipList<(K, Reverse<u64>), Option<V>>,
+}
+
+impl<K: Ord, V> VersionedSkipList<K, V> {
+    pub fn new() -> Self {
+        Self {
+            list: SkipList::new(),
+        }
+    }
+
+    // Counts every version, tombstones included.
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    // Writing the same key and seq twice keeps the later value.
+    pub fn insert_versioned(&mut self, key: K, seq: u64, val: V) {
+        self.list.insert((key, Reverse(seq)), Some(val));
+    }
+
+    pub fn delete_versioned(&mut self, key: K, seq: u64) {
+        self.list.insert((key, Reverse(seq)), None);
+    }
+
+    // The value of the newest version at or below seq, or None if that
+    // version is a delete or there is none.
+    pub fn get_at<Q>(&self, key: &Q, seq: u64) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut update = [None; MAX_LEVEL];
+        unsafe {
+            let node_ptr = self
+                .list
+                .find_by(|(k, s)| (k.borrow(), *s) < (key, Reverse(seq)), &mut update)?;
+            let node = &*node_ptr.as_ptr();
+            if node.key.0.borrow() != key {
+                return None;
+            }
+            node.val().as_ref()
+        }
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get_at(key, u64::MAX)
+    }
+
+    // The live entries as of seq, in key order.
+    pub fn iter_at(&self, seq: u64) -> impl Iterator<Item = (&K, &V)> + '_ {
+        let mut last = None;
+        self.list.iter().filter_map(move |((k, Reverse(s)), v)| {
+            if *s > seq || last == Some(k) {
+                return None;
+            }
+            last = Some(k);
+            v.as_ref().map(|v| (k, v))
+        })
+    }
+
+    // Every version by key, newest first within a key, with None for deletes.
+    pub fn versions(&self) -> impl Iterator<Item = (&K, u64, Option<&V>)> + '_ {
+        self.list
+            .iter()
+            .map(|((k, Reverse(s)), v)| (k, *s, v.as_ref()))
+    }
+}
+
+impl<K: Ord, V> Default for VersionedSkipList<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::VersionedSkipList;
+
+    #[test]
+    fn reads_see_their_sequence() {
+        let mut m = VersionedSkipList::new();
+        m.insert_versioned("a", 1, 10);
+        m.insert_versioned("b", 2, 20);
+        m.insert_versioned("a", 5, 11);
+        m.delete_versioned("b", 6);
+        m.insert_versioned("c", 7, 30);
+        assert_eq!(m.len(), 5);
+        assert_eq!(m.get_at("a", 0), None);
+        assert_eq!(m.get_at("a", 1), Some(&10));
+        assert_eq!(m.get_at("a", 4), Some(&10));
+        assert_eq!(m.get_at("a", 5), Some(&11));
+        assert_eq!(m.get_at("b", 5), Some(&20));
+        assert_eq!(m.get_at("b", 6), None);
+        assert_eq!(m.get("a"), Some(&11));
+        assert_eq!(m.get("z"), None);
+
+        let at = |seq| m.iter_at(seq).map(|(k, v)| (*k, *v)).collect::<Vec<_>>();
+        assert_eq!(at(3), [("a", 10), ("b", 20)]);
+        assert_eq!(at(6), [("a", 11)]);
+        assert_eq!(at(u64::MAX), [("a", 11), ("c", 30)]);
+        assert_eq!(
+            m.versions()
+                .map(|(k, s, v)| (*k, s, v.copied()))
+                .collect::<Vec<_>>(),
+            [
+                ("a", 5, Some(11)),
+                ("a", 1, Some(10)),
+                ("b", 6, None),
+                ("b", 2, Some(20)),
+                ("c", 7, Some(30)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,209 @@
+use crate::{Drain, Entry, Iter, Range, SkipList};
+use core::borrow::Borrow;
+use core::mem;
+use core::ops::RangeBounds;
+
+// How many bytes an entry counts for. Only has to be consistent: the same
+// entry must weigh the same when it goes in and when it comes out.
+pub trait WeightOf<K, V> {
+    fn weight(&self, key: &K, val: &V) -> usize;
+}
+
+impl<K, V, F: Fn(&K, &V) -> usize> WeightOf<K, V> for F {
+    fn weight(&self, key: &K, val: &V) -> usize {
+        self(key, val)
+    }
+}
+
+// Counts each entry as the inline size of its key and value, for when both
+// own no heap memory worth counting.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct InlineSize;
+
+impl<K, V> WeightOf<K, V> for InlineSize {
+    fn weight(&self, _key: &K, _val: &V) -> usize {
+        mem::size_of::<K>() + mem::size_of::<V>()
+    }
+}
+
+// Keeps a running total of the weight of its entries, adjusted on every
+// insert, update and remove, so a memtable can be flushed once it reaches a
+// byte budget instead of an entry count. Values are only handed out mutably
+// through modify, which weighs them again afterwards.
+pub struct WeighedSkipList<K, V, W> {
+    list: SkipList<K, V>,
+    weigh: W,
+    bytes: usize,
+}
+
+impl<K: Ord, V, W: WeightOf<K, V>> WeighedSkipList<K, V, W> {
+    pub fn new(weigh: W) -> Self {
+        Self {
+            list: SkipList::new(),
+            weigh,
+            bytes: 0,
+        }
+    }
+
+    pub fn approximate_bytes(&self) -> usize {
+        self.bytes
+    }
+
+    // The list's memory_usage plus what the weigher says the entries hold,
+    // e.g. |k: &String, v: &Vec<u8>| k.capacity() + v.capacity() for the heap
+    // they own. It's the same running total the budget is checked against,
+    // so the two can't disagree.
+    pub fn memory_usage(&self) -> usize {
+        self.list.memory_usage() + self.bytes
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    pub fn list(&self) -> &SkipList<K, V> {
+        &self.list
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.list.get(key)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.list.contains_key(key)
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        self.list.iter()
+    }
+
+    pub fn range<Q, R>(&self, range: R) -> Range<'_, K, V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        self.list.range(range)
+    }
+
+    pub fn insert(&mut self, key: K, val: V) -> Option<V> {
+        match self.list.entry(key) {
+            Entry::Occupied(mut entry) => {
+                self.bytes += self.weigh.weight(entry.key(), &val);
+                let old = entry.insert(val);
+                self.bytes -= self.weigh.weight(entry.key(), &old);
+                Some(old)
+            }
+            Entry::Vacant(entry) => {
+                self.bytes += self.weigh.weight(entry.key(), &val);
+                entry.insert(val);
+                None
+            }
+        }
+    }
+
+    // Runs f on the value under key and weighs it again; false if the key is
+    // missing.
+    pub fn modify<Q, F: FnOnce(&mut V)>(&mut self, key: &Q, f: F) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let Some(node_ptr) = self.list.find_node(key) else {
+            return false;
+        };
+        let node = unsafe { &mut *node_ptr.as_ptr() };
+        let before = self.weigh.weight(&node.key, node.val());
+        f(node.val_mut());
+        self.bytes = self.bytes - before + self.weigh.weight(&node.key, node.val());
+        true
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let (key, val) = self.list.remove_entry(key)?;
+        self.bytes -= self.weigh.weight(&key, &val);
+        Some(val)
+    }
+
+    // Hands every entry over for flushing and starts the count again from
+    // zero, keeping the list's nodes for the entries written next.
+    pub fn drain(&mut self) -> Drain<'_, K, V> {
+        self.bytes = 0;
+        self.list.drain()
+    }
+
+    pub fn into_list(self) -> SkipList<K, V> {
+        self.list
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{InlineSize, WeighedSkipList};
+
+    #[test]
+    fn weight_follows_every_change() {
+        let weigh = |key: &String, val: &Vec<u8>| key.len() + val.len();
+        let mut list = WeighedSkipList::new(weigh);
+        assert_eq!(list.insert("alpha".to_string(), vec![0; 10]), None);
+        list.insert("beta".to_string(), vec![0; 20]);
+        assert_eq!(list.approximate_bytes(), 39);
+        assert_eq!(
+            list.insert("alpha".to_string(), vec![0; 3]),
+            Some(vec![0; 10])
+        );
+        assert_eq!(list.approximate_bytes(), 32);
+        assert!(list.modify("beta", |val| val.truncate(5)));
+        assert!(!list.modify("gamma", |val| val.clear()));
+        assert_eq!(list.approximate_bytes(), 17);
+        assert_eq!(list.remove("alpha"), Some(vec![0; 3]));
+        assert_eq!(list.remove("alpha"), None);
+        assert_eq!(list.approximate_bytes(), 9);
+
+        let flushed: Vec<_> = list.drain().collect();
+        assert_eq!(flushed, [("beta".to_string(), vec![0; 5])]);
+        assert_eq!(list.approximate_bytes(), 0);
+        assert!(list.is_empty());
+
+        let mut list = WeighedSkipList::new(InlineSize);
+        for i in 0..10u64 {
+            list.insert(i, i as u32);
+        }
+        assert_eq!(list.approximate_bytes(), 120);
+    }
+
+    #[test]
+    fn memory_usage_counts_what_the_weigher_does() {
+        let weigh = |key: &String, val: &Vec<u8>| key.capacity() + val.capacity();
+        let mut list = WeighedSkipList::new(weigh);
+        for i in 0..100u32 {
+            list.insert(i.to_string(), vec![0u8; i as usize]);
+        }
+        let owned: usize = list.iter().map(|(k, v)| weigh(k, v)).sum();
+        assert_eq!(list.approximate_bytes(), owned);
+        assert_eq!(list.memory_usage(), list.list().memory_usage() + owned);
+        assert!(list.modify("7", |val| val.extend([0; 100])));
+        assert_eq!(
+            list.memory_usage() - list.list().memory_usage(),
+            list.approximate_bytes()
+        );
+        list.drain().for_each(drop);
+        assert_eq!(list.memory_usage(), list.list().memory_usage());
+    }
+}
@@ -0,0 +1,121 @@
+use crate::{Node, SkipList};
+use core::mem::{self, MaybeUninit};
+use core::ptr::NonNull;
+use core::slice;
+use zeroize::Zeroize;
+
+pub(crate) struct Wipe<K, V> {
+    key: fn(&mut K),
+    val: fn(&mut V),
+}
+
+impl<K, V> Clone for Wipe<K, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K, V> Copy for Wipe<K, V> {}
+
+impl<K: Ord + Zeroize, V: Zeroize> SkipList<K, V> {
+    // Keys and values are zeroized in place before the list drops them
+    // (truncate, retain, clear, drop, and values replaced by insert). Entries
+    // handed back to the caller, as by remove, are the caller's to wipe.
+    pub fn with_zeroize() -> Self {
+        let mut sk = Self::new();
+        sk.wipe = Some(Wipe {
+            key: K::zeroize,
+            val: V::zeroize,
+        });
+        sk
+    }
+}
+
+impl<K, V> SkipList<K, V> {
+    pub(crate) unsafe fn wipe_node(&self, node_ptr: NonNull<Node<K, V>>) {
+        if let Some(wipe) = self.wipe {
+            let node = node_ptr.as_ptr();
+            (wipe.key)(&mut (*node).key);
+            (wipe.val)((*node).val_mut());
+        }
+    }
+
+    pub(crate) fn wipe_val(&self, val: &mut V) {
+        if let Some(wipe) = self.wipe {
+            (wipe.val)(val);
+        }
+    }
+}
+
+// Clears the node's own copy of the entry, the key with the padding after
+// it and the value slot, whether the entry was dropped or moved out. A boxed
+// value is cleared by Node::take before its box is freed. This runs for every
+// list when the feature is on, since it needs no bounds on K or V.
+pub(crate) unsafe fn zero_entry_bytes<K, V>(node_ptr: NonNull<Node<K, V>>) {
+    let node = node_ptr.as_ptr();
+    zero_bytes(node as *mut u8, mem::offset_of!(Node<K, V>, prev));
+    zero_bytes(
+        Node::slot_ptr(node),
+        Node::<K, V>::slot((*node).boxed).size(),
+    );
+}
+
+pub(crate) unsafe fn zero_bytes(ptr: *mut u8, len: usize) {
+    slice::from_raw_parts_mut(ptr as *mut MaybeUninit<u8>, len).zeroize();
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::SkipList;
+    use std::cell::Cell;
+    use zeroize::Zeroize;
+
+    thread_local! {
+        static WIPED: Cell<usize> = const { Cell::new(0) };
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Secret(Vec<u8>);
+
+    impl Zeroize for Secret {
+        fn zeroize(&mut self) {
+            self.0.zeroize();
+            WIPED.with(|w| w.set(w.get() + 1));
+        }
+    }
+
+    fn wiped() -> usize {
+        WIPED.with(|w| w.replace(0))
+    }
+
+    #[test]
+    fn wipes_before_drop() {
+        let mut sk = SkipList::with_zeroize();
+        for i in 0..10u32 {
+            sk.insert(i, Secret(vec![i as u8; 32]));
+        }
+        assert_eq!(wiped(), 0);
+
+        sk.insert(3, Secret(vec![0xaa]));
+        assert_eq!(wiped(), 1);
+
+        assert_eq!(sk.remove(&4), Some(Secret(vec![4; 32])));
+        assert_eq!(wiped(), 0);
+
+        sk.truncate(6);
+        assert_eq!(wiped(), 3);
+
+        sk.clear_retain_capacity();
+        assert_eq!(wiped(), 6);
+
+        sk.insert(1, Secret(vec![1]));
+        sk.insert(2, Secret(vec![2]));
+        drop(sk);
+        assert_eq!(wiped(), 2);
+
+        let mut plain = SkipList::new();
+        plain.insert(1u32, Secret(vec![1]));
+        drop(plain);
+        assert_eq!(wiped(), 0);
+    }
+}